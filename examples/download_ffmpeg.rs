@@ -25,7 +25,7 @@ async fn main() -> anyhow::Result<()> {
   // Checking the version number before downloading is actually not necessary,
   // but it's a good way to check that the download URL is correct.
   match check_latest_version().await {
-    Ok(version) => println!("Latest available version: {}", version),
+    Ok(version) => println!("Latest available version: {version}"),
     Err(_) => println!("Skipping version check on this platform."),
   }
 
@@ -41,10 +41,10 @@ async fn main() -> anyhow::Result<()> {
   // The built-in download function uses `reqwest` to download the package.
   // For more advanced use cases like async streaming or download progress
   // updates, you could replace this with your own download function.
-  println!("Downloading from: {:?}", download_url);
+  println!("Downloading from: {download_url:?}");
   tokio::fs::create_dir_all(&destination).await?;
   let archive_path = download_ffmpeg_package(download_url, &destination).await?;
-  println!("Downloaded package: {:?}", archive_path);
+  println!("Downloaded package: {archive_path:?}");
 
   // Extraction uses `tar` on all platforms (available in Windows since version 1803)
   println!("Extracting...");
@@ -54,7 +54,7 @@ async fn main() -> anyhow::Result<()> {
   let version = ffmpeg_version_with_path(destination.join("ffmpeg"))
     .await
     .context("error running ffmpeg")?;
-  println!("FFmpeg version: {}", version);
+  println!("FFmpeg version: {version}");
 
   println!("Done! 🏁");
   Ok(())
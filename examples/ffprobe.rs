@@ -14,7 +14,7 @@ async fn main() {
 
   // Try running the executable and printing the version number.
   let version = ffprobe_version().await.unwrap();
-  println!("ffprobe version: {}", version);
+  println!("ffprobe version: {version}");
 }
 
 #[cfg(not(feature = "download_ffmpeg"))]
@@ -0,0 +1,904 @@
+//! Audio processing workflows.
+
+use crate::command::FfmpegCommand;
+use crate::event::{FfmpegEvent, LoudnormStats, StreamTypeSpecificData};
+use crate::log_parser::{
+  try_parse_silence_end, try_parse_silence_start, try_parse_volumedetect_field,
+};
+use crate::outcome::{FfmpegJobSummary, FfmpegOutcomeTracker};
+use crate::stream::{read_stdout, spawn_parsed};
+use anyhow::Context;
+use futures_util::{Stream, StreamExt};
+use std::fmt::Write;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, BufReader};
+
+/// The maximum true peak used by [`normalize_loudness`], in dBTP.
+pub const DEFAULT_TRUE_PEAK: f64 = -1.5;
+
+/// The loudness range used by [`normalize_loudness`], in LU.
+pub const DEFAULT_LOUDNESS_RANGE: f64 = 11.0;
+
+/// The `loudnorm` filter targeting `target_lufs` integrated loudness, with
+/// [`DEFAULT_TRUE_PEAK`] and [`DEFAULT_LOUDNESS_RANGE`].
+///
+/// Without `measured`, this is the measurement pass. With the stats of that
+/// pass, the filter applies a linear gain where possible. The filter always
+/// reports its stats as [`FfmpegEvent::LoudnormStats`].
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::audio::loudnorm_filter;
+///
+/// assert_eq!(
+///   loudnorm_filter(-16.0, None),
+///   "loudnorm=I=-16:TP=-1.5:LRA=11:print_format=json"
+/// );
+/// ```
+pub fn loudnorm_filter(target_lufs: f64, measured: Option<&LoudnormStats>) -> String {
+  let mut filter =
+    format!("loudnorm=I={target_lufs}:TP={DEFAULT_TRUE_PEAK}:LRA={DEFAULT_LOUDNESS_RANGE}");
+  if let Some(stats) = measured {
+    filter.push_str(&format!(
+      ":measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+      stats.input_i, stats.input_tp, stats.input_lra, stats.input_thresh, stats.target_offset
+    ));
+  }
+  filter.push_str(":print_format=json");
+  filter
+}
+
+/// Measure the loudness of the audio of `input`, as needed by the second
+/// pass of [`normalize_loudness`].
+pub async fn measure_loudness<S: AsRef<str>>(
+  input: S,
+  target_lufs: f64,
+) -> anyhow::Result<LoudnormStats> {
+  let (stats, _) = run_pass(measure_command(input.as_ref(), target_lufs)).await?;
+  Ok(stats)
+}
+
+/// Normalize the loudness of `input` to `target_lufs` (e.g. `-16` for
+/// podcasts, `-23` for EBU R128 broadcast) with the two-pass `loudnorm`
+/// workflow, writing the result to `output`.
+///
+/// The first pass measures the input, the second applies the measured
+/// values. Video is copied, and the audio is resampled back to the sample
+/// rate of the input since `loudnorm` upsamples to 192 kHz. Returns the
+/// stats of the second pass, whose `output_*` values describe the result.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::audio::normalize_loudness;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let stats = normalize_loudness("episode.wav", "episode_normalized.wav", -16.0).await?;
+/// println!("normalized to {} LUFS", stats.output_i);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn normalize_loudness<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  output: T,
+  target_lufs: f64,
+) -> anyhow::Result<LoudnormStats> {
+  let input = input.as_ref();
+
+  let (measured, summary) = run_pass(measure_command(input, target_lufs)).await?;
+  if !measured.input_i.is_finite() {
+    anyhow::bail!("cannot normalize silent input {input}");
+  }
+
+  let sample_rate =
+    summary
+      .metadata
+      .input_streams
+      .iter()
+      .find_map(|stream| match &stream.type_specific_data {
+        StreamTypeSpecificData::Audio(audio) => Some(audio.sample_rate),
+        _ => None,
+      });
+
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .arg("-filter:a")
+    .arg(loudnorm_filter(target_lufs, Some(&measured)))
+    .copy_video();
+  if let Some(sample_rate) = sample_rate {
+    command.arg("-ar").arg(sample_rate.to_string());
+  }
+  command.overwrite().output(output);
+
+  let (stats, _) = run_pass(command).await?;
+  Ok(stats)
+}
+
+/// A silent part of the input reported by [`detect_silence`]. Times are in
+/// seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SilenceRange {
+  pub start: f64,
+  pub end: f64,
+  pub duration: f64,
+}
+
+/// Find the parts of the audio of `input` quieter than `threshold_db` (e.g.
+/// `-50.0`) for at least `min_duration`, using the `silencedetect` filter.
+///
+/// Ranges are streamed as soon as they end, so long inputs can be processed
+/// while they are being analyzed. If Ffmpeg fails, the stream ends with the
+/// error. Dropping the stream stops the analysis.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::audio::detect_silence;
+/// use futures_util::StreamExt;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut silences = detect_silence("episode.wav", -50.0, Duration::from_millis(500))?;
+/// while let Some(silence) = silences.next().await {
+///   let silence = silence?;
+///   println!("silent from {} to {}", silence.start, silence.end);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn detect_silence<S: AsRef<str>>(
+  input: S,
+  threshold_db: f64,
+  min_duration: Duration,
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<SilenceRange>> + Send + Unpin> {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .arg("-filter:a")
+    .arg(format!(
+      "silencedetect=noise={threshold_db}dB:d={}",
+      min_duration.as_secs_f64()
+    ))
+    .no_video()
+    .format("null")
+    .output("-");
+
+  let mut start = None;
+  spawn_parsed(command, move |event| {
+    let FfmpegEvent::Log(_, line) = event else {
+      return None;
+    };
+    if let Some(time) = try_parse_silence_start(&line) {
+      start = Some(time);
+      return None;
+    }
+    let (end, duration) = try_parse_silence_end(&line)?;
+    Some(SilenceRange {
+      start: start.take().unwrap_or(end - duration),
+      end,
+      duration,
+    })
+  })
+}
+
+/// The statistics of the `volumedetect` filter, see [`detect_volume`].
+/// Volumes are in dB relative to full scale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeStats {
+  /// The number of samples analyzed, over all channels
+  pub n_samples: u64,
+  /// The mean volume (RMS)
+  pub mean_volume: f64,
+  /// The peak volume
+  pub max_volume: f64,
+  /// The loudest bins of the volume histogram as `(dB, samples)`, e.g.
+  /// `(3, 12)` for 12 samples between -3 and -4 dB. Bins are in increasing
+  /// order of attenuation.
+  pub histogram: Vec<(u32, u64)>,
+}
+
+/// Measure the volume of the audio of `input` with the `volumedetect`
+/// filter, e.g. to find the headroom available for a gain.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::audio::detect_volume;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let stats = detect_volume("episode.wav").await?;
+/// println!("peak at {} dB, {} dB of headroom", stats.max_volume, -stats.max_volume);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn detect_volume<S: AsRef<str>>(input: S) -> anyhow::Result<VolumeStats> {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .arg("-filter:a")
+    .arg("volumedetect")
+    .no_video()
+    .format("null")
+    .output("-");
+
+  let mut fields = spawn_parsed(command, |event| match event {
+    FfmpegEvent::Log(_, line) => try_parse_volumedetect_field(&line),
+    _ => None,
+  })?;
+
+  let mut n_samples = None;
+  let mut mean_volume = None;
+  let mut max_volume = None;
+  let mut histogram = Vec::new();
+  while let Some(field) = fields.next().await {
+    let (key, value) = field?;
+    let number = value.trim_end_matches(" dB");
+    match key.as_str() {
+      "n_samples" => n_samples = number.parse().ok(),
+      "mean_volume" => mean_volume = number.parse().ok(),
+      "max_volume" => max_volume = number.parse().ok(),
+      key => {
+        let bin = key
+          .strip_prefix("histogram_")
+          .and_then(|bin| bin.strip_suffix("db"))
+          .and_then(|bin| bin.parse().ok());
+        if let (Some(bin), Ok(samples)) = (bin, number.parse()) {
+          histogram.push((bin, samples));
+        }
+      }
+    }
+  }
+
+  match (n_samples, mean_volume, max_volume) {
+    (Some(n_samples), Some(mean_volume), Some(max_volume)) => Ok(VolumeStats {
+      n_samples,
+      mean_volume,
+      max_volume,
+      histogram,
+    }),
+    _ => anyhow::bail!("volumedetect did not report its statistics"),
+  }
+}
+
+/// Min/max pairs for drawing the waveform of an audio file, see
+/// [`waveform_peaks`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveformPeaks {
+  /// The sample rate of the audio
+  pub sample_rate: u32,
+  /// The number of samples summarized by each pair
+  pub samples_per_pixel: u32,
+  /// The lowest and highest 16-bit sample of each group of
+  /// `samples_per_pixel` samples, in order
+  pub peaks: Vec<(i16, i16)>,
+}
+
+impl WaveformPeaks {
+  /// The peaks in the JSON format of
+  /// [audiowaveform](https://github.com/bbc/audiowaveform), as read by
+  /// waveform UIs such as peaks.js.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::audio::WaveformPeaks;
+  ///
+  /// let peaks = WaveformPeaks {
+  ///   sample_rate: 44100,
+  ///   samples_per_pixel: 512,
+  ///   peaks: vec![(-120, 97), (-3001, 2870)],
+  /// };
+  /// assert_eq!(
+  ///   peaks.to_json(),
+  ///   r#"{"version":2,"channels":1,"sample_rate":44100,"samples_per_pixel":512,"bits":16,"length":2,"data":[-120,97,-3001,2870]}"#
+  /// );
+  /// ```
+  pub fn to_json(&self) -> String {
+    let mut json = format!(
+      r#"{{"version":2,"channels":1,"sample_rate":{},"samples_per_pixel":{},"bits":16,"length":{},"data":["#,
+      self.sample_rate,
+      self.samples_per_pixel,
+      self.peaks.len()
+    );
+    for (i, (min, max)) in self.peaks.iter().enumerate() {
+      let separator = if i == 0 { "" } else { "," };
+      let _ = write!(json, "{separator}{min},{max}");
+    }
+    json.push_str("]}");
+    json
+  }
+}
+
+/// Decode the audio of `input` to mono 16-bit PCM piped through stdout and
+/// summarize every `samples_per_pixel` samples as a min/max pair, ready to be
+/// drawn one pair per pixel.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::audio::waveform_peaks;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let waveform = waveform_peaks("episode.mp3", 512).await?;
+/// tokio::fs::write("episode.json", waveform.to_json()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn waveform_peaks<S: AsRef<str>>(
+  input: S,
+  samples_per_pixel: u32,
+) -> anyhow::Result<WaveformPeaks> {
+  if samples_per_pixel == 0 {
+    anyhow::bail!("samples_per_pixel must be greater than zero");
+  }
+
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .no_video()
+    .args(["-ac", "1", "-c:a", "pcm_s16le"])
+    .format("s16le")
+    .pipe_stdout();
+  let mut child = command.spawn()?;
+  let stdout = child
+    .take_stdout()
+    .context("stdout of ffmpeg is not piped")?;
+
+  // Drain the log concurrently so that a full stderr pipe can't block Ffmpeg
+  let mut events = child.stream()?;
+  let collector = tokio::spawn(async move {
+    let mut tracker = FfmpegOutcomeTracker::new();
+    while let Some(event) = events.next().await {
+      tracker.observe(&event);
+    }
+    (tracker, events.metadata().clone())
+  });
+
+  let mut reader = BufReader::new(stdout);
+  let mut peaks = Vec::new();
+  let mut current: Option<(i16, i16)> = None;
+  let mut count = 0;
+  while let Ok(sample) = reader.read_i16_le().await {
+    let (min, max) = current.get_or_insert((sample, sample));
+    *min = (*min).min(sample);
+    *max = (*max).max(sample);
+    count += 1;
+    if count == samples_per_pixel {
+      peaks.extend(current.take());
+      count = 0;
+    }
+  }
+  peaks.extend(current);
+
+  let exit_status = child.wait().await?;
+  let (tracker, metadata) = collector.await?;
+  let summary = tracker.into_summary(exit_status, metadata);
+  if !summary.outcome.is_success() {
+    anyhow::bail!("waveform extraction failed: {}", summary.errors.join(""));
+  }
+
+  let sample_rate = summary
+    .metadata
+    .output_streams
+    .iter()
+    .find_map(|stream| match &stream.type_specific_data {
+      StreamTypeSpecificData::Audio(audio) => Some(audio.sample_rate),
+      _ => None,
+    })
+    .context("no audio stream was decoded")?;
+
+  Ok(WaveformPeaks {
+    sample_rate,
+    samples_per_pixel,
+    peaks,
+  })
+}
+
+/// The amplitude scale of a [`Spectrogram`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrogramScale {
+  Linear,
+  Sqrt,
+  Cbrt,
+  /// Logarithmic, Ffmpeg's default
+  Log,
+  FourthRoot,
+  FifthRoot,
+}
+
+impl SpectrogramScale {
+  /// The value of the `scale` option of `showspectrumpic`.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      SpectrogramScale::Linear => "lin",
+      SpectrogramScale::Sqrt => "sqrt",
+      SpectrogramScale::Cbrt => "cbrt",
+      SpectrogramScale::Log => "log",
+      SpectrogramScale::FourthRoot => "4thrt",
+      SpectrogramScale::FifthRoot => "5thrt",
+    }
+  }
+}
+
+/// A spectrogram of a whole audio file rendered as a single PNG by the
+/// `showspectrumpic` filter.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::audio::{Spectrogram, SpectrogramScale};
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let spectrogram = Spectrogram::new("episode.wav")
+///   .size(1920, 1080)
+///   .scale(SpectrogramScale::Log)
+///   .color("viridis");
+///
+/// let mut progress = spectrogram.write("spectrogram.png");
+/// while let Some(fraction) = progress.next().await {
+///   println!("{:.0}%", fraction? * 100.0);
+/// }
+///
+/// let png = spectrogram.legend(false).to_bytes().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrogram {
+  input: String,
+  width: u32,
+  height: u32,
+  legend: bool,
+  scale: SpectrogramScale,
+  color: Option<String>,
+}
+
+impl Spectrogram {
+  /// A spectrogram of the first audio stream of `input`, 1024x512 pixels
+  /// with a legend by default.
+  pub fn new<S: AsRef<str>>(input: S) -> Self {
+    Self {
+      input: input.as_ref().to_string(),
+      width: 1024,
+      height: 512,
+      legend: true,
+      scale: SpectrogramScale::Log,
+      color: None,
+    }
+  }
+
+  /// The size of the spectrogram itself, in pixels. The legend is drawn
+  /// around it, making the image larger.
+  pub fn size(mut self, width: u32, height: u32) -> Self {
+    self.width = width;
+    self.height = height;
+    self
+  }
+
+  /// Whether to draw the time and frequency axes and the color scale.
+  pub fn legend(mut self, legend: bool) -> Self {
+    self.legend = legend;
+    self
+  }
+
+  /// The amplitude scale.
+  pub fn scale(mut self, scale: SpectrogramScale) -> Self {
+    self.scale = scale;
+    self
+  }
+
+  /// The color palette, e.g. `intensity`, `viridis` or `magma`. See the
+  /// [filter documentation](https://ffmpeg.org/ffmpeg-filters.html#showspectrumpic)
+  /// for the full list.
+  pub fn color<S: AsRef<str>>(mut self, color: S) -> Self {
+    self.color = Some(color.as_ref().to_string());
+    self
+  }
+
+  /// The `showspectrumpic` filter with these options.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::audio::Spectrogram;
+  ///
+  /// let spectrogram = Spectrogram::new("episode.wav").size(800, 400).color("magma");
+  /// assert_eq!(
+  ///   spectrogram.filter(),
+  ///   "showspectrumpic=s=800x400:legend=1:scale=log:color=magma"
+  /// );
+  /// ```
+  pub fn filter(&self) -> String {
+    let mut filter = format!(
+      "showspectrumpic=s={}x{}:legend={}:scale={}",
+      self.width,
+      self.height,
+      u8::from(self.legend),
+      self.scale.as_str()
+    );
+    if let Some(color) = &self.color {
+      filter.push_str(&format!(":color={color}"));
+    }
+    filter
+  }
+
+  /// Write the spectrogram to `path` as a PNG.
+  ///
+  /// The filter only outputs its image once the whole input is analyzed, so
+  /// the decoded audio is also sent to a null output, whose progress is
+  /// reported from `0.0` to `1.0`. The stream ends with `1.0` on success or
+  /// with the error. Dropping the stream stops the job.
+  pub fn write<P: AsRef<str>>(
+    &self,
+    path: P,
+  ) -> impl Stream<Item = anyhow::Result<f64>> + Send + Unpin {
+    let mut command = FfmpegCommand::new();
+    command
+      .input(&self.input)
+      .filter_complex(format!("[0:a:0]asplit[a][b];[a]{}[s]", self.filter()))
+      .map_str("[s]")
+      .frames(1)
+      .overwrite()
+      .output(path)
+      .map_str("[b]")
+      .format("null")
+      .output("-");
+
+    let mut total = None;
+    let progress = futures_util::stream::once(async move {
+      spawn_parsed(command, move |event| match event {
+        FfmpegEvent::ParsedDuration(duration) if duration.input_index == 0 => {
+          total = duration.duration;
+          None
+        }
+        FfmpegEvent::Progress(progress) => {
+          let time = progress.time?.as_secs_f64();
+          Some((time / total?).clamp(0.0, 1.0))
+        }
+        _ => None,
+      })
+    });
+
+    Box::pin(
+      progress
+        .flat_map(|result| match result {
+          Ok(fractions) => fractions.left_stream(),
+          Err(e) => futures_util::stream::iter([Err(e)]).right_stream(),
+        })
+        .chain(futures_util::stream::once(async { Ok(1.0) }))
+        .scan(false, |failed, item| {
+          let item = (!*failed).then_some(item);
+          if let Some(Err(_)) = &item {
+            *failed = true;
+          }
+          async move { item }
+        }),
+    )
+  }
+
+  /// Render the spectrogram to PNG bytes piped through stdout.
+  pub async fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+    let mut command = FfmpegCommand::new();
+    command
+      .input(&self.input)
+      .filter_complex(format!("[0:a:0]{}", self.filter()))
+      .codec_video("png")
+      .format("image2pipe")
+      .pipe_stdout();
+    read_stdout(command).await.context("spectrogram failed")
+  }
+}
+
+/// A target format of [`extract`], with its encoder and a default bitrate
+/// suited for listening.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+  /// MP3 with `libmp3lame` at 192 kbit/s
+  Mp3,
+  /// AAC with the native encoder at 192 kbit/s
+  Aac,
+  /// Lossless FLAC
+  Flac,
+  /// Uncompressed 16-bit PCM
+  Wav,
+  /// Opus with `libopus` at 128 kbit/s
+  Opus,
+}
+
+impl AudioFormat {
+  /// The encoder passed to `-c:a`.
+  pub fn codec(&self) -> &'static str {
+    match self {
+      AudioFormat::Mp3 => "libmp3lame",
+      AudioFormat::Aac => "aac",
+      AudioFormat::Flac => "flac",
+      AudioFormat::Wav => "pcm_s16le",
+      AudioFormat::Opus => "libopus",
+    }
+  }
+
+  /// The default bitrate of lossy formats.
+  pub fn default_bitrate(&self) -> Option<&'static str> {
+    match self {
+      AudioFormat::Mp3 | AudioFormat::Aac => Some("192k"),
+      AudioFormat::Opus => Some("128k"),
+      AudioFormat::Flac | AudioFormat::Wav => None,
+    }
+  }
+
+  /// The usual file extension, without the dot.
+  pub fn extension(&self) -> &'static str {
+    match self {
+      AudioFormat::Mp3 => "mp3",
+      AudioFormat::Aac => "m4a",
+      AudioFormat::Flac => "flac",
+      AudioFormat::Wav => "wav",
+      AudioFormat::Opus => "opus",
+    }
+  }
+}
+
+/// The command used by [`extract_track`]: audio track `track` (counting
+/// audio streams only) of `input` is encoded to `format`, dropping video,
+/// subtitles and data. Existing files are overwritten.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::audio::{extract_command, AudioFormat};
+///
+/// let command = extract_command("movie.mkv", "commentary.mp3", AudioFormat::Mp3, 1);
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert_eq!(
+///   args[args.len() - 9..],
+///   ["-map", "0:a:1", "-vn", "-c:a", "libmp3lame", "-b:a", "192k", "-y", "commentary.mp3"]
+/// );
+/// ```
+pub fn extract_command<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  output: T,
+  format: AudioFormat,
+  track: u32,
+) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .map(0, format!("a:{track}"))
+    .no_video()
+    .codec_audio(format.codec());
+  if let Some(bitrate) = format.default_bitrate() {
+    command.args(["-b:a", bitrate]);
+  }
+  command.overwrite().output(output);
+  command
+}
+
+/// Extract the first audio track of `input` to `output` in `format`, e.g.
+/// the soundtrack of a video as MP3. See [`extract_track`] for files with
+/// several audio tracks.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::audio::{extract, AudioFormat};
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// extract("talk.mp4", "talk.mp3", AudioFormat::Mp3).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn extract<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  output: T,
+  format: AudioFormat,
+) -> anyhow::Result<FfmpegJobSummary> {
+  extract_track(input, output, format, 0).await
+}
+
+/// Extract audio track `track` of `input` (`0` being the first audio
+/// stream, whatever the position of the video and subtitle streams) to
+/// `output` in `format`.
+pub async fn extract_track<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  output: T,
+  format: AudioFormat,
+  track: u32,
+) -> anyhow::Result<FfmpegJobSummary> {
+  let summary = extract_command(input, output, format, track)
+    .spawn()?
+    .wait_with_events()
+    .await?;
+  if !summary.outcome.is_success() {
+    anyhow::bail!("audio extraction failed: {}", summary.errors.join(""));
+  }
+  Ok(summary)
+}
+
+/// The measurement pass: decode the audio of `input` through `loudnorm`
+/// without writing anything.
+fn measure_command(input: &str, target_lufs: f64) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .arg("-filter:a")
+    .arg(loudnorm_filter(target_lufs, None))
+    .no_video()
+    .format("null")
+    .output("-");
+  command
+}
+
+/// Run a command whose filtergraph contains `loudnorm`, returning the last
+/// reported stats.
+async fn run_pass(mut command: FfmpegCommand) -> anyhow::Result<(LoudnormStats, FfmpegJobSummary)> {
+  let mut child = command.spawn()?;
+  let mut events = child.stream()?;
+
+  let mut tracker = FfmpegOutcomeTracker::new();
+  let mut stats = None;
+  while let Some(event) = events.next().await {
+    tracker.observe(&event);
+    if let FfmpegEvent::LoudnormStats(event) = event {
+      stats = Some(event);
+    }
+  }
+
+  let summary = tracker.into_summary(child.wait().await?, events.metadata().clone());
+  if !summary.outcome.is_success() {
+    anyhow::bail!("loudnorm pass failed: {}", summary.errors.join(""));
+  }
+  match stats {
+    Some(stats) => Ok((stats, summary)),
+    None => anyhow::bail!("loudnorm did not report its stats"),
+  }
+}
+
+/// Mixes the audio of several inputs into one stream, each at its own
+/// volume, optionally lowering the other inputs while a voice input is
+/// audible ("ducking"). Apply it with [`FfmpegCommand::mix_audio`].
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::audio::AudioMix;
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+///
+/// let mix = AudioMix::new().input(0, 1.0).input(1, 0.5).duck(0);
+///
+/// let mut command = FfmpegCommand::new();
+/// command
+///   .input("voice.wav")
+///   .input("music.mp3")
+///   .mix_audio(&mix)?
+///   .output("voice_over.m4a");
+///
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert_eq!(args[args.len() - 3..], ["-map", "[mix]", "voice_over.m4a"]);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AudioMix {
+  inputs: Vec<(u32, f64)>,
+  ducking: Option<Ducking>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Ducking {
+  voice_input: u32,
+  threshold: f64,
+  ratio: f64,
+}
+
+impl AudioMix {
+  /// The label of the mixed stream in [`filtergraph`](Self::filtergraph).
+  pub const OUTPUT_LABEL: &'static str = "mix";
+
+  /// An empty mix.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add the first audio stream of input `input_index`, scaled by `volume`
+  /// (`1.0` keeping it unchanged).
+  pub fn input(mut self, input_index: u32, volume: f64) -> Self {
+    self.inputs.push((input_index, volume));
+    self
+  }
+
+  /// Lower the other inputs while input `voice_input` is audible, with a
+  /// `sidechaincompress` threshold of `0.05` and a ratio of `8`.
+  pub fn duck(mut self, voice_input: u32) -> Self {
+    self.ducking = Some(Ducking {
+      voice_input,
+      threshold: 0.05,
+      ratio: 8.0,
+    });
+    self
+  }
+
+  /// The level of the voice above which ducking starts, from `0.0` to `1.0`.
+  /// Only applies after [`duck`](Self::duck).
+  pub fn duck_threshold(mut self, threshold: f64) -> Self {
+    if let Some(ducking) = &mut self.ducking {
+      ducking.threshold = threshold;
+    }
+    self
+  }
+
+  /// How strongly the other inputs are lowered, from `1.0` to `20.0`. Only
+  /// applies after [`duck`](Self::duck).
+  pub fn duck_ratio(mut self, ratio: f64) -> Self {
+    if let Some(ducking) = &mut self.ducking {
+      ducking.ratio = ratio;
+    }
+    self
+  }
+
+  /// The `-filter_complex` graph producing the mix on the
+  /// [`OUTPUT_LABEL`](Self::OUTPUT_LABEL) output. `amix` normally lowers
+  /// every input to avoid clipping, which is disabled so that the volumes
+  /// apply as given.
+  ///
+  /// Fails if there are no inputs, or if the ducked voice is not one of the
+  /// inputs or the only one.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::audio::AudioMix;
+  ///
+  /// let mix = AudioMix::new().input(0, 1.0).input(1, 0.3).input(2, 0.3).duck(0);
+  /// assert_eq!(
+  ///   mix.filtergraph()?,
+  ///   "[0:a]volume=1[a0];[1:a]volume=0.3[a1];[2:a]volume=0.3[a2];\
+  ///    [a0]asplit=2[voice][key];\
+  ///    [a1][a2]amix=inputs=2:duration=longest:normalize=0[bed];\
+  ///    [bed][key]sidechaincompress=threshold=0.05:ratio=8[ducked];\
+  ///    [voice][ducked]amix=inputs=2:duration=longest:normalize=0[mix]"
+  /// );
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn filtergraph(&self) -> anyhow::Result<String> {
+    anyhow::ensure!(!self.inputs.is_empty(), "audio mix has no inputs");
+
+    let mut chains = Vec::new();
+    let mut labels = Vec::new();
+    for (i, (input_index, volume)) in self.inputs.iter().enumerate() {
+      chains.push(format!("[{input_index}:a]volume={volume}[a{i}]"));
+      labels.push(format!("[a{i}]"));
+    }
+
+    let Some(ducking) = &self.ducking else {
+      chains.push(format!("{}[{}]", amix(&labels), Self::OUTPUT_LABEL));
+      return Ok(chains.join(";"));
+    };
+
+    let voice = self
+      .inputs
+      .iter()
+      .position(|(input_index, _)| *input_index == ducking.voice_input)
+      .with_context(|| format!("ducked voice input {} is not mixed", ducking.voice_input))?;
+    let voice_label = labels.remove(voice);
+    anyhow::ensure!(!labels.is_empty(), "audio mix has nothing to duck");
+
+    chains.push(format!("{voice_label}asplit=2[voice][key]"));
+    let bed = match labels.as_slice() {
+      [single] => single.clone(),
+      _ => {
+        chains.push(format!("{}[bed]", amix(&labels)));
+        "[bed]".to_string()
+      }
+    };
+    chains.push(format!(
+      "{bed}[key]sidechaincompress=threshold={}:ratio={}[ducked]",
+      ducking.threshold, ducking.ratio
+    ));
+    chains.push(format!(
+      "{}[{}]",
+      amix(&["[voice]".to_string(), "[ducked]".to_string()]),
+      Self::OUTPUT_LABEL
+    ));
+    Ok(chains.join(";"))
+  }
+}
+
+fn amix(labels: &[String]) -> String {
+  format!(
+    "{}amix=inputs={}:duration=longest:normalize=0",
+    labels.concat(),
+    labels.len()
+  )
+}
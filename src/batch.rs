@@ -0,0 +1,321 @@
+//! Transcoding a whole directory of files through a [`FfmpegJobPool`].
+
+use crate::command::{BackgroundCommand, FfmpegCommand};
+use crate::ffprobe::ffprobe_path;
+use crate::pool::{FfmpegJobPool, FfmpegJobState};
+use anyhow::Context;
+use futures_util::Stream;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::{ExitStatus, Stdio};
+use std::task::Poll;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// Number of files transcoded at once by [`transcode_dir`].
+pub const DEFAULT_CONCURRENCY: usize = 2;
+
+/// A file matched by the input pattern of a batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchFile {
+  pub input: PathBuf,
+  pub output: PathBuf,
+  /// Duration in seconds as reported by `ffprobe`, if known.
+  pub duration: Option<f64>,
+}
+
+/// Progress of a batch, see [`BatchTranscode`]. `file` is the index into
+/// [`BatchTranscode::files`], and `overall` the completion of the entire
+/// batch between `0.0` and `1.0`, weighted by the duration of each file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchEvent {
+  /// Ffmpeg was spawned for the file.
+  Started { file: usize, overall: f64 },
+  /// Completion of a single file between `0.0` and `1.0`.
+  Progress {
+    file: usize,
+    fraction: f64,
+    overall: f64,
+  },
+  /// The file was transcoded, failed or could not be probed.
+  Finished {
+    file: usize,
+    result: Result<ExitStatus, String>,
+    overall: f64,
+  },
+}
+
+/// Transcode every file matching `input_glob` (e.g. `"library/**/*.avi"`),
+/// writing each to the path given by `output_template`, see
+/// [`render_output`]. Runs [`DEFAULT_CONCURRENCY`] jobs at a time; use
+/// [`transcode_dir_in`] to share an existing pool.
+///
+/// `customizer` receives each command after its input has been added and
+/// before the output, so it can add output options such as codecs.
+///
+/// Files that `ffprobe` cannot read are reported as failed without being
+/// transcoded.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::batch::{transcode_dir, BatchEvent};
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut batch = transcode_dir("library/*.avi", "converted/{stem}.mp4", |command, _file| {
+///   command.codec_video("libx264").codec_audio("aac");
+/// })
+/// .await?;
+///
+/// while let Some(event) = batch.next().await {
+///   if let BatchEvent::Finished { file, result, overall } = event {
+///     let input = batch.files()[file].input.display();
+///     println!("{input}: {result:?} ({:.0}% overall)", overall * 100.0);
+///   }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn transcode_dir<F>(
+  input_glob: &str,
+  output_template: &str,
+  customizer: F,
+) -> anyhow::Result<BatchTranscode>
+where
+  F: FnMut(&mut FfmpegCommand, &BatchFile),
+{
+  let pool = FfmpegJobPool::new(DEFAULT_CONCURRENCY);
+  transcode_dir_in(&pool, input_glob, output_template, customizer).await
+}
+
+/// Like [`transcode_dir`], but submits the jobs to `pool`.
+pub async fn transcode_dir_in<F>(
+  pool: &FfmpegJobPool,
+  input_glob: &str,
+  output_template: &str,
+  mut customizer: F,
+) -> anyhow::Result<BatchTranscode>
+where
+  F: FnMut(&mut FfmpegCommand, &BatchFile),
+{
+  let mut inputs = glob::glob(input_glob)
+    .with_context(|| format!("invalid input pattern {input_glob}"))?
+    .filter_map(Result::ok)
+    .filter(|path| path.is_file())
+    .collect::<Vec<_>>();
+  inputs.sort();
+
+  let (tx, rx) = mpsc::unbounded_channel();
+  let mut files = Vec::with_capacity(inputs.len());
+
+  for (index, input) in inputs.into_iter().enumerate() {
+    let output = PathBuf::from(render_output(output_template, &input));
+    let probed = probe_duration(&input).await;
+    let file = BatchFile {
+      input,
+      output,
+      duration: probed.as_ref().ok().copied(),
+    };
+
+    if let Err(e) = probed {
+      let _ = tx.send((index, Update::Finished(Err(e.to_string()))));
+      files.push(file);
+      continue;
+    }
+
+    if let Some(parent) = file.output.parent() {
+      tokio::fs::create_dir_all(parent)
+        .await
+        .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    let mut command = FfmpegCommand::new();
+    command.input(file.input.to_string_lossy());
+    customizer(&mut command, &file);
+    command.output(file.output.to_string_lossy());
+
+    let job = pool.submit(command)?;
+    let tx = tx.clone();
+    tokio::spawn(async move {
+      let mut status = job.status();
+      let mut started = false;
+      while status.changed().await.is_ok() {
+        let status = status.borrow_and_update().clone();
+        if status.state != FfmpegJobState::Running {
+          continue;
+        }
+
+        if !started {
+          started = true;
+          let _ = tx.send((index, Update::Started));
+        }
+        if let Some(fraction) = status.fraction {
+          let _ = tx.send((index, Update::Progress(fraction)));
+        }
+      }
+
+      let result = job.wait().await.map_err(|e| e.to_string());
+      let _ = tx.send((index, Update::Finished(result)));
+    });
+
+    files.push(file);
+  }
+
+  Ok(BatchTranscode::new(files, rx))
+}
+
+/// Expand an output template for `input`. Supported placeholders:
+///
+/// - `{stem}`: the file name without extension
+/// - `{name}`: the full file name
+/// - `{ext}`: the extension, without dot
+/// - `{dir}`: the directory containing the input
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::batch::render_output;
+/// use std::path::Path;
+///
+/// let input = Path::new("library/holiday.avi");
+/// assert_eq!(render_output("out/{stem}.mp4", input), "out/holiday.mp4");
+/// assert_eq!(render_output("{dir}/{name}.mkv", input), "library/holiday.avi.mkv");
+/// ```
+pub fn render_output(template: &str, input: &Path) -> String {
+  let lossy = |value: Option<&std::ffi::OsStr>| {
+    value
+      .map(|value| value.to_string_lossy().into_owned())
+      .unwrap_or_default()
+  };
+
+  template
+    .replace("{stem}", &lossy(input.file_stem()))
+    .replace("{name}", &lossy(input.file_name()))
+    .replace("{ext}", &lossy(input.extension()))
+    .replace("{dir}", &lossy(input.parent().map(Path::as_os_str)))
+}
+
+/// The duration of `input` in seconds, as reported by `ffprobe`.
+pub async fn probe_duration<P: AsRef<Path>>(input: P) -> anyhow::Result<f64> {
+  let output = Command::new(ffprobe_path())
+    .create_no_window()
+    .args(["-v", "error", "-show_entries", "format=duration"])
+    .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+    .arg(input.as_ref())
+    .stdin(Stdio::null())
+    .output()
+    .await
+    .context("failed to run ffprobe")?;
+
+  if !output.status.success() {
+    anyhow::bail!(
+      "ffprobe failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+
+  String::from_utf8_lossy(&output.stdout)
+    .lines()
+    .next()
+    .and_then(|line| line.trim().parse().ok())
+    .with_context(|| {
+      format!(
+        "ffprobe reported no duration for {}",
+        input.as_ref().display()
+      )
+    })
+}
+
+enum Update {
+  Started,
+  Progress(f64),
+  Finished(Result<ExitStatus, String>),
+}
+
+/// A running batch, yielding [`BatchEvent`]s until every file has finished.
+pub struct BatchTranscode {
+  files: Vec<BatchFile>,
+  weights: Vec<f64>,
+  fractions: Vec<f64>,
+  updates: mpsc::UnboundedReceiver<(usize, Update)>,
+}
+
+impl BatchTranscode {
+  fn new(files: Vec<BatchFile>, updates: mpsc::UnboundedReceiver<(usize, Update)>) -> Self {
+    // Files of unknown duration count as long as the average known file.
+    let known = files.iter().filter_map(|file| file.duration);
+    let count = known.clone().count();
+    let average = match count {
+      0 => 1.0,
+      count => known.sum::<f64>() / count as f64,
+    };
+
+    Self {
+      weights: files
+        .iter()
+        .map(|file| file.duration.unwrap_or(average))
+        .collect(),
+      fractions: vec![0.0; files.len()],
+      files,
+      updates,
+    }
+  }
+
+  /// The files of the batch, in the order they were submitted.
+  pub fn files(&self) -> &[BatchFile] {
+    &self.files
+  }
+
+  /// Completion of the entire batch between `0.0` and `1.0`.
+  pub fn overall(&self) -> f64 {
+    let total = self.weights.iter().sum::<f64>();
+    if total <= 0.0 {
+      return 1.0;
+    }
+
+    let done = self
+      .weights
+      .iter()
+      .zip(&self.fractions)
+      .map(|(weight, fraction)| weight * fraction)
+      .sum::<f64>();
+    done / total
+  }
+}
+
+impl Stream for BatchTranscode {
+  type Item = BatchEvent;
+
+  fn poll_next(
+    mut self: Pin<&mut Self>,
+    cx: &mut std::task::Context<'_>,
+  ) -> Poll<Option<Self::Item>> {
+    let Some((file, update)) = std::task::ready!(self.updates.poll_recv(cx)) else {
+      return Poll::Ready(None);
+    };
+
+    let event = match update {
+      Update::Started => BatchEvent::Started {
+        file,
+        overall: self.overall(),
+      },
+      Update::Progress(fraction) => {
+        self.fractions[file] = fraction;
+        BatchEvent::Progress {
+          file,
+          fraction,
+          overall: self.overall(),
+        }
+      }
+      Update::Finished(result) => {
+        self.fractions[file] = 1.0;
+        BatchEvent::Finished {
+          file,
+          result,
+          overall: self.overall(),
+        }
+      }
+    };
+
+    Poll::Ready(Some(event))
+  }
+}
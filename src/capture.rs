@@ -0,0 +1,204 @@
+//! Recording from a camera and a microphone.
+//!
+//! Capture devices are opened through a platform-specific input device:
+//! `v4l2` and `alsa` on Linux, `avfoundation` on macOS and `dshow` on
+//! Windows, see [`CaptureBackend`]. [`FfmpegCommand::capture_input`] adds
+//! the inputs with options that keep the audio in sync with the video, and
+//! [`record_camera`] starts a recording to a file.
+//!
+//! [`FfmpegCommand::capture_input`]: crate::command::FfmpegCommand::capture_input
+
+use crate::child::FfmpegChild;
+use crate::command::FfmpegCommand;
+
+/// The input devices used to open capture devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureBackend {
+  /// Linux: the camera is a `/dev/video*` path and the microphone an ALSA
+  /// device such as `default` or `hw:1`, opened as two inputs.
+  V4l2,
+  /// macOS: devices are names or indexes, e.g. `FaceTime HD Camera` or `0`,
+  /// opened as a single input.
+  AvFoundation,
+  /// Windows: devices are names, e.g. `Integrated Camera`, opened as a
+  /// single input.
+  DirectShow,
+}
+
+impl CaptureBackend {
+  /// The backend of the platform the crate is compiled for.
+  pub fn native() -> Self {
+    if cfg!(target_os = "macos") {
+      CaptureBackend::AvFoundation
+    } else if cfg!(windows) {
+      CaptureBackend::DirectShow
+    } else {
+      CaptureBackend::V4l2
+    }
+  }
+}
+
+/// The options of [`FfmpegCommand::capture_input`] and [`record_camera`].
+///
+/// [`FfmpegCommand::capture_input`]: crate::command::FfmpegCommand::capture_input
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureOptions {
+  backend: CaptureBackend,
+  framerate: f32,
+  size: Option<(u32, u32)>,
+}
+
+impl Default for CaptureOptions {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl CaptureOptions {
+  /// Capture at 30 frames per second in the default size of the camera,
+  /// with the [native](CaptureBackend::native) backend.
+  pub fn new() -> Self {
+    Self {
+      backend: CaptureBackend::native(),
+      framerate: 30.0,
+      size: None,
+    }
+  }
+
+  /// Use another backend, e.g. to build a command for another platform.
+  pub fn backend(mut self, backend: CaptureBackend) -> Self {
+    self.backend = backend;
+    self
+  }
+
+  /// The frame rate requested from the camera, which must be one it
+  /// supports.
+  pub fn framerate(mut self, framerate: f32) -> Self {
+    self.framerate = framerate;
+    self
+  }
+
+  /// The size requested from the camera, which must be one it supports.
+  pub fn size(mut self, width: u32, height: u32) -> Self {
+    self.size = Some((width, height));
+    self
+  }
+
+  /// The options and the URL of every input opening `video_device` and,
+  /// optionally, `audio_device`.
+  ///
+  /// Inputs are given a large `-thread_queue_size`, since live devices
+  /// produce packets whether or not Ffmpeg keeps up. Separate camera and
+  /// microphone inputs are timestamped with the wall clock so that they
+  /// share a time base.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::capture::{CaptureBackend, CaptureOptions};
+  ///
+  /// let options = CaptureOptions::new()
+  ///   .backend(CaptureBackend::DirectShow)
+  ///   .size(1280, 720);
+  /// assert_eq!(
+  ///   options.inputs("Integrated Camera", Some("Microphone Array")),
+  ///   [(
+  ///     vec![
+  ///       "-thread_queue_size", "1024", "-f", "dshow", "-framerate", "30",
+  ///       "-video_size", "1280x720",
+  ///     ]
+  ///     .into_iter()
+  ///     .map(String::from)
+  ///     .collect::<Vec<_>>(),
+  ///     "video=Integrated Camera:audio=Microphone Array".to_string(),
+  ///   )]
+  /// );
+  /// ```
+  pub fn inputs(
+    &self,
+    video_device: &str,
+    audio_device: Option<&str>,
+  ) -> Vec<(Vec<String>, String)> {
+    let mut video_options = vec!["-thread_queue_size".to_string(), "1024".to_string()];
+    let format = match self.backend {
+      CaptureBackend::V4l2 => "v4l2",
+      CaptureBackend::AvFoundation => "avfoundation",
+      CaptureBackend::DirectShow => "dshow",
+    };
+    video_options.extend(["-f".to_string(), format.to_string()]);
+    video_options.extend(["-framerate".to_string(), self.framerate.to_string()]);
+    if let Some((width, height)) = self.size {
+      video_options.extend(["-video_size".to_string(), format!("{width}x{height}")]);
+    }
+
+    match self.backend {
+      CaptureBackend::V4l2 => {
+        let wallclock = ["-use_wallclock_as_timestamps", "1"].map(String::from);
+        let mut inputs = Vec::new();
+        if audio_device.is_some() {
+          video_options.extend(wallclock.clone());
+        }
+        inputs.push((video_options, video_device.to_string()));
+        if let Some(audio_device) = audio_device {
+          let mut audio_options = ["-thread_queue_size", "1024", "-f", "alsa"]
+            .map(String::from)
+            .to_vec();
+          audio_options.extend(wallclock);
+          inputs.push((audio_options, audio_device.to_string()));
+        }
+        inputs
+      }
+      CaptureBackend::AvFoundation => {
+        let url = format!("{video_device}:{}", audio_device.unwrap_or("none"));
+        vec![(video_options, url)]
+      }
+      CaptureBackend::DirectShow => {
+        let url = match audio_device {
+          Some(audio_device) => format!("video={video_device}:audio={audio_device}"),
+          None => format!("video={video_device}"),
+        };
+        vec![(video_options, url)]
+      }
+    }
+  }
+}
+
+/// Start recording `video_device` and, optionally, `audio_device` to
+/// `output`, overwriting it. The video is converted to `yuv420p` for
+/// playback compatibility, and the audio is resampled to follow the video
+/// clock should the devices drift apart.
+///
+/// Read the usual events with [`FfmpegChild::stream`], and stop the
+/// recording with [`FfmpegChild::quit`] so that the file is finalized.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::capture::{record_camera, CaptureOptions};
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let options = CaptureOptions::new().size(1280, 720);
+/// let mut child = record_camera("/dev/video0", Some("default"), "webcam.mkv", &options)?;
+/// let mut progress = child.stream()?.filter_progress().take(100);
+/// while let Some(progress) = progress.next().await {
+///   println!("{}", progress.raw_time);
+/// }
+/// child.quit().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn record_camera<O: AsRef<str>>(
+  video_device: &str,
+  audio_device: Option<&str>,
+  output: O,
+  options: &CaptureOptions,
+) -> anyhow::Result<FfmpegChild> {
+  let mut command = FfmpegCommand::new();
+  command
+    .capture_input(video_device, audio_device, options)
+    .pix_fmt("yuv420p");
+  if audio_device.is_some() {
+    command.args(["-filter:a", "aresample=async=1"]);
+  }
+  command.overwrite().output(output);
+  Ok(command.spawn()?)
+}
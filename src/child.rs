@@ -1,20 +1,52 @@
 //! Wrapper around `tokio::process` containing a spawned Ffmpeg command.
 
 use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::time::Instant;
 use tokio::{
   io::{self},
   process::{Child, ChildStderr, ChildStdin, ChildStdout},
 };
 
+use crate::outcome::{FfmpegJobSummary, FfmpegOutcomeTracker};
+use crate::process_group::{KillHandle, ProcessGroup};
 use crate::stream::FfmpegEventStream;
-use anyhow::Context;
+use futures_util::StreamExt;
 use tokio::io::AsyncWriteExt;
 
 /// A wrapper around [`tokio::process::Child`] containing a spawned Ffmpeg command.
 /// Provides interfaces for reading parsed metadata, progress updates, warnings and errors and
 /// piped output frames if applicable.
+///
+/// ## Drop behavior
+///
+/// Dropping a `FfmpegChild` detaches from the process, which keeps running,
+/// unless the command was configured with
+/// [`FfmpegCommand::kill_on_drop`](crate::command::FfmpegCommand::kill_on_drop),
+/// in which case the process is killed.
 pub struct FfmpegChild {
   inner: Child,
+  kill_on_drop: bool,
+  /// The configured timeout and the instant it expires
+  deadline: Option<(Duration, Instant)>,
+  /// Lets the event stream kill the process once the timeout expires
+  kill_handle: Option<KillHandle>,
+  group: Option<ProcessGroup>,
+  expected_outputs: usize,
+  image_sequence: Option<(String, u32)>,
+}
+
+/// Settings made on an [`FfmpegCommand`](crate::command::FfmpegCommand) that
+/// apply to the spawned child rather than to the command line.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChildOptions {
+  pub kill_on_drop: bool,
+  pub timeout: Option<Duration>,
+  pub process_group: bool,
+  /// Number of outputs added with `output` or `output_with`
+  pub expected_outputs: usize,
+  /// Pattern and first number of the last `output_image_sequence`
+  pub image_sequence: Option<(String, u32)>,
 }
 
 impl FfmpegChild {
@@ -29,6 +61,31 @@ impl FfmpegChild {
     FfmpegEventStream::new(self)
   }
 
+  /// Like [`stream`](Self::stream), reading the log in chunks of at most
+  /// `capacity` bytes instead of
+  /// [`DEFAULT_BUFFER_CAPACITY`](crate::stream::DEFAULT_BUFFER_CAPACITY),
+  /// e.g. a larger buffer to keep up with `-loglevel trace` output.
+  ///
+  /// ## Example
+  ///
+  /// ```rust,no_run
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  /// use futures_util::StreamExt;
+  ///
+  /// # async fn run() -> anyhow::Result<()> {
+  /// let mut child = FfmpegCommand::new()
+  ///   .args(["-loglevel", "trace"])
+  ///   .testsrc()
+  ///   .rawvideo()
+  ///   .spawn()?;
+  /// let events = child.stream_with_capacity(256 * 1024)?.collect::<Vec<_>>().await;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn stream_with_capacity(&mut self, capacity: usize) -> anyhow::Result<FfmpegEventStream> {
+    FfmpegEventStream::with_capacity(self, capacity)
+  }
+
   /// Escape hatch to manually control the process' stdout channel.
   /// Calling this method takes ownership of the stdout channel, so
   /// the iterator will no longer include output frames in the stream of events.
@@ -69,8 +126,11 @@ impl FfmpegChild {
   /// q      quit
   /// s      Show QP histogram
   /// ```
+  ///
+  /// Fails with [`MissingChannel::Stdin`] if the command was spawned with
+  /// [`no_stdin`](crate::command::FfmpegCommand::no_stdin) or stdin was taken.
   pub async fn send_stdin_command(&mut self, command: &[u8]) -> anyhow::Result<()> {
-    let mut stdin = self.inner.stdin.take().context("Missing child stdin")?;
+    let mut stdin = self.inner.stdin.take().ok_or(MissingChannel::Stdin)?;
     stdin.write_all(command).await?;
     self.inner.stdin.replace(stdin);
     Ok(())
@@ -86,6 +146,54 @@ impl FfmpegChild {
     self.send_stdin_command(b"q").await
   }
 
+  /// Request a graceful shutdown with [`quit`](Self::quit), and forcibly
+  /// [`kill`](Self::kill) the process if it hasn't exited within `timeout`.
+  ///
+  /// Returns which of the two paths was taken along with the final exit
+  /// status. A process that has already exited is reported as
+  /// [`QuitOutcome::Graceful`].
+  ///
+  /// Ffmpeg can't exit while it is blocked writing to a full stderr or stdout
+  /// pipe, so keep consuming the event stream (or the taken channels) while
+  /// this method runs.
+  ///
+  /// ## Example
+  ///
+  /// ```rust,no_run
+  /// use async_ffmpeg_sidecar::child::QuitOutcome;
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  /// use futures_util::StreamExt;
+  /// use std::time::Duration;
+  ///
+  /// # async fn run() -> anyhow::Result<()> {
+  /// let mut child = FfmpegCommand::new().testsrc().rawvideo().spawn()?;
+  /// let events = child.stream()?;
+  /// let drain = tokio::spawn(events.for_each(|_| async {}));
+  ///
+  /// match child.quit_with_timeout(Duration::from_secs(5)).await? {
+  ///   QuitOutcome::Graceful(status) => println!("ffmpeg exited with {status}"),
+  ///   QuitOutcome::Killed(_) => println!("ffmpeg did not respond to `q` and was killed"),
+  /// }
+  /// drain.await?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub async fn quit_with_timeout(&mut self, timeout: Duration) -> anyhow::Result<QuitOutcome> {
+    // Sending fails if the process already exited and closed its stdin; the
+    // wait below then returns immediately.
+    let _ = self.quit().await;
+
+    let outcome = match tokio::time::timeout(timeout, self.inner.wait()).await {
+      Ok(status) => QuitOutcome::Graceful(status?),
+      Err(_) => {
+        self.inner.kill().await?;
+        QuitOutcome::Killed(self.inner.wait().await?)
+      }
+    };
+    self.release_kill_handle();
+    Ok(outcome)
+  }
+
   /// Forcibly terminate the inner child process.
   ///
   /// Alternatively, you may choose to gracefully stop the child process by
@@ -93,29 +201,173 @@ impl FfmpegChild {
   ///
   /// Identical to `kill` in [`std::process::Child`].
   pub async fn kill(&mut self) -> io::Result<()> {
-    self.inner.kill().await
+    self.inner.kill().await?;
+    self.release_kill_handle();
+    Ok(())
+  }
+
+  /// Forcibly terminate Ffmpeg along with every helper process it launched.
+  ///
+  /// Requires the command to be spawned with
+  /// [`FfmpegCommand::process_group`](crate::command::FfmpegCommand::process_group),
+  /// which places the process tree in its own process group (Unix) or Job
+  /// Object (Windows). Without it, only the Ffmpeg process itself is killed,
+  /// like [`kill`](Self::kill). Does not wait for the processes to exit.
+  pub fn terminate_group(&mut self) -> io::Result<()> {
+    match &self.group {
+      Some(group) => group.terminate(),
+      None => self.inner.start_kill(),
+    }
   }
 
   /// Waits for the inner child process to finish execution.
   ///
-  /// Identical to `wait` in [`std::process::Child`].
+  /// If the command was configured with a
+  /// [`timeout`](crate::command::FfmpegCommand::timeout), the process is
+  /// killed once it expires.
+  ///
+  /// Otherwise identical to `wait` in [`std::process::Child`].
   pub async fn wait(&mut self) -> io::Result<ExitStatus> {
-    self.inner.wait().await
+    let Some((_, deadline)) = self.deadline else {
+      return self.inner.wait().await;
+    };
+
+    let status = match tokio::time::timeout_at(deadline, self.inner.wait()).await {
+      Ok(status) => status,
+      Err(_) => {
+        self.inner.kill().await?;
+        self.inner.wait().await
+      }
+    };
+    self.release_kill_handle();
+    status
+  }
+
+  /// Run the process to completion and report what happened, for callers
+  /// that don't need to react to individual events.
+  ///
+  /// The event stream is drained in a background task while waiting for the
+  /// process to exit, so this method takes the stderr channel like
+  /// [`stream`](Self::stream).
+  ///
+  /// ## Example
+  ///
+  /// ```rust,no_run
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// # async fn run() -> anyhow::Result<()> {
+  /// let summary = FfmpegCommand::new()
+  ///   .input("input.mp4")
+  ///   .output("output.webm")
+  ///   .spawn()?
+  ///   .wait_with_events()
+  ///   .await?;
+  ///
+  /// if !summary.outcome.is_success() {
+  ///   anyhow::bail!("transcode failed: {:?}\n{}", summary.outcome, summary.errors.join("\n"));
+  /// }
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub async fn wait_with_events(&mut self) -> anyhow::Result<FfmpegJobSummary> {
+    let mut events = self.stream()?;
+    let collector = tokio::spawn(async move {
+      let mut tracker = FfmpegOutcomeTracker::new();
+      while let Some(event) = events.next().await {
+        tracker.observe(&event);
+      }
+      (tracker, events.metadata().clone())
+    });
+
+    let exit_status = self.wait().await?;
+    let (tracker, metadata) = collector.await?;
+
+    Ok(tracker.into_summary(exit_status, metadata))
+  }
+
+  /// The timeout configured with
+  /// [`FfmpegCommand::timeout`](crate::command::FfmpegCommand::timeout) and
+  /// the instant it expires.
+  pub fn deadline(&self) -> Option<(Duration, Instant)> {
+    self.deadline
+  }
+
+  /// The handle the event stream kills the process with once the timeout
+  /// expires, if a timeout was configured.
+  pub(crate) fn kill_handle(&self) -> Option<KillHandle> {
+    self.kill_handle.clone()
+  }
+
+  /// Stop the event stream from killing the process by its ID, which may be
+  /// reused once the process has been reaped.
+  fn release_kill_handle(&self) {
+    if let Some(kill_handle) = &self.kill_handle {
+      kill_handle.release();
+    }
+  }
+
+  /// The number of outputs the command was built with, as a lower bound for
+  /// the outputs reported in the metadata.
+  pub(crate) fn expected_outputs(&self) -> usize {
+    self.expected_outputs
+  }
+
+  /// The pattern and first number of the image sequence output, if any.
+  pub(crate) fn image_sequence(&self) -> Option<&(String, u32)> {
+    self.image_sequence.as_ref()
   }
 
   /// Wrap a [`std::process::Child`] in a `FfmpegChild`. Should typically only
   /// be called by `FfmpegCommand::spawn`.
   ///
-  /// ## Panics
+  /// Stdio channels which were not piped are simply absent: methods relying
+  /// on them fail with a [`MissingChannel`] error instead.
+  pub(crate) fn from_inner(mut inner: Child, options: ChildOptions) -> io::Result<Self> {
+    let group = if options.process_group {
+      match ProcessGroup::attach(&inner) {
+        Ok(group) => Some(group),
+        Err(error) => {
+          let _ = inner.start_kill();
+          return Err(error);
+        }
+      }
+    } else {
+      None
+    };
+
+    let kill_handle = options.timeout.map(|_| KillHandle::new(&inner));
+    Ok(Self {
+      inner,
+      kill_on_drop: options.kill_on_drop,
+      deadline: options
+        .timeout
+        .map(|timeout| (timeout, Instant::now() + timeout)),
+      kill_handle,
+      group,
+      expected_outputs: options.expected_outputs,
+      image_sequence: options.image_sequence,
+    })
+  }
+
+  /// The OS-assigned process identifier of the Ffmpeg process, or `None` once
+  /// it has been waited for.
   ///
-  /// Panics if any of the child process's stdio channels were not piped.
-  /// This could be because ffmpeg was spawned with `-nostdin`, or if the
-  /// `Child` instance was not configured with `stdin(Stdio::piped())`.
-  pub(crate) fn from_inner(inner: Child) -> Self {
-    assert!(inner.stdin.is_some(), "stdin was not piped");
-    // assert!(inner.stdout.is_some(), "stdout was not piped");
-    assert!(inner.stderr.is_some(), "stderr was not piped");
-    Self { inner }
+  /// Identical to `id` in [`tokio::process::Child`].
+  pub fn id(&self) -> Option<u32> {
+    self.inner.id()
+  }
+
+  /// Create a sampler for the CPU and memory usage of the Ffmpeg process, or
+  /// `None` if it has already been waited for. Requires the `resource_usage`
+  /// feature.
+  #[cfg(feature = "resource_usage")]
+  pub fn resource_sampler(&self) -> Option<crate::resources::ResourceSampler> {
+    self.id().map(crate::resources::ResourceSampler::new)
+  }
+
+  /// Whether the process is killed when this `FfmpegChild` is dropped.
+  pub fn kills_on_drop(&self) -> bool {
+    self.kill_on_drop
   }
 
   /// Escape hatch to access the inner `Child`.
@@ -128,3 +380,74 @@ impl FfmpegChild {
     &mut self.inner
   }
 }
+
+impl Drop for FfmpegChild {
+  /// Kills the process if `kill_on_drop` was requested or its timeout has
+  /// expired, otherwise detaches from it. See
+  /// [`FfmpegCommand::kill_on_drop`](crate::command::FfmpegCommand::kill_on_drop).
+  fn drop(&mut self) {
+    let timed_out = self
+      .deadline
+      .is_some_and(|(_, deadline)| deadline <= Instant::now());
+
+    self.release_kill_handle();
+    if self.kill_on_drop || timed_out {
+      // Fails only if the process already exited.
+      let _ = self.terminate_group();
+      let _ = self.inner.start_kill();
+    } else if let Some(group) = self.group.take() {
+      group.detach();
+    }
+  }
+}
+
+/// A stdio channel of the child process that is not available, either
+/// because it was not piped when spawning or because it was already taken.
+///
+/// Returned (wrapped in `anyhow::Error`) by the methods that need the
+/// channel, and can be recovered with `downcast_ref`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingChannel {
+  Stdin,
+  Stdout,
+  Stderr,
+}
+
+impl std::fmt::Display for MissingChannel {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name = match self {
+      MissingChannel::Stdin => "stdin",
+      MissingChannel::Stdout => "stdout",
+      MissingChannel::Stderr => "stderr",
+    };
+    write!(
+      f,
+      "the {name} channel of the ffmpeg process is not available"
+    )
+  }
+}
+
+impl std::error::Error for MissingChannel {}
+
+/// How the process was stopped by [`FfmpegChild::quit_with_timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuitOutcome {
+  /// The process exited on its own within the timeout.
+  Graceful(ExitStatus),
+  /// The process was still running after the timeout and was killed.
+  Killed(ExitStatus),
+}
+
+impl QuitOutcome {
+  /// The exit status of the process, regardless of how it was stopped.
+  pub fn exit_status(&self) -> ExitStatus {
+    match self {
+      QuitOutcome::Graceful(status) | QuitOutcome::Killed(status) => *status,
+    }
+  }
+
+  /// Whether the process had to be killed.
+  pub fn was_killed(&self) -> bool {
+    matches!(self, QuitOutcome::Killed(_))
+  }
+}
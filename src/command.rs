@@ -1,8 +1,23 @@
-use crate::child::FfmpegChild;
+use crate::audio::AudioMix;
+use crate::capture::CaptureOptions;
+use crate::child::{ChildOptions, FfmpegChild};
+use crate::compose::Composition;
+use crate::hls::HlsOutput;
+use crate::image_sequence::{has_sequence_number, is_glob_pattern, DEFAULT_OUTPUT_START_NUMBER};
+use crate::metadata::FfmpegMetadata;
+use crate::output::OutputBuilder;
+use crate::pacing::{parse_bitrate, OutputPacing};
 use crate::paths::ffmpeg_path;
+use crate::segment::SegmentOutput;
+use crate::selection::StreamSelection;
+use crate::streaming::{RtmpOutput, SrtOutput};
+use crate::tee::{escape, tee_spec, TeeOutput};
+use anyhow::Context;
 use std::ffi::OsStr;
 use std::io;
+use std::path::Path;
 use std::process::{CommandArgs, Stdio};
+use std::time::Duration;
 use tokio::process::Command;
 
 /// A wrapper around [`tokio::process::Command`] with some convenient preset
@@ -13,6 +28,129 @@ use tokio::process::Command;
 /// list of possible arguments.
 pub struct FfmpegCommand {
   inner: Command,
+  child_options: ChildOptions,
+  /// Input options queued by e.g. [`seek`](Self::seek), emitted before the
+  /// next `-i`, whether passed through [`input`](Self::input) or
+  /// [`arg`](Self::arg).
+  input_options: Vec<String>,
+  /// Video filters queued by e.g. [`scale`](Self::scale), emitted as a
+  /// single `-filter:v` before the next output.
+  video_filters: Vec<String>,
+  /// The `-vf`, `-filter:v` or `-filter` argument passed for the current
+  /// output, which the queued video filters can't be merged into.
+  raw_video_filter: Option<String>,
+  /// Describes an output that was given both, failing [`spawn`](Self::spawn).
+  video_filter_conflict: Option<String>,
+  /// Paths passed to [`input`](Self::input), in order.
+  inputs: Vec<String>,
+  /// The last [`start_number`](Self::start_number), applying to the next
+  /// image sequence.
+  image_start_number: Option<u32>,
+}
+
+/// A time position or duration accepted by methods such as
+/// [`FfmpegCommand::seek`]: a [`Duration`], seconds as `f64`, or a string in
+/// Ffmpeg's [time duration
+/// syntax](https://ffmpeg.org/ffmpeg-utils.html#time-duration-syntax).
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::command::TimeSpec;
+/// use std::time::Duration;
+///
+/// assert_eq!(Duration::from_millis(1500).to_time_spec(), "1.5");
+/// assert_eq!(90.0.to_time_spec(), "90");
+/// assert_eq!("00:01:30".to_time_spec(), "00:01:30");
+/// ```
+pub trait TimeSpec {
+  fn to_time_spec(&self) -> String;
+}
+
+impl TimeSpec for Duration {
+  fn to_time_spec(&self) -> String {
+    self.as_secs_f64().to_string()
+  }
+}
+
+impl TimeSpec for f64 {
+  fn to_time_spec(&self) -> String {
+    self.to_string()
+  }
+}
+
+impl TimeSpec for str {
+  fn to_time_spec(&self) -> String {
+    self.to_string()
+  }
+}
+
+impl TimeSpec for String {
+  fn to_time_spec(&self) -> String {
+    self.clone()
+  }
+}
+
+impl<T: TimeSpec + ?Sized> TimeSpec for &T {
+  fn to_time_spec(&self) -> String {
+    (**self).to_time_spec()
+  }
+}
+
+/// The subtitles burned in by [`FfmpegCommand::burn_subtitles`]: a subtitle
+/// file, converted from a path string, or the index of a subtitle stream of
+/// the first input, converted from a `u32`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubtitleSource {
+  File(String),
+  Stream(u32),
+}
+
+impl From<&str> for SubtitleSource {
+  fn from(path: &str) -> Self {
+    Self::File(path.to_string())
+  }
+}
+
+impl From<String> for SubtitleSource {
+  fn from(path: String) -> Self {
+    Self::File(path)
+  }
+}
+
+impl From<u32> for SubtitleSource {
+  fn from(index: u32) -> Self {
+    Self::Stream(index)
+  }
+}
+
+/// Where [`FfmpegCommand::overlay`] places an image on the video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayPosition {
+  TopLeft,
+  TopRight,
+  BottomLeft,
+  BottomRight,
+  Centered,
+  /// The top left corner of the image at `x`, `y` pixels from the top left
+  /// corner of the video
+  Offset {
+    x: i32,
+    y: i32,
+  },
+}
+
+impl OverlayPosition {
+  /// The `x:y` options of the `overlay` filter, where `W`/`H` are the video's
+  /// dimensions and `w`/`h` the image's.
+  pub fn overlay_options(&self) -> String {
+    match self {
+      OverlayPosition::TopLeft => "0:0".to_string(),
+      OverlayPosition::TopRight => "W-w:0".to_string(),
+      OverlayPosition::BottomLeft => "0:H-h".to_string(),
+      OverlayPosition::BottomRight => "W-w:H-h".to_string(),
+      OverlayPosition::Centered => "(W-w)/2:(H-h)/2".to_string(),
+      OverlayPosition::Offset { x, y } => format!("{x}:{y}"),
+    }
+  }
 }
 
 impl FfmpegCommand {
@@ -47,9 +185,13 @@ impl FfmpegCommand {
   /// Alias for `-i` argument, the input file path or URL.
   ///
   /// To take input from stdin, use the value `-` or `pipe:0`.
+  ///
+  /// Input options queued with [`seek`](Self::seek) are placed right before
+  /// the `-i`.
   pub fn input<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
     self.arg("-i");
     self.arg(path.as_ref());
+    self.inputs.push(path.as_ref().to_string());
     self
   }
 
@@ -60,8 +202,141 @@ impl FfmpegCommand {
   /// preceding it, it is equivalent to calling `.arg()` directly. However,
   /// using this command helps label the purpose of the argument, and makes the
   /// code more readable at a glance.
+  ///
+  /// Video filters queued with [`filter_video`](Self::filter_video) and its
+  /// shortcuts are placed right before the path.
   pub fn output<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
+    self.flush_video_filters();
     self.arg(path.as_ref());
+    self.child_options.expected_outputs += 1;
+    self
+  }
+
+  /// Add an output with its own options, configured on an
+  /// [`OutputBuilder`]. Calling this several times defines several outputs,
+  /// each with its own codecs and filters, and lets
+  /// [`collect_metadata`](crate::stream::FfmpegEventStream::collect_metadata)
+  /// wait for all of them.
+  ///
+  /// Fails without modifying the command if no output path was set.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("input.mp4")
+  ///   .output_with(|o| o.codec_video("libx264").crf(23).size(1280, 720).path("out_720.mp4"))
+  ///   .unwrap()
+  ///   .output_with(|o| o.codec_video("libx264").crf(28).size(640, 360).path("out_360.mp4"))
+  ///   .unwrap();
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[args.len() - 14..],
+  ///   [
+  ///     "-c:v", "libx264", "-crf:v", "23", "-s", "1280x720", "out_720.mp4",
+  ///     "-c:v", "libx264", "-crf:v", "28", "-s", "640x360", "out_360.mp4",
+  ///   ]
+  /// );
+  /// ```
+  pub fn output_with<F>(&mut self, configure: F) -> anyhow::Result<&mut Self>
+  where
+    F: FnOnce(&mut OutputBuilder) -> &mut OutputBuilder,
+  {
+    let mut builder = OutputBuilder::default();
+    configure(&mut builder);
+    let (args, video_filters, path) = builder.into_parts()?;
+
+    self.args(args);
+    self.video_filters.extend(video_filters);
+    Ok(self.output(path))
+  }
+
+  /// Add a sequence of images as an input, played at `framerate` images per
+  /// second. `pattern` is either numbered, e.g. `frames/frame_%04d.png`, or
+  /// a glob pattern, e.g. `photos/*.jpg`, whose matches are read in
+  /// alphabetical order. See the [`image_sequence`](crate::image_sequence)
+  /// module.
+  ///
+  /// A numbered sequence must start at one of 0 to 4, unless a
+  /// [`start_number`](Self::start_number) is set first.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .start_number(1000)
+  ///   .input_image_sequence("frames/frame_%04d.png", 24.0)
+  ///   .input_image_sequence("photos/*.jpg", 1.0);
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[args.len() - 16..],
+  ///   [
+  ///     "-start_number", "1000", "-framerate", "24", "-f", "image2",
+  ///     "-i", "frames/frame_%04d.png",
+  ///     "-framerate", "1", "-pattern_type", "glob", "-f", "image2",
+  ///     "-i", "photos/*.jpg",
+  ///   ]
+  /// );
+  /// ```
+  pub fn input_image_sequence<S: AsRef<str>>(&mut self, pattern: S, framerate: f32) -> &mut Self {
+    self.image_start_number = None;
+    self.args(["-framerate", &framerate.to_string()]);
+    if is_glob_pattern(pattern.as_ref()) {
+      self.args(["-pattern_type", "glob"]);
+    }
+    self.format("image2").input(pattern)
+  }
+
+  /// Write the video as a sequence of images named after `pattern`, e.g.
+  /// `frames/frame_%04d.png`, numbered from 1 or the
+  /// [`start_number`](Self::start_number) set first. The image format is
+  /// guessed from the extension.
+  ///
+  /// The event stream then reports every image written as an
+  /// [`FfmpegEvent::OutputImageWritten`](crate::event::FfmpegEvent::OutputImageWritten).
+  /// Fails if `pattern` has no `%d` or `%0Nd` sequence number.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("input.mp4")
+  ///   .fps(1.0)
+  ///   .output_image_sequence("frames/frame_%04d.png")?;
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[args.len() - 5..],
+  ///   ["-f", "image2", "-filter:v", "fps=1", "frames/frame_%04d.png"]
+  /// );
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn output_image_sequence<S: AsRef<str>>(&mut self, pattern: S) -> anyhow::Result<&mut Self> {
+    let pattern = pattern.as_ref();
+    anyhow::ensure!(
+      has_sequence_number(pattern),
+      "image sequence pattern {pattern:?} has no %d sequence number"
+    );
+
+    let start_number = self
+      .image_start_number
+      .take()
+      .unwrap_or(DEFAULT_OUTPUT_START_NUMBER);
+    self.child_options.image_sequence = Some((pattern.to_string(), start_number));
+    Ok(self.format("image2").output(pattern))
+  }
+
+  /// Alias for `-start_number` argument: the number of the first image of
+  /// the next [`input_image_sequence`](Self::input_image_sequence) or
+  /// [`output_image_sequence`](Self::output_image_sequence).
+  pub fn start_number(&mut self, number: u32) -> &mut Self {
+    self.args(["-start_number", &number.to_string()]);
+    self.image_start_number = Some(number);
     self
   }
 
@@ -102,6 +377,25 @@ impl FfmpegCommand {
     self
   }
 
+  /// Alias for `-c:v copy`: copy the video streams without re-encoding.
+  pub fn copy_video(&mut self) -> &mut Self {
+    self.codec_video("copy")
+  }
+
+  /// Alias for `-c:a copy`: copy the audio streams without re-encoding.
+  pub fn copy_audio(&mut self) -> &mut Self {
+    self.codec_audio("copy")
+  }
+
+  /// Alias for `-c copy`: copy every stream without re-encoding. The output
+  /// container must support the codecs of all copied streams, see
+  /// [`remux`](crate::remux::remux).
+  pub fn copy_all(&mut self) -> &mut Self {
+    self.arg("-c");
+    self.arg("copy");
+    self
+  }
+
   /// Alias for `-c:s` argument.
   ///
   /// Select an encoder (when used before an output file) or a decoder (when
@@ -114,37 +408,34 @@ impl FfmpegCommand {
     self
   }
 
-  /// Alias for `-t` argument.
+  /// Alias for `-t` argument, as an output option.
   ///
-  /// When used as an input option (before `-i`), limit the duration of data
-  /// read from the input file.
+  /// Stop writing the output after its duration reaches `duration`. Applies
+  /// to the next output, so call this before [`output`](Self::output).
   ///
-  /// When used as an output option (before an output url), stop writing the
-  /// output after its duration reaches duration.
-  ///
-  /// `duration` must be a time duration specification, see [(ffmpeg-utils)the
-  /// Time duration section in the ffmpeg-utils(1)
-  /// manual](https://ffmpeg.org/ffmpeg-utils.html#time-duration-syntax).
+  /// `duration` can be a [`Duration`], seconds as `f64` or a string in the
+  /// [time duration
+  /// syntax](https://ffmpeg.org/ffmpeg-utils.html#time-duration-syntax), see
+  /// [`TimeSpec`].
   ///
   /// `-to` and `-t` are mutually exclusive and -t has priority.
-  pub fn duration<S: AsRef<str>>(&mut self, duration: S) -> &mut Self {
+  pub fn duration<T: TimeSpec>(&mut self, duration: T) -> &mut Self {
     self.arg("-t");
-    self.arg(duration.as_ref());
+    self.arg(duration.to_time_spec());
     self
   }
 
-  /// Alias for `-to` argument.
+  /// Alias for `-to` argument, as an output option.
   ///
-  /// Stop writing the output or reading the input at `position`. `position`
-  /// must be a time duration specification, see [(ffmpeg-utils)the Time
-  /// duration section in the ffmpeg-utils(1)
-  /// manual](https://ffmpeg.org/ffmpeg-utils.html#time-duration-syntax).
+  /// Stop writing the output at `position`. Applies to the next output, so
+  /// call this before [`output`](Self::output). See [`TimeSpec`] for the
+  /// accepted values.
   ///
   /// `-to` and `-t` (aka `duration()`) are mutually exclusive and `-t` has
   /// priority.
-  pub fn to<S: AsRef<str>>(&mut self, position: S) -> &mut Self {
+  pub fn to<T: TimeSpec>(&mut self, position: T) -> &mut Self {
     self.arg("-to");
-    self.arg(position.as_ref());
+    self.arg(position.to_time_spec());
     self
   }
 
@@ -159,25 +450,56 @@ impl FfmpegCommand {
     self
   }
 
-  /// Alias for `-ss` argument.
+  /// Alias for `-ss` argument, as an input option.
+  ///
+  /// Seeks in the next input to `position`. Note that in most formats it is
+  /// not possible to seek exactly, so `ffmpeg` will seek to the closest seek
+  /// point before `position`. When transcoding and `-accurate_seek` is
+  /// enabled (the default), this extra segment between the seek point and
+  /// `position` will be decoded and discarded. When doing stream copy or when
+  /// `-noaccurate_seek` is used, it will be preserved.
+  ///
+  /// The option is queued and placed right before the next `-i`, added by
+  /// [`input`](Self::input), [`testsrc`](Self::testsrc) or as a raw
+  /// argument, so it is never mistaken for an output option. Spawning fails
+  /// if no input follows. See [`TimeSpec`] for the
+  /// accepted values.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  /// use std::time::Duration;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .seek(Duration::from_secs(90))
+  ///   .input("input.mp4")
+  ///   .duration(10.0)
+  ///   .output("clip.mp4");
   ///
-  /// When used as an input option (before `-i`), seeks in this input file to
-  /// position. Note that in most formats it is not possible to seek exactly, so
-  /// `ffmpeg` will seek to the closest seek point before `position`. When
-  /// transcoding and `-accurate_seek` is enabled (the default), this extra
-  /// segment between the seek point and `position` will be decoded and
-  /// discarded. When doing stream copy or when `-noaccurate_seek` is used, it
-  /// will be preserved.
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[args.len() - 7..], ["-ss", "90", "-i", "input.mp4", "-t", "10", "clip.mp4"]);
   ///
-  /// When used as an output option (before an output url), decodes but discards
-  /// input until the timestamps reach `position`.
+  /// let mut command = FfmpegCommand::new();
+  /// command.seek(5.0).testsrc().output("clip.mp4");
   ///
-  /// `position` must be a time duration specification, see [(ffmpeg-utils)the
-  /// Time duration section in the ffmpeg-utils(1)
-  /// manual](https://ffmpeg.org/ffmpeg-utils.html#time-duration-syntax).
-  pub fn seek<S: AsRef<str>>(&mut self, position: S) -> &mut Self {
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[args.len() - 7..], ["-f", "lavfi", "-ss", "5", "-i", "testsrc=duration=10", "clip.mp4"]);
+  /// ```
+  pub fn seek<T: TimeSpec>(&mut self, position: T) -> &mut Self {
+    self.input_options.push("-ss".to_string());
+    self.input_options.push(position.to_time_spec());
+    self
+  }
+
+  /// Alias for `-ss` argument, as an output option.
+  ///
+  /// Decodes but discards input until the timestamps reach `position`.
+  /// Slower than [`seek`](Self::seek), but frame-accurate even with stream
+  /// copy. Applies to the next output, so call this before
+  /// [`output`](Self::output). See [`TimeSpec`] for the accepted values.
+  pub fn seek_output<T: TimeSpec>(&mut self, position: T) -> &mut Self {
     self.arg("-ss");
-    self.arg(position.as_ref());
+    self.arg(position.to_time_spec());
     self
   }
 
@@ -205,6 +527,11 @@ impl FfmpegCommand {
   /// See the [`-filter_complex`
   /// option](https://ffmpeg.org/ffmpeg.html#filter_005fcomplex_005foption) if
   /// you want to create filtergraphs with multiple inputs and/or outputs.
+  ///
+  /// Ffmpeg applies only one filtergraph per stream, so this can't be combined
+  /// with [`filter_video`](Self::filter_video) or its shortcuts (e.g.
+  /// [`scale`](Self::scale)) for the same output: [`spawn`](Self::spawn)
+  /// fails instead. Use `filter_video` for every video filter.
   pub fn filter<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
     self.arg("-filter");
     self.arg(filtergraph.as_ref());
@@ -233,12 +560,68 @@ impl FfmpegCommand {
   ///     for libaom for more details](https://trac.ffmpeg.org/wiki/Encode/AV1#ConstantQuality)
   ///   * 0-63 for av1(libsvtav1) (default is 30), see [ffmpeg encoding guide
   ///     for svt-av1 for mode details](https://trac.ffmpeg.org/wiki/Encode/AV1#CRF)
-  pub fn crf(&mut self, crf: u32) -> &mut Self {
+  pub fn crf(&mut self, crf: u8) -> &mut Self {
     self.arg("-crf:v");
     self.arg(crf.to_string());
     self
   }
 
+  /// Alias for `-b:v` argument.
+  ///
+  /// Set the target video bitrate for average bitrate encoding, e.g. `5M` or
+  /// `2500k`. Returns an error without modifying the command if `bitrate` is
+  /// not a valid bitrate, see [`parse_bitrate`].
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command.video_bitrate("5M").unwrap().maxrate("6M").unwrap();
+  /// assert!(command.bufsize("12 megabits").is_err());
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[args.len() - 4..], ["-b:v", "5M", "-maxrate", "6M"]);
+  /// ```
+  pub fn video_bitrate<S: AsRef<str>>(&mut self, bitrate: S) -> anyhow::Result<&mut Self> {
+    self.bitrate_option("-b:v", bitrate.as_ref())
+  }
+
+  /// Alias for `-b:a` argument.
+  ///
+  /// Set the audio bitrate, e.g. `128k`. Returns an error without modifying
+  /// the command if `bitrate` is not a valid bitrate, see [`parse_bitrate`].
+  pub fn audio_bitrate<S: AsRef<str>>(&mut self, bitrate: S) -> anyhow::Result<&mut Self> {
+    self.bitrate_option("-b:a", bitrate.as_ref())
+  }
+
+  /// Alias for `-maxrate` argument.
+  ///
+  /// Cap the video bitrate, e.g. `6M`, to be used together with
+  /// [`bufsize`](Self::bufsize) and either [`crf`](Self::crf) (capped CRF)
+  /// or [`video_bitrate`](Self::video_bitrate). See also [`OutputPacing`].
+  /// Returns an error without modifying the command if `bitrate` is not a
+  /// valid bitrate.
+  pub fn maxrate<S: AsRef<str>>(&mut self, bitrate: S) -> anyhow::Result<&mut Self> {
+    self.bitrate_option("-maxrate", bitrate.as_ref())
+  }
+
+  /// Alias for `-bufsize` argument.
+  ///
+  /// Set the rate control buffer size in bits, e.g. `12M`, which bounds how
+  /// far the bitrate may deviate from [`maxrate`](Self::maxrate). Returns an
+  /// error without modifying the command if `size` is not a valid bitrate.
+  pub fn bufsize<S: AsRef<str>>(&mut self, size: S) -> anyhow::Result<&mut Self> {
+    self.bitrate_option("-bufsize", size.as_ref())
+  }
+
+  fn bitrate_option(&mut self, option: &str, bitrate: &str) -> anyhow::Result<&mut Self> {
+    match parse_bitrate(bitrate) {
+      Some(0) => anyhow::bail!("{option} must be greater than zero"),
+      Some(_) => Ok(self.args([option, bitrate])),
+      None => anyhow::bail!("invalid bitrate for {option}: {bitrate:?}"),
+    }
+  }
+
   /// Alias for `-frames:v` argument.
   ///
   /// Stop writing to the stream after `framecount` frames.
@@ -273,12 +656,38 @@ impl FfmpegCommand {
   /// encoding speed)
   ///
   /// VP9 has no presets
+  ///
+  /// Typed values are available as [`Preset`](crate::encoder::Preset) and
+  /// [`NvencPreset`](crate::encoder::NvencPreset).
   pub fn preset<S: AsRef<str>>(&mut self, preset: S) -> &mut Self {
     self.arg("-preset:v");
     self.arg(preset.as_ref());
     self
   }
 
+  /// Alias for `-tune:v` argument.
+  ///
+  /// Optimize the encoder settings for a kind of content or use case, e.g.
+  /// `film` or `zerolatency`. Typed values are available as
+  /// [`Tune`](crate::encoder::Tune) and
+  /// [`NvencTune`](crate::encoder::NvencTune).
+  pub fn tune<S: AsRef<str>>(&mut self, tune: S) -> &mut Self {
+    self.arg("-tune:v");
+    self.arg(tune.as_ref());
+    self
+  }
+
+  /// Alias for `-profile:v` argument.
+  ///
+  /// Restrict the encoder to a codec profile, e.g. `high` for H.264, so that
+  /// the output plays on devices limited to that profile. Typed values are
+  /// available as [`Profile`](crate::encoder::Profile).
+  pub fn profile<S: AsRef<str>>(&mut self, profile: S) -> &mut Self {
+    self.arg("-profile:v");
+    self.arg(profile.as_ref());
+    self
+  }
+
   /// Alias for `-r` argument.
   ///
   /// Set frame rate (Hz value, fraction or abbreviation).
@@ -309,10 +718,224 @@ impl FfmpegCommand {
   /// The format is `'wxh'` (default - same as source).
   pub fn size(&mut self, width: u32, height: u32) -> &mut Self {
     self.arg("-s");
-    self.arg(format!("{}x{}", width, height));
+    self.arg(format!("{width}x{height}"));
     self
   }
 
+  /// Alias for `-filter:v` argument, a simple video filtergraph for the next
+  /// output.
+  ///
+  /// Ffmpeg only honors the last `-filter:v` (or `-vf`) of an output, so
+  /// instead of being emitted right away, `filtergraph` is queued and joined
+  /// with the other queued filters, e.g. from [`scale`](Self::scale),
+  /// [`fps`](Self::fps), [`crop`](Self::crop) and [`pad`](Self::pad), into
+  /// one `-filter:v` chain placed before the next [`output`](Self::output).
+  /// Filters are applied in the order they were added.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("input.mp4")
+  ///   .crop(1280, 720, 0, 0)
+  ///   .filter_video("hflip")
+  ///   .scale(640, -2)
+  ///   .fps(30.0)
+  ///   .output("output.mp4");
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[args.len() - 3..],
+  ///   ["-filter:v", "crop=1280:720:0:0,hflip,scale=640:-2,fps=30", "output.mp4"]
+  /// );
+  /// ```
+  pub fn filter_video<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.video_filters.push(filtergraph.as_ref().to_string());
+    self
+  }
+
+  /// Add the `scale` filter to the video filter chain, resizing the video
+  /// to `width`x`height`. A dimension of `-1` keeps the aspect ratio, `-2`
+  /// does the same while rounding to an even number as most encoders
+  /// require. See [`filter_video`](Self::filter_video).
+  pub fn scale(&mut self, width: i32, height: i32) -> &mut Self {
+    self.filter_video(scale_filter(width, height))
+  }
+
+  /// Add the `fps` filter to the video filter chain, converting the video to
+  /// a constant frame rate by dropping or duplicating frames. See
+  /// [`filter_video`](Self::filter_video).
+  pub fn fps(&mut self, fps: f32) -> &mut Self {
+    self.filter_video(fps_filter(fps))
+  }
+
+  /// Add the `crop` filter to the video filter chain, keeping the
+  /// `width`x`height` area whose top left corner is at `x`, `y`. See
+  /// [`filter_video`](Self::filter_video).
+  pub fn crop(&mut self, width: u32, height: u32, x: u32, y: u32) -> &mut Self {
+    self.filter_video(crop_filter(width, height, x, y))
+  }
+
+  /// Add the `pad` filter to the video filter chain, placing the video at
+  /// `x`, `y` on a black `width`x`height` canvas. See
+  /// [`filter_video`](Self::filter_video).
+  pub fn pad(&mut self, width: u32, height: u32, x: u32, y: u32) -> &mut Self {
+    self.filter_video(pad_filter(width, height, x, y))
+  }
+
+  /// Render subtitles onto the video by adding the `subtitles` filter, or the
+  /// `ass` filter for `.ass`/`.ssa` files, to the video filter chain. See
+  /// [`filter_video`](Self::filter_video).
+  ///
+  /// `source` is either a subtitle file or the index of a subtitle stream of
+  /// the first [`input`](Self::input), which must then already be added.
+  /// Paths are escaped for the filtergraph, so Windows paths such as
+  /// `C:\subs\movie.srt` can be passed as is.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("movie.mkv")
+  ///   .burn_subtitles(2)?
+  ///   .output("output.mp4");
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[args.len() - 2], "subtitles=movie.mkv:si=2");
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("movie.mp4")
+  ///   .burn_subtitles(r"C:\subs\movie.srt")?
+  ///   .output("output.mp4");
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[args.len() - 2], r"subtitles=C\\:\\\\subs\\\\movie.srt");
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn burn_subtitles<S: Into<SubtitleSource>>(
+    &mut self,
+    source: S,
+  ) -> anyhow::Result<&mut Self> {
+    let filter = self.subtitles_filter(source.into(), None)?;
+    Ok(self.filter_video(filter))
+  }
+
+  /// Like [`burn_subtitles`](Self::burn_subtitles), but also loads the fonts
+  /// in `fonts_dir`, for subtitles styled with fonts that aren't installed.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("movie.mp4")
+  ///   .burn_subtitles_with_fonts("movie.ass", "fonts")?
+  ///   .output("output.mp4");
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[args.len() - 2], "ass=movie.ass:fontsdir=fonts");
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn burn_subtitles_with_fonts<S, D>(
+    &mut self,
+    source: S,
+    fonts_dir: D,
+  ) -> anyhow::Result<&mut Self>
+  where
+    S: Into<SubtitleSource>,
+    D: AsRef<str>,
+  {
+    let filter = self.subtitles_filter(source.into(), Some(fonts_dir.as_ref()))?;
+    Ok(self.filter_video(filter))
+  }
+
+  /// Draw the image at `image_path` over the video, e.g. a watermark or logo,
+  /// by adding a `movie` source and the `overlay` filter to the video filter
+  /// chain. See [`filter_video`](Self::filter_video).
+  ///
+  /// `opacity` ranges from `0.0` (invisible) to `1.0` (opaque), and `scale`
+  /// resizes the image relative to its own size.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::{FfmpegCommand, OverlayPosition};
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("input.mp4")
+  ///   .overlay("logo.png", OverlayPosition::BottomRight, 0.5, 0.25)?
+  ///   .output("output.mp4");
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[args.len() - 2],
+  ///   "null[overlay0];movie=logo.png,format=rgba,colorchannelmixer=aa=0.5,\
+  ///    scale=iw*0.25:-1[watermark0];[overlay0][watermark0]overlay=W-w:H-h"
+  /// );
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn overlay<S: AsRef<str>>(
+    &mut self,
+    image_path: S,
+    position: OverlayPosition,
+    opacity: f32,
+    scale: f32,
+  ) -> anyhow::Result<&mut Self> {
+    anyhow::ensure!(
+      (0.0..=1.0).contains(&opacity),
+      "overlay opacity must be between 0 and 1, got {opacity}"
+    );
+    anyhow::ensure!(scale > 0.0, "overlay scale must be positive, got {scale}");
+
+    // Labels are numbered so several overlays can share one filter chain.
+    let index = self.video_filters.len();
+    let mut image = format!("movie={}", filter_value(image_path.as_ref()));
+    if opacity < 1.0 {
+      image.push_str(&format!(",format=rgba,colorchannelmixer=aa={opacity}"));
+    }
+    if scale != 1.0 {
+      image.push_str(&format!(",scale=iw*{scale}:-1"));
+    }
+    Ok(self.filter_video(format!(
+      "null[overlay{index}];{image}[watermark{index}];[overlay{index}][watermark{index}]overlay={}",
+      position.overlay_options()
+    )))
+  }
+
+  fn subtitles_filter(
+    &self,
+    source: SubtitleSource,
+    fonts_dir: Option<&str>,
+  ) -> anyhow::Result<String> {
+    let mut filter = match source {
+      SubtitleSource::File(path) => {
+        let extension = Path::new(&path)
+          .extension()
+          .and_then(|extension| extension.to_str())
+          .map(|extension| extension.to_ascii_lowercase());
+        let name = match extension.as_deref() {
+          Some("ass" | "ssa") => "ass",
+          _ => "subtitles",
+        };
+        format!("{name}={}", filter_value(&path))
+      }
+      SubtitleSource::Stream(index) => {
+        let input = self
+          .inputs
+          .first()
+          .context("burning in a subtitle stream requires an input")?;
+        format!("subtitles={}:si={index}", filter_value(input))
+      }
+    };
+    if let Some(fonts_dir) = fonts_dir {
+      filter.push_str(":fontsdir=");
+      filter.push_str(&filter_value(fonts_dir));
+    }
+    Ok(filter)
+  }
+
   /// Alias for `-vn` argument.
   ///
   /// As an input option, blocks all video streams of a file from being filtered
@@ -435,12 +1058,48 @@ impl FfmpegCommand {
   /// commandline.
   ///
   /// Using this option disables the default mappings for this output file.
-  pub fn map<S: AsRef<str>>(&mut self, map_string: S) -> &mut Self {
+  ///
+  /// This takes the raw argument, e.g. `0:a:0`, `-0:s` or `[outv]`; see
+  /// [`map`](Self::map) for the common case of streams of one input.
+  pub fn map_str<S: AsRef<str>>(&mut self, map_string: S) -> &mut Self {
     self.arg("-map");
     self.arg(map_string.as_ref());
     self
   }
 
+  /// Map the streams of input `input_index` matching `stream_spec` (e.g.
+  /// `v`, `a:0` or `0`) into the next output; an empty `stream_spec` maps
+  /// every stream of the input. See [`map_str`](Self::map_str) for details.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command.map(0, "v:0").map(1, "a").map(2, "");
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[args.len() - 6..], ["-map", "0:v:0", "-map", "1:a", "-map", "2"]);
+  /// ```
+  pub fn map<S: AsRef<str>>(&mut self, input_index: u32, stream_spec: S) -> &mut Self {
+    self.map_str(map_spec(input_index, stream_spec.as_ref()))
+  }
+
+  /// Append the `-map` arguments resolved from a [`StreamSelection`] against
+  /// previously gathered metadata of the inputs.
+  ///
+  /// Like every use of `-map`, this disables the default stream selection for
+  /// the output. Fails if the selection cannot be satisfied, see
+  /// [`StreamSelection::resolve`].
+  pub fn select_streams(
+    &mut self,
+    selection: &StreamSelection,
+    metadata: &FfmpegMetadata,
+  ) -> anyhow::Result<&mut Self> {
+    let args = selection.resolve(metadata)?;
+    self.args(args);
+    Ok(self)
+  }
+
   /// Alias for `-readrate` argument.
   ///
   /// Limit input read speed.
@@ -463,6 +1122,93 @@ impl FfmpegCommand {
     self
   }
 
+  /// Alias for `-rtbufsize` argument.
+  ///
+  /// Set the maximum memory in bytes used for buffering real-time input
+  /// frames, such as those coming from capture devices. Frames are dropped
+  /// with a warning when the buffer is full, so raise this when a slow
+  /// network output causes `real-time buffer too full` messages.
+  pub fn rtbufsize(&mut self, size_in_bytes: u64) -> &mut Self {
+    self.arg("-rtbufsize");
+    self.arg(size_in_bytes.to_string());
+    self
+  }
+
+  /// Apply an [`OutputPacing`] to the next output, emitting `-maxrate`,
+  /// `-bufsize` and optionally `-flush_packets`.
+  ///
+  /// Returns an error without modifying the command if the pacing options are
+  /// invalid, see [`OutputPacing::validate`].
+  pub fn output_pacing(&mut self, pacing: &OutputPacing) -> anyhow::Result<&mut Self> {
+    pacing.validate()?;
+    self.args(pacing.to_args());
+    Ok(self)
+  }
+
+  /// Write the output to several destinations at once with the `tee`
+  /// muxer, emitting `-f tee` followed by the escaped branches. The streams
+  /// are encoded once and shared by every branch, e.g. to record to disk
+  /// while pushing to an RTMP server. See [`TeeOutput`].
+  ///
+  /// Returns an error without modifying the command if `outputs` is empty.
+  pub fn tee_outputs(&mut self, outputs: &[TeeOutput]) -> anyhow::Result<&mut Self> {
+    if outputs.is_empty() {
+      anyhow::bail!("tee output needs at least one destination");
+    }
+
+    self.format("tee");
+    self.output(tee_spec(outputs));
+    Ok(self)
+  }
+
+  /// Add an HLS output, emitting `-f hls`, the options of `hls` and the
+  /// playlist path. Segment progress is reported as
+  /// [`FfmpegEvent::OutputSegmentOpened`](crate::event::FfmpegEvent::OutputSegmentOpened)
+  /// and [`FfmpegEvent::OutputSegmentClosed`](crate::event::FfmpegEvent::OutputSegmentClosed).
+  pub fn hls_output(&mut self, hls: &HlsOutput) -> &mut Self {
+    self.flush_video_filters();
+    self.args(hls.to_args());
+    self
+  }
+
+  /// Add a segmented output, emitting `-f segment`, the options of
+  /// `segments` and the file name pattern. Every file is reported as
+  /// [`FfmpegEvent::OutputSegmentOpened`](crate::event::FfmpegEvent::OutputSegmentOpened)
+  /// and [`FfmpegEvent::OutputSegmentClosed`](crate::event::FfmpegEvent::OutputSegmentClosed).
+  pub fn segment_output(&mut self, segments: &SegmentOutput) -> &mut Self {
+    self.flush_video_filters();
+    self.args(segments.to_args());
+    self
+  }
+
+  /// Add an RTMP push as output, see [`RtmpOutput`].
+  pub fn rtmp_output(&mut self, rtmp: &RtmpOutput) -> &mut Self {
+    self.flush_video_filters();
+    self.args(rtmp.to_args());
+    self
+  }
+
+  /// Add an SRT push as output, see [`SrtOutput`].
+  pub fn srt_output(&mut self, srt: &SrtOutput) -> &mut Self {
+    self.flush_video_filters();
+    self.args(srt.to_args());
+    self
+  }
+
+  /// Alias for `-progress pipe:2`.
+  ///
+  /// Write machine-readable progress to stderr alongside the log, where it is
+  /// parsed into [`FfmpegEvent::ProgressFeed`](crate::event::FfmpegEvent::ProgressFeed)
+  /// events. Besides the usual statistics, the feed reports the exact number
+  /// of bytes written (`total_size`), which keeps advancing during stream
+  /// copies that produce few frame-based progress lines. See
+  /// [`ByteProgress`](crate::progress::ByteProgress).
+  pub fn progress_feed(&mut self) -> &mut Self {
+    self.arg("-progress");
+    self.arg("pipe:2");
+    self
+  }
+
   /// Alias for `-re`.
   ///
   /// Read input at native frame rate. This is equivalent to setting `-readrate
@@ -535,6 +1281,91 @@ impl FfmpegCommand {
     self
   }
 
+  /// Add the filtergraph of an [`AudioMix`] and map the mixed audio into
+  /// the next output. Fails if the mix is invalid, see
+  /// [`AudioMix::filtergraph`].
+  pub fn mix_audio(&mut self, mix: &AudioMix) -> anyhow::Result<&mut Self> {
+    let filtergraph = mix.filtergraph()?;
+    Ok(
+      self
+        .filter_complex(filtergraph)
+        .map_str(format!("[{}]", AudioMix::OUTPUT_LABEL)),
+    )
+  }
+
+  /// Add the inputs of a [`Composition`], its filtergraph, and map the
+  /// composed video and the chosen audio into the next output. Inputs added
+  /// before, including those of [`testsrc`](Self::testsrc) and raw `-i`
+  /// arguments, are left untouched. Fails if the composition is invalid, see
+  /// [`Composition::filtergraph`].
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::command::{FfmpegCommand, OverlayPosition};
+  /// use async_ffmpeg_sidecar::compose::{Composition, Layout};
+  ///
+  /// let composition = Composition::new(Layout::PictureInPicture {
+  ///   position: OverlayPosition::TopLeft,
+  ///   width: 160,
+  ///   margin: 10,
+  /// })
+  /// .input("screen.mp4")
+  /// .input("camera.mp4");
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command.testsrc().compose(&composition)?.output("recording.mp4");
+  /// assert!(command
+  ///   .get_args()
+  ///   .any(|arg| arg == "[2:v]scale=160:-2[pip];[1:v][pip]overlay=10:10[v]"));
+  ///
+  /// // There is no third input to take the audio from
+  /// let composition = composition.audio_from(2);
+  /// assert!(FfmpegCommand::new().compose(&composition).is_err());
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn compose(&mut self, composition: &Composition) -> anyhow::Result<&mut Self> {
+    let first_input = self.get_args().filter(|arg| *arg == "-i").count() as u32;
+    let filtergraph = composition.filtergraph(first_input)?;
+    for input in composition.inputs() {
+      self.input(input);
+    }
+    self
+      .filter_complex(filtergraph)
+      .map_str(format!("[{}]", Composition::OUTPUT_LABEL));
+    if let Some(audio_input) = composition.audio_input() {
+      self.map(first_input + audio_input, "a?");
+    }
+    Ok(self)
+  }
+
+  /// Add a camera and, optionally, a microphone as inputs, with the
+  /// input device of the platform, see [`CaptureOptions::inputs`].
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::capture::{CaptureBackend, CaptureOptions};
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let options = CaptureOptions::new().backend(CaptureBackend::AvFoundation);
+  /// let mut command = FfmpegCommand::new();
+  /// command.capture_input("0", Some("1"), &options);
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[args.len() - 8..],
+  ///   ["-thread_queue_size", "1024", "-f", "avfoundation", "-framerate", "30", "-i", "0:1"]
+  /// );
+  /// ```
+  pub fn capture_input(
+    &mut self,
+    video_device: &str,
+    audio_device: Option<&str>,
+    options: &CaptureOptions,
+  ) -> &mut Self {
+    for (input_options, url) in options.inputs(video_device, audio_device) {
+      self.args(input_options).input(url);
+    }
+    self
+  }
+
   //// Preset argument sets for common use cases.
 
   /// Generate a procedural test video. Equivalent to `ffmpeg -f lavfi -i
@@ -544,13 +1375,13 @@ impl FfmpegCommand {
   /// [FFmpeg `testsrc` filter
   /// documentation](https://ffmpeg.org/ffmpeg-filters.html#allrgb_002c-allyuv_002c-color_002c-colorchart_002c-colorspectrum_002c-haldclutsrc_002c-nullsrc_002c-pal75bars_002c-pal100bars_002c-rgbtestsrc_002c-smptebars_002c-smptehdbars_002c-testsrc_002c-testsrc2_002c-yuvtestsrc)
   pub fn testsrc(&mut self) -> &mut Self {
-    self.args(["-f", "lavfi", "-i", "testsrc=duration=10"]);
-    self
+    self.format("lavfi").input("testsrc=duration=10")
   }
 
   /// Preset for emitting raw decoded video frames on stdout. Equivalent to `-f
   /// rawvideo -pix_fmt rgb24 -`.
   pub fn rawvideo(&mut self) -> &mut Self {
+    self.flush_video_filters();
     self.args(["-f", "rawvideo", "-pix_fmt", "rgb24", "-"]);
     self
   }
@@ -561,11 +1392,34 @@ impl FfmpegCommand {
   /// 1. Pass `pipe:1` to the ffmpeg command ("output on stdout")
   /// 2. Set the `stdout` field of the inner `Command` to `Stdio::piped()`
   pub fn pipe_stdout(&mut self) -> &mut Self {
+    self.flush_video_filters();
     self.arg("-");
     self.inner.stdout(Stdio::piped());
     self
   }
 
+  /// Alias for `-nostdin`, also closing the stdin channel of the process.
+  ///
+  /// Ffmpeg then no longer reads interactive commands, so
+  /// [`FfmpegChild::quit`] and [`FfmpegChild::send_stdin_command`] fail with
+  /// [`MissingChannel::Stdin`](crate::child::MissingChannel::Stdin); stop
+  /// the process with [`FfmpegChild::kill`] instead. Useful when running in
+  /// the background of a shell, where reading from stdin would suspend the
+  /// process.
+  pub fn no_stdin(&mut self) -> &mut Self {
+    self.arg("-nostdin");
+    self.inner.stdin(Stdio::null());
+    self
+  }
+
+  /// Let the process write directly to the stdout of the parent process
+  /// instead of discarding it, e.g. to forward `-f mpegts -` output in a CLI
+  /// tool. Output chunks are then not available through the child.
+  pub fn inherit_stdout(&mut self) -> &mut Self {
+    self.inner.stdout(Stdio::inherit());
+    self
+  }
+
   /// Automatically applied in the constructor of `FfmpegCommand`. Configures
   /// logging with a level and format expected by the log parser.
   ///
@@ -588,9 +1442,20 @@ impl FfmpegCommand {
 
   /// Adds an argument to pass to the program.
   ///
-  /// Identical to `arg` in [`tokio::process::Command`].
+  /// Identical to `arg` in [`tokio::process::Command`], except that input
+  /// options queued with [`seek`](Self::seek) are placed before a `-i`, and
+  /// that a `-vf`, `-filter:v` or `-filter` argument can't be combined with
+  /// [`filter_video`](Self::filter_video) for the same output, see
+  /// [`filter`](Self::filter).
   pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
-    self.inner.arg(arg.as_ref());
+    let arg = arg.as_ref();
+    if arg == "-i" {
+      let input_options = std::mem::take(&mut self.input_options);
+      self.inner.args(input_options);
+    } else if let Some(flag) = arg.to_str().filter(|arg| is_video_filter_flag(arg)) {
+      self.raw_video_filter = Some(flag.to_string());
+    }
+    self.inner.arg(arg);
     self
   }
 
@@ -627,6 +1492,24 @@ impl FfmpegCommand {
     self
   }
 
+  /// Emits the queued video filters as a single `-filter:v`, ending the
+  /// options of the current output.
+  fn flush_video_filters(&mut self) -> &mut Self {
+    let raw_video_filter = self.raw_video_filter.take();
+    if !self.video_filters.is_empty() {
+      let chain = std::mem::take(&mut self.video_filters).join(",");
+      if let Some(flag) = raw_video_filter {
+        self.video_filter_conflict.get_or_insert_with(|| {
+          format!("`{flag}` can't be combined with the video filters `{chain}` of the same output, add all of them with `filter_video`")
+        });
+      }
+      // Bypasses `arg`, which would take it for a raw video filter
+      self.inner.arg("-filter:v");
+      self.inner.arg(chain);
+    }
+    self
+  }
+
   /// Spawn the ffmpeg command as a child process, wrapping it in a
   /// `FfmpegChild` interface.
   ///
@@ -636,8 +1519,107 @@ impl FfmpegCommand {
   ///
   /// Identical to `spawn` in [`tokio::process::Command`].
   pub fn spawn(&mut self) -> io::Result<FfmpegChild> {
+    if !self.input_options.is_empty() {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+          "input options {:?} are not followed by an input",
+          self.input_options
+        ),
+      ));
+    }
+    if !self.video_filters.is_empty() {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+          "video filters {:?} are not followed by an output",
+          self.video_filters
+        ),
+      ));
+    }
+    if let Some(conflict) = &self.video_filter_conflict {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        conflict.clone(),
+      ));
+    }
+
     self.prevent_overwrite_prompt();
-    self.inner.spawn().map(FfmpegChild::from_inner)
+    let options = self.child_options.clone();
+    FfmpegChild::from_inner(self.inner.spawn()?, options)
+  }
+
+  /// Controls what happens to the Ffmpeg process when the spawned
+  /// [`FfmpegChild`] is dropped, e.g. because the task owning it was
+  /// cancelled.
+  ///
+  /// - `false` (the default): the process is detached and keeps running until
+  ///   it finishes on its own. Tokio reaps it in the background on a best
+  ///   effort basis.
+  /// - `true`: the process is forcibly killed. Ffmpeg gets no chance to
+  ///   flush its buffers or write a trailer, so the output may be truncated
+  ///   or unplayable; call [`FfmpegChild::quit_with_timeout`] first for a
+  ///   clean shutdown.
+  ///
+  /// Identical to `kill_on_drop` in [`tokio::process::Command`].
+  pub fn kill_on_drop(&mut self, kill_on_drop: bool) -> &mut Self {
+    self.child_options.kill_on_drop = kill_on_drop;
+    self.inner.kill_on_drop(kill_on_drop);
+    self
+  }
+
+  /// Spawn Ffmpeg in its own process group (Unix) or Job Object (Windows),
+  /// so that [`FfmpegChild::terminate_group`] also kills any helper
+  /// processes it launches.
+  ///
+  /// On Windows, the Job Object additionally kills the process tree when the
+  /// parent process exits or crashes, so nothing is orphaned. Unix has no
+  /// portable equivalent.
+  ///
+  /// Being in its own process group, Ffmpeg no longer receives the `SIGINT`
+  /// sent to the parent by Ctrl+C in a terminal.
+  pub fn process_group(&mut self) -> &mut Self {
+    self.child_options.process_group = true;
+    crate::process_group::configure(&mut self.inner);
+    self
+  }
+
+  /// Bound the wall-clock duration of the whole job, measured from `spawn`.
+  ///
+  /// Once the timeout expires, the process is killed and the event stream
+  /// yields [`FfmpegEvent::TimedOut`](crate::event::FfmpegEvent::TimedOut)
+  /// and ends. Without an event stream, the process is killed by the next
+  /// call to [`FfmpegChild::wait`] or when the child is dropped.
+  ///
+  /// Unlike [`duration`](Self::duration), this is not an Ffmpeg option and
+  /// does not affect the output.
+  ///
+  /// ## Example
+  ///
+  /// ```rust,no_run
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use futures_util::StreamExt;
+  /// use std::time::Duration;
+  ///
+  /// # async fn run() -> anyhow::Result<()> {
+  /// let mut child = FfmpegCommand::new()
+  ///   .input("input.mp4")
+  ///   .output("output.webm")
+  ///   .timeout(Duration::from_secs(60))
+  ///   .spawn()?;
+  ///
+  /// let timed_out = child
+  ///   .stream()?
+  ///   .any(|event| async move { matches!(event, FfmpegEvent::TimedOut(_)) })
+  ///   .await;
+  /// let exit_status = child.wait().await?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+    self.child_options.timeout = Some(timeout);
+    self
   }
 
   /// Print a command that can be copy-pasted to run in the terminal. Requires
@@ -695,7 +1677,16 @@ impl FfmpegCommand {
     inner.stderr(Stdio::piped());
     inner.stdout(Stdio::null());
 
-    let mut ffmpeg_command = Self { inner };
+    let mut ffmpeg_command = Self {
+      inner,
+      child_options: ChildOptions::default(),
+      input_options: Vec::new(),
+      video_filters: Vec::new(),
+      raw_video_filter: None,
+      video_filter_conflict: None,
+      inputs: Vec::new(),
+      image_start_number: None,
+    };
     ffmpeg_command.set_expected_loglevel();
     ffmpeg_command.create_no_window();
 
@@ -715,6 +1706,40 @@ impl FfmpegCommand {
   }
 }
 
+/// The `-map` argument selecting `stream_spec` of input `input_index`.
+pub(crate) fn map_spec(input_index: u32, stream_spec: &str) -> String {
+  match stream_spec {
+    "" => input_index.to_string(),
+    spec => format!("{input_index}:{spec}"),
+  }
+}
+
+/// Whether `arg` sets the filtergraph of the video streams of an output.
+fn is_video_filter_flag(arg: &str) -> bool {
+  matches!(arg, "-vf" | "-filter" | "-filter:v") || arg.starts_with("-filter:v:")
+}
+
+pub(crate) fn scale_filter(width: i32, height: i32) -> String {
+  format!("scale={width}:{height}")
+}
+
+pub(crate) fn fps_filter(fps: f32) -> String {
+  format!("fps={fps}")
+}
+
+pub(crate) fn crop_filter(width: u32, height: u32, x: u32, y: u32) -> String {
+  format!("crop={width}:{height}:{x}:{y}")
+}
+
+pub(crate) fn pad_filter(width: u32, height: u32, x: u32, y: u32) -> String {
+  format!("pad={width}:{height}:{x}:{y}")
+}
+
+/// Escapes `value` for use as a filter option value inside a filtergraph.
+pub(crate) fn filter_value(value: &str) -> String {
+  escape(&escape(value, ":="), "[],;")
+}
+
 impl Default for FfmpegCommand {
   fn default() -> Self {
     Self::new()
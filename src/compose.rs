@@ -0,0 +1,224 @@
+//! Composition of several videos into one picture.
+//!
+//! A [`Composition`] lays out its inputs either as a picture-in-picture, one
+//! video inset into a corner of another, or as a grid built with `xstack`.
+//! [`FfmpegCommand::compose`] adds the inputs, the generated
+//! `-filter_complex` graph and the `-map` arguments to a command.
+//!
+//! [`FfmpegCommand::compose`]: crate::command::FfmpegCommand::compose
+
+use crate::command::OverlayPosition;
+
+/// How the inputs of a [`Composition`] are arranged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+  /// The second input, scaled to `width` pixels wide, drawn over the first
+  /// at `position`, `margin` pixels away from the edges. The margin is
+  /// ignored for [`OverlayPosition::Centered`] and
+  /// [`OverlayPosition::Offset`].
+  PictureInPicture {
+    position: OverlayPosition,
+    width: u32,
+    margin: u32,
+  },
+  /// Up to `columns`x`rows` inputs in row-major order, each scaled to fit a
+  /// `cell_width`x`cell_height` cell and padded with black. Empty cells are
+  /// black.
+  Grid {
+    columns: u32,
+    rows: u32,
+    cell_width: u32,
+    cell_height: u32,
+  },
+}
+
+/// Several video inputs composed into one picture according to a
+/// [`Layout`], with the audio of one of the inputs.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::command::{FfmpegCommand, OverlayPosition};
+/// use async_ffmpeg_sidecar::compose::{Composition, Layout};
+///
+/// let composition = Composition::new(Layout::PictureInPicture {
+///   position: OverlayPosition::BottomRight,
+///   width: 480,
+///   margin: 20,
+/// })
+/// .input("screen.mp4")
+/// .input("camera.mp4");
+///
+/// let mut command = FfmpegCommand::new();
+/// command.compose(&composition)?.output("recording.mp4");
+///
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert_eq!(
+///   args[args.len() - 7..],
+///   [
+///     "-filter_complex",
+///     "[1:v]scale=480:-2[pip];[0:v][pip]overlay=W-w-20:H-h-20[v]",
+///     "-map",
+///     "[v]",
+///     "-map",
+///     "0:a?",
+///     "recording.mp4",
+///   ]
+/// );
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Composition {
+  layout: Layout,
+  inputs: Vec<String>,
+  audio_input: Option<u32>,
+}
+
+impl Composition {
+  /// The label of the composed video in [`filtergraph`](Self::filtergraph).
+  pub const OUTPUT_LABEL: &'static str = "v";
+
+  /// An empty composition, taking the audio of its first input.
+  pub fn new(layout: Layout) -> Self {
+    Self {
+      layout,
+      inputs: Vec::new(),
+      audio_input: Some(0),
+    }
+  }
+
+  /// Add an input file or URL. For a picture-in-picture, the first input is
+  /// the background and the second the inset.
+  pub fn input<S: AsRef<str>>(mut self, path: S) -> Self {
+    self.inputs.push(path.as_ref().to_string());
+    self
+  }
+
+  /// Take the audio of input `index` of the composition, if it has any,
+  /// instead of the first one.
+  pub fn audio_from(mut self, index: u32) -> Self {
+    self.audio_input = Some(index);
+    self
+  }
+
+  /// Leave the audio out.
+  pub fn no_audio(mut self) -> Self {
+    self.audio_input = None;
+    self
+  }
+
+  /// The input files, in order.
+  pub fn inputs(&self) -> &[String] {
+    &self.inputs
+  }
+
+  /// The input whose audio is kept.
+  pub fn audio_input(&self) -> Option<u32> {
+    self.audio_input
+  }
+
+  /// The `-filter_complex` graph producing the composed video on the
+  /// [`OUTPUT_LABEL`](Self::OUTPUT_LABEL) output, where `first_input` is the
+  /// index of the first input of the composition in the command.
+  ///
+  /// Fails if a picture-in-picture doesn't have exactly two inputs, if a
+  /// grid has fewer than two inputs or more than it has cells, or if the
+  /// input of [`audio_from`](Self::audio_from) doesn't exist.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::compose::{Composition, Layout};
+  ///
+  /// let composition = Composition::new(Layout::Grid {
+  ///   columns: 2,
+  ///   rows: 2,
+  ///   cell_width: 640,
+  ///   cell_height: 360,
+  /// })
+  /// .input("a.mp4")
+  /// .input("b.mp4")
+  /// .input("c.mp4");
+  ///
+  /// assert_eq!(
+  ///   composition.filtergraph(0)?,
+  ///   "[0:v]scale=640:360:force_original_aspect_ratio=decrease,\
+  ///    pad=640:360:(ow-iw)/2:(oh-ih)/2,setsar=1[c0];\
+  ///    [1:v]scale=640:360:force_original_aspect_ratio=decrease,\
+  ///    pad=640:360:(ow-iw)/2:(oh-ih)/2,setsar=1[c1];\
+  ///    [2:v]scale=640:360:force_original_aspect_ratio=decrease,\
+  ///    pad=640:360:(ow-iw)/2:(oh-ih)/2,setsar=1[c2];\
+  ///    [c0][c1][c2]xstack=inputs=3:layout=0_0|640_0|0_360:fill=black[v]"
+  /// );
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn filtergraph(&self, first_input: u32) -> anyhow::Result<String> {
+    let count = self.inputs.len();
+    if let Some(audio_input) = self.audio_input {
+      anyhow::ensure!(
+        (audio_input as usize) < count,
+        "the audio input {audio_input} is out of range for {count} inputs"
+      );
+    }
+    match self.layout {
+      Layout::PictureInPicture {
+        position,
+        width,
+        margin,
+      } => {
+        anyhow::ensure!(count == 2, "picture-in-picture needs 2 inputs, got {count}");
+        Ok(format!(
+          "[{}:v]scale={width}:-2[pip];[{first_input}:v][pip]overlay={}[{}]",
+          first_input + 1,
+          inset_position(position, margin),
+          Self::OUTPUT_LABEL
+        ))
+      }
+      Layout::Grid {
+        columns,
+        rows,
+        cell_width,
+        cell_height,
+      } => {
+        let cells = (columns * rows) as usize;
+        anyhow::ensure!(
+          (2..=cells).contains(&count),
+          "a {columns}x{rows} grid needs 2 to {cells} inputs, got {count}"
+        );
+
+        let mut chains = Vec::new();
+        let mut labels = String::new();
+        let mut layout = Vec::new();
+        for i in 0..count as u32 {
+          chains.push(format!(
+            "[{}:v]scale={cell_width}:{cell_height}:force_original_aspect_ratio=decrease,\
+             pad={cell_width}:{cell_height}:(ow-iw)/2:(oh-ih)/2,setsar=1[c{i}]",
+            first_input + i
+          ));
+          labels.push_str(&format!("[c{i}]"));
+          layout.push(format!(
+            "{}_{}",
+            i % columns * cell_width,
+            i / columns * cell_height
+          ));
+        }
+        chains.push(format!(
+          "{labels}xstack=inputs={count}:layout={}:fill=black[{}]",
+          layout.join("|"),
+          Self::OUTPUT_LABEL
+        ));
+        Ok(chains.join(";"))
+      }
+    }
+  }
+}
+
+/// The `x:y` options of the `overlay` filter placing the inset `margin`
+/// pixels away from the edges.
+fn inset_position(position: OverlayPosition, margin: u32) -> String {
+  match position {
+    OverlayPosition::TopLeft => format!("{margin}:{margin}"),
+    OverlayPosition::TopRight => format!("W-w-{margin}:{margin}"),
+    OverlayPosition::BottomLeft => format!("{margin}:H-h-{margin}"),
+    OverlayPosition::BottomRight => format!("W-w-{margin}:H-h-{margin}"),
+    OverlayPosition::Centered | OverlayPosition::Offset { .. } => position.overlay_options(),
+  }
+}
@@ -46,20 +46,22 @@ pub fn ffmpeg_download_url() -> Result<&'static str> {
 
 /// Check if FFmpeg is installed, and if it's not, download and unpack it.
 /// Automatically selects the correct binaries for Windows, Linux, and MacOS.
-/// The binaries will be placed in the same directory as the Rust executable.
+/// The binaries will be placed in the same directory as the Rust executable,
+/// or in the per-user cache directory if that one is read-only (see
+/// [`download_dir`](crate::paths::download_dir)).
 ///
 /// If FFmpeg is already installed, the method exits early without downloading
 /// anything.
 #[cfg(feature = "download_ffmpeg")]
 pub async fn auto_download() -> Result<()> {
-  use crate::{command::ffmpeg_is_installed, paths::sidecar_dir};
+  use crate::{command::ffmpeg_is_installed, paths::download_dir};
 
   if ffmpeg_is_installed().await {
     return Ok(());
   }
 
   let download_url = ffmpeg_download_url()?;
-  let destination = sidecar_dir()?;
+  let destination = download_dir()?;
   tokio::fs::create_dir_all(&destination).await?;
   let archive_path = download_ffmpeg_package(download_url, &destination).await?;
   unpack_ffmpeg(&archive_path, &destination).await?;
@@ -139,6 +141,81 @@ pub async fn check_latest_version() -> Result<String> {
   }
 }
 
+/// The result of comparing the installed FFmpeg version against the latest
+/// published release, as returned by [`check_for_update`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateStatus {
+  /// Version reported by `ffmpeg -version`, e.g. `7.0.2-static`.
+  pub installed: String,
+  /// Latest version available online, e.g. `7.1`.
+  pub latest: String,
+  /// Whether `latest` is strictly newer than `installed`. Always `false` when
+  /// either version cannot be compared, e.g. for git snapshot builds.
+  pub update_available: bool,
+}
+
+/// Compare two FFmpeg version strings by their leading numeric components,
+/// ignoring build suffixes such as `-static` or `-essentials_build-www.gyan.dev`.
+/// Missing components are treated as zero, so `7.1` equals `7.1.0`.
+///
+/// Returns `None` if either string does not start with a release number, as
+/// is the case for git snapshot builds (`N-109875-geabc304d12` or
+/// `2023-01-18-git-ba36e6ed52`).
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::download::compare_versions;
+/// use std::cmp::Ordering;
+///
+/// assert_eq!(compare_versions("7.0.2-static", "7.1"), Some(Ordering::Less));
+/// assert_eq!(compare_versions("7.1-essentials_build-www.gyan.dev", "7.1"), Some(Ordering::Equal));
+/// assert_eq!(compare_versions("6.1.1", "6.1"), Some(Ordering::Greater));
+/// assert_eq!(compare_versions("N-109875-geabc304d12", "7.1"), None);
+/// ```
+pub fn compare_versions(a: &str, b: &str) -> Option<std::cmp::Ordering> {
+  fn components(version: &str) -> Option<Vec<u64>> {
+    // Git snapshot builds are dated, e.g. `2023-01-18-git-...`
+    if version.contains("-git-") {
+      return None;
+    }
+
+    let numeric = version
+      .trim()
+      .split(|c: char| !c.is_ascii_digit() && c != '.')
+      .next()?;
+
+    numeric
+      .split('.')
+      .map(|part| part.parse::<u64>().ok())
+      .collect()
+  }
+
+  let mut a = components(a)?;
+  let mut b = components(b)?;
+  let len = a.len().max(b.len());
+  a.resize(len, 0);
+  b.resize(len, 0);
+
+  Some(a.cmp(&b))
+}
+
+/// Check whether a newer FFmpeg release is available than the one currently
+/// installed, combining [`ffmpeg_version`](crate::version::ffmpeg_version)
+/// with [`check_latest_version`].
+#[cfg(feature = "download_ffmpeg")]
+pub async fn check_for_update() -> Result<UpdateStatus> {
+  use crate::version::ffmpeg_version;
+
+  let installed = ffmpeg_version().await?;
+  let latest = check_latest_version().await?;
+  let update_available = compare_versions(&installed, &latest) == Some(std::cmp::Ordering::Less);
+
+  Ok(UpdateStatus {
+    installed,
+    latest,
+    update_available,
+  })
+}
+
 /// Make an HTTP request to download an archive from the latest published release online
 #[cfg(feature = "download_ffmpeg")]
 pub async fn download_ffmpeg_package(url: &str, download_dir: &Path) -> Result<PathBuf> {
@@ -251,9 +328,63 @@ pub async fn unpack_ffmpeg(from_archive: &PathBuf, binary_folder: &Path) -> Resu
     remove_file(from_archive).await?;
   }
 
+  crate::paths::invalidate_path_cache();
   Ok(())
 }
 
+/// Delete the FFmpeg binaries installed by [`auto_download`] next to the Rust
+/// executable or in the per-user cache directory, along with any `ffmpeg_release_temp` directory left behind by
+/// an interrupted [`unpack_ffmpeg`].
+///
+/// Returns the paths that were removed. Binaries installed elsewhere (e.g. in
+/// the system `PATH`) are never touched.
+#[cfg(feature = "download_ffmpeg")]
+pub async fn remove_downloaded_ffmpeg() -> Result<Vec<PathBuf>> {
+  use crate::paths::{sidecar_cache_dir, sidecar_dir};
+
+  let mut removed = remove_ffmpeg_from(&sidecar_dir()?).await?;
+  if let Some(cache_dir) = sidecar_cache_dir() {
+    removed.extend(remove_ffmpeg_from(&cache_dir).await?);
+  }
+
+  Ok(removed)
+}
+
+/// Lower level variant of [`remove_downloaded_ffmpeg`] that cleans up a
+/// custom `binary_folder`, as previously passed to [`unpack_ffmpeg`].
+#[cfg(feature = "download_ffmpeg")]
+pub async fn remove_ffmpeg_from(binary_folder: &Path) -> Result<Vec<PathBuf>> {
+  use anyhow::Context;
+  use tokio::fs::{remove_dir_all, remove_file};
+
+  let mut removed = Vec::new();
+
+  for name in ["ffmpeg", "ffprobe", "ffplay"] {
+    let mut path = binary_folder.join(name);
+    if cfg!(windows) {
+      path.set_extension("exe");
+    }
+
+    if path.is_file() {
+      remove_file(&path)
+        .await
+        .with_context(|| format!("failed to remove {}", path.display()))?;
+      removed.push(path);
+    }
+  }
+
+  let temp_folder = binary_folder.join(UNPACK_DIRNAME);
+  if temp_folder.is_dir() {
+    remove_dir_all(&temp_folder)
+      .await
+      .with_context(|| format!("failed to remove {}", temp_folder.display()))?;
+    removed.push(temp_folder);
+  }
+
+  crate::paths::invalidate_path_cache();
+  Ok(removed)
+}
+
 #[cfg(feature = "download_ffmpeg")]
 async fn move_bin(path: &Path, binary_folder: &Path) -> Result<()> {
   use anyhow::Context;
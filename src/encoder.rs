@@ -0,0 +1,169 @@
+//! Typed values for common encoder options.
+//!
+//! Every enum implements `AsRef<str>`, so it can be passed wherever a string
+//! is accepted, e.g. to [`FfmpegCommand::preset`], [`FfmpegCommand::tune`]
+//! and [`FfmpegCommand::profile`], while typos are caught at compile time.
+//!
+//! ```rust
+//! use async_ffmpeg_sidecar::command::FfmpegCommand;
+//! use async_ffmpeg_sidecar::encoder::{Preset, Profile, Tune};
+//!
+//! let mut command = FfmpegCommand::new();
+//! command
+//!   .codec_video("libx264")
+//!   .preset(Preset::Slow)
+//!   .tune(Tune::Film)
+//!   .profile(Profile::High);
+//!
+//! let args = command.get_args().collect::<Vec<_>>();
+//! assert_eq!(
+//!   args[args.len() - 8..],
+//!   ["-c:v", "libx264", "-preset:v", "slow", "-tune:v", "film", "-profile:v", "high"]
+//! );
+//! ```
+//!
+//! [`FfmpegCommand::preset`]: crate::command::FfmpegCommand::preset
+//! [`FfmpegCommand::tune`]: crate::command::FfmpegCommand::tune
+//! [`FfmpegCommand::profile`]: crate::command::FfmpegCommand::profile
+
+/// Speed/compression presets of `libx264` and `libx265`, from fastest to
+/// best compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+  Ultrafast,
+  Superfast,
+  Veryfast,
+  Faster,
+  Fast,
+  /// The default.
+  Medium,
+  Slow,
+  Slower,
+  Veryslow,
+  Placebo,
+}
+
+impl AsRef<str> for Preset {
+  fn as_ref(&self) -> &str {
+    match self {
+      Preset::Ultrafast => "ultrafast",
+      Preset::Superfast => "superfast",
+      Preset::Veryfast => "veryfast",
+      Preset::Faster => "faster",
+      Preset::Fast => "fast",
+      Preset::Medium => "medium",
+      Preset::Slow => "slow",
+      Preset::Slower => "slower",
+      Preset::Veryslow => "veryslow",
+      Preset::Placebo => "placebo",
+    }
+  }
+}
+
+/// Presets of the NVIDIA encoders (`h264_nvenc`, `hevc_nvenc`, `av1_nvenc`),
+/// from fastest (`P1`) to best quality (`P7`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NvencPreset {
+  P1,
+  P2,
+  P3,
+  P4,
+  P5,
+  P6,
+  P7,
+}
+
+impl AsRef<str> for NvencPreset {
+  fn as_ref(&self) -> &str {
+    match self {
+      NvencPreset::P1 => "p1",
+      NvencPreset::P2 => "p2",
+      NvencPreset::P3 => "p3",
+      NvencPreset::P4 => "p4",
+      NvencPreset::P5 => "p5",
+      NvencPreset::P6 => "p6",
+      NvencPreset::P7 => "p7",
+    }
+  }
+}
+
+/// Content tunings of `libx264` and `libx265`. `Film` and `StillImage` are
+/// only supported by `libx264`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tune {
+  Film,
+  Animation,
+  Grain,
+  StillImage,
+  FastDecode,
+  ZeroLatency,
+  Psnr,
+  Ssim,
+}
+
+impl AsRef<str> for Tune {
+  fn as_ref(&self) -> &str {
+    match self {
+      Tune::Film => "film",
+      Tune::Animation => "animation",
+      Tune::Grain => "grain",
+      Tune::StillImage => "stillimage",
+      Tune::FastDecode => "fastdecode",
+      Tune::ZeroLatency => "zerolatency",
+      Tune::Psnr => "psnr",
+      Tune::Ssim => "ssim",
+    }
+  }
+}
+
+/// Tunings of the NVIDIA encoders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NvencTune {
+  /// High quality (the default).
+  HighQuality,
+  /// Low latency.
+  LowLatency,
+  /// Ultra low latency.
+  UltraLowLatency,
+  Lossless,
+}
+
+impl AsRef<str> for NvencTune {
+  fn as_ref(&self) -> &str {
+    match self {
+      NvencTune::HighQuality => "hq",
+      NvencTune::LowLatency => "ll",
+      NvencTune::UltraLowLatency => "ull",
+      NvencTune::Lossless => "lossless",
+    }
+  }
+}
+
+/// Codec profiles, restricting the features used by the encoder for
+/// compatibility with decoders. `Baseline`, `High` and the `High*` variants
+/// are H.264 profiles, `Main10` is an HEVC profile, and `Main` exists for
+/// both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+  Baseline,
+  Main,
+  High,
+  High10,
+  High422,
+  High444,
+  Main10,
+}
+
+impl AsRef<str> for Profile {
+  fn as_ref(&self) -> &str {
+    match self {
+      Profile::Baseline => "baseline",
+      Profile::Main => "main",
+      Profile::High => "high",
+      Profile::High10 => "high10",
+      Profile::High422 => "high422",
+      Profile::High444 => "high444",
+      Profile::Main10 => "main10",
+    }
+  }
+}
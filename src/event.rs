@@ -1,20 +1,84 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
 /// Any event that occurs during the execution of an Ffmpeg command,
 /// inluding log messages, parsed metadata, progress updates, and output.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FfmpegEvent {
   ParsedVersion(FfmpegVersion),
   ParsedConfiguration(FfmpegConfiguration),
-  ParsedStreamMapping(String),
+  ParsedStreamMapping(FfmpegStreamMapping),
   ParsedInput(FfmpegInput),
   ParsedOutput(FfmpegOutput),
   ParsedInputStream(FfmpegStream),
   ParsedOutputStream(FfmpegStream),
   ParsedDuration(FfmpegDuration),
+  /// An entry of a `Metadata:` block, e.g. the `title` of an input or the
+  /// `handler_name` of a stream.
+  ParsedMetadata(MetadataEntry),
+  /// A chapter of an input, listed below its `Chapters:` header.
+  ParsedChapter(FfmpegChapter),
+  /// An entry of the `Side data:` block of a stream, e.g. its display
+  /// matrix.
+  SideData(FfmpegSideData),
+  /// A log line that wasn't parsed into another event, with its level. See
+  /// [`parse_log_line`](crate::log_parser::parse_log_line) for its
+  /// component and message.
   Log(LogLevel, String),
+  /// An error caused by a component that is missing from the FFmpeg build,
+  /// such as an encoder only available in GPL builds.
+  BuildCapabilityError(BuildCapabilityError),
+  /// The output container cannot hold a stream's codec, typically when
+  /// remuxing with stream copy, e.g. PCM audio into MP4.
+  UnsupportedContainerCodec(UnsupportedContainerCodec),
+  /// Ffmpeg reported `Output file is empty, nothing was encoded`. The process
+  /// typically still exits successfully. Contains the raw log message.
+  EmptyOutput(String),
   LogEOF,
-  /// An error that didn't originate from the ffmpeg logs
-  Error(String),
+  /// An `[error]` or `[fatal]` log line with a recognized cause, see
+  /// [`ErrorKind`], or an error that didn't originate from the ffmpeg logs,
+  /// with [`ErrorKind::Other`].
+  Error {
+    kind: ErrorKind,
+    message: String,
+  },
+  /// No progress was reported within the window of a
+  /// [`WatchdogStream`](crate::watchdog::WatchdogStream), which then killed
+  /// the process.
+  Stalled(Duration),
+  /// The job exceeded the
+  /// [`timeout`](crate::command::FfmpegCommand::timeout) configured on its
+  /// command, and the process was killed. No further events follow.
+  TimedOut(Duration),
   Progress(FfmpegProgress),
+  /// A block of machine-readable progress written by `-progress`, see
+  /// [`FfmpegCommand::progress_feed`](crate::command::FfmpegCommand::progress_feed).
+  ProgressFeed(FfmpegProgressFeed),
+  /// A segmenting muxer such as [HLS](crate::hls) or
+  /// [segment](crate::segment) started writing a segment.
+  OutputSegmentOpened(FfmpegOutputSegment),
+  /// A segment is complete: the muxer moved on to the next segment, or
+  /// Ffmpeg exited.
+  OutputSegmentClosed(FfmpegOutputSegment),
+  /// An image of a sequence started with
+  /// [`FfmpegCommand::output_image_sequence`](crate::command::FfmpegCommand::output_image_sequence)
+  /// was written.
+  OutputImageWritten(FfmpegOutputImage),
+  /// The measurements printed by the `loudnorm` filter with
+  /// `print_format=json`, see [`audio`](crate::audio).
+  LoudnormStats(LoudnormStats),
+  /// A black part of the video found by the `blackdetect` filter, see
+  /// [`video::detect_black`](crate::video::detect_black).
+  BlackInterval(BlackInterval),
+  /// The sizes written per stream type, printed for each output when
+  /// Ffmpeg finishes.
+  EncodeSummary(EncodeSummary),
+  /// A line recognized by a matcher registered with
+  /// [`FfmpegLogParser::add_matcher`](crate::log_parser::FfmpegLogParser::add_matcher).
+  Custom(CustomEvent),
   // Not parsing output frames for now
   // OutputFrame(OutputVideoFrame),
   /// A chunk of data that may not correspond to a complete frame.
@@ -27,29 +91,271 @@ pub enum FfmpegEvent {
 
 /// The internal log level designated by FFmpeg on each message.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LogLevel {
   Info,
   Warning,
   Error,
+  /// Also used for `[panic]` messages
   Fatal,
+  Verbose,
+  Debug,
+  Trace,
   Unknown,
 }
 
+/// A log line split into the prefixes added by `-loglevel level` and the
+/// message, see [`parse_log_line`](crate::log_parser::parse_log_line).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegLogLine {
+  pub level: LogLevel,
+  /// The component that logged the message, e.g. `[libx264 @ 0x55d0c8a4b2c0]`
+  pub component: Option<LogComponent>,
+  /// The message, without prefixes nor line ending
+  pub message: String,
+}
+
+/// The `[name @ address]` prefix of a log line. Messages of a component
+/// nested in another one, such as a decoder opened by a demuxer, are
+/// prefixed with both; this is the innermost one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogComponent {
+  /// e.g. `libx264`, `hls` or `in#0`
+  pub name: String,
+  /// The address of the component's context, e.g. `0x55d0c8a4b2c0`, which
+  /// tells apart several instances of the same component
+  pub address: String,
+}
+
+/// The cause of an [`FfmpegEvent::Error`], so that applications can branch
+/// on it without matching the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorKind {
+  /// `No such file or directory`, e.g. a missing input
+  NoSuchFile,
+  /// `Permission denied`, e.g. an output in a read-only directory
+  PermissionDenied,
+  /// `Invalid argument`, or an unrecognized option or option value
+  InvalidArgument,
+  /// `Unknown encoder` with a name FFmpeg doesn't know, e.g. a typo.
+  /// Known encoders missing from the build are reported as
+  /// [`FfmpegEvent::BuildCapabilityError`] instead.
+  UnknownEncoder,
+  /// `Connection refused` by a network input or output
+  ConnectionRefused,
+  /// `moov atom not found`, typically an MP4 file that wasn't finalized
+  MoovAtomNotFound,
+  /// `Device or resource busy`, e.g. a camera used by another process
+  DeviceBusy,
+  /// `Invalid data found when processing input`, a corrupt or unsupported
+  /// input
+  InvalidData,
+  /// Any other error
+  Other,
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegInput {
   pub index: u32,
+  /// The name of the demuxer, e.g. `lavfi`, or the list of formats it
+  /// handles, e.g. `mov,mp4,m4a,3gp,3g2,mj2`
+  pub format: String,
+  /// The path or URL of the input
+  pub from: String,
+  /// Duration in seconds, filled in by
+  /// [`FfmpegMetadata`](crate::metadata::FfmpegMetadata) from the
+  /// [`FfmpegDuration`] of the input
   pub duration: Option<f64>,
+  /// Start time in seconds, filled in like `duration`
+  pub start_time: Option<f64>,
+  /// Overall bitrate in kilobits per second, filled in like `duration`
+  pub bitrate_kbps: Option<u32>,
   pub raw_log_message: String,
 }
 
+/// The `Duration:` line of an input.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegDuration {
   pub input_index: u32,
-  pub duration: f64,
+  /// Duration in seconds, `None` for `N/A`, e.g. for live, piped or `lavfi`
+  /// inputs
+  pub duration: Option<f64>,
+  /// Start time in seconds, if known
+  pub start_time: Option<f64>,
+  /// Overall bitrate in kilobits per second, if known
+  pub bitrate_kbps: Option<u32>,
+  pub raw_log_message: String,
+}
+
+/// A chapter of an input. Times are in seconds.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegChapter {
+  pub input_index: u32,
+  /// The index of the chapter inside the input.
+  pub index: u32,
+  pub start: f64,
+  pub end: f64,
+  /// The `title` metadata entry of the chapter, filled in by
+  /// [`FfmpegMetadata`](crate::metadata::FfmpegMetadata).
+  pub title: Option<String>,
+  pub raw_log_message: String,
+}
+
+/// What an entry of a `Metadata:` or `Side data:` block belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MetadataScope {
+  Input(u32),
+  Output(u32),
+  InputStream { input: u32, stream: u32 },
+  OutputStream { output: u32, stream: u32 },
+  Chapter { input: u32, chapter: u32 },
+}
+
+/// An entry of the `Side data:` block of a stream, such as
+/// `displaymatrix: rotation of -90.00 degrees`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegSideData {
+  pub scope: MetadataScope,
+  /// The type of side data, e.g. `displaymatrix`, `cpb` or `Mastering
+  /// Display Metadata`
+  pub kind: String,
+  /// The rest of the line, e.g. `rotation of -90.00 degrees`
+  pub value: String,
+  pub raw_log_message: String,
+}
+
+impl FfmpegSideData {
+  /// The rotation in degrees of a display matrix, counterclockwise. Phones
+  /// typically record portrait video as landscape frames rotated by `-90`
+  /// or `90` degrees.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::{FfmpegSideData, MetadataScope};
+  ///
+  /// let side_data = FfmpegSideData {
+  ///   scope: MetadataScope::InputStream { input: 0, stream: 0 },
+  ///   kind: "displaymatrix".to_string(),
+  ///   value: "rotation of -90.00 degrees".to_string(),
+  ///   raw_log_message: String::new(),
+  /// };
+  /// assert_eq!(side_data.rotation(), Some(-90.0));
+  /// ```
+  pub fn rotation(&self) -> Option<f64> {
+    let kind = self.kind.to_ascii_lowercase().replace(' ', "");
+    if kind != "displaymatrix" {
+      return None;
+    }
+    self
+      .value
+      .strip_prefix("rotation of ")?
+      .strip_suffix("degrees")?
+      .trim()
+      .parse()
+      .ok()
+  }
+}
+
+/// One `key : value` line of a `Metadata:` block. Values spanning several
+/// lines are reported as one entry per line, with the same key.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetadataEntry {
+  pub scope: MetadataScope,
+  pub key: String,
+  pub value: String,
   pub raw_log_message: String,
 }
 
+/// One line of the `Stream mapping:` section, connecting an input stream
+/// (or filtergraph) to an output stream (or filtergraph).
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegStreamMapping {
+  /// Input index and stream index, or `None` if the stream comes out of a
+  /// filtergraph.
+  pub input: Option<(u32, u32)>,
+  /// Output index and stream index, or `None` if the stream goes into a
+  /// filtergraph.
+  pub output: Option<(u32, u32)>,
+  pub conversion: StreamConversion,
+  pub raw_log_message: String,
+}
+
+impl FfmpegStreamMapping {
+  /// Whether the stream is copied without re-encoding.
+  pub fn is_copy(&self) -> bool {
+    self.conversion == StreamConversion::Copy
+  }
+
+  /// The decoder of a transcoded stream, e.g. `h264 (native)`.
+  pub fn decoder(&self) -> Option<&str> {
+    match &self.conversion {
+      StreamConversion::Transcode { decoder, .. } => Some(decoder),
+      _ => None,
+    }
+  }
+
+  /// The encoder of a transcoded stream, e.g. `hevc (libx265)`.
+  pub fn encoder(&self) -> Option<&str> {
+    match &self.conversion {
+      StreamConversion::Transcode { encoder, .. } => Some(encoder),
+      _ => None,
+    }
+  }
+}
+
+/// How a mapped stream is processed, as shown in parentheses after the
+/// mapping.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StreamConversion {
+  /// `(copy)`: the stream is copied without re-encoding.
+  Copy,
+  /// E.g. `(h264 (native) -> hevc (libx265))`: the stream is decoded and
+  /// re-encoded.
+  Transcode { decoder: String, encoder: String },
+  /// Any other annotation, e.g. only the codec of a stream feeding or fed by
+  /// a filtergraph. Empty if there is none.
+  Other(String),
+}
+
+/// A file written by a segmenting muxer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegOutputSegment {
+  /// The path of the segment, as passed to the muxer.
+  pub path: String,
+  /// The position of the segment among those written by this process,
+  /// starting at 0. Unlike the number in the file name, this does not take
+  /// options such as `-segment_start_number` into account.
+  pub sequence: u32,
+}
+
+/// An image written by the `image2` muxer.
+///
+/// The muxer only logs the files it opens at debug level, so the path is
+/// derived from the pattern and the number of frames in the progress
+/// updates, and reported with the progress update that counted the frame.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegOutputImage {
+  /// The path of the image, i.e. the pattern with its number substituted.
+  pub path: String,
+  /// The position of the image among those written by this process,
+  /// starting at 0.
+  pub sequence: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegOutput {
   pub to: String,
   pub index: u32,
@@ -65,6 +371,7 @@ impl FfmpegOutput {
 
 /// Represents metadata about a stream.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegStream {
   /// Corresponds to stream `-f` parameter, e.g. `rawvideo`, `h264`, `opus` or `srt`.
   pub format: String,
@@ -74,10 +381,16 @@ pub struct FfmpegStream {
   pub parent_index: u32,
   /// The index of the stream inside the input.
   pub stream_index: u32,
+  /// The format-specific ID printed in brackets after the index, e.g. the
+  /// PID of an MPEG-TS stream in `Stream #0:2[0x102]`.
+  pub stream_id: Option<u32>,
   /// The stderr line that this stream was parsed from.
   pub raw_log_message: String,
   // Data that is specific to a certain stream type.
   pub type_specific_data: StreamTypeSpecificData,
+  /// The dispositions printed after the stream, e.g. `default`, `forced`
+  /// or `attached pic`, in order.
+  pub dispositions: Vec<String>,
 }
 
 impl FfmpegStream {
@@ -110,12 +423,28 @@ impl FfmpegStream {
       _ => None,
     }
   }
+
+  /// Whether the stream has the given disposition, e.g. `hearing impaired`.
+  pub fn has_disposition(&self, disposition: &str) -> bool {
+    self.dispositions.iter().any(|d| d == disposition)
+  }
+
+  /// Whether the stream is the default of its type.
+  pub fn is_default(&self) -> bool {
+    self.has_disposition("default")
+  }
+
+  /// Whether the stream is forced, typically subtitles of foreign dialogue.
+  pub fn is_forced(&self) -> bool {
+    self.has_disposition("forced")
+  }
 }
 
 /// Represents metadata that is specific to a stream, e.g. fields that are only found in audio
 /// streams or that are only found in video streams, etc. Storing this in an enum allows function
 /// to accept the generic `Stream` type regardless of its actual type (audio, video, ...).
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StreamTypeSpecificData {
   Audio(AudioStream),
   Video(VideoStream),
@@ -125,15 +454,21 @@ pub enum StreamTypeSpecificData {
 
 /// Represents metadata that is specific to audio stream.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AudioStream {
   /// The sample rate of the audio stream, e.g. 48000 (Hz)
   pub sample_rate: u32,
   /// The number of channels of the audio stream, e.g. `stereo`, `5.1`, or `7.1`
   pub channels: String,
+  /// Sample format, e.g. `fltp` or `s16`, if reported
+  pub sample_fmt: Option<String>,
+  /// Bitrate in kilobits per second, if reported
+  pub bitrate_kbps: Option<u32>,
 }
 
 /// Represents metadata that is specific to video streams.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VideoStream {
   /// Corresponds to stream `-pix_fmt` parameter, e.g. `rgb24`
   pub pix_fmt: String,
@@ -143,32 +478,193 @@ pub struct VideoStream {
   pub height: u32,
   /// Framerate in frames per second
   pub fps: f32,
+  /// Codec profile, e.g. `Main` or `High 10`, if reported
+  pub profile: Option<String>,
+  /// Bitrate in kilobits per second, if reported
+  pub bitrate_kbps: Option<u32>,
+  /// Codec level, e.g. `4.1`, if reported
+  pub level: Option<String>,
+  /// `tv` (limited) or `pc` (full), if specified
+  pub color_range: Option<String>,
+  /// Color space (matrix coefficients), e.g. `bt709` or `bt2020nc`, if
+  /// specified
+  pub color_space: Option<String>,
+  /// Color primaries, e.g. `bt709` or `bt2020`, if specified
+  pub color_primaries: Option<String>,
+  /// Transfer characteristics, e.g. `bt709`, or `smpte2084` and
+  /// `arib-std-b67` for HDR (see
+  /// [`is_hdr_transfer`](crate::video::is_hdr_transfer)), if specified
+  pub color_trc: Option<String>,
+  /// Rotation in degrees from the display matrix side data, filled in by
+  /// [`FfmpegMetadata`](crate::metadata::FfmpegMetadata), see
+  /// [`FfmpegSideData::rotation`]
+  pub rotation: Option<f64>,
+}
+
+/// A component required by the command that the running FFmpeg binary was
+/// built without.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MissingComponent {
+  Encoder(String),
+  Decoder(String),
+  Filter(String),
+  Protocol(String),
+}
+
+impl MissingComponent {
+  /// The name of the encoder, decoder, filter or protocol.
+  pub fn name(&self) -> &str {
+    match self {
+      MissingComponent::Encoder(name)
+      | MissingComponent::Decoder(name)
+      | MissingComponent::Filter(name)
+      | MissingComponent::Protocol(name) => name,
+    }
+  }
+}
+
+/// The license an FFmpeg build must be configured with to include a component.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BuildLicense {
+  /// Requires `--enable-gpl`. The default download sources provide GPL builds.
+  Gpl,
+  /// Requires `--enable-nonfree`. Such builds cannot be redistributed, so
+  /// FFmpeg has to be compiled from source.
+  NonFree,
+}
+
+/// Raised when FFmpeg reports that a component is unavailable in the current
+/// build, e.g. `Unknown encoder 'libx264'` in an LGPL build.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuildCapabilityError {
+  pub component: MissingComponent,
+  /// The license the component is known to require, if any. `None` means the
+  /// component is typically just an optional external library.
+  pub required_license: Option<BuildLicense>,
+  /// A human-readable hint about which FFmpeg build would satisfy the command.
+  pub suggestion: String,
+  pub raw_log_message: String,
+}
+
+/// Raised when a muxer rejects a stream's codec, e.g. `Could not find tag for
+/// codec pcm_s16le in stream #1, codec not currently supported in container`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnsupportedContainerCodec {
+  /// The muxer that rejected the stream, e.g. `mp4`.
+  pub muxer: String,
+  /// The codec of the rejected stream, e.g. `pcm_s16le`.
+  pub codec: String,
+  /// The index of the rejected stream in the output.
+  pub stream_index: u32,
+  pub raw_log_message: String,
+}
+
+impl std::fmt::Display for UnsupportedContainerCodec {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "codec {} of stream #{} is not supported by the {} container",
+      self.codec, self.stream_index, self.muxer
+    )
+  }
 }
 
+impl std::error::Error for UnsupportedContainerCodec {}
+
+/// The JSON block printed by the `loudnorm` filter at the end of a run.
+/// Loudness values are in LUFS, true peaks in dBTP and loudness ranges in LU.
+/// Silent input is reported as negative infinity.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoudnormStats {
+  /// Integrated loudness of the input
+  pub input_i: f64,
+  /// True peak of the input
+  pub input_tp: f64,
+  /// Loudness range of the input
+  pub input_lra: f64,
+  /// Gating threshold of the input
+  pub input_thresh: f64,
+  /// Integrated loudness of the output
+  pub output_i: f64,
+  /// True peak of the output
+  pub output_tp: f64,
+  /// Loudness range of the output
+  pub output_lra: f64,
+  /// Gating threshold of the output
+  pub output_thresh: f64,
+  /// `dynamic` or `linear`. A linear normalization may fall back to dynamic
+  /// if the target cannot be reached without exceeding the true peak.
+  pub normalization_type: String,
+  /// Offset gain to pass to a second pass
+  pub target_offset: f64,
+  pub raw_log_message: String,
+}
+
+/// A black part of the video reported by the `blackdetect` filter. Times are
+/// in seconds.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlackInterval {
+  pub start: f64,
+  pub end: f64,
+  pub duration: f64,
+  pub raw_log_message: String,
+}
+
+/// The closing summary of an output, e.g. `video:17884KiB audio:456KiB
+/// subtitle:0KiB other streams:0KiB global headers:0KiB muxing overhead:
+/// 0.51%`. Sizes are in kibibytes, which older Ffmpeg versions print as
+/// `kB`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncodeSummary {
+  pub video_kb: u64,
+  pub audio_kb: u64,
+  pub subtitle_kb: u64,
+  pub other_streams_kb: u64,
+  pub global_headers_kb: u64,
+  /// The size of the container data relative to the size of the streams,
+  /// in percent. `None` when Ffmpeg reports it as `unknown`, e.g. when
+  /// nothing was written.
+  pub muxing_overhead_pct: Option<f64>,
+  pub raw_log_message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegVersion {
   pub version: String,
   pub raw_log_message: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegConfiguration {
   pub configuration: Vec<String>,
   pub raw_log_message: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegProgress {
   /// index of the current output frame
-  pub frame: u32,
+  pub frame: u64,
   /// frames per second
   pub fps: f32,
   /// Quality factor (if applicable)
   pub q: f32,
-  /// Current total size of the output in kilobytes
-  pub size_kb: u32,
-  /// The raw time string in format like `00:03:29.04`
-  pub time: String,
+  /// Current total size of the output in kibibytes
+  pub size_kb: u64,
+  /// The position of the output. `None` until Ffmpeg reports one, while it
+  /// prints `N/A` or a negative time.
+  pub time: Option<Duration>,
+  /// The raw time string in format like `00:03:29.04`, or `N/A`
+  pub raw_time: String,
   /// Bitrate in kilo**bits** per second
   pub bitrate_kbps: f32,
   /// Processing speed as a ratio of the input duration
@@ -176,10 +672,53 @@ pub struct FfmpegProgress {
   /// - 1x is realtime
   /// - 2x means 2 seconds of input are processed in 1 second of wall clock time
   pub speed: f32,
+  /// Frames duplicated to keep the output frame rate, if reported. Only
+  /// present once Ffmpeg had to duplicate a frame.
+  pub dup: Option<u64>,
+  /// Frames dropped to keep the output frame rate, if reported. A growing
+  /// count in a live encode means the input runs faster than the output.
+  pub drop: Option<u64>,
   /// The line that this progress was parsed from
   pub raw_log_message: String,
 }
 
+/// One update of the `-progress` feed: the `key=value` lines written since
+/// the previous update, up to and including the terminating `progress` key.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegProgressFeed {
+  /// The fields in the order Ffmpeg wrote them, e.g. `("total_size", "1048576")`
+  pub fields: Vec<(String, String)>,
+}
+
+impl FfmpegProgressFeed {
+  /// The value of the last field named `key`.
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self
+      .fields
+      .iter()
+      .rev()
+      .find(|(k, _)| k == key)
+      .map(|(_, value)| value.as_str())
+  }
+
+  /// Bytes written to the output so far (`total_size`). Unlike the `frame`
+  /// count, this keeps increasing during stream copies.
+  pub fn total_size(&self) -> Option<u64> {
+    self.get("total_size")?.parse().ok()
+  }
+
+  /// Output timestamp in microseconds (`out_time_us`).
+  pub fn out_time_us(&self) -> Option<i64> {
+    self.get("out_time_us")?.parse().ok()
+  }
+
+  /// Whether this is the final update (`progress=end`).
+  pub fn is_end(&self) -> bool {
+    self.get("progress") == Some("end")
+  }
+}
+
 // #[derive(Clone, PartialEq)]
 // pub struct OutputVideoFrame {
 //     /// The width of this video frame in pixels
@@ -212,3 +751,79 @@ pub struct FfmpegProgress {
 //             .finish()
 //     }
 // }
+
+/// The value returned by a custom matcher for a log line, see
+/// [`FfmpegLogParser::add_matcher`](crate::log_parser::FfmpegLogParser::add_matcher).
+///
+/// Events are equal if they were produced by the same matcher from the same
+/// line.
+///
+/// With the `serde` feature, only the name and the line are serialized: the
+/// value of a deserialized event is `()`.
+#[derive(Clone)]
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(into = "SerializedCustomEvent", from = "SerializedCustomEvent")
+)]
+pub struct CustomEvent {
+  /// The name the matcher was registered with
+  pub name: String,
+  value: Arc<dyn Any + Send + Sync>,
+  pub raw_log_message: String,
+}
+
+impl CustomEvent {
+  pub fn new<T: Any + Send + Sync>(name: &str, value: T, raw_log_message: &str) -> Self {
+    Self {
+      name: name.to_string(),
+      value: Arc::new(value),
+      raw_log_message: raw_log_message.to_string(),
+    }
+  }
+
+  /// The value returned by the matcher, if it is a `T`.
+  pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+    self.value.downcast_ref()
+  }
+}
+
+impl fmt::Debug for CustomEvent {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("CustomEvent")
+      .field("name", &self.name)
+      .field("raw_log_message", &self.raw_log_message)
+      .finish_non_exhaustive()
+  }
+}
+
+impl PartialEq for CustomEvent {
+  fn eq(&self, other: &Self) -> bool {
+    self.name == other.name && self.raw_log_message == other.raw_log_message
+  }
+}
+
+/// The serialized fields of a [`CustomEvent`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedCustomEvent {
+  name: String,
+  raw_log_message: String,
+}
+
+#[cfg(feature = "serde")]
+impl From<CustomEvent> for SerializedCustomEvent {
+  fn from(event: CustomEvent) -> Self {
+    Self {
+      name: event.name,
+      raw_log_message: event.raw_log_message,
+    }
+  }
+}
+
+#[cfg(feature = "serde")]
+impl From<SerializedCustomEvent> for CustomEvent {
+  fn from(event: SerializedCustomEvent) -> Self {
+    Self::new(&event.name, (), &event.raw_log_message)
+  }
+}
@@ -0,0 +1,307 @@
+//! Utilities related to the FFplay binary.
+//!
+//! [`FfplayCommand`] spawns a preview window, e.g. to watch the output of a
+//! filter graph while debugging it. Like FFprobe, FFplay isn't part of all
+//! FFmpeg distributions.
+
+use crate::command::BackgroundCommand;
+use crate::paths::{sidecar_binary_path, BinaryLocator, PathCache};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{CommandArgs, Stdio};
+use std::sync::OnceLock;
+use tokio::process::{Child, Command};
+
+/// Environment variable that overrides the location of the FFplay binary.
+pub const FFPLAY_PATH_ENV: &str = "FFPLAY_PATH";
+
+static FFPLAY_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+pub(crate) static FFPLAY_PATH_CACHE: PathCache = PathCache::new();
+
+/// Set a process-wide path to the FFplay binary, used by [`ffplay_path`].
+///
+/// The override can only be set once; subsequent calls return an error.
+pub fn set_ffplay_path<P: Into<PathBuf>>(path: P) -> anyhow::Result<()> {
+  FFPLAY_PATH_OVERRIDE
+    .set(path.into())
+    .map_err(|path| anyhow::anyhow!("ffplay path was already set, ignoring {}", path.display()))?;
+  FFPLAY_PATH_CACHE.clear();
+  Ok(())
+}
+
+/// Returns the path of the FFplay executable, searching the locations of
+/// [`BinaryLocator::ffplay`] in the same order as
+/// [`ffmpeg_path`](crate::paths::ffmpeg_path). Falls back to plain `ffplay`.
+///
+/// The path is resolved once and cached, see
+/// [`invalidate_path_cache`](crate::paths::invalidate_path_cache).
+pub fn ffplay_path() -> PathBuf {
+  FFPLAY_PATH_CACHE
+    .get_or_resolve(|| BinaryLocator::ffplay().locate().map(|located| located.path))
+    .unwrap_or_else(|| Path::new("ffplay").to_path_buf())
+}
+
+/// The path passed to [`set_ffplay_path`], if any.
+pub(crate) fn ffplay_path_override() -> Option<&'static PathBuf> {
+  FFPLAY_PATH_OVERRIDE.get()
+}
+
+/// The (expected) path to an FFplay binary adjacent to the Rust binary.
+pub fn ffplay_sidecar_path() -> anyhow::Result<PathBuf> {
+  sidecar_binary_path("ffplay")
+}
+
+/// Alias for `ffplay -version`, returning its output.
+pub async fn ffplay_version() -> anyhow::Result<String> {
+  ffplay_version_with_path(ffplay_path()).await
+}
+
+/// Lower level variant of `ffplay_version` that exposes a customized path to
+/// the ffplay binary.
+pub async fn ffplay_version_with_path<S: AsRef<OsStr>>(path: S) -> anyhow::Result<String> {
+  let output = Command::new(&path)
+    .arg("-version")
+    .create_no_window()
+    .output()
+    .await?;
+
+  Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Verify whether ffplay is installed on the system, see [`ffplay_path`].
+pub async fn ffplay_is_installed() -> bool {
+  Command::new(ffplay_path())
+    .create_no_window()
+    .arg("-version")
+    .stderr(Stdio::null())
+    .stdout(Stdio::null())
+    .status()
+    .await
+    .map(|s| s.success())
+    .unwrap_or_else(|_| false)
+}
+
+/// A wrapper around [`tokio::process::Command`] with FFplay-specific argument
+/// methods, mirroring [`FfmpegCommand`](crate::command::FfmpegCommand).
+///
+/// The child's stdin is piped, so that media can be written to it with
+/// [`pipe_stdin`](Self::pipe_stdin). Its logs are discarded; FFplay reports
+/// errors in its window and exit status.
+///
+/// ## Example
+///
+/// Preview the output of a filter, piped from FFmpeg:
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::ffplay::FfplayCommand;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut ffmpeg = FfmpegCommand::new();
+/// ffmpeg
+///   .testsrc()
+///   .filter_video("hflip")
+///   .format("nut")
+///   .pipe_stdout();
+/// let mut ffmpeg = ffmpeg.as_inner_mut().stderr(std::process::Stdio::null()).spawn()?;
+///
+/// let mut ffplay = FfplayCommand::new()
+///   .window_title("hflip")
+///   .autoexit()
+///   .pipe_stdin()
+///   .spawn()?;
+///
+/// let mut frames = ffmpeg.stdout.take().unwrap();
+/// let mut preview = ffplay.stdin.take().unwrap();
+/// tokio::io::copy(&mut frames, &mut preview).await?;
+/// drop(preview);
+/// ffplay.wait().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FfplayCommand {
+  inner: Command,
+}
+
+impl Default for FfplayCommand {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl FfplayCommand {
+  //// Argument presets and aliases
+
+  /// Alias for `-hide_banner` argument.
+  pub fn hide_banner(&mut self) -> &mut Self {
+    self.arg("-hide_banner");
+    self
+  }
+
+  /// Alias for `-f` argument, the format of the input.
+  pub fn format<S: AsRef<str>>(&mut self, format: S) -> &mut Self {
+    self.arg("-f");
+    self.arg(format.as_ref());
+    self
+  }
+
+  /// Alias for `-i` argument, the file or URL to play. FFplay plays a single
+  /// input.
+  pub fn input<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
+    self.arg("-i");
+    self.arg(path.as_ref());
+    self
+  }
+
+  /// Play what is written to the child's stdin. Equivalent to `-i -`.
+  pub fn pipe_stdin(&mut self) -> &mut Self {
+    self.input("-")
+  }
+
+  /// Alias for `-window_title` argument.
+  pub fn window_title<S: AsRef<str>>(&mut self, title: S) -> &mut Self {
+    self.arg("-window_title");
+    self.arg(title.as_ref());
+    self
+  }
+
+  /// The size of the window, with `-x` and `-y`. By default, the window has
+  /// the size of the video.
+  pub fn window_size(&mut self, width: u32, height: u32) -> &mut Self {
+    self.args(["-x", &width.to_string(), "-y", &height.to_string()]);
+    self
+  }
+
+  /// Alias for `-fs` argument, starting in fullscreen mode.
+  pub fn fullscreen(&mut self) -> &mut Self {
+    self.arg("-fs");
+    self
+  }
+
+  /// Alias for `-nodisp` argument, playing the audio without a window.
+  pub fn no_display(&mut self) -> &mut Self {
+    self.arg("-nodisp");
+    self
+  }
+
+  /// Alias for `-an` argument, disabling audio.
+  pub fn no_audio(&mut self) -> &mut Self {
+    self.arg("-an");
+    self
+  }
+
+  /// Alias for `-autoexit` argument, exiting at the end of the input instead
+  /// of keeping the last frame on screen.
+  pub fn autoexit(&mut self) -> &mut Self {
+    self.arg("-autoexit");
+    self
+  }
+
+  /// Alias for `-loop` argument, playing the input `count` times, or forever
+  /// with `0`.
+  pub fn loop_count(&mut self, count: u32) -> &mut Self {
+    self.arg("-loop");
+    self.arg(count.to_string());
+    self
+  }
+
+  /// Alias for `-ss` argument, seeking to `position` (in seconds or
+  /// `[HH:]MM:SS[.m...]`) before playing.
+  pub fn seek<S: AsRef<str>>(&mut self, position: S) -> &mut Self {
+    self.arg("-ss");
+    self.arg(position.as_ref());
+    self
+  }
+
+  /// Alias for `-vf` argument, a filter graph applied to the video.
+  pub fn filter_video<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.arg("-vf");
+    self.arg(filtergraph.as_ref());
+    self
+  }
+
+  /// Alias for `-af` argument, a filter graph applied to the audio.
+  pub fn filter_audio<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.arg("-af");
+    self.arg(filtergraph.as_ref());
+    self
+  }
+
+  //// `std::process::Command` passthrough methods
+
+  /// Adds an argument to pass to the program.
+  pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+    self.inner.arg(arg.as_ref());
+    self
+  }
+
+  /// Adds multiple arguments to pass to the program.
+  pub fn args<I, S>(&mut self, args: I) -> &mut Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    for arg in args {
+      self.arg(arg.as_ref());
+    }
+    self
+  }
+
+  /// Returns an iterator of the arguments that will be passed to the program.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::ffplay::FfplayCommand;
+  ///
+  /// let mut command = FfplayCommand::new();
+  /// command.window_size(640, 360).autoexit().input("clip.mp4");
+  /// assert_eq!(
+  ///   command.get_args().collect::<Vec<_>>(),
+  ///   ["-x", "640", "-y", "360", "-autoexit", "-i", "clip.mp4"]
+  /// );
+  /// ```
+  pub fn get_args(&self) -> CommandArgs<'_> {
+    self.inner.as_std().get_args()
+  }
+
+  /// Spawn the FFplay window. Unlike FfmpegCommand, no events are parsed:
+  /// wait for the child to exit, or kill it to close the window.
+  pub fn spawn(&mut self) -> std::io::Result<Child> {
+    self.inner.spawn()
+  }
+
+  /// Disable creating a new console window for the spawned process on
+  /// Windows. Has no effect on other platforms. Applied by default.
+  pub fn create_no_window(&mut self) -> &mut Self {
+    self.inner.create_no_window();
+    self
+  }
+
+  //// Constructors
+  pub fn new() -> Self {
+    Self::new_with_path(ffplay_path())
+  }
+
+  pub fn new_with_path<S: AsRef<OsStr>>(path: S) -> Self {
+    let mut inner = Command::new(&path);
+    inner.stdin(Stdio::piped());
+    inner.stdout(Stdio::null());
+    inner.stderr(Stdio::null());
+
+    let mut ffplay_command = Self { inner };
+    ffplay_command.create_no_window();
+
+    ffplay_command
+  }
+
+  //// Escape hatches
+
+  /// Escape hatch to access to the inner `Command`.
+  pub fn as_inner(&mut self) -> &Command {
+    &self.inner
+  }
+
+  /// Escape hatch to mutably access to the inner `Command`.
+  pub fn as_inner_mut(&mut self) -> &mut Command {
+    &mut self.inner
+  }
+}
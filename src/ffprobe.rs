@@ -1,40 +1,73 @@
 //! Utilities related to the FFprobe binary.
+//!
+//! [`probe`] reads the metadata of an input, and [`FfprobeCommand`] runs
+//! FFprobe with custom arguments, e.g. to list every packet with
+//! [`show_packets`](FfprobeCommand::show_packets).
 
+use crate::child::MissingChannel;
 use crate::command::BackgroundCommand;
+use crate::event::{FfmpegEvent, LogLevel};
+use crate::metadata::FfmpegMetadata;
+use crate::paths::{sidecar_binary_path, BinaryLocator, PathCache};
+use crate::stream::FfmpegEventStream;
 use anyhow::Context;
-use std::{env::current_exe, ffi::OsStr, path::PathBuf};
+use futures_util::{Stream, StreamExt};
+use std::process::CommandArgs;
+use std::sync::OnceLock;
+use std::{ffi::OsStr, path::PathBuf};
 use std::{path::Path, process::Stdio};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 
 use tokio::process::Command;
 
-/// Returns the path of the downloaded FFprobe executable, or falls back to
-/// assuming its installed in the system path. Note that not all FFmpeg
-/// distributions include FFprobe.
+/// Environment variable that overrides the location of the FFprobe binary.
+pub const FFPROBE_PATH_ENV: &str = "FFPROBE_PATH";
+
+static FFPROBE_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+pub(crate) static FFPROBE_PATH_CACHE: PathCache = PathCache::new();
+
+/// Set a process-wide path to the FFprobe binary, used by [`ffprobe_path`].
+///
+/// The override can only be set once; subsequent calls return an error.
+pub fn set_ffprobe_path<P: Into<PathBuf>>(path: P) -> anyhow::Result<()> {
+  FFPROBE_PATH_OVERRIDE
+    .set(path.into())
+    .map_err(|path| anyhow::anyhow!("ffprobe path was already set, ignoring {}", path.display()))?;
+  FFPROBE_PATH_CACHE.clear();
+  Ok(())
+}
+
+/// Returns the path of the FFprobe executable, searching the locations of
+/// [`BinaryLocator::ffprobe`] in the same order as
+/// [`ffmpeg_path`](crate::paths::ffmpeg_path): the path passed to
+/// [`set_ffprobe_path`], the `FFPROBE_PATH` environment variable, the
+/// downloaded sidecar binary, platform-typical locations and the system path.
+/// Falls back to plain `ffprobe`. Note that not all FFmpeg distributions
+/// include FFprobe.
+///
+/// The path is resolved once and cached, see
+/// [`invalidate_path_cache`](crate::paths::invalidate_path_cache).
 pub fn ffprobe_path() -> PathBuf {
-  let default = Path::new("ffprobe").to_path_buf();
-  match ffprobe_sidecar_path() {
-    Ok(sidecar_path) => match sidecar_path.exists() {
-      true => sidecar_path,
-      false => default,
-    },
-    Err(_) => default,
-  }
+  FFPROBE_PATH_CACHE
+    .get_or_resolve(|| {
+      BinaryLocator::ffprobe()
+        .locate()
+        .map(|located| located.path)
+    })
+    .unwrap_or_else(|| Path::new("ffprobe").to_path_buf())
+}
+
+/// The path passed to [`set_ffprobe_path`], if any.
+pub(crate) fn ffprobe_path_override() -> Option<&'static PathBuf> {
+  FFPROBE_PATH_OVERRIDE.get()
 }
 
-/// The (expected) path to an FFmpeg binary adjacent to the Rust binary.
+/// The (expected) path to an FFprobe binary adjacent to the Rust binary.
 ///
 /// The extension between platforms, with Windows using `.exe`, while Mac and
 /// Linux have no extension.
 pub fn ffprobe_sidecar_path() -> anyhow::Result<PathBuf> {
-  let mut path = current_exe()?
-    .parent()
-    .context("Can't get parent of current_exe")?
-    .join("ffmpeg_dir")
-    .join("ffprobe");
-  if cfg!(windows) {
-    path.set_extension("exe");
-  }
-  Ok(path)
+  sidecar_binary_path("ffprobe")
 }
 
 /// Alias for `ffprobe -version`, parsing the version number and returning it.
@@ -70,3 +103,366 @@ pub async fn ffprobe_is_installed() -> bool {
     .map(|s| s.success())
     .unwrap_or_else(|_| false)
 }
+
+/// Probe `input` with FFprobe and return its inputs, streams, chapters and
+/// tags, parsed from the same log as the input section of an Ffmpeg job.
+/// Fails with the logged errors if FFprobe does, e.g. for a missing file.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::ffprobe::probe;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let metadata = probe("input.mp4").await?;
+/// println!("{:?} seconds, {} streams", metadata.duration(), metadata.input_streams.len());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn probe<S: AsRef<OsStr>>(input: S) -> anyhow::Result<FfmpegMetadata> {
+  let mut child = Command::new(ffprobe_path())
+    .create_no_window()
+    .args(["-hide_banner", "-loglevel", "level+info"])
+    .arg(input.as_ref())
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .kill_on_drop(true)
+    .spawn()
+    .context("failed to run ffprobe")?;
+
+  let stderr = child.stderr.take().ok_or(MissingChannel::Stderr)?;
+  let mut events = FfmpegEventStream::from_reader(stderr);
+  let mut errors = Vec::new();
+  while let Some(event) = events.next().await {
+    if let FfmpegEvent::Error { message: e, .. } | FfmpegEvent::Log(LogLevel::Error, e) = event {
+      errors.push(e);
+    }
+  }
+
+  let status = child.wait().await?;
+  if !status.success() {
+    anyhow::bail!("ffprobe exited with {status}: {}", errors.join(""));
+  }
+  Ok(events.metadata().clone())
+}
+
+/// [`probe`] every input, running at most `concurrency` FFprobe processes at
+/// a time, e.g. to scan a media library without running out of file
+/// descriptors. The results are emitted in the order of `inputs`.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::ffprobe::probe_many;
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let paths = ["a.mp4", "b.mkv", "c.mov"];
+/// let mut results = probe_many(paths, 8);
+/// let mut paths = paths.iter();
+/// while let (Some(path), Some(result)) = (paths.next(), results.next().await) {
+///   match result {
+///     Ok(metadata) => println!("{path}: {:?} seconds", metadata.duration()),
+///     Err(e) => println!("{path}: {e}"),
+///   }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn probe_many<I, S>(
+  inputs: I,
+  concurrency: usize,
+) -> impl Stream<Item = anyhow::Result<FfmpegMetadata>>
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<OsStr>,
+{
+  futures_util::stream::iter(inputs)
+    .map(probe)
+    .buffered(concurrency.max(1))
+}
+
+/// A packet of an input, as listed by [`FfprobeCommand::show_packets`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfprobePacket {
+  /// Index of the stream the packet belongs to.
+  pub stream_index: u32,
+  /// Presentation timestamp in the time base of the stream, if any.
+  pub pts: Option<i64>,
+  /// Decoding timestamp in the time base of the stream, if any.
+  pub dts: Option<i64>,
+  /// Size of the packet in bytes.
+  pub size: u64,
+  /// Flags of the packet, e.g. `K__` for a keyframe: `K` for keyframe, `D`
+  /// for discard and `C` for corrupt.
+  pub flags: String,
+}
+
+impl FfprobePacket {
+  /// Parse a line of `ffprobe -show_entries packet=stream_index,pts,dts,size,flags -of csv`.
+  /// Returns `None` for lines of other sections.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::ffprobe::FfprobePacket;
+  ///
+  /// let packet = FfprobePacket::from_csv("packet,0,1024,N/A,5309,K__").unwrap();
+  /// assert_eq!(packet.stream_index, 0);
+  /// assert_eq!(packet.pts, Some(1024));
+  /// assert_eq!(packet.dts, None);
+  /// assert_eq!(packet.size, 5309);
+  /// assert!(packet.is_keyframe());
+  /// assert_eq!(FfprobePacket::from_csv("stream,0,h264"), None);
+  /// ```
+  pub fn from_csv(line: &str) -> Option<Self> {
+    let mut fields = line.trim_end().strip_prefix("packet,")?.split(',');
+    let timestamp = |field: &str| match field {
+      "N/A" => Some(None),
+      field => field.parse().ok().map(Some),
+    };
+
+    let packet = Self {
+      stream_index: fields.next()?.parse().ok()?,
+      pts: timestamp(fields.next()?)?,
+      dts: timestamp(fields.next()?)?,
+      size: fields.next()?.parse().ok()?,
+      flags: fields.next().unwrap_or_default().to_string(),
+    };
+    match fields.next() {
+      Some(_) => None,
+      None => Some(packet),
+    }
+  }
+
+  /// Whether the packet holds a keyframe, i.e. its flags contain `K`.
+  pub fn is_keyframe(&self) -> bool {
+    self.flags.contains('K')
+  }
+
+  /// Whether the packet is marked to be discarded, i.e. its flags contain `D`.
+  pub fn is_discard(&self) -> bool {
+    self.flags.contains('D')
+  }
+
+  /// Whether the packet is corrupt, i.e. its flags contain `C`.
+  pub fn is_corrupt(&self) -> bool {
+    self.flags.contains('C')
+  }
+}
+
+/// A wrapper around [`tokio::process::Command`] with FFprobe-specific
+/// argument methods, mirroring [`FfmpegCommand`](crate::command::FfmpegCommand).
+///
+/// ## Example
+///
+/// Count the keyframes of the first video stream without buffering the
+/// whole packet list:
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::ffprobe::FfprobeCommand;
+/// use futures_util::{pin_mut, TryStreamExt};
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let packets = FfprobeCommand::new()
+///   .select_streams("v:0")
+///   .input("input.mp4")
+///   .show_packets()?;
+/// pin_mut!(packets);
+///
+/// let mut keyframes = 0;
+/// while let Some(packet) = packets.try_next().await? {
+///   if packet.is_keyframe() {
+///     keyframes += 1;
+///   }
+/// }
+/// println!("{keyframes} keyframes");
+/// # Ok(())
+/// # }
+/// ```
+pub struct FfprobeCommand {
+  inner: Command,
+}
+
+impl Default for FfprobeCommand {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl FfprobeCommand {
+  //// Argument presets and aliases
+
+  /// Alias for `-hide_banner` argument.
+  pub fn hide_banner(&mut self) -> &mut Self {
+    self.arg("-hide_banner");
+    self
+  }
+
+  /// Alias for `-f` argument, the format of the input.
+  pub fn format<S: AsRef<str>>(&mut self, format: S) -> &mut Self {
+    self.arg("-f");
+    self.arg(format.as_ref());
+    self
+  }
+
+  /// Alias for `-i` argument, the file or URL to probe. FFprobe reads a
+  /// single input.
+  pub fn input<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
+    self.arg("-i");
+    self.arg(path.as_ref());
+    self
+  }
+
+  /// Alias for `-select_streams` argument, limiting the output to the
+  /// streams matching `stream_specifier`, e.g. `v:0` or `a`.
+  pub fn select_streams<S: AsRef<str>>(&mut self, stream_specifier: S) -> &mut Self {
+    self.arg("-select_streams");
+    self.arg(stream_specifier.as_ref());
+    self
+  }
+
+  /// Alias for `-read_intervals` argument, e.g. `30%+10` to read 10 seconds
+  /// starting at 30 seconds.
+  pub fn read_intervals<S: AsRef<str>>(&mut self, intervals: S) -> &mut Self {
+    self.arg("-read_intervals");
+    self.arg(intervals.as_ref());
+    self
+  }
+
+  //// Output parsing
+
+  /// Spawn FFprobe with `-show_packets` and stream the packets of the input
+  /// as they are printed, without buffering the whole list, which can be
+  /// hundreds of megabytes for long inputs.
+  ///
+  /// The stream ends with an error if FFprobe fails, e.g. for a missing
+  /// input. Dropping the stream kills FFprobe.
+  pub fn show_packets(
+    &mut self,
+  ) -> anyhow::Result<impl Stream<Item = anyhow::Result<FfprobePacket>> + Send> {
+    self.args([
+      "-loglevel",
+      "error",
+      "-show_entries",
+      "packet=stream_index,pts,dts,size,flags",
+      "-of",
+      "csv",
+    ]);
+    let mut child = self
+      .inner
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .kill_on_drop(true)
+      .spawn()
+      .context("failed to run ffprobe")?;
+
+    let stdout = child.stdout.take().ok_or(MissingChannel::Stdout)?;
+    let mut stderr = child.stderr.take().ok_or(MissingChannel::Stderr)?;
+    // Drain the log concurrently so that a full stderr pipe can't block FFprobe
+    let errors = tokio::spawn(async move {
+      let mut errors = String::new();
+      stderr.read_to_string(&mut errors).await.map(|_| errors)
+    });
+
+    let lines = BufReader::new(stdout).lines();
+    let state = Some((lines, child, errors));
+    Ok(futures_util::stream::unfold(state, |state| async move {
+      let (mut lines, mut child, errors) = state?;
+      loop {
+        match lines.next_line().await {
+          Ok(Some(line)) => {
+            if let Some(packet) = FfprobePacket::from_csv(&line) {
+              return Some((Ok(packet), Some((lines, child, errors))));
+            }
+          }
+          Ok(None) => break,
+          Err(e) => return Some((Err(e.into()), None)),
+        }
+      }
+
+      let result = async {
+        let status = child.wait().await?;
+        let errors = errors.await??;
+        if !status.success() {
+          anyhow::bail!("ffprobe exited with {status}: {}", errors.trim_end());
+        }
+        Ok(())
+      };
+      match result.await {
+        Ok(()) => None,
+        Err(e) => Some((Err(e), None)),
+      }
+    }))
+  }
+
+  //// `std::process::Command` passthrough methods
+
+  /// Adds an argument to pass to the program.
+  pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+    self.inner.arg(arg.as_ref());
+    self
+  }
+
+  /// Adds multiple arguments to pass to the program.
+  pub fn args<I, S>(&mut self, args: I) -> &mut Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    for arg in args {
+      self.arg(arg.as_ref());
+    }
+    self
+  }
+
+  /// Returns an iterator of the arguments that will be passed to the program.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::ffprobe::FfprobeCommand;
+  ///
+  /// let mut command = FfprobeCommand::new();
+  /// command.select_streams("v:0").input("clip.mp4");
+  /// assert_eq!(
+  ///   command.get_args().collect::<Vec<_>>(),
+  ///   ["-select_streams", "v:0", "-i", "clip.mp4"]
+  /// );
+  /// ```
+  pub fn get_args(&self) -> CommandArgs<'_> {
+    self.inner.as_std().get_args()
+  }
+
+  /// Disable creating a new console window for the spawned process on
+  /// Windows. Has no effect on other platforms. Applied by default.
+  pub fn create_no_window(&mut self) -> &mut Self {
+    self.inner.create_no_window();
+    self
+  }
+
+  //// Constructors
+  pub fn new() -> Self {
+    Self::new_with_path(ffprobe_path())
+  }
+
+  pub fn new_with_path<S: AsRef<OsStr>>(path: S) -> Self {
+    let mut inner = Command::new(&path);
+    inner.stdin(Stdio::null());
+
+    let mut ffprobe_command = Self { inner };
+    ffprobe_command.create_no_window();
+
+    ffprobe_command
+  }
+
+  //// Escape hatches
+
+  /// Escape hatch to access to the inner `Command`.
+  pub fn as_inner(&mut self) -> &Command {
+    &self.inner
+  }
+
+  /// Escape hatch to mutably access to the inner `Command`.
+  pub fn as_inner_mut(&mut self) -> &mut Command {
+    &mut self.inner
+  }
+}
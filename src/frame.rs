@@ -0,0 +1,239 @@
+//! Reading raw video frames from the output of Ffmpeg.
+//!
+//! A [`FrameReader`] splits the stdout of a `-f rawvideo` job into frames of
+//! a fixed size. Frames are handed out as [`Bytes`] cut from a single buffer
+//! instead of a fresh `Vec<u8>` each: once the frames read before are
+//! dropped, their memory is reused for the next ones, which keeps the
+//! allocation rate of 4K or high frame rate outputs in check.
+//!
+//! [`RawVideoFrameCodec`] does the same as a [`Decoder`], for use with
+//! [`FramedRead`](tokio_util::codec::FramedRead).
+
+use crate::event::VideoStream;
+use crate::pix_fmt::{frame_size, get_bytes_per_frame};
+use bytes::{Bytes, BytesMut};
+use futures_util::Stream;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::codec::Decoder;
+
+/// Splits raw video output into frames of `frame_size` bytes.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::frame::FrameReader;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut child = FfmpegCommand::new().testsrc().rawvideo().spawn()?;
+///
+/// // testsrc is 320x240
+/// let stdout = child.take_stdout().unwrap();
+/// let mut frames = FrameReader::new(stdout, 320 * 240 * 3);
+/// while let Some(frame) = frames.next_frame().await? {
+///   // `frame` derefs to `&[u8]`, and is cheap to clone or send elsewhere
+///   println!("{} bytes", frame.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FrameReader<R> {
+  reader: R,
+  frame_size: usize,
+  buf: BytesMut,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+  /// Read frames of `frame_size` bytes from `reader`. Reading fails if
+  /// `frame_size` is 0.
+  pub fn new(reader: R, frame_size: usize) -> Self {
+    Self {
+      reader,
+      frame_size,
+      buf: BytesMut::with_capacity(frame_size),
+    }
+  }
+
+  /// Read frames of the size of `stream`, see
+  /// [`get_bytes_per_frame`]. `None` if its pixel format is unknown or its
+  /// frames are empty, e.g. for a stream reported as `0x0`.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::frame::FrameReader;
+  /// use async_ffmpeg_sidecar::log_parser::try_parse_stream;
+  ///
+  /// let stream = try_parse_stream(
+  ///   "[info]   Stream #0:0: Video: rawvideo (RGB[24] / 0x18424752), rgb24, 320x240, q=2-31, 46080 kb/s, 25 fps, 25 tbn\n",
+  /// )
+  /// .unwrap();
+  /// let frames = FrameReader::for_stream(tokio::io::empty(), stream.video_data().unwrap()).unwrap();
+  /// assert_eq!(frames.frame_size(), 320 * 240 * 3);
+  /// ```
+  pub fn for_stream(reader: R, stream: &VideoStream) -> Option<Self> {
+    let frame_size = get_bytes_per_frame(stream).filter(|size| *size > 0)?;
+    Some(Self::new(reader, frame_size as usize))
+  }
+
+  /// The size of a frame in bytes.
+  pub fn frame_size(&self) -> usize {
+    self.frame_size
+  }
+
+  /// Read the next frame, or `None` at the end of the output. Fails with
+  /// [`io::ErrorKind::UnexpectedEof`] if the output ends within a frame, and
+  /// with [`io::ErrorKind::InvalidInput`] if the frame size is 0.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::frame::FrameReader;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() -> std::io::Result<()> {
+  /// let output: &[u8] = &[1, 2, 3, 4, 5, 6, 7];
+  /// let mut frames = FrameReader::new(output, 3);
+  ///
+  /// assert_eq!(frames.next_frame().await?.unwrap(), [1, 2, 3].as_slice());
+  /// assert_eq!(frames.next_frame().await?.unwrap(), [4, 5, 6].as_slice());
+  /// assert!(frames.next_frame().await.is_err());
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub async fn next_frame(&mut self) -> io::Result<Option<Bytes>> {
+    if self.frame_size == 0 {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "frames can't be empty",
+      ));
+    }
+
+    while self.buf.len() < self.frame_size {
+      // Reclaims the memory of the frames handed out before if they were
+      // dropped, and allocates otherwise
+      self.buf.reserve(self.frame_size - self.buf.len());
+      if self.reader.read_buf(&mut self.buf).await? == 0 {
+        if self.buf.is_empty() {
+          return Ok(None);
+        }
+        return Err(io::Error::new(
+          io::ErrorKind::UnexpectedEof,
+          format!(
+            "output ended {} bytes into a frame of {} bytes",
+            self.buf.len(),
+            self.frame_size
+          ),
+        ));
+      }
+    }
+
+    Ok(Some(self.buf.split_to(self.frame_size).freeze()))
+  }
+
+  /// A stream of the frames, see [`next_frame`](Self::next_frame). Ends
+  /// after the first error.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::frame::FrameReader;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let output: &[u8] = &[1, 2, 3];
+  /// let frames = FrameReader::new(output, 0).into_stream().collect::<Vec<_>>().await;
+  /// assert_eq!(frames.len(), 1);
+  /// assert!(frames[0].is_err());
+  /// # }
+  /// ```
+  pub fn into_stream(self) -> impl Stream<Item = io::Result<Bytes>> {
+    futures_util::stream::unfold(Some(self), |frames| async move {
+      let mut frames = frames?;
+      match frames.next_frame().await {
+        Ok(Some(frame)) => Some((Ok(frame), Some(frames))),
+        Ok(None) => None,
+        Err(e) => Some((Err(e), None)),
+      }
+    })
+  }
+}
+
+/// Decodes raw video output into frames, like a [`FrameReader`].
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::frame::RawVideoFrameCodec;
+/// use futures_util::StreamExt;
+/// use tokio_util::codec::FramedRead;
+///
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// // Two 2x1 rgb24 frames, as written by `-f rawvideo -pix_fmt rgb24`
+/// let output: &[u8] = &[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+/// let codec = RawVideoFrameCodec::new(2, 1, "rgb24").unwrap();
+///
+/// let frames = FramedRead::new(output, codec).collect::<Vec<_>>().await;
+/// assert_eq!(frames.len(), 2);
+/// assert_eq!(frames[1].as_ref().unwrap()[..], [0, 0, 255, 255, 255, 255]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawVideoFrameCodec {
+  frame_size: usize,
+}
+
+impl RawVideoFrameCodec {
+  /// Decode frames of `width` by `height` pixels of `pix_fmt`, e.g. `rgb24`.
+  /// `None` if the pixel format is unknown, see
+  /// [`frame_size`](crate::pix_fmt::frame_size), or if the frames are empty.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::frame::RawVideoFrameCodec;
+  ///
+  /// assert_eq!(RawVideoFrameCodec::new(2, 1, "rgb24").unwrap().frame_size(), 6);
+  /// assert_eq!(RawVideoFrameCodec::new(0, 0, "rgb24"), None);
+  /// ```
+  pub fn new(width: u32, height: u32, pix_fmt: &str) -> Option<Self> {
+    let frame_size = frame_size(pix_fmt, width, height).filter(|size| *size > 0)?;
+    Some(Self::with_frame_size(frame_size))
+  }
+
+  /// Decode frames of the size of `stream`. `None` if its pixel format is
+  /// unknown or its frames are empty.
+  pub fn for_stream(stream: &VideoStream) -> Option<Self> {
+    Self::new(stream.width, stream.height, &stream.pix_fmt)
+  }
+
+  /// Decode frames of `frame_size` bytes, e.g. for pixel formats unknown to
+  /// the [`pix_fmt`](crate::pix_fmt) module. Decoding fails if `frame_size`
+  /// is 0.
+  pub fn with_frame_size(frame_size: usize) -> Self {
+    Self { frame_size }
+  }
+
+  /// The size of a frame in bytes.
+  pub fn frame_size(&self) -> usize {
+    self.frame_size
+  }
+}
+
+impl Decoder for RawVideoFrameCodec {
+  type Item = Bytes;
+  type Error = io::Error;
+
+  fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Bytes>> {
+    if self.frame_size == 0 {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "frames can't be empty",
+      ));
+    }
+
+    if src.len() < self.frame_size {
+      src.reserve(self.frame_size - src.len());
+      return Ok(None);
+    }
+
+    Ok(Some(src.split_to(self.frame_size).freeze()))
+  }
+}
@@ -0,0 +1,202 @@
+//! HTTP Live Streaming output.
+//!
+//! [`HlsOutput`] collects the options of Ffmpeg's `hls` muxer. While the job
+//! runs, the log parser turns the muxer's `Opening '...' for writing` lines
+//! into [`FfmpegEvent::OutputSegmentOpened`] and
+//! [`FfmpegEvent::OutputSegmentClosed`] events, so that finished segments can
+//! be uploaded while the encode is still going.
+//!
+//! [`FfmpegEvent::OutputSegmentOpened`]: crate::event::FfmpegEvent::OutputSegmentOpened
+//! [`FfmpegEvent::OutputSegmentClosed`]: crate::event::FfmpegEvent::OutputSegmentClosed
+
+use std::time::Duration;
+
+/// The `-hls_playlist_type` of the media playlist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HlsPlaylistType {
+  /// Segments are only appended, the playlist is never trimmed.
+  Event,
+  /// A complete, static playlist.
+  Vod,
+}
+
+/// A flag passed in `-hls_flags`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HlsFlag {
+  /// Delete segments that dropped out of the playlist window.
+  DeleteSegments,
+  /// Mark every segment as starting with a keyframe.
+  IndependentSegments,
+  /// Append to an existing playlist instead of starting over.
+  AppendList,
+  /// Do not write `#EXT-X-ENDLIST` at the end.
+  OmitEndlist,
+  /// Split segments on time even without a keyframe.
+  SplitByTime,
+  /// Write segments and playlists to a temporary file first and rename
+  /// them once complete.
+  TempFile,
+  /// Write `#EXT-X-PROGRAM-DATE-TIME` tags.
+  ProgramDateTime,
+}
+
+impl HlsFlag {
+  /// The name of the flag as understood by Ffmpeg.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      HlsFlag::DeleteSegments => "delete_segments",
+      HlsFlag::IndependentSegments => "independent_segments",
+      HlsFlag::AppendList => "append_list",
+      HlsFlag::OmitEndlist => "omit_endlist",
+      HlsFlag::SplitByTime => "split_by_time",
+      HlsFlag::TempFile => "temp_file",
+      HlsFlag::ProgramDateTime => "program_date_time",
+    }
+  }
+}
+
+/// The options of an `-f hls` output, see the [module documentation](self).
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::hls::{HlsFlag, HlsOutput, HlsPlaylistType};
+/// use std::time::Duration;
+///
+/// let hls = HlsOutput::new("out/stream.m3u8")
+///   .segment_duration(Duration::from_secs(4))
+///   .playlist_type(HlsPlaylistType::Event)
+///   .flag(HlsFlag::IndependentSegments)
+///   .segment_filename("out/seg%04d.ts");
+///
+/// let mut command = FfmpegCommand::new();
+/// command.input("input.mp4").hls_output(&hls);
+///
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert!(args.windows(2).any(|w| w == ["-hls_time", "4"]));
+/// assert!(args.windows(2).any(|w| w == ["-hls_playlist_type", "event"]));
+/// assert!(args.windows(2).any(|w| w == ["-hls_flags", "independent_segments"]));
+/// assert_eq!(*args.last().unwrap(), "out/stream.m3u8");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlsOutput {
+  playlist: String,
+  segment_duration: Option<Duration>,
+  list_size: Option<u32>,
+  playlist_type: Option<HlsPlaylistType>,
+  flags: Vec<HlsFlag>,
+  segment_filename: Option<String>,
+  master_playlist: Option<String>,
+  var_stream_map: Option<String>,
+}
+
+impl HlsOutput {
+  /// Write the media playlist to `playlist`, e.g. `out/stream.m3u8`.
+  pub fn new<S: AsRef<str>>(playlist: S) -> Self {
+    Self {
+      playlist: playlist.as_ref().to_string(),
+      segment_duration: None,
+      list_size: None,
+      playlist_type: None,
+      flags: Vec::new(),
+      segment_filename: None,
+      master_playlist: None,
+      var_stream_map: None,
+    }
+  }
+
+  /// Target segment length (`-hls_time`, default 2 seconds). Segments can
+  /// only be cut on keyframes, so set a matching keyframe interval.
+  pub fn segment_duration(mut self, duration: Duration) -> Self {
+    self.segment_duration = Some(duration);
+    self
+  }
+
+  /// Maximum number of segments in the playlist (`-hls_list_size`, default
+  /// 5). `0` keeps all segments.
+  pub fn list_size(mut self, list_size: u32) -> Self {
+    self.list_size = Some(list_size);
+    self
+  }
+
+  /// The `-hls_playlist_type`.
+  pub fn playlist_type(mut self, playlist_type: HlsPlaylistType) -> Self {
+    self.playlist_type = Some(playlist_type);
+    self
+  }
+
+  /// Add a flag to `-hls_flags`.
+  pub fn flag(mut self, flag: HlsFlag) -> Self {
+    if !self.flags.contains(&flag) {
+      self.flags.push(flag);
+    }
+    self
+  }
+
+  /// Pattern for segment file names (`-hls_segment_filename`), e.g.
+  /// `out/seg%04d.ts`.
+  pub fn segment_filename<S: AsRef<str>>(mut self, pattern: S) -> Self {
+    self.segment_filename = Some(pattern.as_ref().to_string());
+    self
+  }
+
+  /// Also write a master playlist named `name` next to the media playlist
+  /// (`-master_pl_name`).
+  pub fn master_playlist<S: AsRef<str>>(mut self, name: S) -> Self {
+    self.master_playlist = Some(name.as_ref().to_string());
+    self
+  }
+
+  /// Group the output streams into variants (`-var_stream_map`), e.g.
+  /// `v:0,a:0 v:1,a:1`. Use `%v` in the playlist and segment names.
+  pub fn var_stream_map<S: AsRef<str>>(mut self, map: S) -> Self {
+    self.var_stream_map = Some(map.as_ref().to_string());
+    self
+  }
+
+  /// The path of the media playlist.
+  pub fn playlist(&self) -> &str {
+    &self.playlist
+  }
+
+  /// The output arguments, from `-f hls` up to and including the playlist
+  /// path.
+  pub fn to_args(&self) -> Vec<String> {
+    let mut args = vec!["-f".to_string(), "hls".to_string()];
+    let mut push = |key: &str, value: String| {
+      args.push(key.to_string());
+      args.push(value);
+    };
+
+    if let Some(duration) = self.segment_duration {
+      push("-hls_time", duration.as_secs_f64().to_string());
+    }
+    if let Some(list_size) = self.list_size {
+      push("-hls_list_size", list_size.to_string());
+    }
+    if let Some(playlist_type) = self.playlist_type {
+      let value = match playlist_type {
+        HlsPlaylistType::Event => "event",
+        HlsPlaylistType::Vod => "vod",
+      };
+      push("-hls_playlist_type", value.to_string());
+    }
+    if !self.flags.is_empty() {
+      let flags = self.flags.iter().map(HlsFlag::as_str).collect::<Vec<_>>();
+      push("-hls_flags", flags.join("+"));
+    }
+    if let Some(pattern) = &self.segment_filename {
+      push("-hls_segment_filename", pattern.clone());
+    }
+    if let Some(name) = &self.master_playlist {
+      push("-master_pl_name", name.clone());
+    }
+    if let Some(map) = &self.var_stream_map {
+      push("-var_stream_map", map.clone());
+    }
+
+    args.push(self.playlist.clone());
+    args
+  }
+}
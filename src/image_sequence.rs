@@ -0,0 +1,267 @@
+//! Numbered image files as input or output.
+//!
+//! Ffmpeg's `image2` demuxer and muxer read and write sequences of images
+//! named after a pattern such as `frames/frame_%04d.png`, where `%04d` is
+//! replaced by the number of the image, zero-padded to 4 digits. Inputs can
+//! also be matched with a glob pattern such as `photos/*.jpg`, except on
+//! Windows builds of Ffmpeg.
+//!
+//! See [`FfmpegCommand::input_image_sequence`] and
+//! [`FfmpegCommand::output_image_sequence`], which makes the event stream
+//! report every image written as [`FfmpegEvent::OutputImageWritten`].
+//!
+//! [`Timelapse`] turns a directory of photos into a video.
+//!
+//! [`FfmpegCommand::input_image_sequence`]: crate::command::FfmpegCommand::input_image_sequence
+//! [`FfmpegCommand::output_image_sequence`]: crate::command::FfmpegCommand::output_image_sequence
+//! [`FfmpegEvent::OutputImageWritten`]: crate::event::FfmpegEvent::OutputImageWritten
+
+use crate::command::FfmpegCommand;
+use crate::event::FfmpegEvent;
+use crate::stream::spawn_parsed;
+use crate::video::{concat_file_line, temp_path};
+use anyhow::Context;
+use futures_util::{Stream, StreamExt};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+/// The number of the first image written by the `image2` muxer, unless
+/// changed with [`start_number`](crate::command::FfmpegCommand::start_number).
+pub const DEFAULT_OUTPUT_START_NUMBER: u32 = 1;
+
+/// Substitutes `number` for the `%d` or `%0Nd` of an image sequence pattern.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::image_sequence::sequence_path;
+///
+/// assert_eq!(sequence_path("frames/frame_%04d.png", 12), "frames/frame_0012.png");
+/// assert_eq!(sequence_path("sheet_%d.jpg", 3), "sheet_3.jpg");
+/// assert_eq!(sequence_path("poster.jpg", 3), "poster.jpg");
+/// ```
+pub fn sequence_path(pattern: &str, number: u32) -> String {
+  let Some(start) = pattern.find('%') else {
+    return pattern.to_string();
+  };
+  let Some(len) = pattern[start + 1..].find('d') else {
+    return pattern.to_string();
+  };
+  let width = pattern[start + 1..start + 1 + len].parse().unwrap_or(0);
+  format!(
+    "{}{number:0width$}{}",
+    &pattern[..start],
+    &pattern[start + 2 + len..]
+  )
+}
+
+/// Whether `pattern` contains a `%d` or `%0Nd` sequence number.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::image_sequence::has_sequence_number;
+///
+/// assert!(has_sequence_number("frame_%04d.png"));
+/// assert!(has_sequence_number("frame_%d.png"));
+/// assert!(!has_sequence_number("frame.png"));
+/// assert!(!has_sequence_number("100%.png"));
+/// ```
+pub fn has_sequence_number(pattern: &str) -> bool {
+  pattern.match_indices('%').any(|(start, _)| {
+    let rest = &pattern[start + 1..];
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    rest[digits..].starts_with('d')
+  })
+}
+
+/// Whether `pattern` is a glob pattern, read with `-pattern_type glob`.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::image_sequence::is_glob_pattern;
+///
+/// assert!(is_glob_pattern("photos/*.jpg"));
+/// assert!(is_glob_pattern("photos/IMG_00??.jpg"));
+/// assert!(!is_glob_pattern("frames/frame_%04d.png"));
+/// ```
+pub fn is_glob_pattern(pattern: &str) -> bool {
+  pattern.contains(['*', '?', '['])
+}
+
+/// The extensions of the files picked from a directory by [`Timelapse`].
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "bmp", "tif", "tiff"];
+
+/// A video made of a set of photos, one frame each, e.g. from a camera or a
+/// drone shooting at an interval. Photos of different sizes or aspect
+/// ratios are scaled to fit the video and padded with black.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::image_sequence::Timelapse;
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut progress = Timelapse::new("DCIM/100MEDIA", 30.0)
+///   .size(3840, 2160)
+///   .write("timelapse.mp4");
+/// while let Some(fraction) = progress.next().await {
+///   println!("{:.0}%", fraction? * 100.0);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timelapse {
+  source: String,
+  fps: f32,
+  width: u32,
+  height: u32,
+}
+
+impl Timelapse {
+  /// A timelapse of the images in the directory `source`, or matching the
+  /// glob pattern `source`, played at `fps` images per second. The video is
+  /// 1920x1080 by default.
+  pub fn new<S: AsRef<str>>(source: S, fps: f32) -> Self {
+    Self {
+      source: source.as_ref().to_string(),
+      fps,
+      width: 1920,
+      height: 1080,
+    }
+  }
+
+  /// The size of the video, in pixels.
+  pub fn size(mut self, width: u32, height: u32) -> Self {
+    self.width = width;
+    self.height = height;
+    self
+  }
+
+  /// The images of the timelapse, sorted by path: the files of the source
+  /// directory with one of the [`IMAGE_EXTENSIONS`], or the files matching
+  /// the source pattern.
+  pub fn images(&self) -> anyhow::Result<Vec<PathBuf>> {
+    let mut images = if Path::new(&self.source).is_dir() {
+      std::fs::read_dir(&self.source)
+        .with_context(|| format!("failed to read {}", self.source))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+          path.extension().is_some_and(|extension| {
+            let extension = extension.to_string_lossy().to_ascii_lowercase();
+            IMAGE_EXTENSIONS.contains(&extension.as_str())
+          })
+        })
+        .collect::<Vec<_>>()
+    } else {
+      glob::glob(&self.source)
+        .with_context(|| format!("invalid image pattern {}", self.source))?
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>()
+    };
+    images.retain(|path| path.is_file());
+    images.sort();
+    Ok(images)
+  }
+
+  /// The video filter fitting every image into the video.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::image_sequence::Timelapse;
+  ///
+  /// let timelapse = Timelapse::new("photos", 24.0).size(1280, 720);
+  /// assert_eq!(
+  ///   timelapse.filter(),
+  ///   "scale=1280:720:force_original_aspect_ratio=decrease,\
+  ///    pad=1280:720:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=24,format=yuv420p"
+  /// );
+  /// ```
+  pub fn filter(&self) -> String {
+    let (width, height) = (self.width, self.height);
+    format!(
+      "scale={width}:{height}:force_original_aspect_ratio=decrease,\
+       pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={},format=yuv420p",
+      self.fps
+    )
+  }
+
+  /// Encode the timelapse to `output`, with the default video encoder of
+  /// its format. Existing files are overwritten.
+  ///
+  /// The images are listed with [`images`](Self::images) and read in order
+  /// with the concat demuxer. The fraction of images encoded is reported
+  /// from `0.0` to `1.0`, and the stream ends with `1.0` on success or with
+  /// the error. Dropping the stream stops the job.
+  pub fn write<P: AsRef<str>>(
+    &self,
+    output: P,
+  ) -> impl Stream<Item = anyhow::Result<f64>> + Send + Unpin {
+    let timelapse = self.clone();
+    let output = output.as_ref().to_string();
+
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+      let list = temp_path("timelapse", "ffconcat");
+      let result = timelapse.run(&list, &output, &tx).await;
+      let _ = tokio::fs::remove_file(&list).await;
+      let _ = tx.send(result.map(|()| 1.0)).await;
+    });
+
+    Box::pin(futures_util::stream::unfold(rx, |mut rx| async move {
+      let item = rx.recv().await?;
+      Some((item, rx))
+    }))
+  }
+
+  async fn run(
+    &self,
+    list: &Path,
+    output: &str,
+    tx: &mpsc::Sender<anyhow::Result<f64>>,
+  ) -> anyhow::Result<()> {
+    let images = self.images()?;
+    let Some(last) = images.last() else {
+      anyhow::bail!("no images found in {}", self.source);
+    };
+
+    let mut script = "ffconcat version 1.0\n".to_string();
+    for image in &images {
+      script.push_str(&concat_file_line(image));
+      script.push_str(&format!("duration {}\n", 1.0 / self.fps));
+    }
+    // The duration of the last file is only taken into account if another
+    // file follows
+    script.push_str(&concat_file_line(last));
+    tokio::fs::write(list, script).await?;
+
+    let mut command = FfmpegCommand::new();
+    command
+      .args(["-f", "concat", "-safe", "0"])
+      .input(list.to_string_lossy())
+      .filter_video(self.filter())
+      .frames(images.len() as u32)
+      .overwrite()
+      .output(output);
+
+    let count = images.len() as f64;
+    let mut frames = spawn_parsed(command, |event| match event {
+      FfmpegEvent::Progress(progress) => Some(progress.frame),
+      _ => None,
+    })?;
+    while let Some(frame) = frames.next().await {
+      let fraction = (frame? as f64 / count).min(1.0);
+      if tx.send(Ok(fraction)).await.is_err() {
+        anyhow::bail!("progress stream was dropped");
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Encode a timelapse of the images in the directory or matching the glob
+/// pattern `source` to `output`, at `fps` images per second. See
+/// [`Timelapse`] for the options.
+pub fn timelapse<S: AsRef<str>, P: AsRef<str>>(
+  source: S,
+  fps: f32,
+  output: P,
+) -> impl Stream<Item = anyhow::Result<f64>> + Send + Unpin {
+  Timelapse::new(source, fps).write(output)
+}
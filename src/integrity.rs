@@ -0,0 +1,324 @@
+//! Media integrity checks.
+//!
+//! [`validate`] decodes every video and audio stream of a file without
+//! writing anything and reports the errors Ffmpeg logs along the way, such as
+//! corrupt packets or undecodable frames. [`validate_summary`] condenses them
+//! into a [`ValidationSummary`] for QC reports, and [`is_valid`] stops at the
+//! first error for a quick pass/fail check.
+//!
+//! [`frame_hashes`] and [`stream_hashes`] hash the decoded output, to verify
+//! that two encodes are bit-exact.
+
+use crate::command::FfmpegCommand;
+use crate::event::{ErrorKind, FfmpegEvent, LogLevel};
+use crate::stream::{read_stdout, spawn_parsed};
+use futures_util::{Stream, StreamExt};
+
+/// Errors less than this many seconds apart belong to the same
+/// [`CorruptRegion`].
+pub const CORRUPT_REGION_GAP: f64 = 1.0;
+
+/// An error logged while decoding the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+  /// The decoding position when the error was logged, in seconds. Taken
+  /// from the last progress report, so it can lag behind by up to about
+  /// half a second.
+  pub time: f64,
+  /// The log line, e.g. `[h264 @ 0x55d0c8a4b2c0] error while decoding MB 28 9`
+  pub message: String,
+}
+
+/// A part of the input with decoding errors, see [`CORRUPT_REGION_GAP`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorruptRegion {
+  /// The time of the first error of the region, in seconds
+  pub start: f64,
+  /// The time of the last error of the region, in seconds
+  pub end: f64,
+  pub error_count: usize,
+}
+
+/// The outcome of [`validate_summary`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationSummary {
+  pub error_count: usize,
+  pub first_error: Option<DecodeError>,
+  pub last_error: Option<DecodeError>,
+  pub corrupt_regions: Vec<CorruptRegion>,
+}
+
+impl ValidationSummary {
+  /// Summarize `errors`, given in the order they were logged.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::integrity::{CorruptRegion, DecodeError, ValidationSummary};
+  ///
+  /// let error = |time: f64| DecodeError {
+  ///   time,
+  ///   message: "[h264 @ 0x1] error while decoding MB 28 9".to_string(),
+  /// };
+  /// let summary = ValidationSummary::from_errors(vec![error(3.0), error(3.5), error(12.0)]);
+  ///
+  /// assert!(!summary.is_valid());
+  /// assert_eq!(summary.error_count, 3);
+  /// assert_eq!(summary.last_error, Some(error(12.0)));
+  /// assert_eq!(
+  ///   summary.corrupt_regions,
+  ///   [
+  ///     CorruptRegion { start: 3.0, end: 3.5, error_count: 2 },
+  ///     CorruptRegion { start: 12.0, end: 12.0, error_count: 1 },
+  ///   ]
+  /// );
+  /// ```
+  pub fn from_errors(errors: Vec<DecodeError>) -> Self {
+    let mut corrupt_regions: Vec<CorruptRegion> = Vec::new();
+    for error in &errors {
+      match corrupt_regions.last_mut() {
+        Some(region) if error.time - region.end < CORRUPT_REGION_GAP => {
+          region.end = region.end.max(error.time);
+          region.error_count += 1;
+        }
+        _ => corrupt_regions.push(CorruptRegion {
+          start: error.time,
+          end: error.time,
+          error_count: 1,
+        }),
+      }
+    }
+
+    Self {
+      error_count: errors.len(),
+      first_error: errors.first().cloned(),
+      last_error: errors.last().cloned(),
+      corrupt_regions,
+    }
+  }
+
+  /// Whether the input decoded without errors.
+  pub fn is_valid(&self) -> bool {
+    self.error_count == 0
+  }
+}
+
+/// The command used by [`validate`] and [`is_valid`]: every video and audio
+/// stream of `input` is decoded to the `null` muxer. Subtitle and data
+/// streams are left out, since the `null` muxer can't take most of them.
+/// With `exit_on_error`, `-xerror` makes Ffmpeg stop at the first error.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::integrity::validate_command;
+///
+/// let command = validate_command("upload.mp4", true);
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert_eq!(
+///   args[args.len() - 10..],
+///   ["-i", "upload.mp4", "-map", "0:v?", "-map", "0:a?", "-xerror", "-f", "null", "-"]
+/// );
+/// ```
+pub fn validate_command<S: AsRef<str>>(input: S, exit_on_error: bool) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command.input(input).map(0, "v?").map(0, "a?");
+  if exit_on_error {
+    command.arg("-xerror");
+  } else {
+    // Keep going however much of the input is corrupt, instead of failing
+    // once two thirds of the frames couldn't be decoded.
+    command.args(["-max_error_rate", "1"]);
+  }
+  command.format("null").output("-");
+  command
+}
+
+/// Decode the whole of `input` and stream the errors logged along the way.
+/// The stream ends with an error only if Ffmpeg fails for another reason,
+/// e.g. when `input` can't be opened. Dropping the stream stops the job.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::integrity::validate;
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut errors = validate("upload.mp4")?;
+/// while let Some(error) = errors.next().await {
+///   let error = error?;
+///   println!("{:.1}s: {}", error.time, error.message);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn validate<S: AsRef<str>>(
+  input: S,
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<DecodeError>> + Send + Unpin> {
+  let mut time = 0.0;
+  spawn_parsed(validate_command(input, false), move |event| match event {
+    FfmpegEvent::Progress(progress) => {
+      time = progress.time.map_or(time, |time| time.as_secs_f64());
+      None
+    }
+    FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, message) => {
+      Some(DecodeError { time, message })
+    }
+    // Classified log lines, as opposed to failures to read the logs
+    FfmpegEvent::Error { kind, message } if kind != ErrorKind::Other => {
+      Some(DecodeError { time, message })
+    }
+    _ => None,
+  })
+}
+
+/// Decode the whole of `input` and summarize the errors, see [`validate`].
+pub async fn validate_summary<S: AsRef<str>>(input: S) -> anyhow::Result<ValidationSummary> {
+  let mut stream = validate(input)?;
+  let mut errors = Vec::new();
+  while let Some(error) = stream.next().await {
+    errors.push(error?);
+  }
+  Ok(ValidationSummary::from_errors(errors))
+}
+
+/// Whether `input` decodes without errors, stopping at the first one. An
+/// input that can't be opened isn't valid either.
+pub async fn is_valid<S: AsRef<str>>(input: S) -> anyhow::Result<bool> {
+  let summary = validate_command(input, true)
+    .spawn()?
+    .wait_with_events()
+    .await?;
+  Ok(summary.outcome.is_success() && summary.errors.is_empty())
+}
+
+/// The hash of one frame, as written by the `framehash` muxer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameHash {
+  pub stream_index: u32,
+  /// Decoding timestamp, in the time base of the stream
+  pub dts: i64,
+  /// Presentation timestamp, in the time base of the stream
+  pub pts: i64,
+  /// Duration, in the time base of the stream
+  pub duration: i64,
+  /// Size of the frame, in bytes
+  pub size: u64,
+  /// Hex digest of the frame, SHA-256 by default
+  pub hash: String,
+}
+
+/// The hash of one stream, as written by the `streamhash` muxer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamHash {
+  pub stream_index: u32,
+  /// `v` for video, `a` for audio, etc.
+  pub media_type: String,
+  /// The hash function, e.g. `SHA256`
+  pub algorithm: String,
+  /// Hex digest of the whole stream
+  pub hash: String,
+}
+
+/// Parse the output of the `framehash` muxer. Header lines, starting with
+/// `#`, are skipped.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::integrity::parse_framehash;
+///
+/// let output = "#format: frame checksums\n\
+///   #tb 0: 1/25\n\
+///   0,          0,          0,        1,   115200, 8a0b3f2c\n\
+///   0,          1,          1,        1,   115200, 51d29c4e\n";
+/// let frames = parse_framehash(output);
+/// assert_eq!(frames.len(), 2);
+/// assert_eq!(frames[1].pts, 1);
+/// assert_eq!(frames[1].size, 115200);
+/// assert_eq!(frames[1].hash, "51d29c4e");
+/// ```
+pub fn parse_framehash(output: &str) -> Vec<FrameHash> {
+  output
+    .lines()
+    .filter(|line| !line.starts_with('#'))
+    .filter_map(|line| {
+      let mut fields = line.split(',').map(str::trim);
+      Some(FrameHash {
+        stream_index: fields.next()?.parse().ok()?,
+        dts: fields.next()?.parse().ok()?,
+        pts: fields.next()?.parse().ok()?,
+        duration: fields.next()?.parse().ok()?,
+        size: fields.next()?.parse().ok()?,
+        hash: fields.next()?.to_string(),
+      })
+    })
+    .collect()
+}
+
+/// Parse the output of the `streamhash` muxer.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::integrity::parse_streamhash;
+///
+/// let hashes = parse_streamhash("0,v,SHA256=8a0b3f2c\n1,a,SHA256=51d29c4e\n");
+/// assert_eq!(hashes.len(), 2);
+/// assert_eq!(hashes[1].media_type, "a");
+/// assert_eq!(hashes[1].algorithm, "SHA256");
+/// assert_eq!(hashes[1].hash, "51d29c4e");
+/// ```
+pub fn parse_streamhash(output: &str) -> Vec<StreamHash> {
+  output
+    .lines()
+    .filter_map(|line| {
+      let mut fields = line.trim().splitn(3, ',');
+      let stream_index = fields.next()?.parse().ok()?;
+      let media_type = fields.next()?.to_string();
+      let (algorithm, hash) = fields.next()?.split_once('=')?;
+      Some(StreamHash {
+        stream_index,
+        media_type,
+        algorithm: algorithm.to_string(),
+        hash: hash.to_string(),
+      })
+    })
+    .collect()
+}
+
+/// Hash every decoded frame of every video and audio stream of `input` with
+/// the `framehash` muxer. Two encodes decode to bit-exact output if their
+/// hashes are equal. Subtitle and data streams are left out, and the stream
+/// indices count the video streams first, then the audio streams.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::integrity::frame_hashes;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let reference = frame_hashes("reference.mkv").await?;
+/// let candidate = frame_hashes("candidate.mkv").await?;
+/// if let Some((a, b)) = reference.iter().zip(&candidate).find(|(a, b)| a.hash != b.hash) {
+///   println!("first mismatch at pts {} of stream {}", a.pts, a.stream_index);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn frame_hashes<S: AsRef<str>>(input: S) -> anyhow::Result<Vec<FrameHash>> {
+  let output = read_stdout(hash_command(input.as_ref(), "framehash")).await?;
+  Ok(parse_framehash(&String::from_utf8_lossy(&output)))
+}
+
+/// Hash every decoded video and audio stream of `input` as a whole with the
+/// `streamhash` muxer, in the same order as [`frame_hashes`]. Cheaper to
+/// compare than [`frame_hashes`], but doesn't tell where two inputs differ.
+pub async fn stream_hashes<S: AsRef<str>>(input: S) -> anyhow::Result<Vec<StreamHash>> {
+  let output = read_stdout(hash_command(input.as_ref(), "streamhash")).await?;
+  Ok(parse_streamhash(&String::from_utf8_lossy(&output)))
+}
+
+fn hash_command(input: &str, format: &str) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .map(0, "v?")
+    .map(0, "a?")
+    .format(format)
+    .pipe_stdout();
+  command
+}
@@ -1,16 +1,50 @@
+pub mod audio;
+pub mod batch;
+pub mod capture;
 pub mod child;
 pub mod comma_iter;
 pub mod command;
+pub mod compose;
 pub mod download;
+pub mod encoder;
 pub mod event;
+pub mod ffplay;
 pub mod ffprobe;
+pub mod frame;
+pub mod hls;
+pub mod image_sequence;
+pub mod integrity;
 pub mod log_parser;
 pub mod metadata;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod outcome;
+pub mod output;
+pub mod pacing;
 pub mod paths;
 pub mod pix_fmt;
+pub mod pool;
+mod process_group;
+pub mod progress;
+#[cfg(feature = "indicatif")]
+pub mod progress_bar;
+pub mod quality;
 pub mod read_until_any;
+pub mod recorder;
+pub mod remux;
+pub mod replay;
+#[cfg(feature = "resource_usage")]
+pub mod resources;
+pub mod segment;
+pub mod selection;
+pub mod storyboard;
 pub mod stream;
+pub mod streaming;
+pub mod supervisor;
+pub mod tee;
 pub mod version;
+pub mod video;
+pub mod watchdog;
 
 #[cfg(test)]
 mod test;
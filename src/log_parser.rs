@@ -2,23 +2,78 @@
 // and adapted to use tokio instead sync
 
 use crate::comma_iter::CommaIter;
+use crate::download::ffmpeg_download_url;
 use crate::event::{
-  AudioStream, FfmpegConfiguration, FfmpegDuration, FfmpegEvent, FfmpegInput, FfmpegOutput,
-  FfmpegProgress, FfmpegStream, FfmpegVersion, LogLevel, StreamTypeSpecificData, VideoStream,
+  AudioStream, BlackInterval, BuildCapabilityError, BuildLicense, CustomEvent, EncodeSummary,
+  ErrorKind, FfmpegChapter, FfmpegConfiguration, FfmpegDuration, FfmpegEvent, FfmpegInput,
+  FfmpegLogLine, FfmpegOutput, FfmpegOutputImage, FfmpegOutputSegment, FfmpegProgress,
+  FfmpegProgressFeed, FfmpegSideData, FfmpegStream, FfmpegStreamMapping, FfmpegVersion,
+  LogComponent, LogLevel, LoudnormStats, MetadataEntry, MetadataScope, MissingComponent,
+  StreamConversion, StreamTypeSpecificData, UnsupportedContainerCodec, VideoStream,
 };
-use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader, Lines};
+use crate::image_sequence::sequence_path;
+use crate::quality::{PsnrSummary, SsimSummary};
+use crate::read_until_any::read_until_any;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 
-#[derive(Debug, Clone, PartialEq)]
-enum LogSection {
+/// The part of the Ffmpeg log a line belongs to. Ffmpeg prints nested blocks
+/// (durations, metadata, streams) below an `Input #n` or `Output #n` header
+/// without repeating the index, so the parser tracks the enclosing section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LogSection {
+  /// Below an `Input #n, ...` header
   Input(u32),
+  /// Below an `Output #n, ...` header
   Output(u32),
+  /// Below the `Stream mapping:` header
   StreamMapping,
+  /// Anything else, e.g. the banner or progress updates
   Other,
 }
 
+/// A custom matcher, see [`FfmpegLogParser::add_matcher`].
+type LogMatcher = Box<dyn Fn(&str) -> Option<CustomEvent> + Send + Sync>;
+
 pub struct FfmpegLogParser<R: AsyncBufRead + Unpin> {
-  lines: Lines<BufReader<R>>,
+  reader: R,
+  /// The line being read, kept across calls so that a cancelled read doesn't
+  /// lose its start.
+  line: Vec<u8>,
   cur_section: LogSection,
+  /// Fields of the `-progress` feed received since the last complete update.
+  progress_feed: Vec<(String, String)>,
+  /// The segments of segmenting muxers (e.g. HLS) currently being written,
+  /// in the order they were opened, see [`segment_series`].
+  open_segments: Vec<(String, FfmpegOutputSegment)>,
+  /// Number of segments opened so far.
+  segment_count: u32,
+  /// Events parsed from a previous line that are yet to be returned.
+  pending: VecDeque<FfmpegEvent>,
+  /// Lines of the JSON block following a `loudnorm` log line.
+  loudnorm_block: Option<Vec<String>>,
+  /// The pattern and first number of an image sequence output, see
+  /// [`expect_image_sequence`](Self::expect_image_sequence).
+  image_sequence: Option<(String, u32)>,
+  /// Number of images of the sequence reported so far.
+  images_written: u32,
+  /// The input, output or stream a `Metadata:` block would belong to.
+  metadata_owner: Option<MetadataScope>,
+  /// The scope and indentation of the `Metadata:` block being parsed, and
+  /// the key of its last entry.
+  metadata_block: Option<(MetadataScope, usize, String)>,
+  /// The scope and indentation of the `Side data:` block being parsed.
+  side_data_block: Option<(MetadataScope, usize)>,
+  /// Matchers added with [`add_matcher`](Self::add_matcher).
+  matchers: Vec<LogMatcher>,
+  /// Whether the last line ended with `\r`, which may be followed by `\n`.
+  after_cr: bool,
+  /// Whether events keep their line in `raw_log_message`, see
+  /// [`capture_raw_log_messages`](Self::capture_raw_log_messages).
+  capture_raw_log_messages: bool,
 }
 
 impl<R: AsyncBufRead + Unpin> FfmpegLogParser<R> {
@@ -34,82 +89,410 @@ impl<R: AsyncBufRead + Unpin> FfmpegLogParser<R> {
   /// - `\r\n` (Windows)
   /// - `\r` (Windows, progress updates which overwrite the previous line)
   pub async fn parse_next_event(&mut self) -> anyhow::Result<FfmpegEvent> {
-    let Some(line) = self.lines.next_line().await? else {
-      return Ok(FfmpegEvent::LogEOF);
+    if let Some(event) = self.pending.pop_front() {
+      return Ok(event);
+    }
+
+    let line = loop {
+      let Some(line) = self.next_line().await? else {
+        // The last segments are complete once Ffmpeg has exited
+        let open_segments = std::mem::take(&mut self.open_segments);
+        self.pending.extend(
+          open_segments
+            .into_iter()
+            .map(|(_, segment)| FfmpegEvent::OutputSegmentClosed(segment)),
+        );
+        return Ok(self.pending.pop_front().unwrap_or(FfmpegEvent::LogEOF));
+      };
+
+      // The `loudnorm` JSON block spans several unprefixed lines
+      if let Some(mut block) = self.loudnorm_block.take() {
+        if !block.is_empty() || line.trim() == "{" {
+          let is_last = line.trim() == "}";
+          block.push(line);
+          if !is_last {
+            self.loudnorm_block = Some(block);
+          } else if let Some(stats) = try_parse_loudnorm_stats(&block.join("\n")) {
+            return Ok(FfmpegEvent::LoudnormStats(stats));
+          }
+          continue;
+        }
+      }
+
+      // `-progress` fields are buffered until the block is complete
+      let Some((key, value)) = try_parse_progress_feed_field(&line) else {
+        break line;
+      };
+      let is_last = key == "progress";
+      self.progress_feed.push((key, value));
+      if is_last {
+        return Ok(FfmpegEvent::ProgressFeed(FfmpegProgressFeed {
+          fields: std::mem::take(&mut self.progress_feed),
+        }));
+      }
     };
 
     // let line = from_utf8(line)?.trim();
-    let raw_log_message = line.clone();
+
+    // Entries of a `Metadata:` block are indented below its header
+    if let Some((scope, indent, last_key)) = self.metadata_block.take() {
+      if log_indent(&line) > indent {
+        if let Some((key, value)) = try_parse_metadata_entry(&line) {
+          // Continuation lines of multi-line values have an empty key
+          let key = if key.is_empty() { last_key } else { key };
+          self.metadata_block = Some((scope, indent, key.clone()));
+          return Ok(FfmpegEvent::ParsedMetadata(MetadataEntry {
+            scope,
+            key,
+            value,
+            raw_log_message: self.raw_log_message(line),
+          }));
+        }
+      }
+    }
+
+    if let Some((scope, indent)) = self.side_data_block.take() {
+      if log_indent(&line) > indent {
+        if let Some((kind, value)) = try_parse_side_data(&line) {
+          self.side_data_block = Some((scope, indent));
+          return Ok(FfmpegEvent::SideData(FfmpegSideData {
+            scope,
+            kind,
+            value,
+            raw_log_message: self.raw_log_message(line),
+          }));
+        }
+      }
+    }
 
     // Track log section
-    if let Some(input_number) = try_parse_input(&line) {
-      self.cur_section = LogSection::Input(input_number);
-      return Ok(FfmpegEvent::ParsedInput(FfmpegInput {
-        index: input_number,
-        duration: None,
-        raw_log_message,
-      }));
-    } else if let Some(output) = try_parse_output(&line) {
+    if let Some(mut input) = parse_input_header(&line) {
+      self.cur_section = LogSection::Input(input.index);
+      self.metadata_owner = Some(MetadataScope::Input(input.index));
+      input.raw_log_message = self.raw_log_message(line);
+      return Ok(FfmpegEvent::ParsedInput(input));
+    } else if let Some(mut output) = parse_output(&line) {
       self.cur_section = LogSection::Output(output.index);
+      self.metadata_owner = Some(MetadataScope::Output(output.index));
+      output.raw_log_message = self.raw_log_message(line);
       return Ok(FfmpegEvent::ParsedOutput(output));
     } else if line.contains("Stream mapping:") {
       self.cur_section = LogSection::StreamMapping;
     }
 
+    if is_metadata_header(&line) {
+      if let Some(scope) = self.metadata_owner {
+        self.metadata_block = Some((scope, log_indent(&line), String::new()));
+      }
+    } else if is_side_data_header(&line) {
+      if let Some(scope) = self.metadata_owner {
+        self.side_data_block = Some((scope, log_indent(&line)));
+      }
+    }
+
+    if line.contains("[Parsed_loudnorm_") {
+      self.loudnorm_block = Some(Vec::new());
+    }
+
+    if let Some(mut event) = self.matchers.iter().find_map(|matcher| matcher(&line)) {
+      event.raw_log_message = self.raw_log_message(line);
+      return Ok(FfmpegEvent::Custom(event));
+    }
+
     // Parse
     if let Some(version) = try_parse_version(&line) {
       Ok(FfmpegEvent::ParsedVersion(FfmpegVersion {
         version,
-        raw_log_message,
+        raw_log_message: self.raw_log_message(line),
       }))
     } else if let Some(configuration) = try_parse_configuration(&line) {
       Ok(FfmpegEvent::ParsedConfiguration(FfmpegConfiguration {
         configuration,
-        raw_log_message,
+        raw_log_message: self.raw_log_message(line),
       }))
-    } else if let Some(duration) = try_parse_duration(&line) {
+    } else if duration_line(&line).is_some() {
       match self.cur_section {
         LogSection::Input(input_index) => Ok(FfmpegEvent::ParsedDuration(FfmpegDuration {
           input_index,
-          duration,
-          raw_log_message,
+          duration: try_parse_duration(&line),
+          start_time: try_parse_start_time(&line),
+          bitrate_kbps: try_parse_overall_bitrate(&line),
+          raw_log_message: self.raw_log_message(line),
         })),
-        _ => Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string())),
+        _ => Ok(FfmpegEvent::Log(LogLevel::Info, line)),
       }
-    } else if self.cur_section == LogSection::StreamMapping && line.contains("  Stream #") {
-      Ok(FfmpegEvent::ParsedStreamMapping(line.to_string()))
-    } else if let Some(stream) = try_parse_stream(&line) {
+    } else if let Some(mut chapter) = self.try_parse_chapter(&line) {
+      self.metadata_owner = Some(MetadataScope::Chapter {
+        input: chapter.input_index,
+        chapter: chapter.index,
+      });
+      chapter.raw_log_message = self.raw_log_message(line);
+      Ok(FfmpegEvent::ParsedChapter(chapter))
+    } else if let Some(mut mapping) = self.try_parse_stream_mapping(&line) {
+      mapping.raw_log_message = self.raw_log_message(line);
+      Ok(FfmpegEvent::ParsedStreamMapping(mapping))
+    } else if let Some(mut stream) = parse_stream(&line) {
       match self.cur_section {
-        LogSection::Input(_) => Ok(FfmpegEvent::ParsedInputStream(stream)),
-        LogSection::Output(_) => Ok(FfmpegEvent::ParsedOutputStream(stream)),
+        LogSection::Input(input) => {
+          self.metadata_owner = Some(MetadataScope::InputStream {
+            input,
+            stream: stream.stream_index,
+          });
+          stream.raw_log_message = self.raw_log_message(line);
+          Ok(FfmpegEvent::ParsedInputStream(stream))
+        }
+        LogSection::Output(output) => {
+          self.metadata_owner = Some(MetadataScope::OutputStream {
+            output,
+            stream: stream.stream_index,
+          });
+          stream.raw_log_message = self.raw_log_message(line);
+          Ok(FfmpegEvent::ParsedOutputStream(stream))
+        }
         LogSection::Other | LogSection::StreamMapping => Err(anyhow::Error::msg(format!(
-          "Unexpected stream specification: {}",
-          line
+          "Unexpected stream specification: {line}"
         ))),
       }
-    } else if let Some(progress) = try_parse_progress(&line) {
+    } else if let Some(mut progress) = parse_progress(&line) {
+      progress.raw_log_message = self.raw_log_message(line);
       self.cur_section = LogSection::Other;
+      self.metadata_owner = None;
+      if let Some((pattern, start_number)) = &self.image_sequence {
+        while u64::from(self.images_written) < progress.frame {
+          self
+            .pending
+            .push_back(FfmpegEvent::OutputImageWritten(FfmpegOutputImage {
+              path: sequence_path(pattern, start_number + self.images_written),
+              sequence: self.images_written,
+            }));
+          self.images_written += 1;
+        }
+      }
       Ok(FfmpegEvent::Progress(progress))
-    } else if line.contains("[info]") {
-      Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string()))
-    } else if line.contains("[warning]") {
-      Ok(FfmpegEvent::Log(LogLevel::Warning, line.to_string()))
-    } else if line.contains("[error]") {
-      Ok(FfmpegEvent::Log(LogLevel::Error, line.to_string()))
-    } else if line.contains("[fatal]") {
-      Ok(FfmpegEvent::Log(LogLevel::Fatal, line.to_string()))
+    } else if let Some(mut error) = parse_build_capability_error(&line) {
+      error.raw_log_message = self.raw_log_message(line);
+      Ok(FfmpegEvent::BuildCapabilityError(error))
+    } else if let Some(mut error) = parse_unsupported_container_codec(&line) {
+      error.raw_log_message = self.raw_log_message(line);
+      Ok(FfmpegEvent::UnsupportedContainerCodec(error))
+    } else if is_empty_output_warning(&line) {
+      Ok(FfmpegEvent::EmptyOutput(line))
+    } else if let Some(mut interval) = parse_black_interval(&line) {
+      interval.raw_log_message = self.raw_log_message(line);
+      Ok(FfmpegEvent::BlackInterval(interval))
+    } else if let Some(mut summary) = parse_encode_summary(&line) {
+      summary.raw_log_message = self.raw_log_message(line);
+      Ok(FfmpegEvent::EncodeSummary(summary))
+    } else if let Some(path) = try_parse_output_segment(&line) {
+      let segment = FfmpegOutputSegment {
+        path,
+        sequence: self.segment_count,
+      };
+      self.segment_count += 1;
+
+      // A segment is complete as soon as the next one of its series is
+      // opened
+      let series = segment_series(&line, &segment.path);
+      let opened = FfmpegEvent::OutputSegmentOpened(segment.clone());
+      let previous = self
+        .open_segments
+        .iter()
+        .position(|(open, _)| *open == series)
+        .map(|index| self.open_segments.remove(index).1);
+      self.open_segments.push((series, segment));
+      match previous {
+        Some(previous) => {
+          self.pending.push_back(opened);
+          Ok(FfmpegEvent::OutputSegmentClosed(previous))
+        }
+        None => Ok(opened),
+      }
+    } else if let Some(kind) = try_parse_error_kind(&line) {
+      Ok(FfmpegEvent::Error {
+        kind,
+        message: line,
+      })
     } else {
-      Ok(FfmpegEvent::Log(LogLevel::Unknown, line.to_string()))
+      let (level, _, _) = split_log_prefixes(&line);
+      Ok(FfmpegEvent::Log(level, line))
     }
   }
 
-  pub fn new(inner: R) -> Self {
-    let buf_read = BufReader::new(inner);
-    let lines = buf_read.lines();
+  /// Mapping lines are only recognized below the `Stream mapping:` header.
+  fn try_parse_stream_mapping(&self, line: &str) -> Option<FfmpegStreamMapping> {
+    match self.cur_section {
+      LogSection::StreamMapping => parse_stream_mapping(line),
+      _ => None,
+    }
+  }
+
+  /// Chapters are only recognized in the section of their input.
+  fn try_parse_chapter(&self, line: &str) -> Option<FfmpegChapter> {
+    match self.cur_section {
+      LogSection::Input(input_index) => {
+        parse_chapter(line).filter(|chapter| chapter.input_index == input_index)
+      }
+      _ => None,
+    }
+  }
+
+  /// Parse the lines recognized by `matcher` into
+  /// [`FfmpegEvent::Custom`] events, e.g. the output of a filter the crate
+  /// doesn't know about. The line is passed with its `-loglevel` prefixes,
+  /// see [`parse_log_line`].
+  ///
+  /// Matchers are tried in the order they were added, before the built-in
+  /// parsers, but after the lines of `Metadata:` and `Side data:` blocks and
+  /// of `-progress` updates.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::log_parser::{parse_log_line, FfmpegLogParser};
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() -> anyhow::Result<()> {
+  /// let log = "[Parsed_freezedetect_0 @ 0x1] [info] lavfi.freezedetect.freeze_start: 1.6\n";
+  /// let mut parser = FfmpegLogParser::new(log.as_bytes());
+  /// parser.add_matcher("freeze_start", |line| {
+  ///   let message = parse_log_line(line).message;
+  ///   let start = message.strip_prefix("lavfi.freezedetect.freeze_start: ")?;
+  ///   start.parse::<f64>().ok()
+  /// });
+  ///
+  /// let FfmpegEvent::Custom(event) = parser.parse_next_event().await? else {
+  ///   panic!("expected a custom event");
+  /// };
+  /// assert_eq!(event.name, "freeze_start");
+  /// assert_eq!(event.downcast_ref::<f64>(), Some(&1.6));
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn add_matcher<T, F>(&mut self, name: &str, matcher: F) -> &mut Self
+  where
+    T: Any + Send + Sync,
+    F: Fn(&str) -> Option<T> + Send + Sync + 'static,
+  {
+    let name = name.to_string();
+    self.matchers.push(Box::new(move |line| {
+      let value = matcher(line)?;
+      // The parser fills in the line, see `capture_raw_log_messages`
+      Some(CustomEvent::new(&name, value, ""))
+    }));
+    self
+  }
+
+  /// Whether events keep the line they were parsed from in their
+  /// `raw_log_message` field, which is the default. Disabling this leaves the
+  /// fields empty, so that events that are kept around, e.g. the progress
+  /// updates of long jobs, don't hold on to their lines.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::log_parser::FfmpegLogParser;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() -> anyhow::Result<()> {
+  /// let log = "[info] frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\n";
+  /// let mut parser = FfmpegLogParser::new(log.as_bytes());
+  /// parser.capture_raw_log_messages(false);
+  ///
+  /// let FfmpegEvent::Progress(progress) = parser.parse_next_event().await? else {
+  ///   panic!("expected a progress update");
+  /// };
+  /// assert_eq!(progress.frame, 48);
+  /// assert_eq!(progress.raw_log_message, "");
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn capture_raw_log_messages(&mut self, capture: bool) -> &mut Self {
+    self.capture_raw_log_messages = capture;
+    self
+  }
+
+  /// The `raw_log_message` of an event parsed from `line`.
+  fn raw_log_message(&self, line: String) -> String {
+    if self.capture_raw_log_messages {
+      line
+    } else {
+      String::new()
+    }
+  }
+
+  /// Read the next line, without its line ending. Bytes that aren't valid
+  /// UTF-8, e.g. in file names, are replaced with `U+FFFD` rather than
+  /// failing, so that the rest of the logs can still be parsed.
+  async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+    // The `\n` of a `\r\n` line ending may arrive separately
+    if self.after_cr {
+      if self.reader.fill_buf().await?.first() == Some(&b'\n') {
+        self.reader.consume(1);
+      }
+      self.after_cr = false;
+    }
+
+    let read = read_until_any(&mut self.reader, b"\r\n", &mut self.line).await?;
+    if read == 0 && self.line.is_empty() {
+      return Ok(None);
+    }
+
+    match self.line.last() {
+      Some(b'\r') => {
+        self.after_cr = true;
+        self.line.pop();
+      }
+      Some(b'\n') => {
+        self.line.pop();
+      }
+      // The last line, without a line ending
+      _ => {}
+    }
+    let line = String::from_utf8_lossy(&self.line).into_owned();
+    self.line.clear();
+    Ok(Some(line))
+  }
+
+  /// The section of the most recently parsed line.
+  pub fn current_section(&self) -> LogSection {
+    self.cur_section
+  }
+
+  /// Report an [`FfmpegEvent::OutputImageWritten`] for every frame counted
+  /// by the progress updates, named after `pattern` starting at
+  /// `start_number`.
+  pub fn expect_image_sequence(&mut self, pattern: &str, start_number: u32) {
+    self.image_sequence = Some((pattern.to_string(), start_number));
+  }
+
+  /// The underlying reader.
+  pub(crate) fn get_mut(&mut self) -> &mut R {
+    &mut self.reader
+  }
 
+  /// Parse the log read from `inner`. Lines are split in its buffer, so its
+  /// capacity bounds how much of the log is read ahead, see
+  /// [`BufReader::with_capacity`](tokio::io::BufReader::with_capacity).
+  pub fn new(inner: R) -> Self {
     Self {
-      lines,
+      reader: inner,
+      line: Vec::new(),
       cur_section: LogSection::Other,
+      progress_feed: Vec::new(),
+      open_segments: Vec::new(),
+      segment_count: 0,
+      pending: VecDeque::new(),
+      loudnorm_block: None,
+      image_sequence: None,
+      images_written: 0,
+      metadata_owner: None,
+      metadata_block: None,
+      side_data_block: None,
+      matchers: Vec::new(),
+      after_cr: false,
+      capture_raw_log_messages: true,
     }
   }
 }
@@ -187,6 +570,254 @@ pub fn try_parse_input(string: &str) -> Option<u32> {
     .and_then(|s| s.parse::<u32>().ok())
 }
 
+/// Events that keep the line they were parsed from.
+trait RawLogMessage {
+  fn raw_log_message(&mut self) -> &mut String;
+}
+
+macro_rules! impl_raw_log_message {
+  ($($event:ty),*) => {
+    $(impl RawLogMessage for $event {
+      fn raw_log_message(&mut self) -> &mut String {
+        &mut self.raw_log_message
+      }
+    })*
+  };
+}
+
+impl_raw_log_message!(
+  FfmpegInput,
+  FfmpegChapter,
+  FfmpegOutput,
+  FfmpegStream,
+  UnsupportedContainerCodec,
+  FfmpegStreamMapping,
+  BlackInterval,
+  EncodeSummary,
+  FfmpegProgress,
+  BuildCapabilityError
+);
+
+/// Run `parse` on `string`, keeping a copy of the line in the parsed event.
+fn capture_line<T: RawLogMessage>(string: &str, parse: fn(&str) -> Option<T>) -> Option<T> {
+  let mut parsed = parse(string)?;
+  *parsed.raw_log_message() = string.to_string();
+  Some(parsed)
+}
+
+/// Parse an input section header like the following, extracting the index,
+/// the container format and the source of the input. The duration, start time
+/// and bitrate are printed on the next line, see [`try_parse_duration`].
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_input_header;
+/// let line = "[info] Input #1, mov,mp4,m4a,3gp,3g2,mj2, from 'clips/intro.mp4':\n";
+/// let input = try_parse_input_header(line).unwrap();
+/// assert_eq!(input.index, 1);
+/// assert_eq!(input.format, "mov,mp4,m4a,3gp,3g2,mj2");
+/// assert_eq!(input.from, "clips/intro.mp4");
+/// assert_eq!(input.duration, None);
+/// ```
+pub fn try_parse_input_header(string: &str) -> Option<FfmpegInput> {
+  capture_line(string, parse_input_header)
+}
+
+/// [`try_parse_input_header`] without capturing the raw log message.
+fn parse_input_header(string: &str) -> Option<FfmpegInput> {
+  let index = try_parse_input(string)?;
+  let (_, rest) = string.split_once(", ")?;
+  let (format, from) = rest.split_once(", from '")?;
+  let from = from.trim_end().strip_suffix("':").unwrap_or(from);
+
+  Some(FfmpegInput {
+    index,
+    format: format.to_string(),
+    from: from.to_string(),
+    duration: None,
+    start_time: None,
+    bitrate_kbps: None,
+    raw_log_message: String::new(),
+  })
+}
+
+/// Parse a chapter listed below the `Chapters:` header of an input.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_chapter;
+///
+/// let line = "[info]     Chapter #0:1: start 300.000000, end 612.480000\n";
+/// let chapter = try_parse_chapter(line).unwrap();
+/// assert_eq!(chapter.input_index, 0);
+/// assert_eq!(chapter.index, 1);
+/// assert_eq!(chapter.start, 300.0);
+/// assert_eq!(chapter.end, 612.48);
+/// assert_eq!(chapter.title, None);
+/// ```
+pub fn try_parse_chapter(string: &str) -> Option<FfmpegChapter> {
+  capture_line(string, parse_chapter)
+}
+
+/// [`try_parse_chapter`] without capturing the raw log message.
+fn parse_chapter(string: &str) -> Option<FfmpegChapter> {
+  let rest = string
+    .strip_prefix("[info]")
+    .unwrap_or(string)
+    .trim()
+    .strip_prefix("Chapter #")?;
+  let (indices, times) = rest.split_once(": ")?;
+  let (input_index, index) = indices.split_once(':')?;
+  let (start, end) = times.split_once(", ")?;
+
+  Some(FfmpegChapter {
+    input_index: input_index.parse().ok()?,
+    index: index.parse().ok()?,
+    start: start.strip_prefix("start ")?.parse().ok()?,
+    end: end.strip_prefix("end ")?.parse().ok()?,
+    title: None,
+    raw_log_message: String::new(),
+  })
+}
+
+/// The indentation of a log line, not counting the log level prefix.
+fn log_indent(string: &str) -> usize {
+  let message = string.strip_prefix("[info]").unwrap_or(string);
+  message.len() - message.trim_start().len()
+}
+
+/// Whether the line is the header of a `Metadata:` block, which belongs to
+/// the input, output, or stream printed before it.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::is_metadata_header;
+///
+/// assert!(is_metadata_header("[info]     Metadata:\n"));
+/// assert!(!is_metadata_header("[info]       encoder         : Lavc60.3.100 libx264\n"));
+/// ```
+pub fn is_metadata_header(string: &str) -> bool {
+  string.strip_prefix("[info]").unwrap_or(string).trim() == "Metadata:"
+}
+
+/// Whether the line is the header of a `Side data:` block, which belongs to
+/// the stream printed before it.
+pub fn is_side_data_header(string: &str) -> bool {
+  string.strip_prefix("[info]").unwrap_or(string).trim() == "Side data:"
+}
+
+/// Parse an entry of a `Side data:` block into its type and the rest of the
+/// line, split at the first `:` or `,`.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_side_data;
+///
+/// let line = "[info]       displaymatrix: rotation of -90.00 degrees\n";
+/// assert_eq!(
+///   try_parse_side_data(line),
+///   Some(("displaymatrix".to_string(), "rotation of -90.00 degrees".to_string()))
+/// );
+///
+/// let line = "[info]       Content Light Level Metadata, MaxCLL=1000, MaxFALL=400\n";
+/// let (kind, value) = try_parse_side_data(line).unwrap();
+/// assert_eq!(kind, "Content Light Level Metadata");
+/// assert_eq!(value, "MaxCLL=1000, MaxFALL=400");
+/// ```
+pub fn try_parse_side_data(string: &str) -> Option<(String, String)> {
+  let entry = string.strip_prefix("[info]").unwrap_or(string).trim();
+  let (kind, value) = entry.split_at(entry.find([':', ','])?);
+  let kind = kind.trim();
+  if kind.is_empty() {
+    return None;
+  }
+  Some((kind.to_string(), value[1..].trim().to_string()))
+}
+
+/// Parse a `key : value` line of a `Metadata:` block. The key is empty for
+/// the continuation lines of multi-line values.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_metadata_entry;
+///
+/// let line = "[info]     creation_time   : 2024-05-01T10:00:00.000000Z\n";
+/// assert_eq!(
+///   try_parse_metadata_entry(line),
+///   Some(("creation_time".to_string(), "2024-05-01T10:00:00.000000Z".to_string()))
+/// );
+///
+/// let line = "[info]                     : second line of a comment\n";
+/// assert_eq!(
+///   try_parse_metadata_entry(line),
+///   Some(("".to_string(), "second line of a comment".to_string()))
+/// );
+/// ```
+pub fn try_parse_metadata_entry(string: &str) -> Option<(String, String)> {
+  let (key, value) = string
+    .strip_prefix("[info]")
+    .unwrap_or(string)
+    .trim()
+    .split_once(':')?;
+  Some((key.trim().to_string(), value.trim().to_string()))
+}
+
+/// The fields of the `Duration:` line of an input, after the prefix.
+fn duration_line(string: &str) -> Option<&str> {
+  string
+    .strip_prefix("[info]")
+    .unwrap_or(string)
+    .trim()
+    .strip_prefix("Duration:")
+}
+
+/// The value of the `key: value` field of the `Duration:` line of an input.
+fn duration_line_field<'a>(string: &'a str, key: &str) -> Option<&'a str> {
+  duration_line(string)?
+    .split(',')
+    .find_map(|field| field.trim().strip_prefix(key))
+    .map(str::trim)
+}
+
+/// Parse the start time of an input in seconds, from its `Duration:` line.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_start_time;
+/// let line = "[info]   Duration: N/A, start: 1.400000, bitrate: N/A\n";
+/// assert_eq!(try_parse_start_time(line), Some(1.4));
+/// ```
+pub fn try_parse_start_time(string: &str) -> Option<f64> {
+  duration_line_field(string, "start:")?.parse().ok()
+}
+
+/// Parse the overall bitrate of an input in kilobits per second, from its
+/// `Duration:` line.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_overall_bitrate;
+/// let line = "[info]   Duration: 00:01:02.05, start: 0.000000, bitrate: 2514 kb/s\n";
+/// assert_eq!(try_parse_overall_bitrate(line), Some(2514));
+///
+/// let line = "[info]   Duration: N/A, start: 0.000000, bitrate: N/A\n";
+/// assert_eq!(try_parse_overall_bitrate(line), None);
+/// ```
+pub fn try_parse_overall_bitrate(string: &str) -> Option<u32> {
+  duration_line_field(string, "bitrate:")?
+    .strip_suffix("kb/s")?
+    .trim()
+    .parse()
+    .ok()
+}
+
 /// ## Example
 ///
 /// ```rust
@@ -205,11 +836,7 @@ pub fn try_parse_input(string: &str) -> Option<u32> {
 /// assert_eq!(duration, None);
 /// ```
 pub fn try_parse_duration(string: &str) -> Option<f64> {
-  string
-    .strip_prefix("[info]")
-    .unwrap_or(string)
-    .trim()
-    .strip_prefix("Duration:")?
+  duration_line(string)?
     .trim()
     .split(',')
     .next()
@@ -231,9 +858,12 @@ pub fn try_parse_duration(string: &str) -> Option<f64> {
 ///     raw_log_message: line.to_string()
 /// }));
 /// ```
-pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
-  let raw_log_message = s.to_string();
+pub fn try_parse_output(s: &str) -> Option<FfmpegOutput> {
+  capture_line(s, parse_output)
+}
 
+/// [`try_parse_output`] without capturing the raw log message.
+fn parse_output(mut s: &str) -> Option<FfmpegOutput> {
   s = s
     .strip_prefix("[info]")
     .unwrap_or(s)
@@ -251,7 +881,7 @@ pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
   Some(FfmpegOutput {
     index,
     to,
-    raw_log_message,
+    raw_log_message: String::new(),
   })
 }
 
@@ -296,8 +926,52 @@ pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
 ///  assert_eq!(video_data.width, 320);
 ///  assert_eq!(video_data.height, 240);
 ///  assert_eq!(video_data.fps, 25.0);
+///  assert_eq!(video_data.profile, None);
 ///  ```
 ///
+/// #### Profile, bitrate and color
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_stream;
+/// let line = "[info]   Stream #0:0[0x1](und): Video: hevc (Main 10) (hvc1 / 0x31637668), yuv420p10le(tv, bt2020nc/bt2020/smpte2084), 3840x2160 [SAR 1:1 DAR 16:9], 15023 kb/s, 23.98 fps, 23.98 tbr, 24k tbn (default)\n";
+/// let stream = try_parse_stream(line).unwrap();
+/// assert_eq!(stream.format, "hevc");
+///
+/// let video_data = stream.video_data().unwrap();
+/// assert_eq!(video_data.pix_fmt, "yuv420p10le");
+/// assert_eq!(video_data.fps, 23.98);
+/// assert_eq!(video_data.profile.as_deref(), Some("Main 10"));
+/// assert_eq!(video_data.bitrate_kbps, Some(15023));
+/// assert_eq!(video_data.level, None);
+/// assert_eq!(video_data.color_range.as_deref(), Some("tv"));
+/// assert_eq!(video_data.color_space.as_deref(), Some("bt2020nc"));
+/// assert_eq!(video_data.color_primaries.as_deref(), Some("bt2020"));
+/// assert_eq!(video_data.color_trc.as_deref(), Some("smpte2084"));
+/// ```
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_stream;
+/// let line = "[info]   Stream #0:0: Video: h264 (High) (avc1 / 0x31637661), yuv420p(tv, bt709, progressive), 1920x1080, 25 fps, 25 tbr, 12800 tbn\n";
+/// let video_data = try_parse_stream(line).unwrap().video_data().cloned().unwrap();
+/// assert_eq!(video_data.color_range.as_deref(), Some("tv"));
+/// assert_eq!(video_data.color_space.as_deref(), Some("bt709"));
+/// assert_eq!(video_data.color_trc.as_deref(), Some("bt709"));
+/// ```
+///
+/// The name of the encoder is not mistaken for the profile:
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_stream;
+/// let line = "[info]   Stream #0:0: Video: h264 (libx264) (High) (avc1 / 0x31637661), yuv420p(progressive), 1280x720, q=2-31, 30 fps, 15360 tbn\n";
+/// let video_data = try_parse_stream(line).unwrap().video_data().cloned().unwrap();
+/// assert_eq!(video_data.profile.as_deref(), Some("High"));
+///
+/// let line = "[info]   Stream #0:0: Video: h264 (libx264) (avc1 / 0x31637661), yuv420p(progressive), 1280x720, q=2-31, 30 fps, 15360 tbn\n";
+/// let video_data = try_parse_stream(line).unwrap().video_data().cloned().unwrap();
+/// assert_eq!(video_data.profile, None);
+/// assert_eq!(video_data.level, None);
+/// ```
+///
 /// ### Audio
 ///
 /// #### Input Stream
@@ -314,6 +988,9 @@ pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
 /// let audio_data = stream.audio_data().unwrap();
 /// assert_eq!(audio_data.sample_rate, 48000);
 /// assert_eq!(audio_data.channels, "stereo");
+/// assert_eq!(audio_data.sample_fmt.as_deref(), Some("fltp"));
+/// assert_eq!(audio_data.bitrate_kbps, None);
+/// assert!(stream.is_default());
 /// ```
 ///
 /// ```rust
@@ -328,6 +1005,7 @@ pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
 /// let audio_data = stream.audio_data().unwrap();
 /// assert_eq!(audio_data.sample_rate, 48000);
 /// assert_eq!(audio_data.channels, "7.1");
+/// assert_eq!(audio_data.sample_fmt.as_deref(), Some("s32p"));
 /// ```
 ///
 /// ### Output stream
@@ -344,6 +1022,9 @@ pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
 /// let audio_data = stream.audio_data().unwrap();
 /// assert_eq!(audio_data.sample_rate, 44100);
 /// assert_eq!(audio_data.channels, "mono");
+/// assert_eq!(audio_data.sample_fmt.as_deref(), Some("s16"));
+/// assert_eq!(audio_data.bitrate_kbps, Some(384));
+/// assert!(stream.dispositions.is_empty());
 /// ```
 ///
 /// ### Subtitle
@@ -359,6 +1040,8 @@ pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
 /// assert_eq!(stream.parent_index, 0);
 /// assert_eq!(stream.stream_index, 4);
 /// assert!(stream.is_subtitle());
+/// assert_eq!(stream.dispositions, ["default", "forced"]);
+/// assert!(stream.is_forced());
 /// ```
 ///
 /// ```rust
@@ -369,7 +1052,14 @@ pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
 /// assert_eq!(stream.language, "dut");
 /// assert_eq!(stream.parent_index, 0);
 /// assert_eq!(stream.stream_index, 13);
+/// assert_eq!(stream.stream_id, None);
 /// assert!(stream.is_subtitle());
+///
+/// let line = "[info]   Stream #0:3[0x1200](fre): Subtitle: hdmv_pgs_subtitle ([144][0][0][0] / 0x0090)\n";
+/// let stream = try_parse_stream(line).unwrap();
+/// assert_eq!(stream.stream_index, 3);
+/// assert_eq!(stream.stream_id, Some(0x1200));
+/// assert_eq!(stream.language, "fre");
 /// ```
 /// ### Other
 ///
@@ -394,24 +1084,35 @@ pub fn try_parse_output(mut s: &str) -> Option<FfmpegOutput> {
 /// assert_eq!(stream.language, "eng");
 /// assert_eq!(stream.parent_index, 0);
 /// assert_eq!(stream.stream_index, 2);
+/// assert_eq!(stream.stream_id, Some(3));
 /// assert!(stream.is_other());
 /// ```
 pub fn try_parse_stream(s: &str) -> Option<FfmpegStream> {
-  let raw_log_message = s.to_string();
+  capture_line(s, parse_stream)
+}
 
+/// [`try_parse_stream`] without capturing the raw log message.
+fn parse_stream(s: &str) -> Option<FfmpegStream> {
   let s = s
     .strip_prefix("[info]")
     .unwrap_or(s)
     .trim()
     .strip_prefix("Stream #")?;
+  let (s, dispositions) = split_dispositions(s);
   let mut comma_iter = CommaIter::new(s);
-  let mut colon_iter = comma_iter.next()?.split(':');
+  let header = comma_iter.next()?;
+  let mut colon_iter = header.split(':');
 
   let parent_index = colon_iter.next()?.parse::<u32>().ok()?;
 
   // Here handle the pattern such as `2[0x3](eng)`
-  let indices_nad_maybe_language = colon_iter
-    .next()?
+  let index_part = colon_iter.next()?;
+  // The format-specific ID, e.g. the PID of an MPEG-TS stream
+  let stream_id = index_part
+    .split_once('[')
+    .and_then(|(_, rest)| rest.split_once(']'))
+    .and_then(|(id, _)| u32::from_str_radix(id.trim().strip_prefix("0x")?, 16).ok());
+  let indices_nad_maybe_language = index_part
     // Remove everything inside and including square brackets
     .split(['[', ']'])
     .step_by(2)
@@ -435,7 +1136,7 @@ pub fn try_parse_stream(s: &str) -> Option<FfmpegStream> {
   let type_specific_data: StreamTypeSpecificData = match stream_type {
     "Audio" => try_parse_audio_stream(comma_iter)?,
     "Subtitle" => StreamTypeSpecificData::Subtitle,
-    "Video" => try_parse_video_stream(comma_iter)?,
+    "Video" => try_parse_video_stream(header.split_once("Video:")?.1, comma_iter)?,
     _ => StreamTypeSpecificData::Other,
   };
 
@@ -444,11 +1145,52 @@ pub fn try_parse_stream(s: &str) -> Option<FfmpegStream> {
     language,
     parent_index,
     stream_index,
-    raw_log_message,
+    stream_id,
+    raw_log_message: String::new(),
     type_specific_data,
+    dispositions,
   })
 }
 
+/// The dispositions Ffmpeg prints in parentheses at the end of stream lines.
+const STREAM_DISPOSITIONS: &[&str] = &[
+  "default",
+  "dub",
+  "original",
+  "comment",
+  "lyrics",
+  "karaoke",
+  "forced",
+  "hearing impaired",
+  "visual impaired",
+  "clean effects",
+  "attached pic",
+  "timed thumbnails",
+  "non-diegetic",
+  "captions",
+  "descriptions",
+  "metadata",
+  "dependent",
+  "still image",
+  "multilayer",
+];
+
+/// Splits the trailing dispositions such as ` (default) (forced)` off a
+/// stream line.
+fn split_dispositions(mut s: &str) -> (&str, Vec<String>) {
+  let mut dispositions = Vec::new();
+  while let Some((rest, disposition)) = s
+    .trim_end()
+    .strip_suffix(')')
+    .and_then(|rest| rest.rsplit_once('('))
+    .filter(|(_, disposition)| STREAM_DISPOSITIONS.contains(disposition))
+  {
+    dispositions.insert(0, disposition.to_string());
+    s = rest.trim_end();
+  }
+  (s, dispositions)
+}
+
 /// Parses the log output part that is specific to audio streams.
 fn try_parse_audio_stream(mut comma_iter: CommaIter) -> Option<StreamTypeSpecificData> {
   let sample_rate = comma_iter
@@ -460,106 +1202,964 @@ fn try_parse_audio_stream(mut comma_iter: CommaIter) -> Option<StreamTypeSpecifi
 
   let channels = comma_iter.next()?.trim().to_string();
 
+  let sample_fmt = comma_iter
+    .next()
+    .and_then(|part| part.split_whitespace().next()) // trim trailing junk like "(24 bit)"
+    .map(str::to_string);
+  let bitrate_kbps = comma_iter
+    .find(|part| part.trim().ends_with("kb/s"))
+    .and_then(|part| part.split_whitespace().next()?.parse::<u32>().ok());
+
   Some(StreamTypeSpecificData::Audio(AudioStream {
     sample_rate,
     channels,
+    sample_fmt,
+    bitrate_kbps,
   }))
 }
 
-/// Parses the log output part that is specific to video streams.
-fn try_parse_video_stream(mut comma_iter: CommaIter) -> Option<StreamTypeSpecificData> {
-  let pix_fmt = comma_iter
-    .next()?
-    .trim()
-    .split(&[' ', '(']) // trim trailing junk like "(tv, progressive)"
+/// Parses the log output part that is specific to video streams. `codec` is
+/// the part after `Video:`, e.g. `h264 (High) (avc1 / 0x31637661)`.
+fn try_parse_video_stream(
+  codec: &str,
+  mut comma_iter: CommaIter,
+) -> Option<StreamTypeSpecificData> {
+  // The codec can be followed by the encoder or decoder name, the profile
+  // and the codec tag, e.g. `h264 (libx264) (High) (avc1 / 0x31637661)`
+  let profile = codec
+    .split('(')
+    .skip(1)
+    .filter_map(|group| Some(group.split_once(')')?.0.trim()))
+    .find(|group| !group.is_empty() && !group.contains(" / ") && !is_codec_name(group))
+    .map(str::to_string);
+
+  let pix_fmt_part = comma_iter.next()?.trim();
+  let pix_fmt = pix_fmt_part
+    .split(&[' ', '(']) // the qualifiers like "(tv, progressive)" are parsed below
     .next()?
     .to_string();
+  let color = pix_fmt_part
+    .split_once('(')
+    .map(|(_, qualifiers)| parse_color_qualifiers(qualifiers.trim_end_matches(')')))
+    .unwrap_or_default();
 
   let dims = comma_iter.next()?.split_whitespace().next()?;
   let mut dims_iter = dims.split('x');
   let width = dims_iter.next()?.parse::<u32>().ok()?;
   let height = dims_iter.next()?.parse::<u32>().ok()?;
 
-  // FPS does not have to be the next part, so we iterate until we find it. There is nothing else we
-  // are interested in at this point, so its OK to skip anything in-between.
-  let fps = comma_iter
-    .find_map(|part| {
-      if part.trim().ends_with("fps") {
-        part.split_whitespace().next()
-      } else {
-        None
+  // The remaining parts are optional and can appear in any order, e.g.
+  // `q=2-31, 1500 kb/s, 25 fps, 25 tbr, 12800 tbn`.
+  let mut fps = None;
+  let mut bitrate_kbps = None;
+  let mut level = None;
+  for part in comma_iter {
+    let part = part.trim();
+    if part.ends_with("fps") && fps.is_none() {
+      fps = Some(part.split_whitespace().next()?.parse::<f32>().ok()?);
+    } else if part.ends_with("kb/s") {
+      bitrate_kbps = part.split_whitespace().next()?.parse::<u32>().ok();
+    } else if let Some(value) = part.strip_prefix("level ") {
+      let value = value.trim();
+      if !value.is_empty() && !value.contains(char::is_whitespace) {
+        level = Some(value.to_string());
       }
-    })
-    .and_then(|fps_str| fps_str.parse::<f32>().ok())?;
+    }
+  }
 
   Some(StreamTypeSpecificData::Video(VideoStream {
     pix_fmt,
     width,
     height,
-    fps,
+    fps: fps?,
+    profile,
+    bitrate_kbps,
+    level,
+    color_range: color.range,
+    color_space: color.space,
+    color_primaries: color.primaries,
+    color_trc: color.trc,
+    rotation: None,
   }))
 }
 
-/// Parse a progress update line from ffmpeg.
+/// Whether a parenthesized group after the codec is the name of an encoder or
+/// decoder such as `libx264` or `h264_nvenc`. These are lowercase, unlike
+/// profiles such as `High` or `Main 10`.
+fn is_codec_name(group: &str) -> bool {
+  group.chars().any(|c| c.is_ascii_lowercase())
+    && group
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+/// The color properties among the qualifiers of a pixel format.
+#[derive(Default)]
+struct ColorQualifiers {
+  range: Option<String>,
+  space: Option<String>,
+  primaries: Option<String>,
+  trc: Option<String>,
+}
+
+/// Parses the qualifiers of a pixel format such as `tv, bt2020nc/bt2020/smpte2084`
+/// or `tv, bt709, progressive`. Ffmpeg prints the color space, primaries and
+/// transfer characteristics once if they are all the same, and leaves out
+/// unspecified properties.
+fn parse_color_qualifiers(qualifiers: &str) -> ColorQualifiers {
+  let known = |value: &str| (value != "unknown").then(|| value.to_string());
+  let mut color = ColorQualifiers::default();
+  for qualifier in qualifiers.split(',').map(str::trim) {
+    match qualifier {
+      "tv" | "pc" => color.range = Some(qualifier.to_string()),
+      // Field order and chroma location
+      "progressive" | "left" | "center" | "topleft" | "top" | "bottomleft" | "bottom" => {}
+      _ if qualifier.contains("first") => {}
+      _ if qualifier.contains('/') => {
+        let mut values = qualifier.split('/');
+        color.space = values.next().and_then(known);
+        color.primaries = values.next().and_then(known);
+        color.trc = values.next().and_then(known);
+      }
+      _ if color.space.is_none() => {
+        color.space = known(qualifier);
+        color.primaries = known(qualifier);
+        color.trc = known(qualifier);
+      }
+      _ => {}
+    }
+  }
+  color
+}
+
+/// Whether the line is the warning Ffmpeg prints when an output ends up
+/// without any encoded data.
 ///
 /// ## Example
+///
 /// ```rust
-/// use async_ffmpeg_sidecar::log_parser::try_parse_progress;
-/// let line = "[info] frame= 1996 fps=1984 q=-1.0 Lsize=     372kB time=00:01:19.72 bitrate=  38.2kbits/s speed=79.2x\n";
-/// let progress = try_parse_progress(line).unwrap();
-/// assert_eq!(progress.frame, 1996);
-/// assert_eq!(progress.fps, 1984.0);
-/// assert_eq!(progress.q, -1.0);
-/// assert_eq!(progress.size_kb, 372);
-/// assert_eq!(progress.time, "00:01:19.72");
-/// assert_eq!(progress.bitrate_kbps, 38.2);
-/// assert_eq!(progress.speed, 79.2);
+/// use async_ffmpeg_sidecar::log_parser::is_empty_output_warning;
+///
+/// let line = "[warning] Output file is empty, nothing was encoded (check -ss / -t / -frames parameters if used)\n";
+/// assert!(is_empty_output_warning(line));
+/// assert!(!is_empty_output_warning("[info] Output #0, mp4, to 'out.mp4':\n"));
 /// ```
-pub fn try_parse_progress(mut string: &str) -> Option<FfmpegProgress> {
-  let raw_log_message = string.to_string();
+pub fn is_empty_output_warning(string: &str) -> bool {
+  string.contains("Output file is empty, nothing was encoded")
+}
 
-  string = string.strip_prefix("[info]").unwrap_or(string).trim();
+/// Parse the error logged by a muxer that cannot store a stream's codec.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_unsupported_container_codec;
+///
+/// let line = "[mp4 @ 0x5581f8a3c2c0] [error] Could not find tag for codec pcm_s16le in stream #1, codec not currently supported in container\n";
+/// let error = try_parse_unsupported_container_codec(line).unwrap();
+/// assert_eq!(error.muxer, "mp4");
+/// assert_eq!(error.codec, "pcm_s16le");
+/// assert_eq!(error.stream_index, 1);
+/// ```
+pub fn try_parse_unsupported_container_codec(string: &str) -> Option<UnsupportedContainerCodec> {
+  capture_line(string, parse_unsupported_container_codec)
+}
 
-  let frame = string
-    .split("frame=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .parse::<u32>()
-    .ok()?;
-  let fps = string
-    .split("fps=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .parse::<f32>()
-    .ok()?;
-  let q = string
-    .split("q=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .parse::<f32>()
-    .ok()?;
-  let size_kb = string
-    .split("size=") // captures "Lsize=" AND "size="
+/// [`try_parse_unsupported_container_codec`] without capturing the raw log message.
+fn parse_unsupported_container_codec(string: &str) -> Option<UnsupportedContainerCodec> {
+  let (prefix, rest) = string.split_once("Could not find tag for codec ")?;
+  let (codec, rest) = rest.split_once(" in stream #")?;
+  let (stream_index, rest) = rest.split_once(',')?;
+  if !rest.contains("not currently supported in container") {
+    return None;
+  }
+
+  let muxer = prefix
+    .rsplit_once(" @ ")
+    .and_then(|(context, _)| context.rsplit('[').next())
+    .unwrap_or_default();
+
+  Some(UnsupportedContainerCodec {
+    muxer: muxer.to_string(),
+    codec: codec.to_string(),
+    stream_index: stream_index.parse().ok()?,
+    raw_log_message: String::new(),
+  })
+}
+
+/// Parse a line of the `Stream mapping:` section.
+///
+/// ## Examples
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::event::StreamConversion;
+/// use async_ffmpeg_sidecar::log_parser::try_parse_stream_mapping;
+///
+/// let line = "[info]   Stream #0:0 -> #1:0 (h264 (native) -> hevc (libx265))\n";
+/// let mapping = try_parse_stream_mapping(line).unwrap();
+/// assert_eq!(mapping.input, Some((0, 0)));
+/// assert_eq!(mapping.output, Some((1, 0)));
+/// assert_eq!(
+///   mapping.conversion,
+///   StreamConversion::Transcode {
+///     decoder: "h264 (native)".to_string(),
+///     encoder: "hevc (libx265)".to_string(),
+///   }
+/// );
+/// assert_eq!(mapping.encoder(), Some("hevc (libx265)"));
+///
+/// let line = "[info]   Stream #0:1 -> #0:1 (copy)\n";
+/// let mapping = try_parse_stream_mapping(line).unwrap();
+/// assert_eq!(mapping.conversion, StreamConversion::Copy);
+/// assert!(mapping.is_copy());
+/// assert_eq!(mapping.decoder(), None);
+///
+/// let line = "[info]   scale:default (graph 0) -> Stream #0:0 (libx264)\n";
+/// let mapping = try_parse_stream_mapping(line).unwrap();
+/// assert_eq!(mapping.input, None);
+/// assert_eq!(mapping.output, Some((0, 0)));
+/// assert_eq!(mapping.conversion, StreamConversion::Other("libx264".to_string()));
+///
+/// let line = "[info]   Stream #0:0 (h264) -> scale:default (graph 0)\n";
+/// let mapping = try_parse_stream_mapping(line).unwrap();
+/// assert_eq!(mapping.input, Some((0, 0)));
+/// assert_eq!(mapping.output, None);
+/// assert_eq!(mapping.conversion, StreamConversion::Other("h264".to_string()));
+/// ```
+pub fn try_parse_stream_mapping(string: &str) -> Option<FfmpegStreamMapping> {
+  capture_line(string, parse_stream_mapping)
+}
+
+/// [`try_parse_stream_mapping`] without capturing the raw log message.
+fn parse_stream_mapping(string: &str) -> Option<FfmpegStreamMapping> {
+  let line = string.strip_prefix("[info]").unwrap_or(string).trim();
+  let (source, destination) = split_top_level(line, " -> ")?;
+
+  let (source, source_note) = split_trailing_parens(source);
+  let (destination, destination_note) = split_trailing_parens(destination);
+
+  // The `(graph N)` of a filtergraph end says nothing about the conversion
+  let is_conversion = |note: &&str| !note.starts_with("graph ");
+  let conversion = match destination_note
+    .filter(is_conversion)
+    .or(source_note.filter(is_conversion))
+  {
+    Some("copy") => StreamConversion::Copy,
+    Some(note) => match split_top_level(note, " -> ") {
+      Some((decoder, encoder)) => StreamConversion::Transcode {
+        decoder: decoder.to_string(),
+        encoder: encoder.to_string(),
+      },
+      None => StreamConversion::Other(note.to_string()),
+    },
+    None => StreamConversion::Other(String::new()),
+  };
+
+  Some(FfmpegStreamMapping {
+    input: parse_stream_reference(source),
+    output: parse_stream_reference(destination),
+    conversion,
+    raw_log_message: String::new(),
+  })
+}
+
+/// Splits at the first `separator` outside of parentheses.
+fn split_top_level<'a>(string: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+  let mut depth = 0i32;
+  for (i, c) in string.char_indices() {
+    match c {
+      '(' => depth += 1,
+      ')' => depth -= 1,
+      _ if depth == 0 && string[i..].starts_with(separator) => {
+        return Some((&string[..i], &string[i + separator.len()..]));
+      }
+      _ => {}
+    }
+  }
+  None
+}
+
+/// Splits `stream (note)` into `stream` and `note`, respecting nested
+/// parentheses inside the note.
+fn split_trailing_parens(string: &str) -> (&str, Option<&str>) {
+  let string = string.trim();
+  if !string.ends_with(')') {
+    return (string, None);
+  }
+
+  let mut depth = 0i32;
+  for (i, c) in string.char_indices().rev() {
+    match c {
+      ')' => depth += 1,
+      '(' => {
+        depth -= 1;
+        if depth == 0 {
+          return (string[..i].trim(), Some(&string[i + 1..string.len() - 1]));
+        }
+      }
+      _ => {}
+    }
+  }
+  (string, None)
+}
+
+/// Parses `Stream #1:2` or `#1:2` into `(1, 2)`.
+fn parse_stream_reference(string: &str) -> Option<(u32, u32)> {
+  let reference = string.strip_prefix("Stream ").unwrap_or(string);
+  let (file, stream) = reference.strip_prefix('#')?.split_once(':')?;
+  let stream = stream
+    .split(|c: char| !c.is_ascii_digit())
+    .next()
+    .unwrap_or_default();
+  Some((file.parse().ok()?, stream.parse().ok()?))
+}
+
+/// Parse the path of a new segment from the `Opening '...' for writing` line
+/// logged by the `hls`, `dash` and `segment` muxers. Playlists, manifests and
+/// segment lists, which are rewritten after every segment, are ignored, and
+/// the `.tmp` suffix used by the `temp_file` HLS flag is removed.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_output_segment;
+///
+/// let line = "[info] [hls @ 0x7f8b1c004a80] Opening 'out/seg0001.ts' for writing\n";
+/// assert_eq!(try_parse_output_segment(line), Some("out/seg0001.ts".to_string()));
+///
+/// let line = "[info] [hls @ 0x7f8b1c004a80] Opening 'out/stream.m3u8.tmp' for writing\n";
+/// assert_eq!(try_parse_output_segment(line), None);
+/// ```
+pub fn try_parse_output_segment(string: &str) -> Option<String> {
+  let is_segmenter = ["[hls @", "[dash @", "[segment @"]
+    .iter()
+    .any(|muxer| string.contains(muxer));
+  if !is_segmenter {
+    return None;
+  }
+
+  let path = string
+    .split_once("Opening '")?
+    .1
+    .rsplit_once("' for writing")?
+    .0;
+  let path = path.strip_suffix(".tmp").unwrap_or(path);
+
+  let is_playlist = [".m3u8", ".mpd", ".csv", ".ffcat", ".ffconcat", ".txt"]
+    .iter()
+    .any(|extension| path.ends_with(extension));
+  if is_playlist {
+    return None;
+  }
+
+  Some(path.to_string())
+}
+
+/// Identifies the series a segment belongs to: the muxer that logged it,
+/// which tells outputs apart, and its path without the segment number, which
+/// tells apart the variants of an HLS `var_stream_map`.
+fn segment_series(line: &str, path: &str) -> String {
+  let muxer = line
+    .split_once(" @ ")
+    .and_then(|(_, rest)| rest.split_once(']'))
+    .map_or("", |(address, _)| address);
+
+  let name_start = path.rfind(['/', '\\']).map_or(0, |i| i + 1);
+  let name = &path[name_start..];
+  let number_end = name
+    .rfind(|c: char| c.is_ascii_digit())
+    .map_or(0, |i| i + 1);
+  let number_start = name[..number_end]
+    .rfind(|c: char| !c.is_ascii_digit())
+    .map_or(0, |i| i + 1);
+  format!(
+    "{muxer} {}{}{}",
+    &path[..name_start],
+    &name[..number_start],
+    &name[number_end..]
+  )
+}
+
+/// Parse a single `key=value` line of the machine-readable feed written by
+/// `-progress`. Unlike regular log lines, these have no log level prefix.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_progress_feed_field;
+///
+/// let field = try_parse_progress_feed_field("total_size=1048576\n").unwrap();
+/// assert_eq!(field, ("total_size".to_string(), "1048576".to_string()));
+///
+/// assert!(try_parse_progress_feed_field("[info] frame=1 fps=0.0 q=0.0 size=0kB").is_none());
+/// ```
+pub fn try_parse_progress_feed_field(string: &str) -> Option<(String, String)> {
+  let (key, value) = string.trim_end().split_once('=')?;
+  let is_key = !key.is_empty()
+    && key
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+  if !is_key || value.contains(char::is_whitespace) {
+    return None;
+  }
+
+  Some((key.to_string(), value.to_string()))
+}
+
+/// Parse the JSON block printed by the `loudnorm` filter with
+/// `print_format=json`, given as a single string.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_loudnorm_stats;
+///
+/// let block = r#"{
+///   "input_i" : "-27.61",
+///   "input_tp" : "-4.47",
+///   "input_lra" : "18.06",
+///   "input_thresh" : "-39.20",
+///   "output_i" : "-16.58",
+///   "output_tp" : "-1.50",
+///   "output_lra" : "14.78",
+///   "output_thresh" : "-27.71",
+///   "normalization_type" : "dynamic",
+///   "target_offset" : "0.58"
+/// }"#;
+///
+/// let stats = try_parse_loudnorm_stats(block).unwrap();
+/// assert_eq!(stats.input_i, -27.61);
+/// assert_eq!(stats.output_tp, -1.5);
+/// assert_eq!(stats.normalization_type, "dynamic");
+/// assert_eq!(stats.target_offset, 0.58);
+/// ```
+pub fn try_parse_loudnorm_stats(string: &str) -> Option<LoudnormStats> {
+  let fields = string
+    .lines()
+    .filter_map(|line| {
+      let (key, value) = line.trim().trim_end_matches(',').split_once(':')?;
+      Some((key.trim().trim_matches('"'), value.trim().trim_matches('"')))
+    })
+    .collect::<Vec<_>>();
+  let field = |name: &str| {
+    fields
+      .iter()
+      .find(|(key, _)| *key == name)
+      .map(|(_, value)| *value)
+  };
+  let number = |name: &str| field(name)?.parse::<f64>().ok();
+
+  Some(LoudnormStats {
+    input_i: number("input_i")?,
+    input_tp: number("input_tp")?,
+    input_lra: number("input_lra")?,
+    input_thresh: number("input_thresh")?,
+    output_i: number("output_i")?,
+    output_tp: number("output_tp")?,
+    output_lra: number("output_lra")?,
+    output_thresh: number("output_thresh")?,
+    normalization_type: field("normalization_type")?.to_string(),
+    target_offset: number("target_offset")?,
+    raw_log_message: string.to_string(),
+  })
+}
+
+/// Parse the start time in seconds from a `silence_start` line of the
+/// `silencedetect` filter.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_silence_start;
+///
+/// let line = "[info] [silencedetect @ 0x1] silence_start: 1.50002\n";
+/// assert_eq!(try_parse_silence_start(line), Some(1.50002));
+/// ```
+pub fn try_parse_silence_start(string: &str) -> Option<f64> {
+  let (_, rest) = string.split_once("] silence_start: ")?;
+  rest.trim().parse().ok()
+}
+
+/// Parse the end time and the duration in seconds from a `silence_end`
+/// line of the `silencedetect` filter.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_silence_end;
+///
+/// let line = "[info] [silencedetect @ 0x1] silence_end: 3.5 | silence_duration: 2.00002\n";
+/// assert_eq!(try_parse_silence_end(line), Some((3.5, 2.00002)));
+/// ```
+pub fn try_parse_silence_end(string: &str) -> Option<(f64, f64)> {
+  let (_, rest) = string.split_once("] silence_end: ")?;
+  let (end, duration) = rest.split_once(" | silence_duration: ")?;
+  Some((end.trim().parse().ok()?, duration.trim().parse().ok()?))
+}
+
+/// Parse the presentation time in seconds of a frame logged by the
+/// `metadata=print` or `showinfo` filters.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_frame_pts_time;
+///
+/// let line = "[info] [Parsed_metadata_1 @ 0x1] frame:3    pts:48      pts_time:1.92\n";
+/// assert_eq!(try_parse_frame_pts_time(line), Some(1.92));
+/// ```
+pub fn try_parse_frame_pts_time(string: &str) -> Option<f64> {
+  let (_, rest) = string.split_once(" pts_time:")?;
+  rest.split_whitespace().next()?.parse().ok()
+}
+
+/// Parse the score logged by `metadata=print` for a frame selected by
+/// `select='gt(scene,...)'`.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_scene_score;
+///
+/// let line = "[info] [Parsed_metadata_1 @ 0x1] lavfi.scene_score=0.521370\n";
+/// assert_eq!(try_parse_scene_score(line), Some(0.52137));
+/// ```
+pub fn try_parse_scene_score(string: &str) -> Option<f64> {
+  let (_, rest) = string.split_once("] lavfi.scene_score=")?;
+  rest.trim().parse().ok()
+}
+
+/// Parse a black interval reported by the `blackdetect` filter.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_black_interval;
+///
+/// let line = "[info] [blackdetect @ 0x1] black_start:0 black_end:2.04 black_duration:2.04\n";
+/// let interval = try_parse_black_interval(line).unwrap();
+/// assert_eq!(interval.start, 0.0);
+/// assert_eq!(interval.end, 2.04);
+/// assert_eq!(interval.duration, 2.04);
+/// ```
+pub fn try_parse_black_interval(string: &str) -> Option<BlackInterval> {
+  capture_line(string, parse_black_interval)
+}
+
+/// [`try_parse_black_interval`] without capturing the raw log message.
+fn parse_black_interval(string: &str) -> Option<BlackInterval> {
+  let (_, rest) = string.split_once("] black_start:")?;
+  let mut values = rest.split_whitespace();
+  let start = values.next()?.parse().ok()?;
+  let end = values.next()?.strip_prefix("black_end:")?.parse().ok()?;
+  let duration = values
+    .next()?
+    .strip_prefix("black_duration:")?
+    .parse()
+    .ok()?;
+
+  Some(BlackInterval {
+    start,
+    end,
+    duration,
+    raw_log_message: String::new(),
+  })
+}
+
+/// Split a log line into its level, its component and its message. The
+/// prefixes are the `[name @ address]` of the components and the `[level]`
+/// added by `-loglevel level`, in any order. Lines without a level prefix
+/// have [`LogLevel::Unknown`].
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::event::LogLevel;
+/// use async_ffmpeg_sidecar::log_parser::parse_log_line;
+///
+/// let line = "[hls @ 0x55d0c8a4b2c0] [warning] Opening 'out5.ts' for writing\n";
+/// let log = parse_log_line(line);
+/// assert_eq!(log.level, LogLevel::Warning);
+/// let component = log.component.unwrap();
+/// assert_eq!(component.name, "hls");
+/// assert_eq!(component.address, "0x55d0c8a4b2c0");
+/// assert_eq!(log.message, "Opening 'out5.ts' for writing");
+///
+/// let log = parse_log_line("[mov,mp4 @ 0x1] [h264 @ 0x2] [debug] nal_unit_type: 7\n");
+/// assert_eq!(log.level, LogLevel::Debug);
+/// assert_eq!(log.component.unwrap().name, "h264");
+///
+/// let log = parse_log_line("[info] [graph 0 input from stream 0:0 @ 0x3] tb:1/25\n");
+/// assert_eq!(log.level, LogLevel::Info);
+/// assert_eq!(log.component.unwrap().name, "graph 0 input from stream 0:0");
+///
+/// let log = parse_log_line("    Last message repeated 2 times\n");
+/// assert_eq!(log.level, LogLevel::Unknown);
+/// assert_eq!(log.component, None);
+/// assert_eq!(log.message, "    Last message repeated 2 times");
+/// ```
+pub fn parse_log_line(string: &str) -> FfmpegLogLine {
+  let (level, component, message) = split_log_prefixes(string);
+  FfmpegLogLine {
+    level,
+    component: component.map(|(name, address)| LogComponent {
+      name: name.to_string(),
+      address: address.to_string(),
+    }),
+    message: message.to_string(),
+  }
+}
+
+/// The level, the innermost component name and address, and the message of
+/// a log line, borrowed from it, see [`parse_log_line`].
+fn split_log_prefixes(string: &str) -> (LogLevel, Option<(&str, &str)>, &str) {
+  let mut level = LogLevel::Unknown;
+  let mut component = None;
+  let mut rest = string.trim_end_matches(['\r', '\n']);
+
+  while let Some(tag) = rest.strip_prefix('[') {
+    let Some((tag, after)) = tag.split_once(']') else {
+      break;
+    };
+    if let Some(name_address) = tag.rsplit_once(" @ ") {
+      component = Some(name_address);
+    } else if let Some(tag_level) = parse_log_level(tag) {
+      level = tag_level;
+    } else {
+      // Part of the message, e.g. a bracketed stream specifier
+      break;
+    }
+    rest = after.strip_prefix(' ').unwrap_or(after);
+  }
+
+  (level, component, rest)
+}
+
+/// The level of a `-loglevel level` prefix, e.g. `warning`.
+fn parse_log_level(name: &str) -> Option<LogLevel> {
+  match name {
+    "trace" => Some(LogLevel::Trace),
+    "debug" => Some(LogLevel::Debug),
+    "verbose" => Some(LogLevel::Verbose),
+    "info" => Some(LogLevel::Info),
+    "warning" => Some(LogLevel::Warning),
+    "error" => Some(LogLevel::Error),
+    "fatal" | "panic" => Some(LogLevel::Fatal),
+    _ => None,
+  }
+}
+
+/// The substrings identifying each [`ErrorKind`], checked in order.
+const ERROR_KIND_MESSAGES: &[(&str, ErrorKind)] = &[
+  ("No such file or directory", ErrorKind::NoSuchFile),
+  ("Permission denied", ErrorKind::PermissionDenied),
+  ("Unknown encoder", ErrorKind::UnknownEncoder),
+  ("Connection refused", ErrorKind::ConnectionRefused),
+  ("moov atom not found", ErrorKind::MoovAtomNotFound),
+  ("Device or resource busy", ErrorKind::DeviceBusy),
+  (
+    "Invalid data found when processing input",
+    ErrorKind::InvalidData,
+  ),
+  ("Invalid argument", ErrorKind::InvalidArgument),
+  ("Unrecognized option", ErrorKind::InvalidArgument),
+  ("Option not found", ErrorKind::InvalidArgument),
+];
+
+/// Classify an error message by its cause, falling back to
+/// [`ErrorKind::Other`].
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::event::ErrorKind;
+/// use async_ffmpeg_sidecar::log_parser::classify_error;
+///
+/// assert_eq!(classify_error("missing.mp4: No such file or directory"), ErrorKind::NoSuchFile);
+/// assert_eq!(classify_error("Unrecognized option 'foo'."), ErrorKind::InvalidArgument);
+/// assert_eq!(classify_error("Conversion failed!"), ErrorKind::Other);
+/// ```
+pub fn classify_error(message: &str) -> ErrorKind {
+  ERROR_KIND_MESSAGES
+    .iter()
+    .find(|(pattern, _)| message.contains(pattern))
+    .map_or(ErrorKind::Other, |(_, kind)| *kind)
+}
+
+/// The cause of an `[error]` or `[fatal]` log line, if it is one of the
+/// recognized [`ErrorKind`]s. Other lines remain plain log messages.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::event::ErrorKind;
+/// use async_ffmpeg_sidecar::log_parser::try_parse_error_kind;
+///
+/// let line = "[fatal] [in#0 @ 0x1] Error opening input: No such file or directory\n";
+/// assert_eq!(try_parse_error_kind(line), Some(ErrorKind::NoSuchFile));
+///
+/// let line = "[error] [mov,mp4,m4a,3gp,3g2,mj2 @ 0x1] moov atom not found\n";
+/// assert_eq!(try_parse_error_kind(line), Some(ErrorKind::MoovAtomNotFound));
+///
+/// assert_eq!(try_parse_error_kind("[error] Conversion failed!\n"), None);
+/// assert_eq!(try_parse_error_kind("[info] Permission denied\n"), None);
+/// ```
+pub fn try_parse_error_kind(string: &str) -> Option<ErrorKind> {
+  let level = parse_log_line(string).level;
+  if !matches!(level, LogLevel::Error | LogLevel::Fatal) {
+    return None;
+  }
+  match classify_error(string) {
+    ErrorKind::Other => None,
+    kind => Some(kind),
+  }
+}
+
+/// Parse the summary printed for each output when Ffmpeg finishes.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_encode_summary;
+///
+/// let line = "[info] [out#0/mp4 @ 0x1] video:17884KiB audio:456KiB subtitle:0KiB other streams:0KiB global headers:0KiB muxing overhead: 0.51%\n";
+/// let summary = try_parse_encode_summary(line).unwrap();
+/// assert_eq!(summary.video_kb, 17884);
+/// assert_eq!(summary.audio_kb, 456);
+/// assert_eq!(summary.muxing_overhead_pct, Some(0.51));
+///
+/// let line = "[info] video:0kB audio:0kB subtitle:0kB other streams:0kB global headers:0kB muxing overhead: unknown\n";
+/// let summary = try_parse_encode_summary(line).unwrap();
+/// assert_eq!(summary.video_kb, 0);
+/// assert_eq!(summary.muxing_overhead_pct, None);
+/// ```
+pub fn try_parse_encode_summary(string: &str) -> Option<EncodeSummary> {
+  capture_line(string, parse_encode_summary)
+}
+
+/// [`try_parse_encode_summary`] without capturing the raw log message.
+fn parse_encode_summary(string: &str) -> Option<EncodeSummary> {
+  let size = |key: &str| -> Option<u64> {
+    let (_, rest) = string.split_once(key)?;
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    rest[..digits].parse().ok()
+  };
+  let (_, overhead) = string.split_once("muxing overhead:")?;
+
+  Some(EncodeSummary {
+    video_kb: size("video:")?,
+    audio_kb: size("audio:")?,
+    subtitle_kb: size("subtitle:")?,
+    other_streams_kb: size("other streams:")?,
+    global_headers_kb: size("global headers:")?,
+    muxing_overhead_pct: overhead.trim().trim_end_matches('%').parse().ok(),
+    raw_log_message: String::new(),
+  })
+}
+
+/// Parse the frame number, the percentage of black pixels and the time in
+/// seconds of a frame reported by the `blackframe` filter.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_black_frame;
+///
+/// let line = "[info] [Parsed_blackframe_0 @ 0x1] frame:10 pblack:99 pts:10 t:0.400000 type:P last_keyframe:0\n";
+/// assert_eq!(try_parse_black_frame(line), Some((10, 99, 0.4)));
+/// ```
+pub fn try_parse_black_frame(string: &str) -> Option<(u32, u32, f64)> {
+  let (_, rest) = string.split_once("] frame:")?;
+  let mut values = rest.split_whitespace();
+  let frame = values.next()?.parse().ok()?;
+  let percent_black = values.next()?.strip_prefix("pblack:")?.parse().ok()?;
+  let time = values
+    .find_map(|value| value.strip_prefix("t:"))?
+    .parse()
+    .ok()?;
+
+  Some((frame, percent_black, time))
+}
+
+/// Parse the `crop=w:h:x:y` suggestion of a `cropdetect` line into width,
+/// height, x and y.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_crop_suggestion;
+///
+/// let line = "[info] [Parsed_cropdetect_0 @ 0x1] x1:0 x2:1919 y1:140 y2:939 w:1920 h:800 x:0 y:140 pts:1 t:0.040000 limit:0.094118 crop=1920:800:0:140\n";
+/// assert_eq!(try_parse_crop_suggestion(line), Some((1920, 800, 0, 140)));
+/// ```
+pub fn try_parse_crop_suggestion(string: &str) -> Option<(u32, u32, u32, u32)> {
+  if !string.contains("[Parsed_cropdetect_") {
+    return None;
+  }
+  let (_, crop) = string.rsplit_once(" crop=")?;
+  let mut values = crop.trim().split(':').map(|value| value.parse().ok());
+  Some((
+    values.next()??,
+    values.next()??,
+    values.next()??,
+    values.next()??,
+  ))
+}
+
+/// Parse a `key: value` line printed by the `volumedetect` filter at the end
+/// of a run.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_volumedetect_field;
+///
+/// let line = "[info] [Parsed_volumedetect_0 @ 0x1] mean_volume: -20.5 dB\n";
+/// assert_eq!(
+///   try_parse_volumedetect_field(line),
+///   Some(("mean_volume".to_string(), "-20.5 dB".to_string()))
+/// );
+/// ```
+pub fn try_parse_volumedetect_field(string: &str) -> Option<(String, String)> {
+  let (_, rest) = string.split_once("[Parsed_volumedetect_")?;
+  let (_, field) = rest.split_once("] ")?;
+  let (key, value) = field.split_once(": ")?;
+  Some((key.to_string(), value.trim().to_string()))
+}
+
+/// Parse the summary printed by the `psnr` filter at the end of a run.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_psnr_summary;
+///
+/// let line = "[info] [Parsed_psnr_4 @ 0x1] PSNR y:34.57 u:40.12 v:40.55 average:35.90 min:33.10 max:inf\n";
+/// let psnr = try_parse_psnr_summary(line).unwrap();
+/// assert_eq!(psnr.components[0], ("y".to_string(), 34.57));
+/// assert_eq!(psnr.average, 35.9);
+/// assert_eq!(psnr.max, f64::INFINITY);
+/// ```
+pub fn try_parse_psnr_summary(string: &str) -> Option<PsnrSummary> {
+  let (_, rest) = string.split_once("] PSNR ")?;
+  let mut components = Vec::new();
+  let (mut average, mut min, mut max) = (None, None, None);
+  for value in rest.split_whitespace() {
+    let (key, value) = value.split_once(':')?;
+    let value = value.parse().ok()?;
+    match key {
+      "average" => average = Some(value),
+      "min" => min = Some(value),
+      "max" => max = Some(value),
+      key => components.push((key.to_string(), value)),
+    }
+  }
+
+  Some(PsnrSummary {
+    components,
+    average: average?,
+    min: min?,
+    max: max?,
+  })
+}
+
+/// Parse the summary printed by the `ssim` filter at the end of a run. The
+/// dB values in parentheses are skipped.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_ssim_summary;
+///
+/// let line = "[info] [Parsed_ssim_5 @ 0x1] SSIM Y:0.958 (13.77) U:0.970 (15.23) V:0.971 (15.37) All:0.962 (14.19)\n";
+/// let ssim = try_parse_ssim_summary(line).unwrap();
+/// assert_eq!(ssim.components.len(), 3);
+/// assert_eq!(ssim.components[0], ("Y".to_string(), 0.958));
+/// assert_eq!(ssim.all, 0.962);
+/// ```
+pub fn try_parse_ssim_summary(string: &str) -> Option<SsimSummary> {
+  let (_, rest) = string.split_once("] SSIM ")?;
+  let mut components = Vec::new();
+  let mut all = None;
+  for value in rest.split_whitespace().filter(|v| !v.starts_with('(')) {
+    let (key, value) = value.split_once(':')?;
+    let value = value.parse().ok()?;
+    match key {
+      "All" => all = Some(value),
+      key => components.push((key.to_string(), value)),
+    }
+  }
+
+  Some(SsimSummary {
+    components,
+    all: all?,
+  })
+}
+
+/// Parse a progress update line from ffmpeg.
+///
+/// ## Example
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_progress;
+/// use std::time::Duration;
+///
+/// let line = "[info] frame= 1996 fps=1984 q=-1.0 Lsize=     372kB time=00:01:19.72 bitrate=  38.2kbits/s speed=79.2x\n";
+/// let progress = try_parse_progress(line).unwrap();
+/// assert_eq!(progress.frame, 1996);
+/// assert_eq!(progress.fps, 1984.0);
+/// assert_eq!(progress.q, -1.0);
+/// assert_eq!(progress.size_kb, 372);
+/// assert_eq!(progress.time, Some(Duration::from_millis(79_720)));
+/// assert_eq!(progress.raw_time, "00:01:19.72");
+/// assert_eq!(progress.bitrate_kbps, 38.2);
+/// assert_eq!(progress.speed, 79.2);
+/// assert_eq!(progress.dup, None);
+///
+/// let line = "[info] frame=  300 fps= 30 q=28.0 size=    1024KiB time=00:00:10.00 bitrate= 838.9kbits/s dup=12 drop=3 speed=1.0x\n";
+/// let progress = try_parse_progress(line).unwrap();
+/// assert_eq!(progress.dup, Some(12));
+/// assert_eq!(progress.drop, Some(3));
+///
+/// let line = "[info] frame=    0 fps=0.0 q=0.0 size=       0KiB time=N/A bitrate=N/A speed=N/A\n";
+/// assert_eq!(try_parse_progress(line).unwrap().time, None);
+///
+/// let line = "[info] frame=    1 fps=0.0 q=0.0 size=       0KiB time=-577014:32:22.77 bitrate=  -0.0kbits/s speed=N/A\n";
+/// let progress = try_parse_progress(line).unwrap();
+/// assert_eq!(progress.time, None);
+/// assert_eq!(progress.raw_time, "-577014:32:22.77");
+/// ```
+pub fn try_parse_progress(string: &str) -> Option<FfmpegProgress> {
+  capture_line(string, parse_progress)
+}
+
+/// [`try_parse_progress`] without capturing the raw log message.
+fn parse_progress(mut string: &str) -> Option<FfmpegProgress> {
+  string = string.strip_prefix("[info]").unwrap_or(string).trim();
+
+  let frame = string
+    .split("frame=")
     .nth(1)?
     .split_whitespace()
-    .next()
-    .map(|s| s.trim())
-    .and_then(|s| {
-      s.strip_suffix("KiB") // FFmpeg v7.0 and later
-        .or_else(|| s.strip_suffix("kB")) // FFmpeg v6.0 and prior
-        .or_else(|| s.ends_with("N/A").then(|| "0")) // handles "N/A"
-    })?
-    .parse::<u32>()
+    .next()?
+    .parse::<u64>()
+    .ok()?;
+  let fps = string
+    .split("fps=")
+    .nth(1)?
+    .split_whitespace()
+    .next()?
+    .parse::<f32>()
     .ok()?;
-  let time = string
+  let q = string
+    .split("q=")
+    .nth(1)?
+    .split_whitespace()
+    .next()?
+    .parse::<f32>()
+    .ok()?;
+  let size_kb = parse_size_kb(
+    string
+      .split("size=") // captures "Lsize=" AND "size="
+      .nth(1)?
+      .split_whitespace()
+      .next()?,
+  )?;
+  let raw_time = string
     .split("time=")
     .nth(1)?
     .split_whitespace()
     .next()?
     .to_string();
+  // "N/A" before the first output, and negative before the first timestamp
+  let time = parse_time_str(&raw_time)
+    .filter(|seconds| *seconds >= 0.0)
+    .map(Duration::from_secs_f64);
   let bitrate_kbps = string
     .split("bitrate=")
     .nth(1)?
@@ -577,6 +2177,17 @@ pub fn try_parse_progress(mut string: &str) -> Option<FfmpegProgress> {
     .strip_suffix('x')
     .map(|s| s.parse::<f32>().unwrap_or(0.0))
     .unwrap_or(0.0);
+  let count = |key: &str| {
+    string
+      .split(key)
+      .nth(1)?
+      .split_whitespace()
+      .next()?
+      .parse::<u64>()
+      .ok()
+  };
+  let dup = count("dup=");
+  let drop = count("drop=");
 
   Some(FfmpegProgress {
     frame,
@@ -584,9 +2195,220 @@ pub fn try_parse_progress(mut string: &str) -> Option<FfmpegProgress> {
     q,
     size_kb,
     time,
+    raw_time,
     bitrate_kbps,
     speed,
-    raw_log_message,
+    dup,
+    drop,
+    raw_log_message: String::new(),
+  })
+}
+
+/// Parse the `size` of a progress line in kibibytes. Ffmpeg 7.0 and later
+/// print binary units such as `KiB`, and earlier versions `kB`, `mB` and
+/// `gB` with the same meaning. `N/A` is reported as zero.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::parse_size_kb;
+///
+/// assert_eq!(parse_size_kb("10KiB"), Some(10));
+/// assert_eq!(parse_size_kb("256kB"), Some(256));
+/// assert_eq!(parse_size_kb("2MiB"), Some(2048));
+/// assert_eq!(parse_size_kb("5GiB"), Some(5 * 1024 * 1024));
+/// assert_eq!(parse_size_kb("N/A"), Some(0));
+/// assert_eq!(parse_size_kb("lots"), None);
+/// ```
+pub fn parse_size_kb(string: &str) -> Option<u64> {
+  let string = string.trim();
+  if string.ends_with("N/A") {
+    return Some(0);
+  }
+
+  let split = string
+    .find(|c: char| !c.is_ascii_digit() && c != '.')
+    .unwrap_or(string.len());
+  let (number, unit) = string.split_at(split);
+  let factor = match unit {
+    "B" => 1.0 / 1024.0,
+    "KiB" | "kB" | "KB" => 1.0,
+    "MiB" | "mB" | "MB" => 1024.0,
+    "GiB" | "gB" | "GB" => 1024.0 * 1024.0,
+    "TiB" | "tB" | "TB" => 1024.0 * 1024.0 * 1024.0,
+    _ => return None,
+  };
+  Some((number.parse::<f64>().ok()? * factor).round() as u64)
+}
+
+/// Encoders, decoders and filters which are only included in builds
+/// configured with `--enable-gpl`.
+const GPL_COMPONENTS: &[&str] = &[
+  "libx264",
+  "libx264rgb",
+  "libx265",
+  "libxvid",
+  "libxavs",
+  "libxavs2",
+  "libdavs2",
+  "librubberband",
+  "rubberband",
+  "vidstabdetect",
+  "vidstabtransform",
+  "frei0r",
+  "frei0r_src",
+  "blackframe",
+  "cropdetect",
+  "delogo",
+  "eq",
+  "hqdn3d",
+  "mpdecimate",
+  "pp",
+  "pp7",
+  "spp",
+  "uspp",
+  "fspp",
+  "sab",
+  "smartblur",
+  "stereo3d",
+  "tinterlace",
+  "interlace",
+];
+
+/// Components which are only included in builds configured with `--enable-nonfree`.
+const NONFREE_COMPONENTS: &[&str] = &["libfdk_aac"];
+
+/// The suffixes of the encoders and decoders of hardware APIs, which builds
+/// may leave out regardless of their license.
+const HARDWARE_CODEC_SUFFIXES: &[&str] = &[
+  "_nvenc",
+  "_cuvid",
+  "_qsv",
+  "_vaapi",
+  "_amf",
+  "_videotoolbox",
+  "_at",
+  "_mediacodec",
+  "_v4l2m2m",
+  "_mf",
+  "_rkmpp",
+  "_omx",
+];
+
+/// Whether an encoder or decoder wraps an external library or a hardware
+/// API, and may therefore be missing from a build. Other unknown codec names
+/// are most likely typos, see [`ErrorKind::UnknownEncoder`].
+fn is_optional_codec(name: &str) -> bool {
+  name.starts_with("lib")
+    || HARDWARE_CODEC_SUFFIXES
+      .iter()
+      .any(|suffix| name.ends_with(suffix))
+}
+
+/// Detects errors caused by a component missing from the FFmpeg build, such
+/// as an encoder that is only available in GPL builds or a disabled protocol.
+///
+/// Unknown encoders and decoders are only reported if they look optional,
+/// i.e. wrap an external library (`lib*`) or a hardware API (e.g. `*_nvenc`
+/// or `*_vaapi`), so that a typo like `h265` is reported as
+/// [`ErrorKind::UnknownEncoder`] instead.
+///
+/// ## Examples
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::event::{BuildLicense, MissingComponent};
+/// use async_ffmpeg_sidecar::log_parser::try_parse_build_capability_error;
+///
+/// let line = "[error] Unknown encoder 'libx264'\n";
+/// let error = try_parse_build_capability_error(line).unwrap();
+/// assert_eq!(error.component, MissingComponent::Encoder("libx264".to_string()));
+/// assert_eq!(error.required_license, Some(BuildLicense::Gpl));
+///
+/// for name in ["libopenh264", "hevc_v4l2m2m", "vp9_vaapi", "aac_at", "h264_rkmpp"] {
+///   let line = format!("[error] Unknown encoder '{name}'\n");
+///   assert!(try_parse_build_capability_error(&line).is_some());
+/// }
+/// assert_eq!(try_parse_build_capability_error("[error] Unknown encoder 'h265'\n"), None);
+/// ```
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::event::MissingComponent;
+/// use async_ffmpeg_sidecar::log_parser::try_parse_build_capability_error;
+///
+/// let line = "[error] https://example.com/video.mp4: Protocol not found\n";
+/// let error = try_parse_build_capability_error(line).unwrap();
+/// assert_eq!(error.component, MissingComponent::Protocol("https".to_string()));
+/// assert_eq!(error.required_license, None);
+/// ```
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::event::MissingComponent;
+/// use async_ffmpeg_sidecar::log_parser::try_parse_build_capability_error;
+///
+/// let line = "[AVFilterGraph @ 0x600002a2c000] [error] No such filter: 'subtitles'\n";
+/// let error = try_parse_build_capability_error(line).unwrap();
+/// assert_eq!(error.component, MissingComponent::Filter("subtitles".to_string()));
+/// ```
+pub fn try_parse_build_capability_error(string: &str) -> Option<BuildCapabilityError> {
+  capture_line(string, parse_build_capability_error)
+}
+
+/// [`try_parse_build_capability_error`] without capturing the raw log message.
+fn parse_build_capability_error(string: &str) -> Option<BuildCapabilityError> {
+  let quoted = |marker: &str| -> Option<String> {
+    let rest = string.split(marker).nth(1)?;
+    Some(rest.split('\'').next()?.to_string())
+  };
+
+  let component = if let Some(name) = quoted("Unknown encoder '") {
+    MissingComponent::Encoder(name)
+  } else if let Some(name) = quoted("Unknown decoder '") {
+    MissingComponent::Decoder(name)
+  } else if let Some(name) = quoted("No such filter: '") {
+    MissingComponent::Filter(name)
+  } else if string.contains(": Protocol not found") {
+    let url = string
+      .split(": Protocol not found")
+      .next()?
+      .rsplit("] ")
+      .next()?
+      .trim();
+    MissingComponent::Protocol(url.split(':').next()?.to_string())
+  } else {
+    return None;
+  };
+
+  let name = component.name();
+  let required_license = if GPL_COMPONENTS.contains(&name) {
+    Some(BuildLicense::Gpl)
+  } else if NONFREE_COMPONENTS.contains(&name) {
+    Some(BuildLicense::NonFree)
+  } else {
+    None
+  };
+
+  let is_codec = matches!(
+    component,
+    MissingComponent::Encoder(_) | MissingComponent::Decoder(_)
+  );
+  if is_codec && required_license.is_none() && !is_optional_codec(name) {
+    return None;
+  }
+
+  let suggestion = match required_license {
+    Some(BuildLicense::Gpl) => match ffmpeg_download_url() {
+      Ok(url) => format!("`{name}` requires a GPL build of FFmpeg, such as the one available at {url}"),
+      Err(_) => format!("`{name}` requires a GPL build of FFmpeg (configured with --enable-gpl)"),
+    },
+    Some(BuildLicense::NonFree) => format!(
+      "`{name}` requires FFmpeg built with --enable-nonfree, which cannot be redistributed; compile FFmpeg from source"
+    ),
+    None => format!("`{name}` is not enabled in this FFmpeg build; use a full build that includes it"),
+  };
+
+  Some(BuildCapabilityError {
+    component,
+    required_license,
+    suggestion,
+    raw_log_message: String::new(),
   })
 }
 
@@ -604,9 +2426,15 @@ pub fn try_parse_progress(mut string: &str) -> Option<FfmpegProgress> {
 /// assert_eq!(parse_time_str("1:00.0"), Some(60.0));
 /// assert_eq!(parse_time_str("1:01.0"), Some(61.0));
 /// assert_eq!(parse_time_str("1:01:01.123"), Some(3661.123));
+/// assert_eq!(parse_time_str("-00:00:00.04"), Some(-0.04));
+/// assert_eq!(parse_time_str("-01:00:00.50"), Some(-3600.5));
 /// assert_eq!(parse_time_str("N/A"), None);
 /// ```
 pub fn parse_time_str(s: &str) -> Option<f64> {
+  if let Some(s) = s.strip_prefix('-') {
+    return parse_time_str(s).map(|seconds| -seconds);
+  }
+
   let mut seconds = 0.0;
 
   let mut smh = s.split(':').rev();
@@ -633,6 +2461,7 @@ mod tests {
   use crate::paths::ffmpeg_path;
   use std::io::{Cursor, Seek, SeekFrom, Write};
   use std::process::Stdio;
+  use tokio::io::BufReader;
   use tokio::process::Command;
 
   #[tokio::test]
@@ -699,6 +2528,419 @@ mod tests {
     assert!(num_events > 1);
   }
 
+  #[tokio::test]
+  async fn test_progress_feed_block() {
+    let log = "[info] Press [q] to stop, [?] for help\nframe=0\ntotal_size=1048576\nout_time_us=2000000\nprogress=continue\n[info] frame=    0 fps=0.0 q=-1.0 size=    1024kB time=00:00:02.00 bitrate=4194.3kbits/s speed=4x\ntotal_size=2097152\nprogress=end\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+
+    assert!(matches!(
+      parser.parse_next_event().await.unwrap(),
+      FfmpegEvent::Log(LogLevel::Info, _)
+    ));
+    let FfmpegEvent::ProgressFeed(feed) = parser.parse_next_event().await.unwrap() else {
+      panic!("expected a progress feed update");
+    };
+    assert_eq!(feed.fields.len(), 4);
+    assert_eq!(feed.total_size(), Some(1048576));
+    assert_eq!(feed.out_time_us(), Some(2000000));
+    assert!(!feed.is_end());
+
+    assert!(matches!(
+      parser.parse_next_event().await.unwrap(),
+      FfmpegEvent::Progress(_)
+    ));
+    let FfmpegEvent::ProgressFeed(feed) = parser.parse_next_event().await.unwrap() else {
+      panic!("expected a progress feed update");
+    };
+    assert_eq!(feed.total_size(), Some(2097152));
+    assert!(feed.is_end());
+    assert_eq!(
+      parser.parse_next_event().await.unwrap(),
+      FfmpegEvent::LogEOF
+    );
+  }
+
+  #[tokio::test]
+  async fn test_output_segments() {
+    let log = "[info] [hls @ 0x1] Opening 'out/seg0.ts' for writing\n[info] [hls @ 0x1] Opening 'out/stream.m3u8.tmp' for writing\n[info] [hls @ 0x1] Opening 'out/seg1.ts' for writing\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+
+    let mut events = Vec::new();
+    loop {
+      match parser.parse_next_event().await.unwrap() {
+        FfmpegEvent::LogEOF => break,
+        FfmpegEvent::Log(..) => {}
+        event => events.push(event),
+      }
+    }
+
+    assert_eq!(
+      events,
+      [
+        FfmpegEvent::OutputSegmentOpened(FfmpegOutputSegment {
+          path: "out/seg0.ts".to_string(),
+          sequence: 0,
+        }),
+        FfmpegEvent::OutputSegmentClosed(FfmpegOutputSegment {
+          path: "out/seg0.ts".to_string(),
+          sequence: 0,
+        }),
+        FfmpegEvent::OutputSegmentOpened(FfmpegOutputSegment {
+          path: "out/seg1.ts".to_string(),
+          sequence: 1,
+        }),
+        FfmpegEvent::OutputSegmentClosed(FfmpegOutputSegment {
+          path: "out/seg1.ts".to_string(),
+          sequence: 1,
+        }),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_output_segments_per_series() {
+    // Two variants of one HLS output, and a second segment output
+    let log = "[info] [hls @ 0x1] Opening 'out/v0/seg0.ts' for writing\n\
+      [info] [hls @ 0x1] Opening 'out/v1/seg0.ts' for writing\n\
+      [info] [segment @ 0x2] Opening 'rec_000.mkv' for writing\n\
+      [info] [hls @ 0x1] Opening 'out/v0/seg1.ts' for writing\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+
+    let mut events = Vec::new();
+    loop {
+      match parser.parse_next_event().await.unwrap() {
+        FfmpegEvent::LogEOF => break,
+        FfmpegEvent::OutputSegmentOpened(segment) => events.push(("opened", segment.path)),
+        FfmpegEvent::OutputSegmentClosed(segment) => events.push(("closed", segment.path)),
+        _ => {}
+      }
+    }
+
+    assert_eq!(
+      events,
+      [
+        ("opened", "out/v0/seg0.ts".to_string()),
+        ("opened", "out/v1/seg0.ts".to_string()),
+        ("opened", "rec_000.mkv".to_string()),
+        ("closed", "out/v0/seg0.ts".to_string()),
+        ("opened", "out/v0/seg1.ts".to_string()),
+        ("closed", "out/v1/seg0.ts".to_string()),
+        ("closed", "rec_000.mkv".to_string()),
+        ("closed", "out/v0/seg1.ts".to_string()),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_metadata_blocks() {
+    let log = "[info] Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mp4':\n[info]   Metadata:\n[info]     title           : Holiday\n[info]     comment         : line one\n[info]                     : line two\n[info]   Duration: 00:00:05.00, start: 0.000000, bitrate: 16 kb/s\n[info]   Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p, 320x240, 25 fps, 25 tbr, 12800 tbn (default)\n[info]     Metadata:\n[info]       handler_name    : VideoHandler\n[info]     Side data:\n[info]       displaymatrix: rotation of -90.00 degrees\n[info]   Chapters:\n[info]     Chapter #0:0: start 0.000000, end 2.500000\n[info]       Metadata:\n[info]         title           : Intro\n[info] Stream mapping:\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+
+    let mut entries = Vec::new();
+    let mut rotations = Vec::new();
+    loop {
+      match parser.parse_next_event().await.unwrap() {
+        FfmpegEvent::ParsedMetadata(entry) => entries.push((entry.scope, entry.key, entry.value)),
+        FfmpegEvent::SideData(side_data) => rotations.push((side_data.scope, side_data.rotation())),
+        FfmpegEvent::LogEOF => break,
+        _ => {}
+      }
+    }
+
+    let input = MetadataScope::Input(0);
+    let stream = MetadataScope::InputStream {
+      input: 0,
+      stream: 0,
+    };
+    let chapter = MetadataScope::Chapter {
+      input: 0,
+      chapter: 0,
+    };
+    assert_eq!(
+      entries,
+      [
+        (input, "title".to_string(), "Holiday".to_string()),
+        (input, "comment".to_string(), "line one".to_string()),
+        (input, "comment".to_string(), "line two".to_string()),
+        (
+          stream,
+          "handler_name".to_string(),
+          "VideoHandler".to_string()
+        ),
+        (chapter, "title".to_string(), "Intro".to_string()),
+      ]
+    );
+    assert_eq!(rotations, [(stream, Some(-90.0))]);
+  }
+
+  #[tokio::test]
+  async fn test_loudnorm_block() {
+    let log = "[info] [Parsed_loudnorm_0 @ 0x1] \n{\n\t\"input_i\" : \"-inf\",\n\t\"input_tp\" : \"-inf\",\n\t\"input_lra\" : \"0.00\",\n\t\"input_thresh\" : \"-70.00\",\n\t\"output_i\" : \"-inf\",\n\t\"output_tp\" : \"-inf\",\n\t\"output_lra\" : \"0.00\",\n\t\"output_thresh\" : \"-70.00\",\n\t\"normalization_type\" : \"dynamic\",\n\t\"target_offset\" : \"inf\"\n}\n[info] [out#0/null @ 0x2] video:0KiB audio:0KiB\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+
+    assert!(matches!(
+      parser.parse_next_event().await.unwrap(),
+      FfmpegEvent::Log(LogLevel::Info, _)
+    ));
+    let FfmpegEvent::LoudnormStats(stats) = parser.parse_next_event().await.unwrap() else {
+      panic!("expected loudnorm stats");
+    };
+    assert_eq!(stats.input_i, f64::NEG_INFINITY);
+    assert_eq!(stats.input_thresh, -70.0);
+    assert_eq!(stats.target_offset, f64::INFINITY);
+    assert!(matches!(
+      parser.parse_next_event().await.unwrap(),
+      FfmpegEvent::Log(LogLevel::Info, _)
+    ));
+    assert_eq!(
+      parser.parse_next_event().await.unwrap(),
+      FfmpegEvent::LogEOF
+    );
+  }
+
+  #[tokio::test]
+  async fn test_image_sequence_events() {
+    let log = "[info] frame=    2 fps=0.0 q=-0.0 size=N/A time=00:00:00.08 bitrate=N/A speed=1x\n[info] frame=    3 fps=0.0 q=-0.0 Lsize=N/A time=00:00:00.12 bitrate=N/A speed=1x\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+    parser.expect_image_sequence("out/frame_%03d.png", 1);
+
+    let mut images = Vec::new();
+    loop {
+      match parser.parse_next_event().await.unwrap() {
+        FfmpegEvent::OutputImageWritten(image) => images.push(image),
+        FfmpegEvent::LogEOF => break,
+        _ => {}
+      }
+    }
+    assert_eq!(
+      images,
+      [
+        FfmpegOutputImage {
+          path: "out/frame_001.png".to_string(),
+          sequence: 0,
+        },
+        FfmpegOutputImage {
+          path: "out/frame_002.png".to_string(),
+          sequence: 1,
+        },
+        FfmpegOutputImage {
+          path: "out/frame_003.png".to_string(),
+          sequence: 2,
+        },
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_error_kinds() {
+    let log = "[error] [in#0 @ 0x1] Error opening input: No such file or directory\n[fatal] Error opening input file missing.mp4.\n[error] Error opening input files: No such file or directory\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+
+    let mut events = Vec::new();
+    loop {
+      match parser.parse_next_event().await.unwrap() {
+        FfmpegEvent::LogEOF => break,
+        event => events.push(event),
+      }
+    }
+
+    assert!(matches!(
+      events[0],
+      FfmpegEvent::Error {
+        kind: ErrorKind::NoSuchFile,
+        ..
+      }
+    ));
+    assert_eq!(
+      events[1],
+      FfmpegEvent::Log(
+        LogLevel::Fatal,
+        "[fatal] Error opening input file missing.mp4.".to_string()
+      )
+    );
+    assert!(matches!(
+      events[2],
+      FfmpegEvent::Error {
+        kind: ErrorKind::NoSuchFile,
+        ..
+      }
+    ));
+  }
+
+  #[tokio::test]
+  async fn test_log_levels() {
+    let log = "[libx264 @ 0x1] [verbose] using cpu capabilities: MMX2 SSE2Fast\n[h264 @ 0x2] [debug] nal_unit_type: 7\n[trace] detected 8 logical cores\n[hls @ 0x3] [warning] Duration of segment too long\n[info] Press [q] to stop\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+
+    let mut levels = Vec::new();
+    loop {
+      match parser.parse_next_event().await.unwrap() {
+        FfmpegEvent::Log(level, _) => levels.push(level),
+        FfmpegEvent::LogEOF => break,
+        event => panic!("unexpected event {event:?}"),
+      }
+    }
+
+    assert_eq!(
+      levels,
+      [
+        LogLevel::Verbose,
+        LogLevel::Debug,
+        LogLevel::Trace,
+        LogLevel::Warning,
+        LogLevel::Info,
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_invalid_utf8() {
+    let log = b"[info] Input #0, wav, from 'caf\xe9.wav':\r\n[info]   Duration: 00:00:05.00, bitrate: 1411 kb/s\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log.to_vec())));
+
+    let FfmpegEvent::ParsedInput(input) = parser.parse_next_event().await.unwrap() else {
+      panic!("expected an input");
+    };
+    assert_eq!(input.from, "caf\u{FFFD}.wav");
+    let FfmpegEvent::ParsedDuration(duration) = parser.parse_next_event().await.unwrap() else {
+      panic!("expected a duration");
+    };
+    assert_eq!(duration.duration, Some(5.0));
+  }
+
+  #[tokio::test]
+  async fn test_line_endings() {
+    let log = "[info] a\r\n[info] b\r[info] c\n\n[info] d";
+    // Read a byte at a time, so that `\r\n` is split between two reads
+    let mut parser = FfmpegLogParser::new(BufReader::with_capacity(1, Cursor::new(log)));
+
+    let mut lines = Vec::new();
+    while let Some(line) = parser.next_line().await.unwrap() {
+      lines.push(line);
+    }
+    assert_eq!(lines, ["[info] a", "[info] b", "[info] c", "", "[info] d"]);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_serde_round_trip() {
+    let line = "[info] frame=  300 fps= 30 q=28.0 size=    1024KiB time=00:00:10.00 bitrate= 838.9kbits/s dup=12 drop=3 speed=1.0x";
+    let events = [
+      FfmpegEvent::Progress(try_parse_progress(line).unwrap()),
+      FfmpegEvent::Custom(CustomEvent::new("freeze", 1.5, line)),
+    ];
+
+    let json = events
+      .iter()
+      .map(|event| serde_json::to_string(event).unwrap())
+      .collect::<Vec<_>>();
+    let parsed = json
+      .iter()
+      .map(|json| serde_json::from_str::<FfmpegEvent>(json).unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(parsed, events);
+  }
+
+  #[tokio::test]
+  async fn test_partial_line_across_polls() {
+    use crate::stream::FfmpegEventStream;
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    let (mut tx, rx) = tokio::io::duplex(1024);
+    let mut events = FfmpegEventStream::from_reader(rx);
+
+    // The event stream starts parsing anew on every poll, so the start of the
+    // line must survive the pending read of its end
+    tx.write_all(b"[info] frame=   48 fps=0.0 q=-0.0 size=")
+      .await
+      .unwrap();
+    assert!(futures::poll!(events.next()).is_pending());
+    tx.write_all(b"       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\r")
+      .await
+      .unwrap();
+
+    match events.next().await {
+      Some(FfmpegEvent::Progress(progress)) => assert_eq!(progress.frame, 48),
+      event => panic!("expected progress, got {event:?}"),
+    }
+  }
+
+  #[tokio::test]
+  async fn test_capture_raw_log_messages() {
+    let log = "[info] Input #0, lavfi, from 'testsrc':\n\
+      [info]   Stream #0:0: Video: rawvideo (RGB[24] / 0x18424752), rgb24, 320x240, 25 fps, 25 tbn\n\
+      [info] frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\n";
+
+    for capture in [true, false] {
+      let mut parser = FfmpegLogParser::new(log.as_bytes());
+      parser.capture_raw_log_messages(capture);
+      let mut raw = Vec::new();
+      loop {
+        match parser.parse_next_event().await.unwrap() {
+          FfmpegEvent::ParsedInput(input) => raw.push(input.raw_log_message),
+          FfmpegEvent::ParsedInputStream(stream) => raw.push(stream.raw_log_message),
+          FfmpegEvent::Progress(progress) => raw.push(progress.raw_log_message),
+          FfmpegEvent::LogEOF => break,
+          event => panic!("unexpected event {event:?}"),
+        }
+      }
+
+      assert_eq!(raw.len(), 3);
+      if capture {
+        assert!(raw.iter().all(|line| line.starts_with("[info]")));
+      } else {
+        assert!(raw.iter().all(String::is_empty));
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_unknown_encoder() {
+    let log = "[error] Unknown encoder 'h265'\n[error] Unknown encoder 'libx264'\n";
+    let mut parser = FfmpegLogParser::new(log.as_bytes());
+
+    match parser.parse_next_event().await.unwrap() {
+      FfmpegEvent::Error { kind, message } => {
+        assert_eq!(kind, ErrorKind::UnknownEncoder);
+        assert_eq!(message, "[error] Unknown encoder 'h265'");
+      }
+      event => panic!("expected an error, got {event:?}"),
+    }
+    match parser.parse_next_event().await.unwrap() {
+      FfmpegEvent::BuildCapabilityError(error) => {
+        assert_eq!(error.required_license, Some(BuildLicense::Gpl));
+      }
+      event => panic!("expected a build capability error, got {event:?}"),
+    }
+  }
+
+  #[tokio::test]
+  async fn test_unknown_duration() {
+    let log = "[info] Input #0, lavfi, from 'testsrc':\n[info]   Duration: N/A, start: 1.400000, bitrate: N/A\n";
+    let mut parser = FfmpegLogParser::new(BufReader::new(Cursor::new(log)));
+
+    parser.parse_next_event().await.unwrap();
+    let FfmpegEvent::ParsedDuration(duration) = parser.parse_next_event().await.unwrap() else {
+      panic!("expected a duration");
+    };
+    assert_eq!(duration.duration, None);
+    assert_eq!(duration.start_time, Some(1.4));
+    assert_eq!(duration.bitrate_kbps, None);
+  }
+
+  #[test]
+  fn test_video_stream_profile_and_level() {
+    let line = "[info]   Stream #0:0: Video: hevc (hevc_nvenc) (Main 10), p010le(tv, progressive), 3840x2160, level 153, 60 fps, 15360 tbn\n";
+    let video_data = try_parse_stream(line)
+      .unwrap()
+      .video_data()
+      .cloned()
+      .unwrap();
+    assert_eq!(video_data.profile.as_deref(), Some("Main 10"));
+    assert_eq!(video_data.level.as_deref(), Some("153"));
+  }
+
   /// Test case for https://github.com/nathanbabcock/ffmpeg-sidecar/issues/31
   /// Covers regression in progress parsing introduced in FFmpeg 7.0
   /// The string format for `Lsize` units went from `kB` to `KiB`
@@ -710,7 +2952,8 @@ mod tests {
     assert_eq!(progress.fps, 0.0);
     assert_eq!(progress.q, -1.0);
     assert_eq!(progress.size_kb, 10);
-    assert_eq!(progress.time, "00:00:03.00");
+    assert_eq!(progress.time, Some(Duration::from_secs(3)));
+    assert_eq!(progress.raw_time, "00:00:03.00");
     assert_eq!(progress.bitrate_kbps, 27.2);
     assert_eq!(progress.speed, 283.0);
   }
@@ -723,7 +2966,8 @@ mod tests {
     assert!(progress.fps == 13.0);
     assert!(progress.q == 4.4);
     assert!(progress.size_kb == 0);
-    assert!(progress.time == "00:13:35.00");
+    assert!(progress.time == Some(Duration::from_secs(815)));
+    assert!(progress.raw_time == "00:13:35.00");
     assert!(progress.bitrate_kbps == 0.0);
     assert!(progress.speed == 64.7);
   }
@@ -739,7 +2983,8 @@ mod tests {
     assert_eq!(progress.fps, 0.0);
     assert_eq!(progress.q, -0.0);
     assert_eq!(progress.size_kb, 0);
-    assert_eq!(progress.time, "00:00:00.00");
+    assert_eq!(progress.time, Some(Duration::ZERO));
+    assert_eq!(progress.raw_time, "00:00:00.00");
     assert_eq!(progress.bitrate_kbps, 0.0);
     assert_eq!(progress.speed, 0.0);
   }
@@ -1,15 +1,25 @@
 //! Information about an Ffmpeg process and its streams.
 
-use crate::event::{FfmpegEvent, FfmpegInput, FfmpegOutput, FfmpegStream};
+use crate::event::{
+  FfmpegChapter, FfmpegEvent, FfmpegInput, FfmpegOutput, FfmpegSideData, FfmpegStream,
+  MetadataEntry, MetadataScope, StreamTypeSpecificData,
+};
 use anyhow::bail;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegMetadata {
-  expected_output_streams: usize,
+  /// The output and stream indices of the stream mappings with an output.
+  expected_output_streams: Vec<(u32, u32)>,
+  expected_outputs: usize,
   pub outputs: Vec<FfmpegOutput>,
   pub output_streams: Vec<FfmpegStream>,
   pub inputs: Vec<FfmpegInput>,
   pub input_streams: Vec<FfmpegStream>,
+  /// The entries of the `Metadata:` blocks, in order. Multi-line values are
+  /// joined with `\n`.
+  pub tags: Vec<MetadataEntry>,
+  chapters: Vec<FfmpegChapter>,
 
   /// Whether all metadata from the parent process has been gathered into this struct
   completed: bool,
@@ -24,15 +34,26 @@ impl Default for FfmpegMetadata {
 impl FfmpegMetadata {
   pub fn new() -> Self {
     Self {
-      expected_output_streams: 0,
+      expected_output_streams: Vec::new(),
+      expected_outputs: 0,
       outputs: Vec::new(),
       output_streams: Vec::new(),
       inputs: Vec::new(),
       input_streams: Vec::new(),
+      tags: Vec::new(),
+      chapters: Vec::new(),
       completed: false,
     }
   }
 
+  /// Don't consider the metadata complete before at least `outputs`
+  /// outputs were parsed. Set automatically for commands built with
+  /// [`FfmpegCommand::output`](crate::command::FfmpegCommand::output) or
+  /// [`FfmpegCommand::output_with`](crate::command::FfmpegCommand::output_with).
+  pub fn expect_outputs(&mut self, outputs: usize) {
+    self.expected_outputs = outputs;
+  }
+
   pub fn is_completed(&self) -> bool {
     self.completed
   }
@@ -41,9 +62,87 @@ impl FfmpegMetadata {
   ///
   /// Usually this is the duration of the first input stream. Theoretically
   /// different streams could have different (or conflicting) durations, but
-  /// this handles the common case.
+  /// this handles the common case. `None` until the first input was parsed.
   pub fn duration(&self) -> Option<f64> {
-    self.inputs[0].duration
+    self.inputs.first()?.duration
+  }
+
+  /// The chapters of every input, with their titles.
+  pub fn chapters(&self) -> &[FfmpegChapter] {
+    &self.chapters
+  }
+
+  /// The value of the metadata entry `key` of `scope`, e.g. the `title` of
+  /// the first input.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::{FfmpegEvent, MetadataEntry, MetadataScope};
+  /// use async_ffmpeg_sidecar::metadata::FfmpegMetadata;
+  ///
+  /// let mut metadata = FfmpegMetadata::new();
+  /// for value in ["first line", "second line"] {
+  ///   metadata.handle_event(&FfmpegEvent::ParsedMetadata(MetadataEntry {
+  ///     scope: MetadataScope::Input(0),
+  ///     key: "comment".to_string(),
+  ///     value: value.to_string(),
+  ///     raw_log_message: String::new(),
+  ///   }))?;
+  /// }
+  ///
+  /// assert_eq!(
+  ///   metadata.tag(MetadataScope::Input(0), "comment"),
+  ///   Some("first line\nsecond line")
+  /// );
+  /// assert_eq!(metadata.tag(MetadataScope::Input(0), "title"), None);
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn tag(&self, scope: MetadataScope, key: &str) -> Option<&str> {
+    self
+      .tags
+      .iter()
+      .find(|entry| entry.scope == scope && entry.key == key)
+      .map(|entry| entry.value.as_str())
+  }
+
+  /// Add a metadata entry, joining continuation lines of multi-line values.
+  fn add_tag(&mut self, entry: &MetadataEntry) {
+    match self.tags.last_mut() {
+      Some(last) if last.scope == entry.scope && last.key == entry.key => {
+        last.value.push('\n');
+        last.value.push_str(&entry.value);
+      }
+      _ => self.tags.push(entry.clone()),
+    }
+  }
+
+  /// Fill in the rotation of the video stream a display matrix belongs to.
+  fn add_side_data(&mut self, side_data: &FfmpegSideData) {
+    let (streams, parent, index) = match side_data.scope {
+      MetadataScope::InputStream { input, stream } => (&mut self.input_streams, input, stream),
+      MetadataScope::OutputStream { output, stream } => (&mut self.output_streams, output, stream),
+      _ => return,
+    };
+    let stream = streams
+      .iter_mut()
+      .find(|s| s.parent_index == parent && s.stream_index == index);
+    if let Some(StreamTypeSpecificData::Video(video)) =
+      stream.map(|stream| &mut stream.type_specific_data)
+    {
+      video.rotation = side_data.rotation().or(video.rotation);
+    }
+  }
+
+  /// Whether an output stream was parsed for every stream mapping.
+  fn has_expected_output_streams(&self) -> bool {
+    self
+      .expected_output_streams
+      .iter()
+      .all(|&(output, stream)| {
+        self
+          .output_streams
+          .iter()
+          .any(|s| s.parent_index == output && s.stream_index == stream)
+      })
   }
 
   pub fn handle_event(&mut self, item: &FfmpegEvent) -> anyhow::Result<()> {
@@ -52,20 +151,46 @@ impl FfmpegMetadata {
     }
 
     match item {
-      // Every stream mapping corresponds to one output stream
-      // We count these to know when we're received all the output streams
-      FfmpegEvent::ParsedStreamMapping(_) => self.expected_output_streams += 1,
+      // Every stream mapping with an output corresponds to one output stream
+      // We keep track of these to know when we've received all the output
+      // streams, whatever the order of the lines
+      FfmpegEvent::ParsedStreamMapping(mapping) => {
+        if let Some(output) = mapping.output {
+          if !self.expected_output_streams.contains(&output) {
+            self.expected_output_streams.push(output);
+          }
+        }
+      }
       FfmpegEvent::ParsedInput(input) => self.inputs.push(input.clone()),
       FfmpegEvent::ParsedOutput(output) => self.outputs.push(output.clone()),
       FfmpegEvent::ParsedDuration(duration) => {
-        self.inputs[duration.input_index as usize].duration = Some(duration.duration)
+        let input = &mut self.inputs[duration.input_index as usize];
+        input.duration = duration.duration;
+        input.start_time = duration.start_time;
+        input.bitrate_kbps = duration.bitrate_kbps;
       }
       FfmpegEvent::ParsedOutputStream(stream) => self.output_streams.push(stream.clone()),
       FfmpegEvent::ParsedInputStream(stream) => self.input_streams.push(stream.clone()),
+      FfmpegEvent::ParsedChapter(chapter) => self.chapters.push(chapter.clone()),
+      FfmpegEvent::SideData(side_data) => self.add_side_data(side_data),
+      FfmpegEvent::ParsedMetadata(entry) => {
+        if let MetadataScope::Chapter { input, chapter } = entry.scope {
+          let chapter = self
+            .chapters
+            .iter_mut()
+            .find(|c| c.input_index == input && c.index == chapter);
+          if let (Some(chapter), "title") = (chapter, entry.key.as_str()) {
+            chapter.title = Some(entry.value.clone());
+          }
+        }
+        self.add_tag(entry);
+      }
       _ => (),
     }
 
-    if self.expected_output_streams > 0 && self.output_streams.len() == self.expected_output_streams
+    if !self.expected_output_streams.is_empty()
+      && self.has_expected_output_streams()
+      && self.outputs.len() >= self.expected_outputs
     {
       self.completed = true;
     }
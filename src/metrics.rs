@@ -0,0 +1,136 @@
+//! Job metrics through the [`metrics`](::metrics) facade, e.g. to track a
+//! transcoding farm on a Prometheus dashboard.
+//!
+//! A [`JobMetrics`] observes the events of one job and updates the metrics
+//! below, all labeled with [`JOB_LABEL`] set to a name chosen by the caller,
+//! such as the kind of job. Install a recorder, e.g. from
+//! `metrics-exporter-prometheus`, to export them.
+//!
+//! Requires the `metrics` feature.
+
+use crate::event::FfmpegEvent;
+use crate::outcome::FfmpegOutcome;
+use ::metrics::{counter, describe_counter, describe_gauge, gauge, Unit};
+
+/// The label holding the name of the job.
+pub const JOB_LABEL: &str = "job";
+
+/// Gauge of the jobs currently running.
+pub const ACTIVE_JOBS: &str = "ffmpeg_active_jobs";
+/// Counter of the frames encoded, as reported by the progress updates.
+pub const FRAMES_ENCODED: &str = "ffmpeg_frames_encoded_total";
+/// Counter of the frames dropped to keep the output frame rate.
+pub const FRAMES_DROPPED: &str = "ffmpeg_frames_dropped_total";
+/// Gauge of the encoding speed of the most recent progress update, as a
+/// ratio of the input duration.
+pub const ENCODE_SPEED: &str = "ffmpeg_encode_speed";
+/// Counter of the jobs that finished successfully.
+pub const JOBS_SUCCEEDED: &str = "ffmpeg_jobs_succeeded_total";
+/// Counter of the jobs that failed or produced no output.
+pub const JOB_FAILURES: &str = "ffmpeg_job_failures_total";
+
+/// Register the descriptions and units of the metrics with the installed
+/// recorder. Call it once, after installing the recorder.
+pub fn describe_metrics() {
+  describe_gauge!(ACTIVE_JOBS, "Ffmpeg jobs currently running");
+  describe_counter!(FRAMES_ENCODED, "Frames encoded by Ffmpeg jobs");
+  describe_counter!(FRAMES_DROPPED, "Frames dropped by Ffmpeg jobs");
+  describe_gauge!(
+    ENCODE_SPEED,
+    Unit::Count,
+    "Encoding speed of Ffmpeg jobs, as a ratio of the input duration"
+  );
+  describe_counter!(JOBS_SUCCEEDED, "Ffmpeg jobs that finished successfully");
+  describe_counter!(
+    JOB_FAILURES,
+    "Ffmpeg jobs that failed or produced no output"
+  );
+}
+
+/// Updates the metrics of a single job from its events.
+///
+/// The job counts as active from the creation of the `JobMetrics` until it
+/// is dropped, so a job that is cancelled or panics isn't counted forever.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::metrics::JobMetrics;
+/// use async_ffmpeg_sidecar::outcome::FfmpegOutcomeTracker;
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut child = FfmpegCommand::new()
+///   .input("upload.mov")
+///   .codec_video("libx264")
+///   .output("upload.mp4")
+///   .spawn()?;
+///
+/// let mut metrics = JobMetrics::new("transcode");
+/// let mut tracker = FfmpegOutcomeTracker::new();
+/// let mut events = child.stream()?;
+/// while let Some(event) = events.next().await {
+///   metrics.observe(&event);
+///   tracker.observe(&event);
+/// }
+/// metrics.finish(&tracker.finish(child.wait().await?));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JobMetrics {
+  job: String,
+  frames: u64,
+  dropped: u64,
+}
+
+impl JobMetrics {
+  /// Start tracking a job, labeled with `job`.
+  pub fn new<S: Into<String>>(job: S) -> Self {
+    let job = job.into();
+    gauge!(ACTIVE_JOBS, JOB_LABEL => job.clone()).increment(1.0);
+    Self {
+      job,
+      frames: 0,
+      dropped: 0,
+    }
+  }
+
+  /// Update the metrics from a single event of the job.
+  pub fn observe(&mut self, event: &FfmpegEvent) {
+    let FfmpegEvent::Progress(progress) = event else {
+      return;
+    };
+
+    // Progress updates report totals since the start of the job
+    if progress.frame > self.frames {
+      counter!(FRAMES_ENCODED, JOB_LABEL => self.job.clone())
+        .increment(progress.frame - self.frames);
+      self.frames = progress.frame;
+    }
+    let dropped = progress.drop.unwrap_or(0);
+    if dropped > self.dropped {
+      counter!(FRAMES_DROPPED, JOB_LABEL => self.job.clone()).increment(dropped - self.dropped);
+      self.dropped = dropped;
+    }
+    gauge!(ENCODE_SPEED, JOB_LABEL => self.job.clone()).set(progress.speed);
+  }
+
+  /// Count the job as succeeded or failed depending on its outcome, and
+  /// stop counting it as active.
+  pub fn finish(self, outcome: &FfmpegOutcome) {
+    let name = if outcome.is_success() {
+      JOBS_SUCCEEDED
+    } else {
+      JOB_FAILURES
+    };
+    counter!(name, JOB_LABEL => self.job.clone()).increment(1);
+  }
+}
+
+impl Drop for JobMetrics {
+  fn drop(&mut self) {
+    gauge!(ACTIVE_JOBS, JOB_LABEL => self.job.clone()).decrement(1.0);
+  }
+}
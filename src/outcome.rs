@@ -0,0 +1,181 @@
+//! Classification of finished Ffmpeg runs beyond the exit status.
+//!
+//! Ffmpeg exits successfully in some situations where the result is useless,
+//! most notably when nothing was encoded at all (e.g. a `-ss` past the end of
+//! the input). [`FfmpegOutcomeTracker`] watches the event stream and reports
+//! such runs as [`FfmpegOutcome::EmptyOutput`] instead of a success.
+
+use crate::event::{FfmpegEvent, FfmpegProgress, LogLevel};
+use crate::metadata::FfmpegMetadata;
+use std::process::ExitStatus;
+
+/// How a finished Ffmpeg run turned out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FfmpegOutcome {
+  /// The process exited successfully and produced output.
+  Success,
+  /// The process exited with a non-zero status.
+  Failed(ExitStatus),
+  /// The process produced no output, regardless of its exit status: Ffmpeg
+  /// printed `Output file is empty, nothing was encoded`, or the final
+  /// progress update reported zero frames for an output with video streams.
+  EmptyOutput {
+    exit_status: ExitStatus,
+    /// The log line that revealed the empty output
+    raw_log_message: String,
+  },
+}
+
+impl FfmpegOutcome {
+  /// Whether the run succeeded and produced output.
+  pub fn is_success(&self) -> bool {
+    matches!(self, FfmpegOutcome::Success)
+  }
+}
+
+/// Observes the events of a run to classify it once the process has exited.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::outcome::{FfmpegOutcome, FfmpegOutcomeTracker};
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut child = FfmpegCommand::new()
+///   .input("input.mp4")
+///   .seek_output("99:00:00")
+///   .output("output.mp4")
+///   .spawn()?;
+///
+/// let mut tracker = FfmpegOutcomeTracker::new();
+/// let mut events = child.stream()?;
+/// while let Some(event) = events.next().await {
+///   tracker.observe(&event);
+/// }
+///
+/// if let FfmpegOutcome::EmptyOutput { .. } = tracker.finish(child.wait().await?) {
+///   eprintln!("nothing was encoded");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FfmpegOutcomeTracker {
+  empty_output: Option<String>,
+  has_video_output: bool,
+  last_progress: Option<FfmpegProgress>,
+  errors: Vec<String>,
+}
+
+impl FfmpegOutcomeTracker {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Record a single event of the run.
+  pub fn observe(&mut self, event: &FfmpegEvent) {
+    match event {
+      FfmpegEvent::EmptyOutput(line) => self.empty_output = Some(line.clone()),
+      FfmpegEvent::ParsedOutputStream(stream) if stream.is_video() => self.has_video_output = true,
+      FfmpegEvent::Progress(progress) => self.last_progress = Some(progress.clone()),
+      _ => {}
+    }
+
+    // Same selection as `FfmpegEventStream::filter_errors`
+    match event {
+      FfmpegEvent::Error { message: e, .. }
+      | FfmpegEvent::Log(LogLevel::Error, e)
+      | FfmpegEvent::EmptyOutput(e) => self.errors.push(e.clone()),
+      FfmpegEvent::BuildCapabilityError(e) => self.errors.push(e.raw_log_message.clone()),
+      FfmpegEvent::UnsupportedContainerCodec(e) => self.errors.push(e.raw_log_message.clone()),
+      _ => {}
+    }
+  }
+
+  /// The error messages observed so far.
+  pub fn errors(&self) -> &[String] {
+    &self.errors
+  }
+
+  /// The most recent progress update, if any.
+  pub fn last_progress(&self) -> Option<&FfmpegProgress> {
+    self.last_progress.as_ref()
+  }
+
+  /// Classify the run given the exit status of the process.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::log_parser::{try_parse_progress, try_parse_stream};
+  /// use async_ffmpeg_sidecar::outcome::{FfmpegOutcome, FfmpegOutcomeTracker};
+  /// # #[cfg(unix)]
+  /// # {
+  /// use std::os::unix::process::ExitStatusExt;
+  /// use std::process::ExitStatus;
+  ///
+  /// let mut tracker = FfmpegOutcomeTracker::new();
+  /// let stream = try_parse_stream("[info]   Stream #0:0: Video: h264, yuv420p, 320x240, q=2-31, 25 fps, 12800 tbn\n").unwrap();
+  /// tracker.observe(&FfmpegEvent::ParsedOutputStream(stream));
+  /// let progress = try_parse_progress("[info] frame=    0 fps=0.0 q=-1.0 Lsize=       0KiB time=N/A bitrate=N/A speed=N/A\n").unwrap();
+  /// tracker.observe(&FfmpegEvent::Progress(progress));
+  ///
+  /// let outcome = tracker.finish(ExitStatus::from_raw(0));
+  /// assert!(matches!(outcome, FfmpegOutcome::EmptyOutput { .. }));
+  /// # }
+  /// ```
+  pub fn finish(&self, exit_status: ExitStatus) -> FfmpegOutcome {
+    if let Some(raw_log_message) = &self.empty_output {
+      return FfmpegOutcome::EmptyOutput {
+        exit_status,
+        raw_log_message: raw_log_message.clone(),
+      };
+    }
+
+    if !exit_status.success() {
+      return FfmpegOutcome::Failed(exit_status);
+    }
+
+    match &self.last_progress {
+      Some(progress) if self.has_video_output && progress.frame == 0 => {
+        FfmpegOutcome::EmptyOutput {
+          exit_status,
+          raw_log_message: progress.raw_log_message.clone(),
+        }
+      }
+      _ => FfmpegOutcome::Success,
+    }
+  }
+
+  /// Combine the observed events with the exit status and the metadata
+  /// gathered by the event stream into a [`FfmpegJobSummary`].
+  pub fn into_summary(self, exit_status: ExitStatus, metadata: FfmpegMetadata) -> FfmpegJobSummary {
+    FfmpegJobSummary {
+      outcome: self.finish(exit_status),
+      exit_status,
+      metadata,
+      errors: self.errors,
+      last_progress: self.last_progress,
+    }
+  }
+}
+
+/// Everything notable about a finished run, as returned by
+/// [`FfmpegChild::wait_with_events`](crate::child::FfmpegChild::wait_with_events).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FfmpegJobSummary {
+  /// The exit status of the process
+  pub exit_status: ExitStatus,
+  /// The classification of the run, which may be a failure even if the
+  /// process exited successfully
+  pub outcome: FfmpegOutcome,
+  /// The input and output metadata, possibly incomplete if Ffmpeg failed
+  /// before printing all of it
+  pub metadata: FfmpegMetadata,
+  /// Error messages in the order they occurred, see
+  /// [`FfmpegEventStream::filter_errors`](crate::stream::FfmpegEventStream::filter_errors)
+  pub errors: Vec<String>,
+  /// The final progress update, if any
+  pub last_progress: Option<FfmpegProgress>,
+}
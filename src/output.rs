@@ -0,0 +1,182 @@
+//! Per-output options for commands with several outputs.
+
+use crate::command::{crop_filter, fps_filter, map_spec, pad_filter, scale_filter, TimeSpec};
+
+/// The options and path of a single output, see
+/// [`FfmpegCommand::output_with`](crate::command::FfmpegCommand::output_with).
+///
+/// The methods mirror the output options of
+/// [`FfmpegCommand`](crate::command::FfmpegCommand), but only apply to this
+/// output.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputBuilder {
+  args: Vec<String>,
+  video_filters: Vec<String>,
+  path: Option<String>,
+}
+
+impl OutputBuilder {
+  /// The output file path or URL. Required.
+  pub fn path<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
+    self.path = Some(path.as_ref().to_string());
+    self
+  }
+
+  /// Alias for `-f`, the container format.
+  pub fn format<S: AsRef<str>>(&mut self, format: S) -> &mut Self {
+    self.arg("-f").arg(format)
+  }
+
+  /// Alias for `-c:v`, the video encoder or `copy`.
+  pub fn codec_video<S: AsRef<str>>(&mut self, codec: S) -> &mut Self {
+    self.arg("-c:v").arg(codec)
+  }
+
+  /// Alias for `-c:a`, the audio encoder or `copy`.
+  pub fn codec_audio<S: AsRef<str>>(&mut self, codec: S) -> &mut Self {
+    self.arg("-c:a").arg(codec)
+  }
+
+  /// Alias for `-c:s`, the subtitle encoder or `copy`.
+  pub fn codec_subtitle<S: AsRef<str>>(&mut self, codec: S) -> &mut Self {
+    self.arg("-c:s").arg(codec)
+  }
+
+  /// Alias for `-crf:v`, see
+  /// [`FfmpegCommand::crf`](crate::command::FfmpegCommand::crf).
+  pub fn crf(&mut self, crf: u8) -> &mut Self {
+    self.arg("-crf:v").arg(crf.to_string())
+  }
+
+  /// Alias for `-preset:v`, the encoder speed preset, see
+  /// [`Preset`](crate::encoder::Preset).
+  pub fn preset<S: AsRef<str>>(&mut self, preset: S) -> &mut Self {
+    self.arg("-preset:v").arg(preset)
+  }
+
+  /// Alias for `-tune:v`, see [`Tune`](crate::encoder::Tune).
+  pub fn tune<S: AsRef<str>>(&mut self, tune: S) -> &mut Self {
+    self.arg("-tune:v").arg(tune)
+  }
+
+  /// Alias for `-profile:v`, see [`Profile`](crate::encoder::Profile).
+  pub fn profile<S: AsRef<str>>(&mut self, profile: S) -> &mut Self {
+    self.arg("-profile:v").arg(profile)
+  }
+
+  /// Alias for `-b:v`, the video bitrate, e.g. `2500k`.
+  pub fn video_bitrate<S: AsRef<str>>(&mut self, bitrate: S) -> &mut Self {
+    self.arg("-b:v").arg(bitrate)
+  }
+
+  /// Alias for `-b:a`, the audio bitrate, e.g. `128k`.
+  pub fn audio_bitrate<S: AsRef<str>>(&mut self, bitrate: S) -> &mut Self {
+    self.arg("-b:a").arg(bitrate)
+  }
+
+  /// Alias for `-s`, scaling the video to `width`x`height`.
+  pub fn size(&mut self, width: u32, height: u32) -> &mut Self {
+    self.arg("-s").arg(format!("{width}x{height}"))
+  }
+
+  /// Alias for `-r`, the output frame rate.
+  pub fn rate(&mut self, fps: f32) -> &mut Self {
+    self.arg("-r").arg(fps.to_string())
+  }
+
+  /// Alias for `-pix_fmt`.
+  pub fn pix_fmt<S: AsRef<str>>(&mut self, format: S) -> &mut Self {
+    self.arg("-pix_fmt").arg(format)
+  }
+
+  /// Alias for `-filter:v`, a simple video filtergraph. Like
+  /// [`FfmpegCommand::filter_video`](crate::command::FfmpegCommand::filter_video),
+  /// all video filters of this output are joined into one chain.
+  pub fn filter_video<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.video_filters.push(filtergraph.as_ref().to_string());
+    self
+  }
+
+  /// Add the `scale` filter, see
+  /// [`FfmpegCommand::scale`](crate::command::FfmpegCommand::scale).
+  pub fn scale(&mut self, width: i32, height: i32) -> &mut Self {
+    self.filter_video(scale_filter(width, height))
+  }
+
+  /// Add the `fps` filter, see
+  /// [`FfmpegCommand::fps`](crate::command::FfmpegCommand::fps).
+  pub fn fps(&mut self, fps: f32) -> &mut Self {
+    self.filter_video(fps_filter(fps))
+  }
+
+  /// Add the `crop` filter, see
+  /// [`FfmpegCommand::crop`](crate::command::FfmpegCommand::crop).
+  pub fn crop(&mut self, width: u32, height: u32, x: u32, y: u32) -> &mut Self {
+    self.filter_video(crop_filter(width, height, x, y))
+  }
+
+  /// Add the `pad` filter, see
+  /// [`FfmpegCommand::pad`](crate::command::FfmpegCommand::pad).
+  pub fn pad(&mut self, width: u32, height: u32, x: u32, y: u32) -> &mut Self {
+    self.filter_video(pad_filter(width, height, x, y))
+  }
+
+  /// Alias for `-filter:a`, a simple audio filtergraph.
+  pub fn filter_audio<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.arg("-filter:a").arg(filtergraph)
+  }
+
+  /// Map the streams of input `input_index` matching `stream_spec` into
+  /// this output, see
+  /// [`FfmpegCommand::map`](crate::command::FfmpegCommand::map).
+  pub fn map<S: AsRef<str>>(&mut self, input_index: u32, stream_spec: S) -> &mut Self {
+    self.map_str(map_spec(input_index, stream_spec.as_ref()))
+  }
+
+  /// Alias for `-map` with a raw argument, see
+  /// [`FfmpegCommand::map_str`](crate::command::FfmpegCommand::map_str).
+  pub fn map_str<S: AsRef<str>>(&mut self, map_string: S) -> &mut Self {
+    self.arg("-map").arg(map_string)
+  }
+
+  /// Alias for `-vn`, dropping video from this output.
+  pub fn no_video(&mut self) -> &mut Self {
+    self.arg("-vn")
+  }
+
+  /// Alias for `-an`, dropping audio from this output.
+  pub fn no_audio(&mut self) -> &mut Self {
+    self.arg("-an")
+  }
+
+  /// Alias for `-t`, stopping this output after `duration`.
+  pub fn duration<T: TimeSpec>(&mut self, duration: T) -> &mut Self {
+    self.arg("-t").arg(duration.to_time_spec())
+  }
+
+  /// Add any other output option.
+  pub fn arg<S: AsRef<str>>(&mut self, arg: S) -> &mut Self {
+    self.args.push(arg.as_ref().to_string());
+    self
+  }
+
+  /// Add several output options.
+  pub fn args<I, S>(&mut self, args: I) -> &mut Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    for arg in args {
+      self.arg(arg);
+    }
+    self
+  }
+
+  /// The options, the video filters and the path.
+  pub(crate) fn into_parts(self) -> anyhow::Result<(Vec<String>, Vec<String>, String)> {
+    match self.path {
+      Some(path) => Ok((self.args, self.video_filters, path)),
+      None => anyhow::bail!("output has no path"),
+    }
+  }
+}
@@ -0,0 +1,158 @@
+//! Output pacing for network destinations such as ingest servers.
+//!
+//! Many ingest servers (RTMP, SRT, some HLS origins) reject or throttle
+//! senders that push data in large bursts. [`OutputPacing`] collects the
+//! Ffmpeg output options that control this (`-maxrate`, `-bufsize` and
+//! `-flush_packets`) and validates them before they reach the command line.
+//!
+//! For real-time inputs, see also
+//! [`FfmpegCommand::rtbufsize`](crate::command::FfmpegCommand::rtbufsize).
+
+use std::time::Duration;
+
+/// A validated set of rate-limiting options for an Ffmpeg output.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::pacing::OutputPacing;
+/// use std::time::Duration;
+///
+/// let pacing = OutputPacing::new(3_000_000)
+///   .burst(Duration::from_secs(2))
+///   .flush_packets(true);
+///
+/// let mut command = FfmpegCommand::new();
+/// command.output_pacing(&pacing).unwrap();
+///
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert!(args.windows(2).any(|w| w == ["-maxrate", "3000000"]));
+/// assert!(args.windows(2).any(|w| w == ["-bufsize", "6000000"]));
+/// assert!(args.windows(2).any(|w| w == ["-flush_packets", "1"]));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputPacing {
+  max_rate: u64,
+  buffer_size: Option<u64>,
+  flush_packets: Option<bool>,
+}
+
+impl OutputPacing {
+  /// Limit the output to `max_rate` bits per second.
+  ///
+  /// Without an explicit [`buffer_size`](Self::buffer_size) or
+  /// [`burst`](Self::burst), the rate control buffer defaults to one second
+  /// worth of data.
+  pub fn new(max_rate: u64) -> Self {
+    Self {
+      max_rate,
+      buffer_size: None,
+      flush_packets: None,
+    }
+  }
+
+  /// Maximum bitrate in bits per second, passed as `-maxrate`.
+  pub fn max_rate(&self) -> u64 {
+    self.max_rate
+  }
+
+  /// Size of the rate control buffer in bits, passed as `-bufsize`.
+  ///
+  /// Together with the maximum rate, this bounds how large a burst the
+  /// encoder may emit: a smaller buffer produces a smoother stream at the
+  /// cost of quality on complex scenes.
+  pub fn buffer_size(mut self, bits: u64) -> Self {
+    self.buffer_size = Some(bits);
+    self
+  }
+
+  /// Express the rate control buffer as the longest burst allowed at the
+  /// maximum rate. Equivalent to `buffer_size(max_rate * burst)`.
+  pub fn burst(mut self, burst: Duration) -> Self {
+    self.buffer_size = Some((self.max_rate as f64 * burst.as_secs_f64()).round() as u64);
+    self
+  }
+
+  /// The effective `-bufsize` in bits.
+  pub fn effective_buffer_size(&self) -> u64 {
+    self.buffer_size.unwrap_or(self.max_rate)
+  }
+
+  /// Alias for `-flush_packets`. When enabled, packets are flushed to the
+  /// output immediately instead of being accumulated in the muxer's IO
+  /// buffer, trading throughput for lower and steadier latency.
+  pub fn flush_packets(mut self, flush: bool) -> Self {
+    self.flush_packets = Some(flush);
+    self
+  }
+
+  /// Check that the options are consistent before building arguments.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::pacing::OutputPacing;
+  /// use std::time::Duration;
+  ///
+  /// assert!(OutputPacing::new(1_000_000).validate().is_ok());
+  /// assert!(OutputPacing::new(0).validate().is_err());
+  /// assert!(OutputPacing::new(1_000_000).burst(Duration::ZERO).validate().is_err());
+  /// ```
+  pub fn validate(&self) -> anyhow::Result<()> {
+    if self.max_rate == 0 {
+      anyhow::bail!("max_rate must be greater than zero");
+    }
+
+    if self.effective_buffer_size() == 0 {
+      anyhow::bail!("buffer_size must be greater than zero");
+    }
+
+    Ok(())
+  }
+
+  /// The output arguments, to be placed before the output path or URL.
+  pub fn to_args(&self) -> Vec<String> {
+    let mut args = vec![
+      "-maxrate".to_string(),
+      self.max_rate.to_string(),
+      "-bufsize".to_string(),
+      self.effective_buffer_size().to_string(),
+    ];
+
+    if let Some(flush) = self.flush_packets {
+      args.push("-flush_packets".to_string());
+      args.push(if flush { "1" } else { "0" }.to_string());
+    }
+
+    args
+  }
+}
+
+/// Parse a bitrate in the notation Ffmpeg accepts for options such as `-b:v`
+/// and `-maxrate`: a number with an optional `k`, `M` or `G` suffix
+/// (powers of 1000).
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::pacing::parse_bitrate;
+///
+/// assert_eq!(parse_bitrate("5M"), Some(5_000_000));
+/// assert_eq!(parse_bitrate("128k"), Some(128_000));
+/// assert_eq!(parse_bitrate("2.5M"), Some(2_500_000));
+/// assert_eq!(parse_bitrate("800000"), Some(800_000));
+/// assert_eq!(parse_bitrate("5 Mbps"), None);
+/// assert_eq!(parse_bitrate("-1k"), None);
+/// ```
+pub fn parse_bitrate(bitrate: &str) -> Option<u64> {
+  let (number, multiplier) = match bitrate.char_indices().last()? {
+    (i, 'k' | 'K') => (&bitrate[..i], 1e3),
+    (i, 'M') => (&bitrate[..i], 1e6),
+    (i, 'G') => (&bitrate[..i], 1e9),
+    _ => (bitrate, 1.0),
+  };
+
+  if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+    return None;
+  }
+
+  let value = number.parse::<f64>().ok()? * multiplier;
+  Some(value.round() as u64)
+}
@@ -4,22 +4,127 @@ use anyhow::Context;
 use std::{
   env::current_exe,
   path::{Path, PathBuf},
+  sync::{OnceLock, RwLock},
 };
 
+/// Environment variable that overrides the location of the FFmpeg binary.
+pub const FFMPEG_PATH_ENV: &str = "FFMPEG_PATH";
+
+static FFMPEG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+static FFMPEG_PATH_CACHE: PathCache = PathCache::new();
+
+/// Set a process-wide path to the FFmpeg binary, used by [`ffmpeg_path`] and
+/// therefore by every `FfmpegCommand::new()` and `ffmpeg_version()` call.
+///
+/// The override can only be set once; subsequent calls return an error.
+pub fn set_ffmpeg_path<P: Into<PathBuf>>(path: P) -> anyhow::Result<()> {
+  FFMPEG_PATH_OVERRIDE
+    .set(path.into())
+    .map_err(|path| anyhow::anyhow!("ffmpeg path was already set, ignoring {}", path.display()))?;
+  FFMPEG_PATH_CACHE.clear();
+  Ok(())
+}
+
 /// Returns the default path of the FFmpeg executable, to be used as the
-/// argument to `Command::new`. It should first attempt to locate an FFmpeg
-/// binary adjacent to the Rust executable. If that fails, it should invoke
-/// `ffmpeg` expecting it to be in the system path. If that fails, an
-/// informative error message should be printed (not when this function is
-/// called, but when the command is actually run).
+/// argument to `Command::new`. The locations of [`BinaryLocator::ffmpeg`] are
+/// tried in order:
+///
+/// 1. The path passed to [`set_ffmpeg_path`], if any.
+/// 2. The `FFMPEG_PATH` environment variable, if set and non-empty.
+/// 3. An FFmpeg binary adjacent to the Rust executable (see [`sidecar_path`]).
+/// 4. An FFmpeg binary in the per-user cache directory (see [`sidecar_cache_dir`]).
+/// 5. Platform-typical install locations such as `/opt/homebrew/bin`.
+/// 6. The directories of the `PATH` environment variable.
+///
+/// If none of these contain a binary, this falls back to plain `ffmpeg`; an
+/// informative error message should then be printed (not when this function
+/// is called, but when the command is actually run).
+///
+/// The path is resolved once and cached, see [`invalidate_path_cache`]. The
+/// fallback is not cached, so a binary installed later is still found.
 pub fn ffmpeg_path() -> PathBuf {
-  let default = Path::new("ffmpeg").to_path_buf();
-  match sidecar_path() {
-    Ok(sidecar_path) => match sidecar_path.exists() {
-      true => sidecar_path,
-      false => default,
-    },
-    Err(_) => default,
+  FFMPEG_PATH_CACHE
+    .get_or_resolve(|| BinaryLocator::ffmpeg().locate().map(|located| located.path))
+    .unwrap_or_else(|| Path::new("ffmpeg").to_path_buf())
+}
+
+/// Forget the paths resolved by [`ffmpeg_path`],
+/// [`ffprobe_path`](crate::ffprobe::ffprobe_path) and
+/// [`ffplay_path`](crate::ffplay::ffplay_path), so that the next calls search
+/// for the binaries again.
+///
+/// The download and removal functions of the `download` module and the
+/// `set_*_path` overrides do this already. Call it after installing or
+/// removing binaries by other means, or after changing `FFMPEG_PATH`.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::paths::{ffmpeg_path, invalidate_path_cache};
+/// use std::path::Path;
+///
+/// std::env::set_var("FFMPEG_PATH", "/srv/tools/ffmpeg");
+/// invalidate_path_cache();
+/// assert_eq!(ffmpeg_path(), Path::new("/srv/tools/ffmpeg"));
+///
+/// std::env::set_var("FFMPEG_PATH", "/opt/ffmpeg/bin/ffmpeg");
+/// assert_eq!(ffmpeg_path(), Path::new("/srv/tools/ffmpeg"));
+///
+/// invalidate_path_cache();
+/// assert_eq!(ffmpeg_path(), Path::new("/opt/ffmpeg/bin/ffmpeg"));
+/// ```
+pub fn invalidate_path_cache() {
+  FFMPEG_PATH_CACHE.clear();
+  crate::ffprobe::FFPROBE_PATH_CACHE.clear();
+  crate::ffplay::FFPLAY_PATH_CACHE.clear();
+}
+
+/// A binary path resolved on first use, see [`invalidate_path_cache`].
+/// Resolving searches the filesystem, which adds up when spawning many short
+/// jobs.
+pub(crate) struct PathCache(RwLock<CachedPath>);
+
+struct CachedPath {
+  path: Option<PathBuf>,
+  /// Incremented by every [`PathCache::clear`], so that a path resolved
+  /// before is not cached after.
+  generation: u64,
+}
+
+impl PathCache {
+  pub(crate) const fn new() -> Self {
+    Self(RwLock::new(CachedPath {
+      path: None,
+      generation: 0,
+    }))
+  }
+
+  /// The cached path, or the one returned by `resolve`, which is cached
+  /// unless it's `None` or the cache was cleared in the meantime.
+  pub(crate) fn get_or_resolve<F: FnOnce() -> Option<PathBuf>>(
+    &self,
+    resolve: F,
+  ) -> Option<PathBuf> {
+    let generation = {
+      let cached = self.0.read().unwrap();
+      if let Some(path) = &cached.path {
+        return Some(path.clone());
+      }
+      cached.generation
+    };
+
+    let path = resolve()?;
+    let mut cached = self.0.write().unwrap();
+    if cached.generation == generation && cached.path.is_none() {
+      cached.path = Some(path.clone());
+    }
+    Some(path)
+  }
+
+  pub(crate) fn clear(&self) {
+    let mut cached = self.0.write().unwrap();
+    cached.path = None;
+    cached.generation += 1;
   }
 }
 
@@ -28,15 +133,7 @@ pub fn ffmpeg_path() -> PathBuf {
 /// The extension between platforms, with Windows using `.exe`, while Mac and
 /// Linux have no extension.
 pub fn sidecar_path() -> anyhow::Result<PathBuf> {
-  let mut path = current_exe()?
-    .parent()
-    .context("Can't get parent of current_exe")?
-    .join("ffmpeg_dir")
-    .join("ffmpeg");
-  if cfg!(windows) {
-    path.set_extension("exe");
-  }
-  Ok(path)
+  sidecar_binary_path("ffmpeg")
 }
 
 /// By default, downloads all temporary files to the same directory as the Rust executable.
@@ -48,3 +145,266 @@ pub fn sidecar_dir() -> anyhow::Result<PathBuf> {
       .to_path_buf(),
   )
 }
+
+/// The per-user cache directory used for downloads when the directory of the
+/// Rust executable is read-only, e.g. `~/.cache/async-ffmpeg-sidecar` on Linux,
+/// `~/Library/Caches/async-ffmpeg-sidecar` on macOS and
+/// `%LOCALAPPDATA%\async-ffmpeg-sidecar` on Windows.
+///
+/// Returns `None` if the platform has no such directory (e.g. `$HOME` is unset).
+pub fn sidecar_cache_dir() -> Option<PathBuf> {
+  Some(dirs::cache_dir()?.join("async-ffmpeg-sidecar"))
+}
+
+/// The directory `auto_download` installs binaries to: [`sidecar_dir`] if it
+/// is writable, otherwise [`sidecar_cache_dir`].
+///
+/// Installs next to the executable are preferred since they travel with the
+/// application, but fail for read-only installs such as `/usr/bin` or a
+/// signed app bundle.
+pub fn download_dir() -> anyhow::Result<PathBuf> {
+  let sidecar = sidecar_dir()?;
+  if is_writable_dir(&sidecar) {
+    return Ok(sidecar);
+  }
+
+  sidecar_cache_dir()
+    .filter(|dir| is_writable_dir(dir))
+    .with_context(|| {
+      format!(
+        "{} is not writable and no user cache directory is available",
+        sidecar.display()
+      )
+    })
+}
+
+/// Creates `dir` if needed and checks that files can be created inside it.
+fn is_writable_dir(dir: &Path) -> bool {
+  if std::fs::create_dir_all(dir).is_err() {
+    return false;
+  }
+
+  let probe = dir.join(".async-ffmpeg-sidecar-write-test");
+  let writable = std::fs::File::create(&probe).is_ok();
+  let _ = std::fs::remove_file(&probe);
+  writable
+}
+
+/// The (expected) path to the binary `name` inside the `ffmpeg_dir` folder
+/// adjacent to the Rust binary.
+pub(crate) fn sidecar_binary_path(name: &str) -> anyhow::Result<PathBuf> {
+  let path = current_exe()?
+    .parent()
+    .context("Can't get parent of current_exe")?
+    .join("ffmpeg_dir")
+    .join(executable_name(name));
+  Ok(path)
+}
+
+/// Reads a binary path from the environment variable `key`, ignoring empty values.
+pub(crate) fn path_from_env(key: &str) -> Option<PathBuf> {
+  std::env::var_os(key)
+    .filter(|value| !value.is_empty())
+    .map(PathBuf::from)
+}
+
+/// Appends `.exe` on Windows.
+fn executable_name(name: &str) -> PathBuf {
+  let mut path = PathBuf::from(name);
+  if cfg!(windows) {
+    path.set_extension("exe");
+  }
+  path
+}
+
+/// A place where a [`BinaryLocator`] looks for a binary.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinarySource {
+  /// A path configured explicitly, e.g. with [`set_ffmpeg_path`]. Used as-is,
+  /// without checking that the file exists.
+  Explicit(PathBuf),
+  /// A path read from an environment variable. Used as-is, without checking
+  /// that the file exists.
+  EnvVar(String),
+  /// The `ffmpeg_dir` folder adjacent to the Rust executable, where
+  /// `auto_download` installs binaries.
+  Sidecar,
+  /// The per-user cache directory, see [`sidecar_cache_dir`], where
+  /// `auto_download` installs binaries if the executable's directory is
+  /// read-only.
+  CacheDir,
+  /// Platform-typical install directories, see [`platform_dirs`].
+  PlatformDefaults,
+  /// The directories of the `PATH` environment variable.
+  SystemPath,
+}
+
+/// A binary found by a [`BinaryLocator`], along with where it was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocatedBinary {
+  pub path: PathBuf,
+  pub source: BinarySource,
+}
+
+/// Searches an ordered list of [`BinarySource`]s for a binary, reporting the
+/// first match. Useful to find out *which* FFmpeg is picked up.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::paths::{BinaryLocator, BinarySource};
+///
+/// // The default search order used by `ffmpeg_path()`
+/// if let Some(located) = BinaryLocator::ffmpeg().locate() {
+///   println!("using {} (found via {:?})", located.path.display(), located.source);
+/// }
+///
+/// // A custom search order
+/// let located = BinaryLocator::new("ffmpeg")
+///   .explicit("/srv/tools/ffmpeg")
+///   .system_path()
+///   .locate()
+///   .unwrap();
+/// assert_eq!(located.source, BinarySource::Explicit("/srv/tools/ffmpeg".into()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryLocator {
+  name: String,
+  sources: Vec<BinarySource>,
+}
+
+impl BinaryLocator {
+  /// An empty locator for the binary `name` (without `.exe` extension).
+  /// Add sources with the builder methods, in order of preference.
+  pub fn new<S: AsRef<str>>(name: S) -> Self {
+    Self {
+      name: name.as_ref().to_string(),
+      sources: Vec::new(),
+    }
+  }
+
+  /// The default search order for FFmpeg, as used by [`ffmpeg_path`].
+  pub fn ffmpeg() -> Self {
+    Self::with_defaults("ffmpeg", FFMPEG_PATH_OVERRIDE.get(), FFMPEG_PATH_ENV)
+  }
+
+  /// The default search order for FFprobe, as used by
+  /// [`ffprobe_path`](crate::ffprobe::ffprobe_path).
+  pub fn ffprobe() -> Self {
+    use crate::ffprobe::{ffprobe_path_override, FFPROBE_PATH_ENV};
+
+    Self::with_defaults("ffprobe", ffprobe_path_override(), FFPROBE_PATH_ENV)
+  }
+
+  /// The default search order for FFplay, as used by
+  /// [`ffplay_path`](crate::ffplay::ffplay_path).
+  pub fn ffplay() -> Self {
+    use crate::ffplay::{ffplay_path_override, FFPLAY_PATH_ENV};
+
+    Self::with_defaults("ffplay", ffplay_path_override(), FFPLAY_PATH_ENV)
+  }
+
+  fn with_defaults(name: &str, explicit: Option<&PathBuf>, env_var: &str) -> Self {
+    let mut locator = Self::new(name);
+    if let Some(path) = explicit {
+      locator = locator.explicit(path);
+    }
+    locator
+      .env_var(env_var)
+      .sidecar()
+      .cache_dir()
+      .platform_defaults()
+      .system_path()
+  }
+
+  /// Use `path` unconditionally if this source is reached.
+  pub fn explicit<P: Into<PathBuf>>(mut self, path: P) -> Self {
+    self.sources.push(BinarySource::Explicit(path.into()));
+    self
+  }
+
+  /// Use the path in the environment variable `key`, if set and non-empty.
+  pub fn env_var<S: AsRef<str>>(mut self, key: S) -> Self {
+    self
+      .sources
+      .push(BinarySource::EnvVar(key.as_ref().to_string()));
+    self
+  }
+
+  /// Look in the `ffmpeg_dir` folder adjacent to the Rust executable.
+  pub fn sidecar(mut self) -> Self {
+    self.sources.push(BinarySource::Sidecar);
+    self
+  }
+
+  /// Look in the per-user cache directory, see [`sidecar_cache_dir`].
+  pub fn cache_dir(mut self) -> Self {
+    self.sources.push(BinarySource::CacheDir);
+    self
+  }
+
+  /// Look in platform-typical install directories, see [`platform_dirs`].
+  pub fn platform_defaults(mut self) -> Self {
+    self.sources.push(BinarySource::PlatformDefaults);
+    self
+  }
+
+  /// Look in the directories of the `PATH` environment variable.
+  pub fn system_path(mut self) -> Self {
+    self.sources.push(BinarySource::SystemPath);
+    self
+  }
+
+  /// The configured sources, in search order.
+  pub fn sources(&self) -> &[BinarySource] {
+    &self.sources
+  }
+
+  /// Search the sources in order, returning the first match.
+  pub fn locate(&self) -> Option<LocatedBinary> {
+    self.sources.iter().find_map(|source| {
+      self.locate_in(source).map(|path| LocatedBinary {
+        path,
+        source: source.clone(),
+      })
+    })
+  }
+
+  fn locate_in(&self, source: &BinarySource) -> Option<PathBuf> {
+    let executable = executable_name(&self.name);
+    let find_in = |dirs: Vec<PathBuf>| {
+      dirs
+        .into_iter()
+        .map(|dir| dir.join(&executable))
+        .find(|path| path.is_file())
+    };
+
+    match source {
+      BinarySource::Explicit(path) => Some(path.clone()),
+      BinarySource::EnvVar(key) => path_from_env(key),
+      BinarySource::Sidecar => sidecar_binary_path(&self.name)
+        .ok()
+        .filter(|path| path.exists()),
+      BinarySource::CacheDir => find_in(sidecar_cache_dir().into_iter().collect()),
+      BinarySource::PlatformDefaults => find_in(platform_dirs()),
+      BinarySource::SystemPath => {
+        find_in(std::env::split_paths(&std::env::var_os("PATH")?).collect())
+      }
+    }
+  }
+}
+
+/// Directories where package managers and manual installs typically put
+/// FFmpeg on the current platform, which may be missing from the `PATH` of
+/// GUI applications.
+pub fn platform_dirs() -> Vec<PathBuf> {
+  let dirs: &[&str] = if cfg!(target_os = "macos") {
+    &["/opt/homebrew/bin", "/usr/local/bin", "/opt/local/bin"]
+  } else if cfg!(windows) {
+    &[r"C:\ffmpeg\bin", r"C:\Program Files\ffmpeg\bin"]
+  } else {
+    &["/usr/local/bin", "/usr/bin", "/snap/bin"]
+  };
+
+  dirs.iter().map(PathBuf::from).collect()
+}
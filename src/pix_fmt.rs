@@ -1,11 +1,458 @@
-//! A database of the pixel formats by Ffmpeg and their size per pixel.
+//! A database of the pixel formats by Ffmpeg, their memory layout and size per
+//! frame.
 
+use crate::command::BackgroundCommand;
 use crate::event::VideoStream;
+use crate::paths::ffmpeg_path;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::sync::RwLock;
+use tokio::process::Command;
+
+/// How the pixels of a format are laid out in memory, as needed to compute the
+/// size of a raw video frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelLayout {
+  /// All components interleaved in a single plane, e.g. `rgb24` or `yuyv422`.
+  Packed {
+    /// Bits of storage per pixel, including padding (`16` for `rgb555le`).
+    bits_per_pixel: u32,
+    /// Horizontal chroma subsampling as a power of two, e.g. `1` for
+    /// `yuyv422`. Lines hold whole groups of `1 << log2_chroma_w` pixels.
+    log2_chroma_w: u32,
+  },
+  /// One plane per component, e.g. `yuv420p` or `gbrp`.
+  Planar {
+    /// Bytes per sample, `2` for high bit depths like `yuv420p10le`.
+    bytes_per_sample: u32,
+    /// Horizontal chroma subsampling as a power of two.
+    log2_chroma_w: u32,
+    /// Vertical chroma subsampling as a power of two.
+    log2_chroma_h: u32,
+  },
+  /// A luma plane followed by a plane of interleaved chroma, e.g. `nv12` or
+  /// `p010le`.
+  SemiPlanar {
+    /// Bytes per sample, `2` for high bit depths like `p010le`.
+    bytes_per_sample: u32,
+    /// Horizontal chroma subsampling as a power of two.
+    log2_chroma_w: u32,
+    /// Vertical chroma subsampling as a power of two.
+    log2_chroma_h: u32,
+  },
+  /// One byte per pixel indexing a palette of 256 RGBA colors, which FFmpeg
+  /// appends to every raw frame.
+  Paletted,
+  /// Frames live in device memory (e.g. `cuda` or `vaapi`) and cannot be
+  /// read as raw video.
+  Hardware,
+  /// A format listed by `ffmpeg -pix_fmts` that is missing from
+  /// [`PIXEL_FORMATS`], whose layout can't be derived from the listing.
+  Unknown,
+}
+
+/// Description of a pixel format, see [`pixel_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormat {
+  /// Number of components, e.g. `3` for `yuv420p` and `4` for `rgba`.
+  pub components: u8,
+  /// Average number of significant bits per pixel, as listed by
+  /// `ffmpeg -pix_fmts` (e.g. `15` for `yuv420p10le`). This excludes padding,
+  /// use [`PixelFormat::frame_size`] for the size in memory.
+  pub bits_per_pixel: u32,
+  /// Memory layout of the format.
+  pub layout: PixelLayout,
+}
+
+impl PixelFormat {
+  const fn packed(
+    components: u8,
+    bits_per_pixel: u32,
+    storage_bits: u32,
+    log2_chroma_w: u32,
+  ) -> Self {
+    Self {
+      components,
+      bits_per_pixel,
+      layout: PixelLayout::Packed {
+        bits_per_pixel: storage_bits,
+        log2_chroma_w,
+      },
+    }
+  }
+
+  const fn planar(
+    components: u8,
+    bits_per_pixel: u32,
+    bytes_per_sample: u32,
+    log2_chroma_w: u32,
+    log2_chroma_h: u32,
+  ) -> Self {
+    Self {
+      components,
+      bits_per_pixel,
+      layout: PixelLayout::Planar {
+        bytes_per_sample,
+        log2_chroma_w,
+        log2_chroma_h,
+      },
+    }
+  }
+
+  const fn semi_planar(
+    bits_per_pixel: u32,
+    bytes_per_sample: u32,
+    log2_chroma_w: u32,
+    log2_chroma_h: u32,
+  ) -> Self {
+    Self {
+      components: 3,
+      bits_per_pixel,
+      layout: PixelLayout::SemiPlanar {
+        bytes_per_sample,
+        log2_chroma_w,
+        log2_chroma_h,
+      },
+    }
+  }
+
+  const fn paletted() -> Self {
+    Self {
+      components: 1,
+      bits_per_pixel: 8,
+      layout: PixelLayout::Paletted,
+    }
+  }
+
+  const fn hardware() -> Self {
+    Self {
+      components: 0,
+      bits_per_pixel: 0,
+      layout: PixelLayout::Hardware,
+    }
+  }
+
+  /// Size in bytes of a `width` by `height` frame as written by
+  /// `-f rawvideo`, i.e. with tightly packed lines and planes.
+  /// `None` for hardware formats and formats of unknown layout.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::pix_fmt::pixel_format;
+  /// let yuv = pixel_format("yuv420p10le").unwrap();
+  /// assert_eq!(yuv.frame_size(1920, 1080), Some(1920 * 1080 * 3));
+  /// ```
+  pub fn frame_size(&self, width: u32, height: u32) -> Option<usize> {
+    let (width, height) = (width as usize, height as usize);
+    let chroma = |size: usize, log2: u32| size.div_ceil(1 << log2);
+    match self.layout {
+      PixelLayout::Packed {
+        bits_per_pixel,
+        log2_chroma_w,
+      } => {
+        let group_bits = (bits_per_pixel as usize) << log2_chroma_w;
+        let line = (chroma(width, log2_chroma_w) * group_bits).div_ceil(8);
+        Some(line * height)
+      }
+      PixelLayout::Planar {
+        bytes_per_sample,
+        log2_chroma_w,
+        log2_chroma_h,
+      } => {
+        let luma = width * height;
+        let chroma = chroma(width, log2_chroma_w) * chroma(height, log2_chroma_h);
+        let alpha = if self.components == 4 { luma } else { 0 };
+        Some((luma + 2 * chroma + alpha) * bytes_per_sample as usize)
+      }
+      PixelLayout::SemiPlanar {
+        bytes_per_sample,
+        log2_chroma_w,
+        log2_chroma_h,
+      } => {
+        let chroma = chroma(width, log2_chroma_w) * chroma(height, log2_chroma_h);
+        Some((width * height + 2 * chroma) * bytes_per_sample as usize)
+      }
+      PixelLayout::Paletted => Some(width * height + 256 * 4),
+      PixelLayout::Hardware | PixelLayout::Unknown => None,
+    }
+  }
+}
+
+/// Pixel formats known without asking FFmpeg, obtained from `ffmpeg -pix_fmts`
+/// and FFmpeg's pixel format descriptors.
+pub const PIXEL_FORMATS: &[(&str, PixelFormat)] = &[
+  ("yuv420p", PixelFormat::planar(3, 12, 1, 1, 1)),
+  ("yuyv422", PixelFormat::packed(3, 16, 16, 1)),
+  ("rgb24", PixelFormat::packed(3, 24, 24, 0)),
+  ("bgr24", PixelFormat::packed(3, 24, 24, 0)),
+  ("yuv422p", PixelFormat::planar(3, 16, 1, 1, 0)),
+  ("yuv444p", PixelFormat::planar(3, 24, 1, 0, 0)),
+  ("yuv410p", PixelFormat::planar(3, 9, 1, 2, 2)),
+  ("yuv411p", PixelFormat::planar(3, 12, 1, 2, 0)),
+  ("gray", PixelFormat::packed(1, 8, 8, 0)),
+  ("monow", PixelFormat::packed(1, 1, 1, 0)),
+  ("monob", PixelFormat::packed(1, 1, 1, 0)),
+  ("pal8", PixelFormat::paletted()),
+  ("yuvj420p", PixelFormat::planar(3, 12, 1, 1, 1)),
+  ("yuvj422p", PixelFormat::planar(3, 16, 1, 1, 0)),
+  ("yuvj444p", PixelFormat::planar(3, 24, 1, 0, 0)),
+  ("uyvy422", PixelFormat::packed(3, 16, 16, 1)),
+  ("uyyvyy411", PixelFormat::packed(3, 12, 12, 2)),
+  ("bgr8", PixelFormat::packed(3, 8, 8, 0)),
+  ("bgr4", PixelFormat::packed(3, 4, 4, 0)),
+  ("rgb8", PixelFormat::packed(3, 8, 8, 0)),
+  ("rgb4", PixelFormat::packed(3, 4, 4, 0)),
+  ("nv12", PixelFormat::semi_planar(12, 1, 1, 1)),
+  ("nv21", PixelFormat::semi_planar(12, 1, 1, 1)),
+  ("argb", PixelFormat::packed(4, 32, 32, 0)),
+  ("rgba", PixelFormat::packed(4, 32, 32, 0)),
+  ("abgr", PixelFormat::packed(4, 32, 32, 0)),
+  ("bgra", PixelFormat::packed(4, 32, 32, 0)),
+  ("gray16be", PixelFormat::packed(1, 16, 16, 0)),
+  ("gray16le", PixelFormat::packed(1, 16, 16, 0)),
+  ("yuv440p", PixelFormat::planar(3, 16, 1, 0, 1)),
+  ("yuvj440p", PixelFormat::planar(3, 16, 1, 0, 1)),
+  ("yuva420p", PixelFormat::planar(4, 20, 1, 1, 1)),
+  ("rgb48be", PixelFormat::packed(3, 48, 48, 0)),
+  ("rgb48le", PixelFormat::packed(3, 48, 48, 0)),
+  ("rgb565be", PixelFormat::packed(3, 16, 16, 0)),
+  ("rgb565le", PixelFormat::packed(3, 16, 16, 0)),
+  ("rgb555be", PixelFormat::packed(3, 15, 16, 0)),
+  ("rgb555le", PixelFormat::packed(3, 15, 16, 0)),
+  ("bgr565be", PixelFormat::packed(3, 16, 16, 0)),
+  ("bgr565le", PixelFormat::packed(3, 16, 16, 0)),
+  ("bgr555be", PixelFormat::packed(3, 15, 16, 0)),
+  ("bgr555le", PixelFormat::packed(3, 15, 16, 0)),
+  ("vaapi", PixelFormat::hardware()),
+  ("yuv420p16le", PixelFormat::planar(3, 24, 2, 1, 1)),
+  ("yuv420p16be", PixelFormat::planar(3, 24, 2, 1, 1)),
+  ("yuv422p16le", PixelFormat::planar(3, 32, 2, 1, 0)),
+  ("yuv422p16be", PixelFormat::planar(3, 32, 2, 1, 0)),
+  ("yuv444p16le", PixelFormat::planar(3, 48, 2, 0, 0)),
+  ("yuv444p16be", PixelFormat::planar(3, 48, 2, 0, 0)),
+  ("dxva2", PixelFormat::hardware()),
+  ("rgb444le", PixelFormat::packed(3, 12, 16, 0)),
+  ("rgb444be", PixelFormat::packed(3, 12, 16, 0)),
+  ("bgr444le", PixelFormat::packed(3, 12, 16, 0)),
+  ("bgr444be", PixelFormat::packed(3, 12, 16, 0)),
+  ("ya8", PixelFormat::packed(2, 16, 16, 0)),
+  ("bgr48be", PixelFormat::packed(3, 48, 48, 0)),
+  ("bgr48le", PixelFormat::packed(3, 48, 48, 0)),
+  ("yuv420p9be", PixelFormat::planar(3, 13, 2, 1, 1)),
+  ("yuv420p9le", PixelFormat::planar(3, 13, 2, 1, 1)),
+  ("yuv420p10be", PixelFormat::planar(3, 15, 2, 1, 1)),
+  ("yuv420p10le", PixelFormat::planar(3, 15, 2, 1, 1)),
+  ("yuv422p10be", PixelFormat::planar(3, 20, 2, 1, 0)),
+  ("yuv422p10le", PixelFormat::planar(3, 20, 2, 1, 0)),
+  ("yuv444p9be", PixelFormat::planar(3, 27, 2, 0, 0)),
+  ("yuv444p9le", PixelFormat::planar(3, 27, 2, 0, 0)),
+  ("yuv444p10be", PixelFormat::planar(3, 30, 2, 0, 0)),
+  ("yuv444p10le", PixelFormat::planar(3, 30, 2, 0, 0)),
+  ("yuv422p9be", PixelFormat::planar(3, 18, 2, 1, 0)),
+  ("yuv422p9le", PixelFormat::planar(3, 18, 2, 1, 0)),
+  ("gbrp", PixelFormat::planar(3, 24, 1, 0, 0)),
+  ("gbrp9be", PixelFormat::planar(3, 27, 2, 0, 0)),
+  ("gbrp9le", PixelFormat::planar(3, 27, 2, 0, 0)),
+  ("gbrp10be", PixelFormat::planar(3, 30, 2, 0, 0)),
+  ("gbrp10le", PixelFormat::planar(3, 30, 2, 0, 0)),
+  ("gbrp16be", PixelFormat::planar(3, 48, 2, 0, 0)),
+  ("gbrp16le", PixelFormat::planar(3, 48, 2, 0, 0)),
+  ("yuva422p", PixelFormat::planar(4, 24, 1, 1, 0)),
+  ("yuva444p", PixelFormat::planar(4, 32, 1, 0, 0)),
+  ("yuva420p9be", PixelFormat::planar(4, 22, 2, 1, 1)),
+  ("yuva420p9le", PixelFormat::planar(4, 22, 2, 1, 1)),
+  ("yuva422p9be", PixelFormat::planar(4, 27, 2, 1, 0)),
+  ("yuva422p9le", PixelFormat::planar(4, 27, 2, 1, 0)),
+  ("yuva444p9be", PixelFormat::planar(4, 36, 2, 0, 0)),
+  ("yuva444p9le", PixelFormat::planar(4, 36, 2, 0, 0)),
+  ("yuva420p10be", PixelFormat::planar(4, 25, 2, 1, 1)),
+  ("yuva420p10le", PixelFormat::planar(4, 25, 2, 1, 1)),
+  ("yuva422p10be", PixelFormat::planar(4, 30, 2, 1, 0)),
+  ("yuva422p10le", PixelFormat::planar(4, 30, 2, 1, 0)),
+  ("yuva444p10be", PixelFormat::planar(4, 40, 2, 0, 0)),
+  ("yuva444p10le", PixelFormat::planar(4, 40, 2, 0, 0)),
+  ("yuva420p16be", PixelFormat::planar(4, 40, 2, 1, 1)),
+  ("yuva420p16le", PixelFormat::planar(4, 40, 2, 1, 1)),
+  ("yuva422p16be", PixelFormat::planar(4, 48, 2, 1, 0)),
+  ("yuva422p16le", PixelFormat::planar(4, 48, 2, 1, 0)),
+  ("yuva444p16be", PixelFormat::planar(4, 64, 2, 0, 0)),
+  ("yuva444p16le", PixelFormat::planar(4, 64, 2, 0, 0)),
+  ("vdpau", PixelFormat::hardware()),
+  ("xyz12le", PixelFormat::packed(3, 36, 48, 0)),
+  ("xyz12be", PixelFormat::packed(3, 36, 48, 0)),
+  ("nv16", PixelFormat::semi_planar(16, 1, 1, 0)),
+  ("nv20le", PixelFormat::semi_planar(20, 2, 1, 0)),
+  ("nv20be", PixelFormat::semi_planar(20, 2, 1, 0)),
+  ("rgba64be", PixelFormat::packed(4, 64, 64, 0)),
+  ("rgba64le", PixelFormat::packed(4, 64, 64, 0)),
+  ("bgra64be", PixelFormat::packed(4, 64, 64, 0)),
+  ("bgra64le", PixelFormat::packed(4, 64, 64, 0)),
+  ("yvyu422", PixelFormat::packed(3, 16, 16, 1)),
+  ("ya16be", PixelFormat::packed(2, 32, 32, 0)),
+  ("ya16le", PixelFormat::packed(2, 32, 32, 0)),
+  ("gbrap", PixelFormat::planar(4, 32, 1, 0, 0)),
+  ("gbrap16be", PixelFormat::planar(4, 64, 2, 0, 0)),
+  ("gbrap16le", PixelFormat::planar(4, 64, 2, 0, 0)),
+  ("qsv", PixelFormat::hardware()),
+  ("mmal", PixelFormat::hardware()),
+  ("d3d11va", PixelFormat::hardware()),
+  ("cuda", PixelFormat::hardware()),
+  ("0rgb", PixelFormat::packed(3, 24, 32, 0)),
+  ("rgb0", PixelFormat::packed(3, 24, 32, 0)),
+  ("0bgr", PixelFormat::packed(3, 24, 32, 0)),
+  ("bgr0", PixelFormat::packed(3, 24, 32, 0)),
+  ("yuv420p12be", PixelFormat::planar(3, 18, 2, 1, 1)),
+  ("yuv420p12le", PixelFormat::planar(3, 18, 2, 1, 1)),
+  ("yuv420p14be", PixelFormat::planar(3, 21, 2, 1, 1)),
+  ("yuv420p14le", PixelFormat::planar(3, 21, 2, 1, 1)),
+  ("yuv422p12be", PixelFormat::planar(3, 24, 2, 1, 0)),
+  ("yuv422p12le", PixelFormat::planar(3, 24, 2, 1, 0)),
+  ("yuv422p14be", PixelFormat::planar(3, 28, 2, 1, 0)),
+  ("yuv422p14le", PixelFormat::planar(3, 28, 2, 1, 0)),
+  ("yuv444p12be", PixelFormat::planar(3, 36, 2, 0, 0)),
+  ("yuv444p12le", PixelFormat::planar(3, 36, 2, 0, 0)),
+  ("yuv444p14be", PixelFormat::planar(3, 42, 2, 0, 0)),
+  ("yuv444p14le", PixelFormat::planar(3, 42, 2, 0, 0)),
+  ("gbrp12be", PixelFormat::planar(3, 36, 2, 0, 0)),
+  ("gbrp12le", PixelFormat::planar(3, 36, 2, 0, 0)),
+  ("gbrp14be", PixelFormat::planar(3, 42, 2, 0, 0)),
+  ("gbrp14le", PixelFormat::planar(3, 42, 2, 0, 0)),
+  ("yuvj411p", PixelFormat::planar(3, 12, 1, 2, 0)),
+  ("xvmc", PixelFormat::hardware()),
+  ("yuv440p10le", PixelFormat::planar(3, 20, 2, 0, 1)),
+  ("yuv440p10be", PixelFormat::planar(3, 20, 2, 0, 1)),
+  ("yuv440p12le", PixelFormat::planar(3, 24, 2, 0, 1)),
+  ("yuv440p12be", PixelFormat::planar(3, 24, 2, 0, 1)),
+  ("ayuv64le", PixelFormat::packed(4, 64, 64, 0)),
+  ("ayuv64be", PixelFormat::packed(4, 64, 64, 0)),
+  ("videotoolbox", PixelFormat::hardware()),
+  ("p010le", PixelFormat::semi_planar(15, 2, 1, 1)),
+  ("p010be", PixelFormat::semi_planar(15, 2, 1, 1)),
+  ("gbrap12be", PixelFormat::planar(4, 48, 2, 0, 0)),
+  ("gbrap12le", PixelFormat::planar(4, 48, 2, 0, 0)),
+  ("gbrap10be", PixelFormat::planar(4, 40, 2, 0, 0)),
+  ("gbrap10le", PixelFormat::planar(4, 40, 2, 0, 0)),
+  ("mediacodec", PixelFormat::hardware()),
+  ("gray12be", PixelFormat::packed(1, 12, 16, 0)),
+  ("gray12le", PixelFormat::packed(1, 12, 16, 0)),
+  ("gray10be", PixelFormat::packed(1, 10, 16, 0)),
+  ("gray10le", PixelFormat::packed(1, 10, 16, 0)),
+  ("p016le", PixelFormat::semi_planar(24, 2, 1, 1)),
+  ("p016be", PixelFormat::semi_planar(24, 2, 1, 1)),
+  ("d3d11", PixelFormat::hardware()),
+  ("gray9be", PixelFormat::packed(1, 9, 16, 0)),
+  ("gray9le", PixelFormat::packed(1, 9, 16, 0)),
+  ("gbrpf32be", PixelFormat::planar(3, 96, 4, 0, 0)),
+  ("gbrpf32le", PixelFormat::planar(3, 96, 4, 0, 0)),
+  ("gbrapf32be", PixelFormat::planar(4, 128, 4, 0, 0)),
+  ("gbrapf32le", PixelFormat::planar(4, 128, 4, 0, 0)),
+  ("drm", PixelFormat::hardware()),
+  ("opencl", PixelFormat::hardware()),
+  ("gray14be", PixelFormat::packed(1, 14, 16, 0)),
+  ("gray14le", PixelFormat::packed(1, 14, 16, 0)),
+  ("grayf32be", PixelFormat::packed(1, 32, 32, 0)),
+  ("grayf32le", PixelFormat::packed(1, 32, 32, 0)),
+  ("yuva422p12be", PixelFormat::planar(4, 36, 2, 1, 0)),
+  ("yuva422p12le", PixelFormat::planar(4, 36, 2, 1, 0)),
+  ("yuva444p12be", PixelFormat::planar(4, 48, 2, 0, 0)),
+  ("yuva444p12le", PixelFormat::planar(4, 48, 2, 0, 0)),
+  ("nv24", PixelFormat::semi_planar(24, 1, 0, 0)),
+  ("nv42", PixelFormat::semi_planar(24, 1, 0, 0)),
+  ("vulkan", PixelFormat::hardware()),
+  ("y210be", PixelFormat::packed(3, 20, 32, 1)),
+  ("y210le", PixelFormat::packed(3, 20, 32, 1)),
+  ("x2rgb10le", PixelFormat::packed(3, 30, 32, 0)),
+  ("x2rgb10be", PixelFormat::packed(3, 30, 32, 0)),
+  ("x2bgr10le", PixelFormat::packed(3, 30, 32, 0)),
+  ("x2bgr10be", PixelFormat::packed(3, 30, 32, 0)),
+  ("p210be", PixelFormat::semi_planar(20, 2, 1, 0)),
+  ("p210le", PixelFormat::semi_planar(20, 2, 1, 0)),
+  ("p410be", PixelFormat::semi_planar(30, 2, 0, 0)),
+  ("p410le", PixelFormat::semi_planar(30, 2, 0, 0)),
+  ("p216be", PixelFormat::semi_planar(32, 2, 1, 0)),
+  ("p216le", PixelFormat::semi_planar(32, 2, 1, 0)),
+  ("p416be", PixelFormat::semi_planar(48, 2, 0, 0)),
+  ("p416le", PixelFormat::semi_planar(48, 2, 0, 0)),
+  ("vuya", PixelFormat::packed(4, 32, 32, 0)),
+  ("rgbaf16be", PixelFormat::packed(4, 64, 64, 0)),
+  ("rgbaf16le", PixelFormat::packed(4, 64, 64, 0)),
+  ("vuyx", PixelFormat::packed(3, 24, 32, 0)),
+  ("p012le", PixelFormat::semi_planar(18, 2, 1, 1)),
+  ("p012be", PixelFormat::semi_planar(18, 2, 1, 1)),
+  ("y212be", PixelFormat::packed(3, 24, 32, 1)),
+  ("y212le", PixelFormat::packed(3, 24, 32, 1)),
+  ("xv30be", PixelFormat::packed(3, 30, 32, 0)),
+  ("xv30le", PixelFormat::packed(3, 30, 32, 0)),
+  ("xv36be", PixelFormat::packed(3, 36, 64, 0)),
+  ("xv36le", PixelFormat::packed(3, 36, 64, 0)),
+  ("rgbf32be", PixelFormat::packed(3, 96, 96, 0)),
+  ("rgbf32le", PixelFormat::packed(3, 96, 96, 0)),
+  ("rgbaf32be", PixelFormat::packed(4, 128, 128, 0)),
+  ("rgbaf32le", PixelFormat::packed(4, 128, 128, 0)),
+  ("bayer_bggr8", PixelFormat::packed(3, 8, 8, 0)),
+  ("bayer_bggr16le", PixelFormat::packed(3, 16, 16, 0)),
+  ("bayer_bggr16be", PixelFormat::packed(3, 16, 16, 0)),
+  ("bayer_rggb8", PixelFormat::packed(3, 8, 8, 0)),
+  ("bayer_rggb16le", PixelFormat::packed(3, 16, 16, 0)),
+  ("bayer_rggb16be", PixelFormat::packed(3, 16, 16, 0)),
+  ("bayer_gbrg8", PixelFormat::packed(3, 8, 8, 0)),
+  ("bayer_gbrg16le", PixelFormat::packed(3, 16, 16, 0)),
+  ("bayer_gbrg16be", PixelFormat::packed(3, 16, 16, 0)),
+  ("bayer_grbg8", PixelFormat::packed(3, 8, 8, 0)),
+  ("bayer_grbg16le", PixelFormat::packed(3, 16, 16, 0)),
+  ("bayer_grbg16be", PixelFormat::packed(3, 16, 16, 0)),
+];
+
+/// A line of `ffmpeg -pix_fmts`, see [`parse_pix_fmts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedPixelFormat {
+  /// The pix_fmt identifier, e.g. `rgb24`.
+  pub name: String,
+  /// Whether FFmpeg can convert from this format.
+  pub input: bool,
+  /// Whether FFmpeg can convert to this format, e.g. for `-f rawvideo`.
+  pub output: bool,
+  /// The format from [`PIXEL_FORMATS`] if known, otherwise the listed
+  /// components and bits per pixel with a [`PixelLayout::Unknown`] layout
+  /// (unless it is a hardware or paletted format).
+  pub format: PixelFormat,
+}
+
+static LISTED_PIXEL_FORMATS: RwLock<Option<HashMap<String, ListedPixelFormat>>> = RwLock::new(None);
+
+fn builtin_pixel_format(pix_fmt: &str) -> Option<PixelFormat> {
+  PIXEL_FORMATS
+    .iter()
+    .find(|(name, _)| *name == pix_fmt)
+    .map(|(_, format)| *format)
+}
+
+fn listed_pixel_format(pix_fmt: &str) -> Option<ListedPixelFormat> {
+  let listed = LISTED_PIXEL_FORMATS
+    .read()
+    .unwrap_or_else(|e| e.into_inner());
+  listed.as_ref()?.get(pix_fmt).cloned()
+}
+
+/// Look up a pixel format by its identifier (e.g. `rgb24`) in
+/// [`PIXEL_FORMATS`], then in the formats found by
+/// [`refresh_pixel_formats`]. Returns `None` if the pix_fmt is unrecognized.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::pix_fmt::{pixel_format, PixelLayout};
+/// let nv12 = pixel_format("nv12").unwrap();
+/// assert_eq!(nv12.components, 3);
+/// assert!(matches!(nv12.layout, PixelLayout::SemiPlanar { .. }));
+/// assert_eq!(pixel_format("asdf"), None);
+/// ```
+pub fn pixel_format(pix_fmt: &str) -> Option<PixelFormat> {
+  builtin_pixel_format(pix_fmt).or_else(|| listed_pixel_format(pix_fmt).map(|l| l.format))
+}
 
 /// Map from the pix_fmt identifier string (e.g. `rgb24`) to the number of bits
 /// per pixel (e.g. `24`). Returns `None` if the pix_fmt is unsupported/unrecognized.
 ///
-/// Obtained from `ffmpeg -pix-fmts`.
+/// Obtained from `ffmpeg -pix-fmts`. These are significant bits, see
+/// [`frame_size`] for the size of a frame in memory.
 ///
 /// ## Exammples
 ///
@@ -15,230 +462,153 @@ use crate::event::VideoStream;
 /// assert_eq!(get_bits_per_pixel("asdf"), None);
 /// ```
 pub fn get_bits_per_pixel(pix_fmt: &str) -> Option<u32> {
-  match pix_fmt {
-    "yuv420p" => Some(12),
-    "yuyv422" => Some(16),
-    "rgb24" => Some(24),
-    "bgr24" => Some(24),
-    "yuv422p" => Some(16),
-    "yuv444p" => Some(24),
-    "yuv410p" => Some(9),
-    "yuv411p" => Some(12),
-    "gray" => Some(8),
-    "monow" => Some(1),
-    "monob" => Some(1),
-    "pal8" => Some(8),
-    "yuvj420p" => Some(12),
-    "yuvj422p" => Some(16),
-    "yuvj444p" => Some(24),
-    "uyvy422" => Some(16),
-    "uyyvyy411" => Some(12),
-    "bgr8" => Some(8),
-    "bgr4" => Some(4),
-    "rgb8" => Some(8),
-    "rgb4" => Some(4),
-    "nv12" => Some(12),
-    "nv21" => Some(12),
-    "argb" => Some(32),
-    "rgba" => Some(32),
-    "abgr" => Some(32),
-    "bgra" => Some(32),
-    "gray16be" => Some(16),
-    "gray16le" => Some(16),
-    "yuv440p" => Some(16),
-    "yuvj440p" => Some(16),
-    "yuva420p" => Some(20),
-    "rgb48be" => Some(48),
-    "rgb48le" => Some(48),
-    "rgb565be" => Some(16),
-    "rgb565le" => Some(16),
-    "rgb555be" => Some(15),
-    "rgb555le" => Some(15),
-    "bgr565be" => Some(16),
-    "bgr565le" => Some(16),
-    "bgr555be" => Some(15),
-    "bgr555le" => Some(15),
-    "vaapi" => Some(0),
-    "yuv420p16le" => Some(24),
-    "yuv420p16be" => Some(24),
-    "yuv422p16le" => Some(32),
-    "yuv422p16be" => Some(32),
-    "yuv444p16le" => Some(48),
-    "yuv444p16be" => Some(48),
-    "dxva2" => Some(0),
-    "rgb444le" => Some(12),
-    "rgb444be" => Some(12),
-    "bgr444le" => Some(12),
-    "bgr444be" => Some(12),
-    "ya8" => Some(16),
-    "bgr48be" => Some(48),
-    "bgr48le" => Some(48),
-    "yuv420p9be" => Some(13),
-    "yuv420p9le" => Some(13),
-    "yuv420p10be" => Some(15),
-    "yuv420p10le" => Some(15),
-    "yuv422p10be" => Some(20),
-    "yuv422p10le" => Some(20),
-    "yuv444p9be" => Some(27),
-    "yuv444p9le" => Some(27),
-    "yuv444p10be" => Some(30),
-    "yuv444p10le" => Some(30),
-    "yuv422p9be" => Some(18),
-    "yuv422p9le" => Some(18),
-    "gbrp" => Some(24),
-    "gbrp9be" => Some(27),
-    "gbrp9le" => Some(27),
-    "gbrp10be" => Some(30),
-    "gbrp10le" => Some(30),
-    "gbrp16be" => Some(48),
-    "gbrp16le" => Some(48),
-    "yuva422p" => Some(24),
-    "yuva444p" => Some(32),
-    "yuva420p9be" => Some(22),
-    "yuva420p9le" => Some(22),
-    "yuva422p9be" => Some(27),
-    "yuva422p9le" => Some(27),
-    "yuva444p9be" => Some(36),
-    "yuva444p9le" => Some(36),
-    "yuva420p10be" => Some(25),
-    "yuva420p10le" => Some(25),
-    "yuva422p10be" => Some(30),
-    "yuva422p10le" => Some(30),
-    "yuva444p10be" => Some(40),
-    "yuva444p10le" => Some(40),
-    "yuva420p16be" => Some(40),
-    "yuva420p16le" => Some(40),
-    "yuva422p16be" => Some(48),
-    "yuva422p16le" => Some(48),
-    "yuva444p16be" => Some(64),
-    "yuva444p16le" => Some(64),
-    "vdpau" => Some(0),
-    "xyz12le" => Some(36),
-    "xyz12be" => Some(36),
-    "nv16" => Some(16),
-    "nv20le" => Some(20),
-    "nv20be" => Some(20),
-    "rgba64be" => Some(64),
-    "rgba64le" => Some(64),
-    "bgra64be" => Some(64),
-    "bgra64le" => Some(64),
-    "yvyu422" => Some(16),
-    "ya16be" => Some(32),
-    "ya16le" => Some(32),
-    "gbrap" => Some(32),
-    "gbrap16be" => Some(64),
-    "gbrap16le" => Some(64),
-    "qsv" => Some(0),
-    "mmal" => Some(0),
-    "d3d11va" => Some(0),
-    "cuda" => Some(0),
-    "0rgb" => Some(24),
-    "rgb0" => Some(24),
-    "0bgr" => Some(24),
-    "bgr0" => Some(24),
-    "yuv420p12be" => Some(18),
-    "yuv420p12le" => Some(18),
-    "yuv420p14be" => Some(21),
-    "yuv420p14le" => Some(21),
-    "yuv422p12be" => Some(24),
-    "yuv422p12le" => Some(24),
-    "yuv422p14be" => Some(28),
-    "yuv422p14le" => Some(28),
-    "yuv444p12be" => Some(36),
-    "yuv444p12le" => Some(36),
-    "yuv444p14be" => Some(42),
-    "yuv444p14le" => Some(42),
-    "gbrp12be" => Some(36),
-    "gbrp12le" => Some(36),
-    "gbrp14be" => Some(42),
-    "gbrp14le" => Some(42),
-    "yuvj411p" => Some(12),
-    "bayer" => Some(3),
-    "xvmc" => Some(0),
-    "yuv440p10le" => Some(20),
-    "yuv440p10be" => Some(20),
-    "yuv440p12le" => Some(24),
-    "yuv440p12be" => Some(24),
-    "ayuv64le" => Some(64),
-    "ayuv64be" => Some(64),
-    "videotoolbox" => Some(0),
-    "p010le" => Some(15),
-    "p010be" => Some(15),
-    "gbrap12be" => Some(48),
-    "gbrap12le" => Some(48),
-    "gbrap10be" => Some(40),
-    "gbrap10le" => Some(40),
-    "mediacodec" => Some(0),
-    "gray12be" => Some(12),
-    "gray12le" => Some(12),
-    "gray10be" => Some(10),
-    "gray10le" => Some(10),
-    "p016le" => Some(24),
-    "p016be" => Some(24),
-    "d3d11" => Some(0),
-    "gray9be" => Some(9),
-    "gray9le" => Some(9),
-    "gbrpf32be" => Some(96),
-    "gbrpf32le" => Some(96),
-    "gbrapf32be" => Some(128),
-    "gbrapf32le" => Some(128),
-    "drm" => Some(0),
-    "opencl" => Some(0),
-    "gray14be" => Some(14),
-    "gray14le" => Some(14),
-    "grayf32be" => Some(32),
-    "grayf32le" => Some(32),
-    "yuva422p12be" => Some(36),
-    "yuva422p12le" => Some(36),
-    "yuva444p12be" => Some(48),
-    "yuva444p12le" => Some(48),
-    "nv24" => Some(24),
-    "nv42" => Some(24),
-    "vulkan" => Some(0),
-    "y210be" => Some(20),
-    "y210le" => Some(20),
-    "x2rgb10le" => Some(30),
-    "x2rgb10be" => Some(30),
-    "x2bgr10le" => Some(30),
-    "x2bgr10be" => Some(30),
-    "p210be" => Some(20),
-    "p210le" => Some(20),
-    "p410be" => Some(30),
-    "p410le" => Some(30),
-    "p216be" => Some(32),
-    "p216le" => Some(32),
-    "p416be" => Some(48),
-    "p416le" => Some(48),
-    "vuya" => Some(32),
-    "rgbaf16be" => Some(64),
-    "rgbaf16le" => Some(64),
-    "vuyx" => Some(24),
-    "p012le" => Some(18),
-    "p012be" => Some(18),
-    "y212be" => Some(24),
-    "y212le" => Some(24),
-    "xv30be" => Some(30),
-    "xv30le" => Some(30),
-    "xv36be" => Some(36),
-    "xv36le" => Some(36),
-    "rgbf32be" => Some(96),
-    "rgbf32le" => Some(96),
-    "rgbaf32be" => Some(128),
-    "rgbaf32le" => Some(128),
-    _ => None,
-  }
+  pixel_format(pix_fmt).map(|format| format.bits_per_pixel)
 }
 
-/// Computes `width * height * bits_per_pixel / 8`
+/// Computes the size of a raw video frame of `video_data`, see [`frame_size`].
 pub fn get_bytes_per_frame(video_data: &VideoStream) -> Option<u32> {
-  let bits_per_pixel = get_bits_per_pixel(&video_data.pix_fmt)?;
-  // Enforce byte-alignment, since we don't currently have buffer reads in
-  // sub-byte increments
-  // Use the full frame buffer size for this, since formats like `yuvj420p` typically restrict a
-  // frame's size such that the buffer size has full bytes
-  let num_bits = video_data.width * video_data.height * bits_per_pixel;
-  match num_bits % 8 {
-    0 => Some(num_bits / 8),
-    _ => None,
+  let size = frame_size(&video_data.pix_fmt, video_data.width, video_data.height)?;
+  u32::try_from(size).ok()
+}
+
+/// Size in bytes of a `width` by `height` frame of `pix_fmt` as written by
+/// `-f rawvideo`. `None` if the pix_fmt is unrecognized, a hardware format or
+/// of unknown layout.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::pix_fmt::frame_size;
+/// assert_eq!(frame_size("yuv420p", 1920, 1080), Some(3_110_400));
+/// // Chroma planes round up for odd dimensions
+/// assert_eq!(frame_size("yuv420p", 3, 3), Some(9 + 2 * 4));
+/// // Samples of high bit depths take two bytes
+/// assert_eq!(frame_size("gray10le", 4, 4), Some(32));
+/// assert_eq!(frame_size("cuda", 1920, 1080), None);
+/// ```
+pub fn frame_size(pix_fmt: &str, width: u32, height: u32) -> Option<usize> {
+  pixel_format(pix_fmt)?.frame_size(width, height)
+}
+
+/// Whether `pix_fmt` can be requested as `-pix_fmt <pix_fmt> -f rawvideo`
+/// and split into frames with [`frame_size`].
+///
+/// Hardware and unknown formats are unsupported, as are formats of unknown
+/// layout. After
+/// [`refresh_pixel_formats`], so are formats the FFmpeg build cannot convert
+/// to.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::pix_fmt::is_supported_for_raw_output;
+/// assert!(is_supported_for_raw_output("rgb24"));
+/// assert!(!is_supported_for_raw_output("vaapi"));
+/// assert!(!is_supported_for_raw_output("asdf"));
+/// ```
+pub fn is_supported_for_raw_output(pix_fmt: &str) -> bool {
+  let Some(format) = pixel_format(pix_fmt) else {
+    return false;
+  };
+  let listed = LISTED_PIXEL_FORMATS
+    .read()
+    .unwrap_or_else(|e| e.into_inner());
+  let output = match listed.as_ref() {
+    Some(listed) => listed.get(pix_fmt).is_some_and(|l| l.output),
+    None => true,
+  };
+  output && format.frame_size(1, 1).is_some()
+}
+
+/// Parse the output of `ffmpeg -pix_fmts`. Lines that do not describe a
+/// pixel format are skipped.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::pix_fmt::{parse_pix_fmts, PixelLayout};
+/// let output = "Pixel formats:
+/// I.... = Supported Input  format for conversion
+/// .O... = Supported Output format for conversion
+/// FLAGS NAME            NB_COMPONENTS BITS_PER_PIXEL BIT_DEPTHS
+/// -----
+/// IO... yuv420p                3             12      8-8-8
+/// ..H.. vaapi                  0              0      0
+/// I.... y216le                 3             32      16-16-16
+/// IO... yuv444p12msble         3             36      12-12-12
+/// ";
+/// let formats = parse_pix_fmts(output);
+/// assert_eq!(formats.len(), 4);
+/// assert!(formats[0].input && formats[0].output);
+/// assert_eq!(formats[2].name, "y216le");
+/// assert_eq!(formats[2].format.bits_per_pixel, 32);
+/// assert!(!formats[2].output);
+///
+/// // The layout of formats missing from `PIXEL_FORMATS` is not guessed
+/// assert_eq!(formats[3].format.layout, PixelLayout::Unknown);
+/// assert_eq!(formats[3].format.frame_size(1920, 1080), None);
+/// ```
+pub fn parse_pix_fmts(output: &str) -> Vec<ListedPixelFormat> {
+  output.lines().filter_map(parse_pix_fmts_line).collect()
+}
+
+fn parse_pix_fmts_line(line: &str) -> Option<ListedPixelFormat> {
+  let mut tokens = line.split_whitespace();
+  let flags = tokens.next()?;
+  let name = tokens.next()?;
+  let components = tokens.next()?.parse::<u8>().ok()?;
+  let bits_per_pixel = tokens.next()?.parse::<u32>().ok()?;
+  let flags = flags.as_bytes();
+  if flags.len() != 5 || !flags.iter().all(|c| b".IOHPB".contains(c)) {
+    return None;
   }
+
+  let format = builtin_pixel_format(name).unwrap_or(PixelFormat {
+    components,
+    bits_per_pixel,
+    layout: match flags {
+      [_, _, b'H', _, _] => PixelLayout::Hardware,
+      [_, _, _, b'P', _] => PixelLayout::Paletted,
+      // Planar and packed formats can't be told apart by the listing
+      _ => PixelLayout::Unknown,
+    },
+  });
+
+  Some(ListedPixelFormat {
+    name: name.to_string(),
+    input: flags[0] == b'I',
+    output: flags[1] == b'O',
+    format,
+  })
+}
+
+/// Ask FFmpeg for the pixel formats it supports with `ffmpeg -pix_fmts`.
+/// Afterwards [`pixel_format`] also knows formats missing from
+/// [`PIXEL_FORMATS`], and [`is_supported_for_raw_output`] takes into account
+/// which formats this FFmpeg build can output.
+pub async fn refresh_pixel_formats() -> anyhow::Result<Vec<ListedPixelFormat>> {
+  refresh_pixel_formats_with_path(ffmpeg_path()).await
+}
+
+/// Lower level variant of [`refresh_pixel_formats`] that exposes a customized
+/// path to the ffmpeg binary.
+pub async fn refresh_pixel_formats_with_path<P: AsRef<OsStr>>(
+  path: P,
+) -> anyhow::Result<Vec<ListedPixelFormat>> {
+  let output = Command::new(&path)
+    .create_no_window()
+    .args(["-hide_banner", "-pix_fmts"])
+    .output()
+    .await?;
+  if !output.status.success() {
+    anyhow::bail!("ffmpeg -pix_fmts exited with non-zero status");
+  }
+
+  let formats = parse_pix_fmts(&String::from_utf8_lossy(&output.stdout));
+  if formats.is_empty() {
+    anyhow::bail!("failed to parse ffmpeg -pix_fmts");
+  }
+
+  let listed = formats
+    .iter()
+    .map(|l| (l.name.clone(), l.clone()))
+    .collect();
+  *LISTED_PIXEL_FORMATS
+    .write()
+    .unwrap_or_else(|e| e.into_inner()) = Some(listed);
+  Ok(formats)
 }
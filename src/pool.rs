@@ -0,0 +1,327 @@
+//! A pool running several Ffmpeg jobs with bounded concurrency.
+
+use crate::command::FfmpegCommand;
+use crate::event::{FfmpegEvent, FfmpegProgress};
+use crate::stream::FfmpegEventStream;
+use futures_util::StreamExt;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{watch, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+/// Runs at most `max_concurrent` Ffmpeg processes at a time. Jobs submitted
+/// beyond that limit wait for a free slot before being spawned.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::pool::FfmpegJobPool;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let pool = FfmpegJobPool::new(2);
+///
+/// let mut command = FfmpegCommand::new();
+/// command.testsrc().overwrite().output("output/pool.mp4");
+/// let job = pool.submit(command)?;
+///
+/// let mut status = job.status();
+/// while status.changed().await.is_ok() {
+///   let overall = pool.progress();
+///   println!("{} running, {:.0}% done", overall.running, overall.fraction * 100.0);
+/// }
+///
+/// // Later, e.g. when the service receives SIGTERM
+/// pool.shutdown(Duration::from_secs(5)).await;
+/// let exit_status = job.wait().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FfmpegJobPool {
+  semaphore: Arc<Semaphore>,
+  tracker: TaskTracker,
+  shutdown: CancellationToken,
+  grace: Arc<OnceLock<Duration>>,
+  jobs: Mutex<PoolJobs>,
+}
+
+/// The queued and running jobs of a pool, and how many have finished.
+#[derive(Default)]
+struct PoolJobs {
+  live: Vec<watch::Receiver<FfmpegJobStatus>>,
+  finished: usize,
+}
+
+impl PoolJobs {
+  /// Count finished jobs and stop tracking them, so that a long-lived pool
+  /// doesn't accumulate every job it ever ran.
+  fn prune(&mut self) {
+    let before = self.live.len();
+    self
+      .live
+      .retain(|job| job.borrow().state != FfmpegJobState::Finished);
+    self.finished += before - self.live.len();
+  }
+}
+
+impl FfmpegJobPool {
+  /// Create a pool running at most `max_concurrent` jobs at once.
+  pub fn new(max_concurrent: usize) -> Self {
+    Self {
+      semaphore: Arc::new(Semaphore::new(max_concurrent)),
+      tracker: TaskTracker::new(),
+      shutdown: CancellationToken::new(),
+      grace: Arc::new(OnceLock::new()),
+      jobs: Mutex::new(PoolJobs::default()),
+    }
+  }
+
+  /// Queue a command to be spawned as soon as a slot is available.
+  ///
+  /// Must be called from within a tokio runtime. Fails if the pool is
+  /// shutting down.
+  pub fn submit(&self, command: FfmpegCommand) -> anyhow::Result<FfmpegJobHandle> {
+    if self.shutdown.is_cancelled() {
+      anyhow::bail!("job pool is shutting down");
+    }
+
+    let semaphore = self.semaphore.clone();
+    let shutdown = self.shutdown.clone();
+    let grace = self.grace.clone();
+    let cancel = CancellationToken::new();
+    let (status_tx, status) = watch::channel(FfmpegJobStatus::default());
+    let task = self.tracker.spawn(run_job(
+      command,
+      semaphore,
+      shutdown,
+      grace,
+      cancel.clone(),
+      status_tx,
+    ));
+
+    let mut jobs = self.jobs.lock().unwrap();
+    jobs.prune();
+    jobs.live.push(status.clone());
+    Ok(FfmpegJobHandle {
+      task,
+      cancel,
+      status,
+    })
+  }
+
+  /// Aggregate progress over the jobs submitted to the pool so far.
+  pub fn progress(&self) -> FfmpegPoolProgress {
+    let mut jobs = self.jobs.lock().unwrap();
+    jobs.prune();
+    let mut progress = FfmpegPoolProgress {
+      finished: jobs.finished,
+      fraction: 1.0,
+      ..Default::default()
+    };
+    let mut fraction_sum = 0.0;
+
+    for job in jobs.live.iter() {
+      let status = job.borrow();
+      match status.state {
+        FfmpegJobState::Queued => progress.queued += 1,
+        FfmpegJobState::Running => {
+          progress.running += 1;
+          fraction_sum += status.fraction.unwrap_or(0.0);
+        }
+        // Finished since pruning
+        FfmpegJobState::Finished => {
+          progress.finished += 1;
+          fraction_sum += 1.0;
+        }
+      }
+    }
+
+    let total = jobs.finished + jobs.live.len();
+    if total > 0 {
+      progress.fraction = (jobs.finished as f64 + fraction_sum) / total as f64;
+    }
+    progress
+  }
+
+  /// Number of jobs that are currently queued or running.
+  pub fn len(&self) -> usize {
+    self.tracker.len()
+  }
+
+  /// Whether no jobs are queued or running.
+  pub fn is_empty(&self) -> bool {
+    self.tracker.is_empty()
+  }
+
+  /// Whether [`shutdown`](Self::shutdown) has been called.
+  pub fn is_shutting_down(&self) -> bool {
+    self.shutdown.is_cancelled()
+  }
+
+  /// Gracefully stop the pool:
+  ///
+  /// 1. Stop accepting new jobs; queued jobs that haven't started are dropped.
+  /// 2. Send `q` to every running Ffmpeg process.
+  /// 3. Wait up to `grace` for each process to flush its output and exit.
+  /// 4. Kill any process still running after the grace period.
+  ///
+  /// Resolves once every job task has finished.
+  pub async fn shutdown(&self, grace: Duration) {
+    let _ = self.grace.set(grace);
+    self.shutdown.cancel();
+    self.tracker.close();
+    self.tracker.wait().await;
+  }
+}
+
+/// Where a job of a [`FfmpegJobPool`] is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FfmpegJobState {
+  /// Waiting for a free slot.
+  #[default]
+  Queued,
+  /// The Ffmpeg process is running.
+  Running,
+  /// The job completed, failed or was cancelled.
+  Finished,
+}
+
+/// The current state and progress of a single job.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FfmpegJobStatus {
+  pub state: FfmpegJobState,
+  /// The most recent progress line reported by Ffmpeg.
+  pub progress: Option<FfmpegProgress>,
+  /// Progress as a fraction between `0.0` and `1.0`, if the duration of the
+  /// first input is known.
+  pub fraction: Option<f64>,
+}
+
+/// Progress across all jobs of a [`FfmpegJobPool`], see
+/// [`FfmpegJobPool::progress`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FfmpegPoolProgress {
+  pub queued: usize,
+  pub running: usize,
+  pub finished: usize,
+  /// Average completion between `0.0` and `1.0` of all jobs submitted so
+  /// far, finished ones counting as complete, so that it only grows while a
+  /// batch drains. Running jobs of unknown duration count as not started.
+  /// `1.0` if no job was submitted.
+  pub fraction: f64,
+}
+
+/// A handle to a job submitted to a [`FfmpegJobPool`].
+pub struct FfmpegJobHandle {
+  task: JoinHandle<anyhow::Result<ExitStatus>>,
+  cancel: CancellationToken,
+  status: watch::Receiver<FfmpegJobStatus>,
+}
+
+impl FfmpegJobHandle {
+  /// Wait for the job to finish, returning the exit status of the process.
+  ///
+  /// Fails if the process could not be spawned, or if the pool was shut down
+  /// before the job started.
+  pub async fn wait(self) -> anyhow::Result<ExitStatus> {
+    self.task.await?
+  }
+
+  /// Whether the job has finished, successfully or not.
+  pub fn is_finished(&self) -> bool {
+    self.task.is_finished()
+  }
+
+  /// Receiver of the job's status, updated on every progress line.
+  pub fn status(&self) -> watch::Receiver<FfmpegJobStatus> {
+    self.status.clone()
+  }
+
+  /// Stop the job. A queued job is never spawned, a running process is
+  /// killed; [`wait`](Self::wait) then reports the outcome.
+  pub fn cancel(&self) {
+    self.cancel.cancel();
+  }
+}
+
+async fn run_job(
+  mut command: FfmpegCommand,
+  semaphore: Arc<Semaphore>,
+  shutdown: CancellationToken,
+  grace: Arc<OnceLock<Duration>>,
+  cancel: CancellationToken,
+  status: watch::Sender<FfmpegJobStatus>,
+) -> anyhow::Result<ExitStatus> {
+  // Marks the job as finished however this function returns.
+  let _finished = FinishGuard(&status);
+
+  let _permit = tokio::select! {
+    permit = semaphore.acquire_owned() => permit?,
+    _ = shutdown.cancelled() => anyhow::bail!("job pool shut down before the job started"),
+    _ = cancel.cancelled() => anyhow::bail!("job was cancelled before it started"),
+  };
+
+  let mut child = command.spawn()?;
+  // Keep draining stderr so that ffmpeg never blocks on a full pipe.
+  let mut events = child.stream()?;
+  status.send_modify(|status| status.state = FfmpegJobState::Running);
+
+  let stopped = tokio::select! {
+    _ = track_progress(&mut events, &status) => None,
+    _ = shutdown.cancelled() => Some(false),
+    _ = cancel.cancelled() => Some(true),
+  };
+
+  match stopped {
+    Some(true) => {
+      child.kill().await?;
+      Ok(child.wait().await?)
+    }
+    Some(false) => {
+      let grace = grace.get().copied().unwrap_or_default();
+      let (outcome, _) = tokio::join!(
+        child.quit_with_timeout(grace),
+        events.for_each(|_| futures::future::ready(()))
+      );
+      Ok(outcome?.exit_status())
+    }
+    None => Ok(child.wait().await?),
+  }
+}
+
+/// Publishes every progress update of `events` to `status`.
+async fn track_progress(events: &mut FfmpegEventStream, status: &watch::Sender<FfmpegJobStatus>) {
+  let mut duration = None;
+
+  while let Some(event) = events.next().await {
+    match event {
+      FfmpegEvent::ParsedDuration(parsed) if parsed.input_index == 0 => {
+        duration = parsed.duration.filter(|duration| *duration > 0.0);
+      }
+      FfmpegEvent::Progress(progress) => {
+        let fraction = duration
+          .zip(progress.time)
+          .map(|(duration, time)| (time.as_secs_f64() / duration).clamp(0.0, 1.0));
+        status.send_modify(|status| {
+          status.progress = Some(progress);
+          status.fraction = fraction;
+        });
+      }
+      _ => {}
+    }
+  }
+}
+
+struct FinishGuard<'a>(&'a watch::Sender<FfmpegJobStatus>);
+
+impl Drop for FinishGuard<'_> {
+  fn drop(&mut self) {
+    self
+      .0
+      .send_modify(|status| status.state = FfmpegJobState::Finished);
+  }
+}
@@ -0,0 +1,281 @@
+//! Containment of Ffmpeg and any helper processes it launches.
+//!
+//! On Unix the child is spawned as the leader of a new process group, on
+//! Windows it is assigned to a Job Object. Either way, the whole tree can
+//! then be terminated at once with
+//! [`FfmpegChild::terminate_group`](crate::child::FfmpegChild::terminate_group).
+//!
+//! On Windows the Job Object is configured with
+//! `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`, so the processes are also killed
+//! when the parent process exits or crashes. Unix has no portable
+//! equivalent; there the group is only terminated on request (or on drop,
+//! with [`kill_on_drop`](crate::command::FfmpegCommand::kill_on_drop)).
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::process::{Child, Command};
+
+/// Prepare `command` so that the spawned process can be contained.
+pub(crate) fn configure(command: &mut Command) {
+  #[cfg(unix)]
+  command.process_group(0);
+
+  // Job Objects are assigned after spawning.
+  #[cfg(not(unix))]
+  let _ = command;
+}
+
+/// A handle to the process group (Unix) or Job Object (Windows) containing
+/// a spawned child.
+#[derive(Debug)]
+pub(crate) struct ProcessGroup {
+  #[cfg(unix)]
+  pgid: libc::pid_t,
+  /// The Job Object handle, stored as an integer to keep the type `Send`.
+  #[cfg(windows)]
+  job: isize,
+}
+
+impl ProcessGroup {
+  /// Take ownership of the group of a child spawned from a command prepared
+  /// with [`configure`].
+  #[cfg(unix)]
+  pub(crate) fn attach(child: &Child) -> io::Result<Self> {
+    let pid = child
+      .id()
+      .ok_or_else(|| io::Error::other("process already exited"))?;
+
+    // `process_group(0)` makes the child the leader of a group with its PID.
+    Ok(Self {
+      pgid: pid as libc::pid_t,
+    })
+  }
+
+  #[cfg(windows)]
+  pub(crate) fn attach(child: &Child) -> io::Result<Self> {
+    use std::mem::{size_of, zeroed};
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::JobObjects::{
+      AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+      SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+      JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    let process = child
+      .raw_handle()
+      .ok_or_else(|| io::Error::other("process already exited"))?;
+
+    unsafe {
+      let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+      if job.is_null() {
+        return Err(io::Error::last_os_error());
+      }
+
+      let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = zeroed();
+      info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+      let configured = SetInformationJobObject(
+        job,
+        JobObjectExtendedLimitInformation,
+        &info as *const _ as *const _,
+        size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+      );
+
+      if configured == 0 || AssignProcessToJobObject(job, process as _) == 0 {
+        let error = io::Error::last_os_error();
+        CloseHandle(job);
+        return Err(error);
+      }
+
+      Ok(Self { job: job as isize })
+    }
+  }
+
+  #[cfg(not(any(unix, windows)))]
+  pub(crate) fn attach(_child: &Child) -> io::Result<Self> {
+    Err(io::Error::new(
+      io::ErrorKind::Unsupported,
+      "process groups are not supported on this platform",
+    ))
+  }
+
+  /// Forcibly terminate every process in the group.
+  #[cfg(unix)]
+  pub(crate) fn terminate(&self) -> io::Result<()> {
+    // SAFETY: killpg has no memory safety preconditions.
+    if unsafe { libc::killpg(self.pgid, libc::SIGKILL) } == 0 {
+      return Ok(());
+    }
+
+    match io::Error::last_os_error() {
+      // The group is already empty
+      error if error.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+      error => Err(error),
+    }
+  }
+
+  #[cfg(windows)]
+  pub(crate) fn terminate(&self) -> io::Result<()> {
+    use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+
+    // SAFETY: the handle stays valid until `self` is dropped.
+    if unsafe { TerminateJobObject(self.job as _, 1) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(())
+  }
+
+  #[cfg(not(any(unix, windows)))]
+  pub(crate) fn terminate(&self) -> io::Result<()> {
+    Ok(())
+  }
+
+  /// Give up the handle without terminating the group. On Windows the Job
+  /// Object stays open until the parent process exits.
+  pub(crate) fn detach(self) {
+    #[cfg(windows)]
+    std::mem::forget(self);
+  }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessGroup {
+  fn drop(&mut self) {
+    // Closing the last handle kills the processes, see
+    // `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`.
+    unsafe { windows_sys::Win32::Foundation::CloseHandle(self.job as _) };
+  }
+}
+
+/// A handle that kills a child without owning it, shared between an
+/// [`FfmpegChild`](crate::child::FfmpegChild) and its event stream so that
+/// the stream can enforce the job timeout.
+///
+/// The child [`release`](Self::release)s the handle once it has reaped the
+/// process. Since the process may also be reaped through
+/// [`FfmpegChild::as_inner_mut`](crate::child::FfmpegChild::as_inner_mut),
+/// the handle never kills a process ID that may have been reused: on Unix it
+/// checks that the ID still belongs to an unreaped child first, and on
+/// Windows it holds a handle to the process, which keeps the ID reserved.
+#[derive(Debug, Clone)]
+pub(crate) struct KillHandle {
+  process: Arc<Mutex<Option<Process>>>,
+}
+
+impl KillHandle {
+  pub(crate) fn new(child: &Child) -> Self {
+    Self {
+      process: Arc::new(Mutex::new(child.id().and_then(Process::open))),
+    }
+  }
+
+  /// Forcibly terminate the process, unless it was already reaped.
+  pub(crate) fn kill(&self) -> io::Result<()> {
+    let process = self.process.lock().unwrap_or_else(|e| e.into_inner());
+    match &*process {
+      Some(process) => process.kill(),
+      None => Ok(()),
+    }
+  }
+
+  /// Stop killing the process, once it has been reaped or handed off.
+  pub(crate) fn release(&self) {
+    *self.process.lock().unwrap_or_else(|e| e.into_inner()) = None;
+  }
+}
+
+#[cfg(not(windows))]
+#[derive(Debug)]
+struct Process {
+  pid: u32,
+}
+
+#[cfg(windows)]
+#[derive(Debug)]
+struct Process {
+  /// The process handle, stored as an integer to keep the type `Send`.
+  handle: isize,
+}
+
+#[cfg(unix)]
+impl Process {
+  fn open(pid: u32) -> Option<Self> {
+    Some(Self { pid })
+  }
+
+  fn kill(&self) -> io::Result<()> {
+    // SAFETY: `info` is a valid out pointer, and `WNOWAIT` leaves the
+    // process to be reaped by its `Child`.
+    let waited = unsafe {
+      let mut info: libc::siginfo_t = std::mem::zeroed();
+      libc::waitid(
+        libc::P_PID,
+        self.pid as libc::id_t,
+        &mut info,
+        libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+      )
+    };
+    if waited != 0 {
+      return match io::Error::last_os_error() {
+        // The process was reaped, so its ID may belong to another process
+        error if error.raw_os_error() == Some(libc::ECHILD) => Ok(()),
+        error => Err(error),
+      };
+    }
+
+    // SAFETY: kill has no memory safety preconditions.
+    if unsafe { libc::kill(self.pid as libc::pid_t, libc::SIGKILL) } == 0 {
+      return Ok(());
+    }
+
+    match io::Error::last_os_error() {
+      // The process already exited
+      error if error.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+      error => Err(error),
+    }
+  }
+}
+
+#[cfg(windows)]
+impl Process {
+  fn open(pid: u32) -> Option<Self> {
+    use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_TERMINATE};
+
+    // SAFETY: the handle is closed when `Self` is dropped.
+    let handle = unsafe { OpenProcess(PROCESS_TERMINATE, 0, pid) };
+    (!handle.is_null()).then_some(Self {
+      handle: handle as isize,
+    })
+  }
+
+  fn kill(&self) -> io::Result<()> {
+    use windows_sys::Win32::System::Threading::TerminateProcess;
+
+    // SAFETY: the handle stays valid until `self` is dropped.
+    if unsafe { TerminateProcess(self.handle as _, 1) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(())
+  }
+}
+
+#[cfg(windows)]
+impl Drop for Process {
+  fn drop(&mut self) {
+    unsafe { windows_sys::Win32::Foundation::CloseHandle(self.handle as _) };
+  }
+}
+
+#[cfg(not(any(unix, windows)))]
+impl Process {
+  fn open(pid: u32) -> Option<Self> {
+    Some(Self { pid })
+  }
+
+  fn kill(&self) -> io::Result<()> {
+    let _ = self.pid;
+    Err(io::Error::new(
+      io::ErrorKind::Unsupported,
+      "killing by process ID is not supported on this platform",
+    ))
+  }
+}
@@ -0,0 +1,182 @@
+//! Completion estimates for progress bars.
+//!
+//! [`EnrichedProgress`] adds the percentage and the estimated time left to a
+//! progress update, from the duration of the input.
+//!
+//! Frame-based progress (`frame=`, `time=`) is sparse or missing when streams
+//! are copied without re-encoding. Since a remux writes roughly as many bytes
+//! as it reads, comparing the output size against the input size with
+//! [`ByteProgress`] gives a usable completion estimate instead.
+
+use crate::command::BackgroundCommand;
+use crate::event::{FfmpegEvent, FfmpegProgress};
+use crate::ffprobe::ffprobe_path;
+use anyhow::Context;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// A progress update along with its completion estimate, see
+/// [`FfmpegEventStream::filter_progress_with_eta`](crate::stream::FfmpegEventStream::filter_progress_with_eta).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnrichedProgress {
+  /// Percent of the input duration processed, clamped to `0.0..=100.0`.
+  /// `None` while the duration or the position is unknown, e.g. for live
+  /// inputs.
+  pub percent: Option<f64>,
+  /// Wall clock time left at the current speed. `None` when the percentage
+  /// is, or while Ffmpeg reports no speed.
+  pub eta: Option<Duration>,
+  /// The progress update
+  pub raw: FfmpegProgress,
+}
+
+impl EnrichedProgress {
+  /// Estimate the completion of `raw` for an input of `duration`.
+  ///
+  /// The estimate assumes the whole input is processed, so it is off for
+  /// jobs trimmed with `-t` or `-ss`.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::log_parser::try_parse_progress;
+  /// use async_ffmpeg_sidecar::progress::EnrichedProgress;
+  /// use std::time::Duration;
+  ///
+  /// let raw = try_parse_progress(
+  ///   "frame=  250 fps=50 q=28.0 size=     512KiB time=00:00:10.00 bitrate= 419.4kbits/s speed=2.00x",
+  /// )
+  /// .unwrap();
+  /// let progress = EnrichedProgress::new(raw, Some(Duration::from_secs(40)));
+  /// assert_eq!(progress.percent, Some(25.0));
+  /// assert_eq!(progress.eta, Some(Duration::from_secs(15)));
+  /// ```
+  pub fn new(raw: FfmpegProgress, duration: Option<Duration>) -> Self {
+    let (percent, eta) = match (duration, raw.time) {
+      (Some(duration), Some(time)) if !duration.is_zero() => {
+        let percent = (time.as_secs_f64() / duration.as_secs_f64() * 100.0).min(100.0);
+        let eta =
+          (raw.speed > 0.0).then(|| duration.saturating_sub(time).div_f64(raw.speed as f64));
+        (Some(percent), eta)
+      }
+      _ => (None, None),
+    };
+
+    Self { percent, eta, raw }
+  }
+}
+
+/// Estimates completion of a copy/remux from the number of bytes written.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::progress::ByteProgress;
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let tracker = ByteProgress::probe("input.mkv").await?;
+/// let mut child = FfmpegCommand::new()
+///   .input("input.mkv")
+///   .codec_video("copy")
+///   .codec_audio("copy")
+///   .progress_feed()
+///   .output("output.mp4")
+///   .spawn()?;
+///
+/// let mut events = child.stream()?;
+/// while let Some(event) = events.next().await {
+///   if let Some(percent) = tracker.percent(&event) {
+///     println!("{percent:.1}%");
+///   }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteProgress {
+  total_bytes: u64,
+}
+
+impl ByteProgress {
+  /// Track progress towards `total_bytes`, usually the size of the input.
+  pub fn new(total_bytes: u64) -> Self {
+    Self { total_bytes }
+  }
+
+  /// Use the container size reported by `ffprobe` as the total. Works for
+  /// local files as well as URLs that report a size.
+  pub async fn probe<S: AsRef<str>>(input: S) -> anyhow::Result<Self> {
+    let output = Command::new(ffprobe_path())
+      .create_no_window()
+      .args(["-v", "error", "-show_entries", "format=size"])
+      .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+      .arg(input.as_ref())
+      .stdin(Stdio::null())
+      .output()
+      .await
+      .context("failed to run ffprobe")?;
+
+    if !output.status.success() {
+      anyhow::bail!(
+        "ffprobe failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+      );
+    }
+
+    let total_bytes = parse_format_size(&String::from_utf8_lossy(&output.stdout))
+      .with_context(|| format!("ffprobe reported no size for {}", input.as_ref()))?;
+
+    Ok(Self::new(total_bytes))
+  }
+
+  /// The expected number of bytes.
+  pub fn total_bytes(&self) -> u64 {
+    self.total_bytes
+  }
+
+  /// Fraction of `processed_bytes` in the total, clamped to `0.0..=1.0`.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::progress::ByteProgress;
+  ///
+  /// let progress = ByteProgress::new(1000);
+  /// assert_eq!(progress.fraction(250), 0.25);
+  /// assert_eq!(progress.fraction(1200), 1.0);
+  /// assert_eq!(ByteProgress::new(0).fraction(10), 0.0);
+  /// ```
+  pub fn fraction(&self, processed_bytes: u64) -> f64 {
+    if self.total_bytes == 0 {
+      return 0.0;
+    }
+
+    (processed_bytes as f64 / self.total_bytes as f64).min(1.0)
+  }
+
+  /// Percent complete for events that report the output size: the exact
+  /// `total_size` of a [`FfmpegEvent::ProgressFeed`], or the rounded
+  /// `size_kb` of a regular [`FfmpegEvent::Progress`] line. The final
+  /// `progress=end` update always reports 100%.
+  pub fn percent(&self, event: &FfmpegEvent) -> Option<f64> {
+    match event {
+      FfmpegEvent::ProgressFeed(feed) if feed.is_end() => Some(100.0),
+      FfmpegEvent::ProgressFeed(feed) => Some(self.fraction(feed.total_size()?) * 100.0),
+      FfmpegEvent::Progress(progress) => Some(self.fraction(progress.size_kb * 1024) * 100.0),
+      _ => None,
+    }
+  }
+}
+
+/// Parse the output of `ffprobe -show_entries format=size` in the
+/// `default=noprint_wrappers=1:nokey=1` format.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::progress::parse_format_size;
+///
+/// assert_eq!(parse_format_size("73400320\n"), Some(73400320));
+/// assert_eq!(parse_format_size("N/A\n"), None);
+/// ```
+pub fn parse_format_size(output: &str) -> Option<u64> {
+  output.lines().next()?.trim().parse().ok()
+}
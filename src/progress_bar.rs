@@ -0,0 +1,134 @@
+//! Progress bars for command line tools, with [`indicatif`].
+//!
+//! [`FfmpegProgressBar`] drives an [`indicatif::ProgressBar`] from the events
+//! of a job: the duration of the first input is the length of the bar, and
+//! the time of each progress update its position, in milliseconds. The speed
+//! of the encode is shown as the message.
+//!
+//! Requires the `indicatif` feature.
+
+use crate::event::{FfmpegEvent, LogLevel};
+use futures_util::{Stream, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// The template of [`FfmpegProgressBar::default_style`].
+pub const DEFAULT_TEMPLATE: &str =
+  "{spinner} [{elapsed_precise}] {wide_bar} {percent:>3}% ETA {eta} {msg}";
+
+/// Updates a progress bar from the events of a job.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::progress_bar::FfmpegProgressBar;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut child = FfmpegCommand::new()
+///   .input("input.mov")
+///   .codec_video("libx264")
+///   .output("output.mp4")
+///   .spawn()?;
+///
+/// FfmpegProgressBar::new().drive(child.stream()?).await;
+/// child.wait().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FfmpegProgressBar {
+  bar: ProgressBar,
+}
+
+impl Default for FfmpegProgressBar {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl FfmpegProgressBar {
+  /// A new progress bar with the [default style](Self::default_style).
+  pub fn new() -> Self {
+    Self::with_bar(ProgressBar::no_length().with_style(Self::default_style()))
+  }
+
+  /// Drive an existing progress bar, e.g. one of a `MultiProgress` or with
+  /// another style. Its length and position are overwritten.
+  pub fn with_bar(bar: ProgressBar) -> Self {
+    Self { bar }
+  }
+
+  /// The percentage, estimated time left and speed of the encode, see
+  /// [`DEFAULT_TEMPLATE`].
+  pub fn default_style() -> ProgressStyle {
+    ProgressStyle::with_template(DEFAULT_TEMPLATE).expect("valid progress bar template")
+  }
+
+  /// The driven progress bar.
+  pub fn bar(&self) -> &ProgressBar {
+    &self.bar
+  }
+
+  /// Update the progress bar from a single event of the job.
+  pub fn observe(&self, event: &FfmpegEvent) {
+    match event {
+      FfmpegEvent::ParsedDuration(duration) if duration.input_index == 0 => {
+        if let Some(duration) = duration.duration {
+          self.bar.set_length((duration * 1000.0) as u64);
+        }
+      }
+      FfmpegEvent::Progress(progress) => {
+        if let Some(time) = progress.time {
+          self.bar.set_position(time.as_millis() as u64);
+        }
+        self.bar.set_message(format!("{}x", progress.speed));
+      }
+      _ => {}
+    }
+  }
+
+  /// Update the progress bar from every event of `events`, and finish it
+  /// once they end. If the job failed, e.g. it timed out or Ffmpeg reported
+  /// a fatal error, the bar is abandoned where it stopped instead.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::progress_bar::FfmpegProgressBar;
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use indicatif::ProgressBar;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] Input #0, lavfi, from 'testsrc':\n\
+  ///            [info]   Duration: 00:00:10.00, start: 0.000000, bitrate: N/A\n\
+  ///            [info] frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\n\
+  ///            [error] Conversion failed!\n";
+  /// let progress = FfmpegProgressBar::with_bar(ProgressBar::hidden());
+  /// progress.drive(FfmpegEventStream::from_reader(log.as_bytes())).await;
+  ///
+  /// assert!(progress.bar().is_finished());
+  /// assert_eq!(progress.bar().position(), 1920);
+  /// # }
+  /// ```
+  pub async fn drive<S: Stream<Item = FfmpegEvent> + Unpin>(&self, mut events: S) {
+    let mut failed = false;
+    while let Some(event) = events.next().await {
+      failed |= is_failure(&event);
+      self.observe(&event);
+    }
+    if failed {
+      self.bar.abandon();
+    } else {
+      self.bar.finish();
+    }
+  }
+}
+
+/// Whether `event` means that the job failed.
+fn is_failure(event: &FfmpegEvent) -> bool {
+  match event {
+    FfmpegEvent::Error { .. } | FfmpegEvent::Stalled(_) | FfmpegEvent::TimedOut(_) => true,
+    FfmpegEvent::Log(LogLevel::Fatal, _) => true,
+    FfmpegEvent::Log(LogLevel::Error, message) => message.contains("Conversion failed!"),
+    _ => false,
+  }
+}
@@ -0,0 +1,235 @@
+//! Objective quality metrics comparing an encode to its source.
+//!
+//! [`compare`] runs the `psnr`, `ssim` and `libvmaf` filters over a
+//! distorted file and its reference in a single pass, and returns their
+//! summaries as a [`QualityReport`]. VMAF requires an Ffmpeg build with
+//! `--enable-libvmaf`.
+
+use crate::command::{filter_value, FfmpegCommand};
+use crate::event::FfmpegEvent;
+use crate::log_parser::{try_parse_psnr_summary, try_parse_ssim_summary};
+use crate::stream::spawn_parsed;
+use crate::video::temp_path;
+use futures_util::StreamExt;
+use std::path::PathBuf;
+
+/// A metric computed by [`compare`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityMetric {
+  /// Peak signal-to-noise ratio, in dB
+  Psnr,
+  /// Structural similarity, from 0 to 1
+  Ssim,
+  /// Netflix's Video Multi-Method Assessment Fusion, from 0 to 100
+  Vmaf,
+}
+
+/// The `PSNR ...` summary printed by the `psnr` filter. Values are in dB and
+/// infinite for identical frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PsnrSummary {
+  /// The PSNR of each plane, e.g. `("y", 34.57)`
+  pub components: Vec<(String, f64)>,
+  pub average: f64,
+  pub min: f64,
+  pub max: f64,
+}
+
+/// The `SSIM ...` summary printed by the `ssim` filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SsimSummary {
+  /// The SSIM of each plane, e.g. `("Y", 0.958)`
+  pub components: Vec<(String, f64)>,
+  /// The SSIM over all planes
+  pub all: f64,
+}
+
+/// The pooled VMAF scores from the JSON log of the `libvmaf` filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmafSummary {
+  pub mean: f64,
+  pub min: f64,
+  pub max: f64,
+  pub harmonic_mean: f64,
+}
+
+/// The results of [`compare`]. Only the requested metrics are present.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QualityReport {
+  pub psnr: Option<PsnrSummary>,
+  pub ssim: Option<SsimSummary>,
+  pub vmaf: Option<VmafSummary>,
+}
+
+/// The results parsed from the log of a comparison.
+enum QualitySummary {
+  Psnr(PsnrSummary),
+  Ssim(SsimSummary),
+}
+
+/// Compare `distorted` to `reference` with each of `metrics`, decoding both
+/// only once.
+///
+/// Both files must have the same resolution and frame rate; timestamps are
+/// reset so that their first frames are compared. Fails if Ffmpeg fails or
+/// a requested metric was not reported.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::quality::{compare, QualityMetric};
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let report = compare(
+///   "source.mp4",
+///   "encoded.mp4",
+///   &[QualityMetric::Psnr, QualityMetric::Vmaf],
+/// )
+/// .await?;
+/// println!("VMAF {}", report.vmaf.unwrap().mean);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn compare<S: AsRef<str>, T: AsRef<str>>(
+  reference: S,
+  distorted: T,
+  metrics: &[QualityMetric],
+) -> anyhow::Result<QualityReport> {
+  if metrics.is_empty() {
+    anyhow::bail!("no quality metric to compute");
+  }
+
+  let vmaf_log = metrics
+    .contains(&QualityMetric::Vmaf)
+    .then(|| temp_path("vmaf", "json"));
+  let filtergraph = comparison_filtergraph(metrics, vmaf_log.as_ref());
+
+  let mut command = FfmpegCommand::new();
+  command
+    .input(distorted)
+    .input(reference)
+    .filter_complex(filtergraph)
+    .no_audio()
+    .format("null")
+    .output("-");
+
+  let mut summaries = spawn_parsed(command, |event| {
+    let FfmpegEvent::Log(_, line) = event else {
+      return None;
+    };
+    try_parse_psnr_summary(&line)
+      .map(QualitySummary::Psnr)
+      .or_else(|| try_parse_ssim_summary(&line).map(QualitySummary::Ssim))
+  })?;
+
+  let mut report = QualityReport::default();
+  let mut result = Ok(());
+  while let Some(summary) = summaries.next().await {
+    match summary {
+      Ok(QualitySummary::Psnr(psnr)) => report.psnr = Some(psnr),
+      Ok(QualitySummary::Ssim(ssim)) => report.ssim = Some(ssim),
+      Err(e) => result = Err(e),
+    }
+  }
+
+  if let Some(path) = vmaf_log {
+    let log = tokio::fs::read_to_string(&path).await;
+    let _ = tokio::fs::remove_file(&path).await;
+    if result.is_ok() {
+      report.vmaf = parse_vmaf_log(&log?);
+    }
+  }
+  result?;
+
+  for metric in metrics {
+    let reported = match metric {
+      QualityMetric::Psnr => report.psnr.is_some(),
+      QualityMetric::Ssim => report.ssim.is_some(),
+      QualityMetric::Vmaf => report.vmaf.is_some(),
+    };
+    if !reported {
+      anyhow::bail!("{metric:?} was not reported");
+    }
+  }
+
+  Ok(report)
+}
+
+/// The filtergraph comparing input 0 (distorted) to input 1 (reference),
+/// splitting both when several metrics are requested.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::quality::{comparison_filtergraph, QualityMetric};
+///
+/// assert_eq!(
+///   comparison_filtergraph(&[QualityMetric::Psnr, QualityMetric::Ssim], None),
+///   "[0:v]setpts=PTS-STARTPTS,split=2[d0][d1];\
+///    [1:v]setpts=PTS-STARTPTS,split=2[r0][r1];\
+///    [d0][r0]psnr;[d1][r1]ssim"
+/// );
+/// ```
+pub fn comparison_filtergraph(metrics: &[QualityMetric], vmaf_log: Option<&PathBuf>) -> String {
+  let n = metrics.len();
+  let labels = |prefix: &str| (0..n).map(|i| format!("[{prefix}{i}]")).collect::<String>();
+  let mut filtergraph = format!(
+    "[0:v]setpts=PTS-STARTPTS,split={n}{};[1:v]setpts=PTS-STARTPTS,split={n}{}",
+    labels("d"),
+    labels("r")
+  );
+
+  for (i, metric) in metrics.iter().enumerate() {
+    let filter = match (metric, vmaf_log) {
+      (QualityMetric::Psnr, _) => "psnr".to_string(),
+      (QualityMetric::Ssim, _) => "ssim".to_string(),
+      (QualityMetric::Vmaf, Some(path)) => {
+        let path = path.to_string_lossy().replace('\\', "/");
+        format!("libvmaf=log_fmt=json:log_path={}", filter_value(&path))
+      }
+      (QualityMetric::Vmaf, None) => "libvmaf".to_string(),
+    };
+    filtergraph.push_str(&format!(";[d{i}][r{i}]{filter}"));
+  }
+
+  filtergraph
+}
+
+/// Extract the pooled scores from the JSON log written by `libvmaf` with
+/// `log_fmt=json`.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::quality::parse_vmaf_log;
+///
+/// let log = r#"{
+///   "version": "2.3.1",
+///   "frames": [{"frameNum": 0, "metrics": {"vmaf": 97.4}}],
+///   "pooled_metrics": {
+///     "integer_adm2": {"min": 0.98, "max": 1.0, "mean": 0.99, "harmonic_mean": 0.99},
+///     "vmaf": {"min": 91.2, "max": 99.8, "mean": 96.3, "harmonic_mean": 96.2}
+///   }
+/// }"#;
+///
+/// let vmaf = parse_vmaf_log(log).unwrap();
+/// assert_eq!(vmaf.mean, 96.3);
+/// assert_eq!(vmaf.min, 91.2);
+/// ```
+pub fn parse_vmaf_log(log: &str) -> Option<VmafSummary> {
+  let (_, pooled) = log.split_once("\"pooled_metrics\"")?;
+  let (_, vmaf) = pooled.split_once("\"vmaf\"")?;
+  let (vmaf, _) = vmaf.split_once('}')?;
+
+  let field = |name: &str| -> Option<f64> {
+    let (_, rest) = vmaf.split_once(&format!("\"{name}\""))?;
+    let value = rest.trim_start().strip_prefix(':')?.trim_start();
+    let end = value
+      .find(|c: char| c == ',' || c.is_whitespace())
+      .unwrap_or(value.len());
+    value[..end].parse().ok()
+  };
+
+  Some(VmafSummary {
+    mean: field("mean")?,
+    min: field("min")?,
+    max: field("max")?,
+    harmonic_mean: field("harmonic_mean")?,
+  })
+}
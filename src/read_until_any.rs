@@ -1 +1,58 @@
+//! An internal utility used to split Ffmpeg logs into lines.
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 
+/// Read bytes into `buf` until any of the `delimiters` or EOF is reached,
+/// like [`AsyncBufReadExt::read_until`] with several delimiters. Ffmpeg ends
+/// progress lines with a bare `\r` to overwrite them in a terminal, which
+/// `lines()` would merge with the next `\n`-terminated line.
+///
+/// The delimiter is included in `buf`. Returns the number of bytes read,
+/// which is 0 at EOF.
+///
+/// Cancel safe: the bytes read before the future is dropped are kept in
+/// `buf`, so calling it again with the same buffer resumes the line.
+///
+/// ## Examples
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::read_until_any::read_until_any;
+///
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let mut reader = "frame=1\rframe=2\r\nDone\n".as_bytes();
+/// let mut buf = Vec::new();
+///
+/// read_until_any(&mut reader, b"\r\n", &mut buf).await?;
+/// assert_eq!(buf, b"frame=1\r");
+///
+/// buf.clear();
+/// read_until_any(&mut reader, b"\r\n", &mut buf).await?;
+/// assert_eq!(buf, b"frame=2\r");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn read_until_any<R: AsyncBufRead + Unpin + ?Sized>(
+  reader: &mut R,
+  delimiters: &[u8],
+  buf: &mut Vec<u8>,
+) -> std::io::Result<usize> {
+  let mut read = 0;
+  loop {
+    let available = reader.fill_buf().await?;
+    if available.is_empty() {
+      return Ok(read);
+    }
+
+    let (found, used) = match available.iter().position(|byte| delimiters.contains(byte)) {
+      Some(end) => (true, end + 1),
+      None => (false, available.len()),
+    };
+    buf.extend_from_slice(&available[..used]);
+    reader.consume(used);
+    read += used;
+
+    if found {
+      return Ok(read);
+    }
+  }
+}
@@ -0,0 +1,237 @@
+//! Recording an input to disk, split into several files.
+//!
+//! A [`Recorder`] runs one Ffmpeg process per file. Each file ends when it
+//! reaches the configured length or size, when [`Recorder::split_now`] is
+//! called, or when the recording is stopped, and the next file starts right
+//! away. Every file is finalized by a graceful quit, so it stays playable
+//! even when the recording is interrupted.
+//!
+//! Since a new process has to open the input for every file, a short gap
+//! (typically well below a second) occurs between consecutive files. For
+//! gapless time-based splitting without on-demand splits, use the
+//! [`segment`](crate::segment) muxer instead.
+
+use crate::command::FfmpegCommand;
+use anyhow::Context;
+use futures_util::StreamExt;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// A change in the files of a [`Recorder`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecorderEvent {
+  /// Ffmpeg started writing the file at the given path.
+  FileStarted(PathBuf),
+  /// The file at the given path was finalized, or its process failed.
+  FileFinished {
+    path: PathBuf,
+    result: Result<ExitStatus, String>,
+  },
+}
+
+enum Control {
+  Split,
+  Stop,
+}
+
+/// Configures a [`Recorder`], see [`Recorder::builder`].
+pub struct RecorderBuilder<B, P> {
+  build: B,
+  path: P,
+  rotate_every: Option<Duration>,
+  max_file_size: Option<u64>,
+  stop_timeout: Duration,
+}
+
+impl<B, P> RecorderBuilder<B, P>
+where
+  B: FnMut(&mut FfmpegCommand) + Send + 'static,
+  P: FnMut(u32) -> PathBuf + Send + 'static,
+{
+  /// Start a new file after `duration` of recorded media (`-t`).
+  pub fn rotate_every(mut self, duration: Duration) -> Self {
+    self.rotate_every = Some(duration);
+    self
+  }
+
+  /// Start a new file once the current one exceeds `bytes` (`-fs`).
+  pub fn max_file_size(mut self, bytes: u64) -> Self {
+    self.max_file_size = Some(bytes);
+    self
+  }
+
+  /// How long to wait for Ffmpeg to finalize a file after asking it to quit,
+  /// before killing it (default: 5 seconds).
+  pub fn stop_timeout(mut self, timeout: Duration) -> Self {
+    self.stop_timeout = timeout;
+    self
+  }
+
+  /// Start recording the first file. Must be called from within a tokio
+  /// runtime.
+  pub fn spawn(self) -> Recorder {
+    let (control_tx, control_rx) = mpsc::unbounded_channel();
+    let (events_tx, events) = mpsc::unbounded_channel();
+    let task = tokio::spawn(self.record(control_rx, events_tx));
+
+    Recorder {
+      control: control_tx,
+      events,
+      task: Some(task),
+    }
+  }
+
+  async fn record(
+    mut self,
+    mut control: mpsc::UnboundedReceiver<Control>,
+    events: mpsc::UnboundedSender<RecorderEvent>,
+  ) -> anyhow::Result<()> {
+    for index in 0.. {
+      let path = (self.path)(index);
+      if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+      {
+        tokio::fs::create_dir_all(parent)
+          .await
+          .with_context(|| format!("failed to create {}", parent.display()))?;
+      }
+
+      let mut command = FfmpegCommand::new();
+      (self.build)(&mut command);
+      if let Some(duration) = self.rotate_every {
+        command.duration(duration);
+      }
+      if let Some(bytes) = self.max_file_size {
+        command.args(["-fs".to_string(), bytes.to_string()]);
+      }
+      command
+        .overwrite()
+        .output(path.to_string_lossy())
+        .kill_on_drop(true);
+
+      let mut child = command.spawn()?;
+      let mut stream = child.stream()?;
+      let _ = events.send(RecorderEvent::FileStarted(path.clone()));
+
+      // `None` if the file ended on its own
+      let requested = tokio::select! {
+        _ = stream.by_ref().for_each(|_| futures::future::ready(())) => None,
+        control = control.recv() => Some(control.unwrap_or(Control::Stop)),
+      };
+
+      let result = match requested {
+        None => child.wait().await.map_err(|e| e.to_string()),
+        Some(_) => {
+          let (outcome, _) = tokio::join!(
+            child.quit_with_timeout(self.stop_timeout),
+            stream.for_each(|_| futures::future::ready(()))
+          );
+          outcome
+            .map(|outcome| outcome.exit_status())
+            .map_err(|e| e.to_string())
+        }
+      };
+
+      let error = match &result {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(format!("Ffmpeg exited with {status}")),
+        Err(e) => Some(e.clone()),
+      }
+      .map(|e| anyhow::anyhow!("failed to record {}: {e}", path.display()));
+      let _ = events.send(RecorderEvent::FileFinished { path, result });
+
+      // A process that failed on its own would most likely fail again
+      if (requested.is_none() && error.is_some()) || matches!(requested, Some(Control::Stop)) {
+        return error.map_or(Ok(()), Err);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A running recording, see the [module documentation](self).
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::recorder::{Recorder, RecorderEvent};
+/// use std::path::PathBuf;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut recorder = Recorder::builder(
+///   |command| {
+///     command.input("rtsp://camera.local/stream").codec_video("copy");
+///   },
+///   |index| PathBuf::from(format!("recordings/cam-{index:04}.mkv")),
+/// )
+/// .rotate_every(Duration::from_secs(15 * 60))
+/// .spawn();
+///
+/// // e.g. when motion is detected
+/// recorder.split_now();
+///
+/// // e.g. on shutdown
+/// recorder.stop().await?;
+/// while let Some(event) = recorder.next_event().await {
+///   if let RecorderEvent::FileFinished { path, .. } = event {
+///     println!("finished {}", path.display());
+///   }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Recorder {
+  control: mpsc::UnboundedSender<Control>,
+  events: mpsc::UnboundedReceiver<RecorderEvent>,
+  task: Option<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl Recorder {
+  /// Configure a recording. `build` adds the input and output options
+  /// (codecs, filters) to the command of each file; `path` returns the path
+  /// of the file with the given index, starting at 0. Existing files are
+  /// overwritten.
+  pub fn builder<B, P>(build: B, path: P) -> RecorderBuilder<B, P>
+  where
+    B: FnMut(&mut FfmpegCommand) + Send + 'static,
+    P: FnMut(u32) -> PathBuf + Send + 'static,
+  {
+    RecorderBuilder {
+      build,
+      path,
+      rotate_every: None,
+      max_file_size: None,
+      stop_timeout: Duration::from_secs(5),
+    }
+  }
+
+  /// Finalize the current file and continue in a new one.
+  pub fn split_now(&self) {
+    let _ = self.control.send(Control::Split);
+  }
+
+  /// Finalize the current file and stop recording. Resolves once Ffmpeg has
+  /// exited; the remaining events can still be read afterwards.
+  ///
+  /// Returns an error if the last file could not be recorded, which also
+  /// ends the recording when its process fails on its own.
+  pub async fn stop(&mut self) -> anyhow::Result<()> {
+    let _ = self.control.send(Control::Stop);
+    match self.task.take() {
+      Some(task) => task.await?,
+      None => Ok(()),
+    }
+  }
+
+  /// The next change in the recorded files, or `None` once the recording
+  /// has stopped and all events were read.
+  pub async fn next_event(&mut self) -> Option<RecorderEvent> {
+    self.events.recv().await
+  }
+}
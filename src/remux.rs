@@ -0,0 +1,72 @@
+//! Changing the container of a file without re-encoding.
+
+use crate::command::FfmpegCommand;
+use crate::event::FfmpegEvent;
+use crate::outcome::{FfmpegJobSummary, FfmpegOutcomeTracker};
+use futures_util::StreamExt;
+
+/// The command used by [`remux`]: every stream of `input` (`-map 0`) is
+/// copied (`-c copy`) into `output`, whose container is guessed from its
+/// extension. Existing files are overwritten.
+pub fn remux_command<S: AsRef<str>, T: AsRef<str>>(input: S, output: T) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .map(0, "")
+    .copy_all()
+    .overwrite()
+    .output(output);
+  command
+}
+
+/// Copy all streams of `input` into the container of `output`, e.g. MKV to
+/// MP4, without re-encoding.
+///
+/// If the output container cannot hold one of the codecs, the returned
+/// error is the [`UnsupportedContainerCodec`](crate::event::UnsupportedContainerCodec)
+/// reported by Ffmpeg, which can be recovered with `downcast_ref`. Other
+/// failures return the collected error messages.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::event::UnsupportedContainerCodec;
+/// use async_ffmpeg_sidecar::remux::remux;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// match remux("recording.mkv", "recording.mp4").await {
+///   Ok(_) => println!("done"),
+///   Err(e) => match e.downcast_ref::<UnsupportedContainerCodec>() {
+///     Some(unsupported) => eprintln!("needs transcoding: {unsupported}"),
+///     None => return Err(e),
+///   },
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn remux<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  output: T,
+) -> anyhow::Result<FfmpegJobSummary> {
+  let mut child = remux_command(input, output).spawn()?;
+  let mut events = child.stream()?;
+
+  let mut tracker = FfmpegOutcomeTracker::new();
+  let mut unsupported = None;
+  while let Some(event) = events.next().await {
+    tracker.observe(&event);
+    if let FfmpegEvent::UnsupportedContainerCodec(error) = event {
+      unsupported.get_or_insert(error);
+    }
+  }
+
+  let summary = tracker.into_summary(child.wait().await?, events.metadata().clone());
+  if let Some(error) = unsupported {
+    return Err(error.into());
+  }
+  if !summary.outcome.is_success() {
+    anyhow::bail!("remux failed: {}", summary.errors.join(""));
+  }
+
+  Ok(summary)
+}
@@ -0,0 +1,258 @@
+//! Recording of Ffmpeg log output and deterministic replay as an event stream.
+//!
+//! An [`EventRecorder`] captures every stderr line of a real Ffmpeg process
+//! along with the time it arrived. The resulting [`EventRecording`] can be
+//! saved as a fixture file and later replayed through the regular log parser
+//! as an [`FfmpegEventStream`], optionally faster than real time. This makes
+//! it possible to write deterministic tests around real Ffmpeg behavior
+//! without running Ffmpeg.
+//!
+//! ## File format
+//!
+//! Recordings are plain text, one line per log line, in the form
+//! `<microseconds since start><TAB><line>`, e.g.
+//! `1520<TAB>[info] Input #0, lavfi, from 'testsrc=duration=10':`.
+
+use crate::child::{FfmpegChild, MissingChannel};
+use crate::stream::FfmpegEventStream;
+use anyhow::Context;
+use futures_util::stream;
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio_util::io::StreamReader;
+
+/// A single log line and the time it was read, relative to the start of the
+/// recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedLine {
+  pub offset: Duration,
+  pub line: String,
+}
+
+/// A sequence of timed log lines, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EventRecording {
+  pub lines: Vec<RecordedLine>,
+}
+
+impl EventRecording {
+  /// Parse a recording from its textual representation.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::replay::EventRecording;
+  /// use std::time::Duration;
+  ///
+  /// let recording = EventRecording::parse("0\t[info] Stream mapping:\n2500\t[info] Press [q] to stop\n").unwrap();
+  /// assert_eq!(recording.lines.len(), 2);
+  /// assert_eq!(recording.lines[1].offset, Duration::from_micros(2500));
+  /// assert_eq!(recording.to_string(), "0\t[info] Stream mapping:\n2500\t[info] Press [q] to stop\n");
+  /// ```
+  pub fn parse(contents: &str) -> anyhow::Result<Self> {
+    let lines = contents
+      .lines()
+      .enumerate()
+      .filter(|(_, line)| !line.is_empty())
+      .map(|(number, line)| {
+        let (offset, line) = line
+          .split_once('\t')
+          .with_context(|| format!("missing tab separator on line {}", number + 1))?;
+        let offset = offset
+          .parse::<u64>()
+          .with_context(|| format!("invalid offset on line {}", number + 1))?;
+
+        Ok(RecordedLine {
+          offset: Duration::from_micros(offset),
+          line: line.to_string(),
+        })
+      })
+      .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(Self { lines })
+  }
+
+  /// Read a recording previously written with [`save`](Self::save).
+  pub async fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+    let contents = tokio::fs::read_to_string(path.as_ref())
+      .await
+      .with_context(|| format!("failed to read {}", path.as_ref().display()))?;
+    Self::parse(&contents)
+  }
+
+  /// Write the recording to `path`, overwriting any existing file.
+  pub async fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+    tokio::fs::write(path.as_ref(), self.to_string())
+      .await
+      .with_context(|| format!("failed to write {}", path.as_ref().display()))
+  }
+
+  /// Replay the recording through the log parser.
+  ///
+  /// `time_scale` stretches the recorded timing: `1.0` reproduces the original
+  /// pace, `0.5` replays twice as fast and `0.0` emits every line immediately.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::replay::EventRecording;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let recording = EventRecording::parse("0\t[info] Input #0, lavfi, from 'testsrc':\n").unwrap();
+  /// let events = recording.replay(0.0).collect::<Vec<_>>().await;
+  /// assert!(matches!(events[0], FfmpegEvent::ParsedInput(_)));
+  /// # }
+  /// ```
+  pub fn replay(self, time_scale: f64) -> FfmpegEventStream {
+    let start = tokio::time::Instant::now();
+    let chunks = stream::unfold(self.lines.into_iter(), move |mut lines| async move {
+      let line = lines.next()?;
+      tokio::time::sleep_until(start + line.offset.mul_f64(time_scale.max(0.0))).await;
+      let chunk = Cursor::new(format!("{}\n", line.line).into_bytes());
+      Some((Ok::<_, std::io::Error>(chunk), lines))
+    });
+
+    FfmpegEventStream::from_reader(StreamReader::new(chunks))
+  }
+}
+
+impl fmt::Display for EventRecording {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for line in &self.lines {
+      writeln!(f, "{}\t{}", line.offset.as_micros(), line.line)?;
+    }
+    Ok(())
+  }
+}
+
+/// Captures the log output of an Ffmpeg process while it is being parsed.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::replay::EventRecorder;
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut child = FfmpegCommand::new().testsrc().rawvideo().spawn()?;
+/// let recorder = EventRecorder::new();
+/// let events = recorder.record(&mut child)?.collect::<Vec<_>>().await;
+/// recorder.recording().save("tests/fixtures/testsrc.log").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EventRecorder {
+  state: Arc<Mutex<RecorderState>>,
+}
+
+#[derive(Debug)]
+struct RecorderState {
+  start: Instant,
+  partial: Vec<u8>,
+  recording: EventRecording,
+}
+
+impl Default for EventRecorder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl EventRecorder {
+  /// Create a recorder. Offsets are measured from this point in time.
+  pub fn new() -> Self {
+    Self {
+      state: Arc::new(Mutex::new(RecorderState {
+        start: Instant::now(),
+        partial: Vec::new(),
+        recording: EventRecording::default(),
+      })),
+    }
+  }
+
+  /// Create an event stream for `child`, recording its stderr as it is read.
+  pub fn record(&self, child: &mut FfmpegChild) -> anyhow::Result<FfmpegEventStream> {
+    let stderr = child.take_stderr().ok_or(MissingChannel::Stderr)?;
+    Ok(FfmpegEventStream::from_reader(self.wrap(stderr)))
+  }
+
+  /// Wrap any reader of Ffmpeg log output so that every line passing through
+  /// it is recorded.
+  pub fn wrap<R: AsyncRead + Unpin>(&self, reader: R) -> RecordingReader<R> {
+    RecordingReader {
+      inner: reader,
+      recorder: self.clone(),
+    }
+  }
+
+  /// A snapshot of the lines recorded so far. An incomplete trailing line is
+  /// included once the underlying reader has reached EOF.
+  pub fn recording(&self) -> EventRecording {
+    self.state.lock().unwrap().recording.clone()
+  }
+
+  fn push_bytes(&self, bytes: &[u8]) {
+    let mut state = self.state.lock().unwrap();
+    let offset = state.start.elapsed();
+
+    for &byte in bytes {
+      match byte {
+        b'\n' | b'\r' => state.flush_line(offset),
+        _ => state.partial.push(byte),
+      }
+    }
+  }
+
+  fn finish(&self) {
+    let mut state = self.state.lock().unwrap();
+    let offset = state.start.elapsed();
+    state.flush_line(offset);
+  }
+}
+
+impl RecorderState {
+  fn flush_line(&mut self, offset: Duration) {
+    if self.partial.is_empty() {
+      return;
+    }
+
+    let line = String::from_utf8_lossy(&self.partial).into_owned();
+    self.partial.clear();
+    self.recording.lines.push(RecordedLine { offset, line });
+  }
+}
+
+/// A reader that records every line read through it, see [`EventRecorder::wrap`].
+pub struct RecordingReader<R> {
+  inner: R,
+  recorder: EventRecorder,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for RecordingReader<R> {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut std::task::Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<std::io::Result<()>> {
+    let filled_before = buf.filled().len();
+    let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+    if let Poll::Ready(Ok(())) = result {
+      let read = &buf.filled()[filled_before..];
+      if read.is_empty() {
+        self.recorder.finish();
+      } else {
+        self.recorder.push_bytes(read);
+      }
+    }
+
+    result
+  }
+}
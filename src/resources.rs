@@ -0,0 +1,75 @@
+//! CPU and memory sampling of running Ffmpeg processes.
+//!
+//! Requires the `resource_usage` feature.
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// A point-in-time measurement of a process' resource usage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+  /// CPU usage since the previous sample, in percent of a single core. May
+  /// exceed 100 for multithreaded encoders.
+  pub cpu_percent: f32,
+  /// Resident set size in bytes.
+  pub rss_bytes: u64,
+}
+
+/// Samples the resource usage of a single process, see
+/// [`FfmpegChild::resource_sampler`](crate::child::FfmpegChild::resource_sampler).
+///
+/// CPU usage is computed from the difference between two samples, so the
+/// first call to [`sample`](Self::sample) always reports `0.0`. Sample at a
+/// regular interval of at least [`sysinfo::MINIMUM_CPU_UPDATE_INTERVAL`].
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let child = FfmpegCommand::new().testsrc().rawvideo().spawn()?;
+/// let mut sampler = child.resource_sampler().unwrap();
+///
+/// let mut interval = tokio::time::interval(Duration::from_secs(1));
+/// while let Some(usage) = sampler.sample() {
+///   println!("cpu {:.0}%, rss {} MiB", usage.cpu_percent, usage.rss_bytes >> 20);
+///   interval.tick().await;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ResourceSampler {
+  system: System,
+  pid: Pid,
+}
+
+impl ResourceSampler {
+  /// Sample the process with the given PID.
+  pub fn new(pid: u32) -> Self {
+    Self {
+      system: System::new(),
+      pid: Pid::from_u32(pid),
+    }
+  }
+
+  /// The PID of the sampled process.
+  pub fn pid(&self) -> u32 {
+    self.pid.as_u32()
+  }
+
+  /// Take a sample, or `None` if the process no longer exists.
+  pub fn sample(&mut self) -> Option<ResourceUsage> {
+    self.system.refresh_processes_specifics(
+      ProcessesToUpdate::Some(&[self.pid]),
+      true,
+      ProcessRefreshKind::nothing().with_cpu().with_memory(),
+    );
+
+    let process = self.system.process(self.pid)?;
+    Some(ResourceUsage {
+      cpu_percent: process.cpu_usage(),
+      rss_bytes: process.memory(),
+    })
+  }
+}
@@ -0,0 +1,192 @@
+//! Splitting an output into files of fixed length with the `segment` muxer.
+//!
+//! Rolling recorders usually want to process each file as soon as it is
+//! complete. While the job runs, the log parser reports every file as
+//! [`FfmpegEvent::OutputSegmentOpened`] and, once the muxer has moved on to
+//! the next one, [`FfmpegEvent::OutputSegmentClosed`], along with its
+//! sequence number.
+//!
+//! [`FfmpegEvent::OutputSegmentOpened`]: crate::event::FfmpegEvent::OutputSegmentOpened
+//! [`FfmpegEvent::OutputSegmentClosed`]: crate::event::FfmpegEvent::OutputSegmentClosed
+
+use std::time::Duration;
+
+/// The format of the file listing the written segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentListType {
+  /// One file name per line.
+  Flat,
+  /// `file name,start time,end time` per line.
+  Csv,
+  /// An HLS playlist.
+  M3u8,
+  /// A script for the `concat` demuxer.
+  Ffconcat,
+}
+
+impl SegmentListType {
+  /// The name of the list type as understood by Ffmpeg.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      SegmentListType::Flat => "flat",
+      SegmentListType::Csv => "csv",
+      SegmentListType::M3u8 => "m3u8",
+      SegmentListType::Ffconcat => "ffconcat",
+    }
+  }
+}
+
+/// The options of an `-f segment` output.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::segment::SegmentOutput;
+/// use std::time::Duration;
+///
+/// let segments = SegmentOutput::new("recordings/cam-%03d.mkv")
+///   .segment_time(Duration::from_secs(600))
+///   .reset_timestamps(true);
+///
+/// let mut command = FfmpegCommand::new();
+/// command
+///   .input("rtsp://camera.local/stream")
+///   .codec_video("copy")
+///   .segment_output(&segments);
+///
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert!(args.windows(2).any(|w| w == ["-f", "segment"]));
+/// assert!(args.windows(2).any(|w| w == ["-segment_time", "600"]));
+/// assert!(args.windows(2).any(|w| w == ["-reset_timestamps", "1"]));
+/// assert_eq!(*args.last().unwrap(), "recordings/cam-%03d.mkv");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentOutput {
+  pattern: String,
+  segment_time: Option<Duration>,
+  segment_format: Option<String>,
+  list: Option<String>,
+  list_type: Option<SegmentListType>,
+  list_size: Option<u32>,
+  start_number: Option<u32>,
+  reset_timestamps: Option<bool>,
+  strftime: Option<bool>,
+}
+
+impl SegmentOutput {
+  /// Write segments named after `pattern`, which contains a `%d`-style
+  /// placeholder for the segment number, e.g. `out-%03d.mp4`, or `strftime`
+  /// fields when [`strftime`](Self::strftime) is enabled.
+  pub fn new<S: AsRef<str>>(pattern: S) -> Self {
+    Self {
+      pattern: pattern.as_ref().to_string(),
+      segment_time: None,
+      segment_format: None,
+      list: None,
+      list_type: None,
+      list_size: None,
+      start_number: None,
+      reset_timestamps: None,
+      strftime: None,
+    }
+  }
+
+  /// Target segment length (`-segment_time`, default 2 seconds). Segments
+  /// are cut on the next keyframe after this time.
+  pub fn segment_time(mut self, duration: Duration) -> Self {
+    self.segment_time = Some(duration);
+    self
+  }
+
+  /// The container of each segment (`-segment_format`), guessed from the
+  /// pattern's extension when not set.
+  pub fn segment_format<S: AsRef<str>>(mut self, format: S) -> Self {
+    self.segment_format = Some(format.as_ref().to_string());
+    self
+  }
+
+  /// Maintain a list of the written segments at `path` (`-segment_list`).
+  pub fn list<S: AsRef<str>>(mut self, path: S) -> Self {
+    self.list = Some(path.as_ref().to_string());
+    self
+  }
+
+  /// The format of the segment list (`-segment_list_type`), guessed from
+  /// its extension when not set.
+  pub fn list_type(mut self, list_type: SegmentListType) -> Self {
+    self.list_type = Some(list_type);
+    self
+  }
+
+  /// Keep at most `size` entries in the segment list (`-segment_list_size`).
+  /// `0` keeps all of them.
+  pub fn list_size(mut self, size: u32) -> Self {
+    self.list_size = Some(size);
+    self
+  }
+
+  /// The number of the first segment (`-segment_start_number`).
+  pub fn start_number(mut self, number: u32) -> Self {
+    self.start_number = Some(number);
+    self
+  }
+
+  /// Start the timestamps of every segment at zero (`-reset_timestamps`),
+  /// so that each file plays on its own.
+  pub fn reset_timestamps(mut self, reset: bool) -> Self {
+    self.reset_timestamps = Some(reset);
+    self
+  }
+
+  /// Expand `strftime` fields such as `%Y-%m-%d_%H-%M-%S` in the pattern
+  /// instead of the segment number (`-strftime`).
+  pub fn strftime(mut self, strftime: bool) -> Self {
+    self.strftime = Some(strftime);
+    self
+  }
+
+  /// The segment file name pattern.
+  pub fn pattern(&self) -> &str {
+    &self.pattern
+  }
+
+  /// The output arguments, from `-f segment` up to and including the
+  /// pattern.
+  pub fn to_args(&self) -> Vec<String> {
+    let mut args = vec!["-f".to_string(), "segment".to_string()];
+    let mut push = |key: &str, value: String| {
+      args.push(key.to_string());
+      args.push(value);
+    };
+    let flag = |value: bool| if value { "1" } else { "0" }.to_string();
+
+    if let Some(duration) = self.segment_time {
+      push("-segment_time", duration.as_secs_f64().to_string());
+    }
+    if let Some(format) = &self.segment_format {
+      push("-segment_format", format.clone());
+    }
+    if let Some(list) = &self.list {
+      push("-segment_list", list.clone());
+    }
+    if let Some(list_type) = self.list_type {
+      push("-segment_list_type", list_type.as_str().to_string());
+    }
+    if let Some(size) = self.list_size {
+      push("-segment_list_size", size.to_string());
+    }
+    if let Some(number) = self.start_number {
+      push("-segment_start_number", number.to_string());
+    }
+    if let Some(reset) = self.reset_timestamps {
+      push("-reset_timestamps", flag(reset));
+    }
+    if let Some(strftime) = self.strftime {
+      push("-strftime", flag(strftime));
+    }
+
+    args.push(self.pattern.clone());
+    args
+  }
+}
@@ -0,0 +1,182 @@
+//! Language-based stream selection, resolved to concrete `-map` arguments.
+//!
+//! Selecting "the English audio track and no subtitles" normally requires
+//! probing the input, finding the right stream indices by hand and passing
+//! them to `-map`. [`StreamSelection`] does this lookup against previously
+//! gathered [`FfmpegMetadata`].
+
+use crate::event::FfmpegStream;
+use crate::metadata::FfmpegMetadata;
+use anyhow::Context;
+
+/// A three-letter language code as reported by Ffmpeg, e.g. `eng`, `ger` or `jpn`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Language<'a>(pub &'a str);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selection {
+  /// Map every stream of this type.
+  All,
+  /// Map no streams of this type.
+  Nothing,
+  /// Map the first stream of this type with the given language.
+  Language(String),
+}
+
+impl From<Option<Language<'_>>> for Selection {
+  fn from(language: Option<Language<'_>>) -> Self {
+    match language {
+      Some(Language(code)) => Selection::Language(code.to_string()),
+      None => Selection::Nothing,
+    }
+  }
+}
+
+/// Per-type stream selection for a single input.
+///
+/// Stream types which are not explicitly selected keep all of their streams.
+/// Passing `None` drops every stream of that type. Data and attachment
+/// streams (e.g. timecode tracks or the fonts of a Matroska file) are never
+/// mapped, like Ffmpeg's default selection, since most output formats can't
+/// hold them; map them separately with
+/// [`FfmpegCommand::map`](crate::command::FfmpegCommand::map) if needed.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::log_parser::try_parse_stream;
+/// use async_ffmpeg_sidecar::metadata::FfmpegMetadata;
+/// use async_ffmpeg_sidecar::selection::{Language, StreamSelection};
+///
+/// let mut metadata = FfmpegMetadata::new();
+/// metadata.input_streams = [
+///   "[info]   Stream #0:0: Video: h264 (High), yuv420p(progressive), 1920x1080, 24 fps, 24 tbr, 1k tbn\n",
+///   "[info]   Stream #0:1(jpn): Audio: aac (LC), 48000 Hz, stereo, fltp (default)\n",
+///   "[info]   Stream #0:2(eng): Audio: aac (LC), 48000 Hz, stereo, fltp\n",
+///   "[info]   Stream #0:3(eng): Subtitle: ass\n",
+///   "[info]   Stream #0:4: Attachment: ttf\n",
+/// ]
+/// .iter()
+/// .map(|line| try_parse_stream(line).unwrap())
+/// .collect();
+///
+/// let args = StreamSelection::new()
+///   .select_audio(Language("eng"))
+///   .select_subtitles(None)
+///   .resolve(&metadata)
+///   .unwrap();
+///
+/// assert_eq!(args, ["-map", "0:0", "-map", "0:2"]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamSelection {
+  input_index: u32,
+  video: Selection,
+  audio: Selection,
+  subtitles: Selection,
+}
+
+impl Default for StreamSelection {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl StreamSelection {
+  /// Select streams from the first input (`#0`).
+  pub fn new() -> Self {
+    Self::for_input(0)
+  }
+
+  /// Select streams from the input with the given index.
+  pub fn for_input(input_index: u32) -> Self {
+    Self {
+      input_index,
+      video: Selection::All,
+      audio: Selection::All,
+      subtitles: Selection::All,
+    }
+  }
+
+  /// Keep only the first video stream in `language`, or no video for `None`.
+  pub fn select_video<'a>(mut self, language: impl Into<Option<Language<'a>>>) -> Self {
+    self.video = language.into().into();
+    self
+  }
+
+  /// Keep only the first audio stream in `language`, or no audio for `None`.
+  pub fn select_audio<'a>(mut self, language: impl Into<Option<Language<'a>>>) -> Self {
+    self.audio = language.into().into();
+    self
+  }
+
+  /// Keep only the first subtitle stream in `language`, or no subtitles for `None`.
+  pub fn select_subtitles<'a>(mut self, language: impl Into<Option<Language<'a>>>) -> Self {
+    self.subtitles = language.into().into();
+    self
+  }
+
+  /// Resolve the selection against the input streams in `metadata`, returning
+  /// the `-map` arguments in input stream order.
+  ///
+  /// Fails if a requested language is not present among the streams of that
+  /// type, rather than silently producing an output without it.
+  pub fn resolve(&self, metadata: &FfmpegMetadata) -> anyhow::Result<Vec<String>> {
+    let streams = metadata
+      .input_streams
+      .iter()
+      .filter(|stream| stream.parent_index == self.input_index)
+      .collect::<Vec<_>>();
+
+    let mut selected = Vec::new();
+    selected.extend(resolve_type(
+      &streams,
+      &self.video,
+      FfmpegStream::is_video,
+      "video",
+    )?);
+    selected.extend(resolve_type(
+      &streams,
+      &self.audio,
+      FfmpegStream::is_audio,
+      "audio",
+    )?);
+    selected.extend(resolve_type(
+      &streams,
+      &self.subtitles,
+      FfmpegStream::is_subtitle,
+      "subtitle",
+    )?);
+    selected.sort_unstable();
+
+    Ok(
+      selected
+        .into_iter()
+        .flat_map(|stream_index| {
+          [
+            "-map".to_string(),
+            format!("{}:{stream_index}", self.input_index),
+          ]
+        })
+        .collect(),
+    )
+  }
+}
+
+fn resolve_type(
+  streams: &[&FfmpegStream],
+  selection: &Selection,
+  is_type: fn(&FfmpegStream) -> bool,
+  type_name: &str,
+) -> anyhow::Result<Vec<u32>> {
+  let mut candidates = streams.iter().filter(|stream| is_type(stream));
+
+  match selection {
+    Selection::All => Ok(candidates.map(|stream| stream.stream_index).collect()),
+    Selection::Nothing => Ok(Vec::new()),
+    Selection::Language(language) => candidates
+      .find(|stream| stream.language.eq_ignore_ascii_case(language))
+      .map(|stream| vec![stream.stream_index])
+      .with_context(|| format!("no {type_name} stream with language `{language}`")),
+  }
+}
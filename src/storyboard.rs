@@ -0,0 +1,283 @@
+//! Thumbnail sprite sheets for the seek bar of video players.
+//!
+//! [`Storyboard`] extracts a thumbnail every `interval`, packs them into
+//! sheets of `columns`x`rows` tiles and describes the position of every
+//! thumbnail in a WebVTT track, the format expected by players such as
+//! Video.js or JW Player, e.g. next to an [HLS](crate::hls) rendition.
+
+use crate::command::FfmpegCommand;
+use crate::event::StreamTypeSpecificData;
+use crate::image_sequence::sequence_path;
+use crate::outcome::FfmpegOutcomeTracker;
+use futures_util::StreamExt;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The options of a storyboard, see the [module documentation](self).
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::storyboard::Storyboard;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let storyboard = Storyboard::new("movie.mp4", "thumbs/sheet_%03d.jpg")
+///   .tile(5, 5)
+///   .interval(Duration::from_secs(10))
+///   .webvtt("thumbs/thumbnails.vtt")
+///   .generate()
+///   .await?;
+/// println!("{} sheets, {} thumbnails", storyboard.sheets.len(), storyboard.cues.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Storyboard {
+  input: String,
+  sheet_pattern: String,
+  columns: u32,
+  rows: u32,
+  interval: Duration,
+  thumbnail_width: u32,
+  webvtt: Option<PathBuf>,
+}
+
+/// The position of one thumbnail, as listed in the WebVTT track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoryboardCue {
+  pub start: Duration,
+  pub end: Duration,
+  /// The sheet holding the thumbnail
+  pub sheet: PathBuf,
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+/// The files written by [`Storyboard::generate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoryboardOutput {
+  /// The sprite sheets, in order
+  pub sheets: Vec<PathBuf>,
+  /// One cue per thumbnail, in order
+  pub cues: Vec<StoryboardCue>,
+}
+
+impl Storyboard {
+  /// A storyboard of `input` written to the sheets named by
+  /// `sheet_pattern`, e.g. `sheet_%03d.jpg` for `sheet_001.jpg`,
+  /// `sheet_002.jpg`, and so on. Defaults to 5x5 tiles of 160 pixels wide
+  /// thumbnails, one every 10 seconds.
+  pub fn new<S: AsRef<str>, T: AsRef<str>>(input: S, sheet_pattern: T) -> Self {
+    Self {
+      input: input.as_ref().to_string(),
+      sheet_pattern: sheet_pattern.as_ref().to_string(),
+      columns: 5,
+      rows: 5,
+      interval: Duration::from_secs(10),
+      thumbnail_width: 160,
+      webvtt: None,
+    }
+  }
+
+  /// The number of thumbnails per row and column of a sheet.
+  pub fn tile(mut self, columns: u32, rows: u32) -> Self {
+    self.columns = columns;
+    self.rows = rows;
+    self
+  }
+
+  /// The time between two thumbnails.
+  pub fn interval(mut self, interval: Duration) -> Self {
+    self.interval = interval;
+    self
+  }
+
+  /// The width of a thumbnail in pixels. The height follows the aspect ratio
+  /// of the input.
+  pub fn thumbnail_width(mut self, width: u32) -> Self {
+    self.thumbnail_width = width;
+    self
+  }
+
+  /// Also write the WebVTT track to `path`. Cues refer to the sheets by file
+  /// name, so the track should be next to the sheets.
+  pub fn webvtt<P: Into<PathBuf>>(mut self, path: P) -> Self {
+    self.webvtt = Some(path.into());
+    self
+  }
+
+  /// The command writing the sheets: a frame is selected every `interval`,
+  /// scaled down and packed with the `tile` filter.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::storyboard::Storyboard;
+  /// use std::time::Duration;
+  ///
+  /// let command = Storyboard::new("movie.mp4", "sheet_%03d.jpg")
+  ///   .tile(4, 3)
+  ///   .interval(Duration::from_secs(5))
+  ///   .command();
+  ///
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[args.len() - 6..],
+  ///   [
+  ///     "-fps_mode",
+  ///     "vfr",
+  ///     "-y",
+  ///     "-filter:v",
+  ///     "select='isnan(prev_selected_t)+gte(t-prev_selected_t,5)',scale=160:-2,tile=4x3",
+  ///     "sheet_%03d.jpg",
+  ///   ]
+  /// );
+  /// ```
+  pub fn command(&self) -> FfmpegCommand {
+    let mut command = FfmpegCommand::new();
+    command
+      .input(&self.input)
+      .no_audio()
+      .filter_video(format!(
+        "select='isnan(prev_selected_t)+gte(t-prev_selected_t,{})'",
+        self.interval.as_secs_f64()
+      ))
+      .scale(self.thumbnail_width as i32, -2)
+      .filter_video(format!("tile={}x{}", self.columns, self.rows))
+      .fps_mode("vfr")
+      .overwrite()
+      .output(&self.sheet_pattern);
+    command
+  }
+
+  /// Check that the tiles and the interval aren't empty, which
+  /// [`generate`](Self::generate) does before running Ffmpeg.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::storyboard::Storyboard;
+  /// use std::time::Duration;
+  ///
+  /// let storyboard = Storyboard::new("movie.mp4", "sheet_%03d.jpg");
+  /// assert!(storyboard.validate().is_ok());
+  /// assert!(storyboard.clone().tile(0, 5).validate().is_err());
+  /// assert!(storyboard.interval(Duration::ZERO).validate().is_err());
+  /// ```
+  pub fn validate(&self) -> anyhow::Result<()> {
+    if self.columns == 0 || self.rows == 0 {
+      anyhow::bail!("tile must have at least one column and one row");
+    }
+
+    if self.interval.is_zero() {
+      anyhow::bail!("interval must be greater than zero");
+    }
+
+    Ok(())
+  }
+
+  /// Write the sheets and, if configured, the WebVTT track. Fails without
+  /// running Ffmpeg if the options are invalid, see
+  /// [`validate`](Self::validate).
+  pub async fn generate(&self) -> anyhow::Result<StoryboardOutput> {
+    self.validate()?;
+    let mut child = self.command().spawn()?;
+    let mut events = child.stream()?;
+
+    let mut tracker = FfmpegOutcomeTracker::new();
+    while let Some(event) = events.next().await {
+      tracker.observe(&event);
+    }
+    let summary = tracker.into_summary(child.wait().await?, events.metadata().clone());
+    if !summary.outcome.is_success() {
+      anyhow::bail!("storyboard failed: {}", summary.errors.join(""));
+    }
+
+    let duration = summary
+      .metadata
+      .inputs
+      .first()
+      .and_then(|input| input.duration)
+      .ok_or_else(|| anyhow::anyhow!("duration of {} is unknown", self.input))?;
+    let sheet_height = summary
+      .metadata
+      .output_streams
+      .iter()
+      .find_map(|stream| match &stream.type_specific_data {
+        StreamTypeSpecificData::Video(video) => Some(video.height),
+        _ => None,
+      })
+      .ok_or_else(|| anyhow::anyhow!("storyboard has no video stream"))?;
+
+    let output = self.layout(duration, sheet_height / self.rows);
+    if let Some(path) = &self.webvtt {
+      tokio::fs::write(path, output.to_webvtt()).await?;
+    }
+    Ok(output)
+  }
+
+  /// The sheets and cues of an input lasting `duration` seconds.
+  fn layout(&self, duration: f64, thumbnail_height: u32) -> StoryboardOutput {
+    let interval = self.interval.as_secs_f64();
+    let count = (duration / interval).ceil() as u32;
+    let per_sheet = self.columns * self.rows;
+
+    let sheets = (0..count.div_ceil(per_sheet))
+      .map(|index| PathBuf::from(sequence_path(&self.sheet_pattern, index + 1)))
+      .collect::<Vec<_>>();
+    let cues = (0..count)
+      .map(|index| {
+        let tile = index % per_sheet;
+        StoryboardCue {
+          start: Duration::from_secs_f64(index as f64 * interval),
+          end: Duration::from_secs_f64(((index + 1) as f64 * interval).min(duration)),
+          sheet: sheets[(index / per_sheet) as usize].clone(),
+          x: (tile % self.columns) * self.thumbnail_width,
+          y: (tile / self.columns) * thumbnail_height,
+          width: self.thumbnail_width,
+          height: thumbnail_height,
+        }
+      })
+      .collect();
+
+    StoryboardOutput { sheets, cues }
+  }
+}
+
+impl StoryboardOutput {
+  /// The WebVTT thumbnail track, with cues such as
+  /// `sheet_001.jpg#xywh=160,0,160,90`.
+  pub fn to_webvtt(&self) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+    for cue in &self.cues {
+      let sheet = cue
+        .sheet
+        .file_name()
+        .unwrap_or(cue.sheet.as_os_str())
+        .to_string_lossy();
+      let _ = write!(
+        vtt,
+        "\n{} --> {}\n{sheet}#xywh={},{},{},{}\n",
+        webvtt_time(cue.start),
+        webvtt_time(cue.end),
+        cue.x,
+        cue.y,
+        cue.width,
+        cue.height
+      );
+    }
+    vtt
+  }
+}
+
+/// Formats `time` as `HH:MM:SS.mmm`.
+fn webvtt_time(time: Duration) -> String {
+  let millis = time.as_millis();
+  format!(
+    "{:02}:{:02}:{:02}.{:03}",
+    millis / 3_600_000,
+    millis / 60_000 % 60,
+    millis / 1000 % 60,
+    millis % 1000
+  )
+}
@@ -1,39 +1,258 @@
 //! A stream of events from an Ffmpeg process.
 
-use crate::event::{FfmpegProgress, LogLevel};
+use crate::child::MissingChannel;
+use crate::command::FfmpegCommand;
+use crate::event::{ErrorKind, FfmpegProgress, LogLevel};
+use crate::log_parser::LogSection;
+use crate::outcome::FfmpegOutcomeTracker;
+use crate::process_group::KillHandle;
+use crate::progress::EnrichedProgress;
 use crate::{
   child::FfmpegChild, event::FfmpegEvent, log_parser::FfmpegLogParser, metadata::FfmpegMetadata,
 };
 use anyhow::Context;
 use futures_util::{Stream, StreamExt};
+use std::any::Any;
 use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
-use tokio::{io::BufReader, pin, process::ChildStderr};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Sleep};
+use tokio::{
+  io::{AsyncRead, AsyncReadExt, AsyncWrite, BufReader, ReadBuf},
+  pin,
+};
+
+/// The type-erased source of log lines, usually the stderr of a child process.
+type LogReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// The capacity of the buffer the log is read in, unless set with
+/// [`FfmpegEventStream::from_reader_with_capacity`] or
+/// [`FfmpegChild::stream_with_capacity`].
+pub const DEFAULT_BUFFER_CAPACITY: usize = 8 * 1024;
 
 pub struct FfmpegEventStream {
   metadata: FfmpegMetadata,
+  /// The job timeout of the child, see `FfmpegCommand::timeout`
+  deadline: Option<(Duration, Pin<Box<Sleep>>)>,
+  /// Kills the child once the deadline expires
+  kill_handle: Option<KillHandle>,
+  timed_out: bool,
+  /// The first error writing the log file of `tee_to_log_file`, to report
+  tee_error: Option<Arc<Mutex<Option<String>>>>,
   // stderr: ChildStderr,
-  log_parser: FfmpegLogParser<BufReader<ChildStderr>>,
+  log_parser: FfmpegLogParser<BufReader<LogReader>>,
   // stdout: Option<ChildStdout>,
   // err: bool,
 }
 
 impl FfmpegEventStream {
   pub fn new(child: &mut FfmpegChild) -> anyhow::Result<Self> {
-    let stderr = child.take_stderr().context("no stderr channel")?;
-    let reader = BufReader::new(stderr);
-    let parser = FfmpegLogParser::new(reader);
+    Self::with_capacity(child, DEFAULT_BUFFER_CAPACITY)
+  }
+
+  /// Like [`new`](Self::new), reading the log in chunks of at most
+  /// `capacity` bytes, see [`FfmpegChild::stream_with_capacity`].
+  pub fn with_capacity(child: &mut FfmpegChild, capacity: usize) -> anyhow::Result<Self> {
+    let stderr = child.take_stderr().ok_or(MissingChannel::Stderr)?;
     // let stdout = child.take_stdout();
 
-    Ok(Self {
+    let mut stream = Self::from_reader_with_capacity(stderr, capacity);
+    stream.metadata.expect_outputs(child.expected_outputs());
+    if let Some((pattern, start_number)) = child.image_sequence() {
+      stream
+        .log_parser
+        .expect_image_sequence(pattern, *start_number);
+    }
+    stream.deadline = child
+      .deadline()
+      .map(|(timeout, deadline)| (timeout, Box::pin(tokio::time::sleep_until(deadline))));
+    stream.kill_handle = child.kill_handle();
+    Ok(stream)
+  }
+
+  /// Parse events from any source of Ffmpeg log output instead of the stderr
+  /// of a running child process.
+  pub fn from_reader<R: AsyncRead + Send + 'static>(reader: R) -> Self {
+    Self::from_reader_with_capacity(reader, DEFAULT_BUFFER_CAPACITY)
+  }
+
+  /// Like [`from_reader`](Self::from_reader), reading the log in chunks of at
+  /// most `capacity` bytes instead of [`DEFAULT_BUFFER_CAPACITY`].
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\r\
+  ///   [info] frame=   96 fps= 95 q=-0.0 size=       0KiB time=00:00:03.84 bitrate=   0.0kbits/s speed=3.8x\r\n";
+  /// let frames = FfmpegEventStream::from_reader_with_capacity(log.as_bytes(), 16)
+  ///   .filter_progress()
+  ///   .map(|progress| progress.frame)
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  /// assert_eq!(frames, [48, 96]);
+  /// # }
+  /// ```
+  pub fn from_reader_with_capacity<R: AsyncRead + Send + 'static>(
+    reader: R,
+    capacity: usize,
+  ) -> Self {
+    let reader: LogReader = Box::pin(reader);
+    let parser = FfmpegLogParser::new(BufReader::with_capacity(capacity, reader));
+
+    Self {
       metadata: FfmpegMetadata::new(),
+      deadline: None,
+      kill_handle: None,
+      timed_out: false,
+      tee_error: None,
       log_parser: parser,
       // stdout,
       // err: false,
-    })
+    }
+  }
+
+  /// Parse the events of a log file, such as the `ffmpeg-*.log` report
+  /// written with `-report` or the `FFREPORT` environment variable, e.g. to
+  /// analyze a failed job after the fact or to test the parser against real
+  /// logs.
+  ///
+  /// Report files don't have the `[level]` prefixes of the stderr of a
+  /// [`FfmpegChild`], so their log messages have [`LogLevel::Unknown`].
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() -> anyhow::Result<()> {
+  /// let path = std::env::temp_dir().join("ffmpeg-20250101-120000.log");
+  /// tokio::fs::write(
+  ///   &path,
+  ///   "ffmpeg started on 2025-01-01 at 12:00:00\n\
+  ///    Input #0, lavfi, from 'testsrc':\n\
+  ///    frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x    \r\
+  ///    frame=   96 fps= 95 q=-0.0 size=       0KiB time=00:00:03.84 bitrate=   0.0kbits/s speed=3.8x    \r\n",
+  /// )
+  /// .await?;
+  ///
+  /// let events = FfmpegEventStream::from_log_file(&path).await?;
+  /// let frames = events
+  ///   .filter_progress()
+  ///   .map(|progress| progress.frame)
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  /// assert_eq!(frames, [48, 96]);
+  /// # tokio::fs::remove_file(&path).await?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub async fn from_log_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+    let file = tokio::fs::File::open(path.as_ref())
+      .await
+      .with_context(|| format!("failed to open {}", path.as_ref().display()))?;
+    Ok(Self::from_reader(file))
+  }
+
+  /// Whether events keep the line they were parsed from in their
+  /// `raw_log_message` field, which is the default. See
+  /// [`FfmpegLogParser::capture_raw_log_messages`](crate::log_parser::FfmpegLogParser::capture_raw_log_messages).
+  pub fn capture_raw_log_messages(mut self, capture: bool) -> Self {
+    self.log_parser.capture_raw_log_messages(capture);
+    self
   }
 
+  /// Write the log to the file at `path`, created or truncated, as it is
+  /// parsed, e.g. to keep the full log of a job for diagnosing a failure
+  /// after the fact.
+  ///
+  /// Only the log read from then on is written, so call it before reading
+  /// any event. The file is written on Tokio's blocking thread pool and
+  /// flushed once the log ends. Writing stops at the first error, which is
+  /// reported as a [`LogLevel::Warning`] event without interrupting the
+  /// stream.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() -> anyhow::Result<()> {
+  /// let path = std::env::temp_dir().join("async-ffmpeg-sidecar-tee.log");
+  /// let log = "[info] Input #0, lavfi, from 'testsrc':\n[error] Conversion failed!\n";
+  /// let events = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .tee_to_log_file(&path)
+  ///   .await?
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  ///
+  /// assert_eq!(events.len(), 2);
+  /// assert_eq!(tokio::fs::read_to_string(&path).await?, log);
+  /// # tokio::fs::remove_file(&path).await?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub async fn tee_to_log_file<P: AsRef<Path>>(mut self, path: P) -> anyhow::Result<Self> {
+    let path = path.as_ref();
+    let file = tokio::fs::File::create(path)
+      .await
+      .with_context(|| format!("failed to create {}", path.display()))?;
+
+    let error = Arc::new(Mutex::new(None));
+    let reader = self.log_parser.get_mut().get_mut();
+    let inner = std::mem::replace(reader, Box::pin(tokio::io::empty()));
+    *reader = Box::pin(TeeReader {
+      inner,
+      file: Some(file),
+      path: path.to_path_buf(),
+      pending: Vec::new(),
+      error: error.clone(),
+    });
+    self.tee_error = Some(error);
+    Ok(self)
+  }
+
+  /// Read events until the inputs, the outputs and the streams of every
+  /// stream mapping have been parsed, and return them. The events read are
+  /// not emitted by the stream anymore.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() -> anyhow::Result<()> {
+  /// let log = "[info] Input #0, lavfi, from 'testsrc':\n\
+  ///   [info]   Stream #0:0: Video: wrapped_avframe, rgb24, 320x240, 25 fps, 25 tbr, 25 tbn\n\
+  ///   [info] Stream mapping:\n\
+  ///   [info]   Stream #0:0 -> #0:1 (wrapped_avframe (native) -> mpeg4 (native))\n\
+  ///   [info]   Stream #0:0 -> #0:0 (wrapped_avframe (native) -> h264 (libx264))\n\
+  ///   [info] Output #0, mp4, to 'out.mp4':\n\
+  ///   [info]   Stream #0:0: Video: h264, yuv420p, 320x240, q=2-31, 25 fps, 12800 tbn\n\
+  ///   [info]   Stream #0:1: Video: mpeg4, yuv420p, 320x240, q=2-31, 25 fps, 12800 tbn\n";
+  /// let metadata = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .collect_metadata()
+  ///   .await?;
+  /// assert_eq!(metadata.output_streams.len(), 2);
+  /// # Ok(())
+  /// # }
+  /// ```
   pub async fn collect_metadata(&mut self) -> anyhow::Result<FfmpegMetadata> {
     let mut event_queue: Vec<FfmpegEvent> = Vec::new();
 
@@ -44,10 +263,7 @@ impl FfmpegEventStream {
         None => {
           let errors = event_queue
             .iter()
-            .filter_map(|e| match e {
-              FfmpegEvent::Error(e) | FfmpegEvent::Log(LogLevel::Error, e) => Some(e.to_string()),
-              _ => None,
-            })
+            .filter_map(metadata_error)
             .collect::<Vec<String>>()
             .join("");
 
@@ -61,18 +277,429 @@ impl FfmpegEventStream {
     Ok(self.metadata.clone())
   }
 
+  /// Split the stream into a future resolving to the metadata once it has
+  /// been gathered, like [`collect_metadata`](Self::collect_metadata), and a
+  /// stream still emitting every event.
+  ///
+  /// The metadata is gathered from the events as they are emitted, so the
+  /// future only resolves while the stream is being consumed, e.g. on another
+  /// task or with `tokio::join!`. It fails if the stream ends or is dropped
+  /// before the metadata is complete.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() -> anyhow::Result<()> {
+  /// let log = "[info] Input #0, lavfi, from 'testsrc':\n\
+  ///   [info]   Stream #0:0: Video: wrapped_avframe, rgb24, 320x240, 25 fps, 25 tbr, 25 tbn\n\
+  ///   [info] Stream mapping:\n\
+  ///   [info]   Stream #0:0 -> #0:0 (wrapped_avframe (native) -> h264 (libx264))\n\
+  ///   [info] Output #0, mp4, to 'out.mp4':\n\
+  ///   [info]   Stream #0:0: Video: h264, yuv420p, 320x240, q=2-31, 25 fps, 12800 tbn\n\
+  ///   [info] frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\n";
+  /// let (metadata, events) = FfmpegEventStream::from_reader(log.as_bytes()).split_metadata();
+  ///
+  /// let (metadata, events) = tokio::join!(metadata, events.collect::<Vec<_>>());
+  /// assert_eq!(metadata?.output_streams.len(), 1);
+  /// assert_eq!(events.len(), 7);
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn split_metadata(
+    self,
+  ) -> (
+    impl Future<Output = anyhow::Result<FfmpegMetadata>> + Send,
+    impl Stream<Item = FfmpegEvent> + Send + Unpin,
+  ) {
+    let (tx, rx) = oneshot::channel();
+    let events = futures_util::stream::unfold(
+      (self, Some(tx), Vec::new()),
+      |(mut stream, mut tx, mut errors)| async move {
+        let Some(event) = stream.next().await else {
+          if let Some(tx) = tx {
+            let _ = tx.send(Err(anyhow::anyhow!(
+              "Stream ran out before metadata was gathered. The following errors occurred: {}",
+              errors.join("")
+            )));
+          }
+          return None;
+        };
+
+        if tx.is_some() {
+          errors.extend(metadata_error(&event));
+          if stream.metadata.is_completed() {
+            if let Some(tx) = tx.take() {
+              let _ = tx.send(Ok(stream.metadata.clone()));
+            }
+            errors = Vec::new();
+          }
+        }
+        Some((event, (stream, tx, errors)))
+      },
+    );
+
+    let metadata = async move {
+      rx.await
+        .context("event stream dropped before metadata was gathered")?
+    };
+    (metadata, Box::pin(events))
+  }
+
+  /// The metadata gathered from the events emitted so far, which is
+  /// incomplete until [`FfmpegMetadata::is_completed`] returns `true`.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'a.mp4':\n\
+  ///   [info]   Duration: 00:00:05.00, start: 0.000000, bitrate: 16 kb/s\n\
+  ///   [info] Stream mapping:\n";
+  /// let mut events = FfmpegEventStream::from_reader(log.as_bytes());
+  /// assert_eq!(events.metadata().duration(), None);
+  ///
+  /// while let Some(event) = events.next().await {
+  ///   if let FfmpegEvent::ParsedDuration(_) = event {
+  ///     assert_eq!(events.metadata().duration(), Some(5.0));
+  ///   }
+  /// }
+  /// assert_eq!(events.metadata().inputs.len(), 1);
+  /// assert!(!events.metadata().is_completed());
+  /// # }
+  /// ```
+  pub fn metadata(&self) -> &FfmpegMetadata {
+    &self.metadata
+  }
+
+  /// Parse the lines recognized by `matcher` into
+  /// [`FfmpegEvent::Custom`] events, see [`FfmpegLogParser::add_matcher`].
+  pub fn add_matcher<T, F>(&mut self, name: &str, matcher: F) -> &mut Self
+  where
+    T: Any + Send + Sync,
+    F: Fn(&str) -> Option<T> + Send + Sync + 'static,
+  {
+    self.log_parser.add_matcher(name, matcher);
+    self
+  }
+
+  /// The log section of the most recently emitted event.
+  pub fn current_section(&self) -> LogSection {
+    self.log_parser.current_section()
+  }
+
+  /// Tag every event with the [`LogSection`] it was parsed in, e.g. to tell
+  /// which input a `ParsedDuration` or a warning belongs to.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::{FfmpegEvent, LogLevel};
+  /// use async_ffmpeg_sidecar::log_parser::LogSection;
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'b.mp4':\n[info]   Duration: 00:00:05.00, start: 0.000000, bitrate: 16 kb/s\n[info] Stream mapping:\n";
+  /// let events = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .with_sections()
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  ///
+  /// assert!(matches!(events[1].event, FfmpegEvent::ParsedDuration(_)));
+  /// assert_eq!(events[1].section, LogSection::Input(0));
+  /// assert_eq!(events[2].section, LogSection::StreamMapping);
+  /// # }
+  /// ```
+  pub fn with_sections(self) -> impl Stream<Item = SectionedEvent> {
+    futures::stream::unfold(self, |mut stream| async move {
+      let event = stream.next().await?;
+      let section = stream.current_section();
+      Some((SectionedEvent { section, event }, stream))
+    })
+  }
+
+  /// Tag every event with the time it was parsed at, e.g. to measure when
+  /// Ffmpeg reported each progress update of a live pipeline.
+  ///
+  /// Events are parsed as soon as Ffmpeg writes their line, provided that the
+  /// stream is consumed promptly; lines read in the same chunk get nearly the
+  /// same time.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\n\
+  ///   [info] frame=   96 fps= 95 q=-0.0 size=       0KiB time=00:00:03.84 bitrate=   0.0kbits/s speed=3.8x\n";
+  /// let events = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .timestamped()
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  ///
+  /// assert!(events[1].instant >= events[0].instant);
+  /// # }
+  /// ```
+  pub fn timestamped(self) -> impl Stream<Item = TimestampedEvent> {
+    self.map(|event| TimestampedEvent {
+      wall_time: SystemTime::now(),
+      instant: std::time::Instant::now(),
+      event,
+    })
+  }
+
+  /// Read the events on a spawned task and send them to a broadcast channel
+  /// of `capacity` events, so that several consumers can receive every
+  /// event, e.g. a progress bar and a log pane. The task ends with the
+  /// stream, whether or not anyone is listening.
+  ///
+  /// Receivers created with [`broadcast::Sender::subscribe`] only get the
+  /// events sent after they subscribed, so subscribe right away. A receiver
+  /// falling more than `capacity` events behind misses the oldest ones, see
+  /// [`broadcast::error::RecvError::Lagged`].
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  ///
+  /// # #[tokio::main(flavor = "current_thread")]
+  /// # async fn main() -> anyhow::Result<()> {
+  /// let log = "[info] Input #0, lavfi, from 'testsrc':\n[info] frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\n";
+  /// let (events, driver) = FfmpegEventStream::from_reader(log.as_bytes()).into_broadcast(64);
+  /// let mut progress = events.subscribe();
+  /// let mut logs = events.subscribe();
+  /// drop(events);
+  ///
+  /// let progress = tokio::spawn(async move {
+  ///   let mut frames = Vec::new();
+  ///   while let Ok(event) = progress.recv().await {
+  ///     if let FfmpegEvent::Progress(progress) = event {
+  ///       frames.push(progress.frame);
+  ///     }
+  ///   }
+  ///   frames
+  /// });
+  /// let mut count = 0;
+  /// while logs.recv().await.is_ok() {
+  ///   count += 1;
+  /// }
+  ///
+  /// driver.await?;
+  /// assert_eq!(progress.await?, [48]);
+  /// assert_eq!(count, 2);
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn into_broadcast(
+    mut self,
+    capacity: usize,
+  ) -> (broadcast::Sender<FfmpegEvent>, JoinHandle<()>) {
+    let (tx, _) = broadcast::channel(capacity);
+    let sender = tx.clone();
+    let driver = tokio::spawn(async move {
+      while let Some(event) = self.next().await {
+        // No receivers is not an error: the logs still have to be read for
+        // Ffmpeg not to block on a full pipe
+        let _ = tx.send(event);
+      }
+    });
+    (sender, driver)
+  }
+
+  /// Return the failures to read or parse the log as errors instead of
+  /// [`FfmpegEvent::Error`] events, so that they can be told apart from the
+  /// errors logged by Ffmpeg and short-circuit with
+  /// [`TryStreamExt`](futures_util::TryStreamExt). The stream ends after the
+  /// first error.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::stream::{FfmpegError, FfmpegEventStream};
+  /// use futures_util::TryStreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] Press [q] to stop, [?] for help\n\
+  ///   [error] Error opening output out.mp4: Permission denied\n\
+  ///   [info]   Stream #0:0: Video: h264, yuv420p, 320x240, 25 fps\n\
+  ///   [info] Press [q] to stop, [?] for help\n";
+  /// let result = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .try_stream()
+  ///   .try_collect::<Vec<_>>()
+  ///   .await;
+  /// assert!(matches!(result, Err(FfmpegError::Parse(_))));
+  /// # }
+  /// ```
+  pub fn try_stream(mut self) -> impl Stream<Item = Result<FfmpegEvent, FfmpegError>> {
+    let mut failed = false;
+    futures_util::stream::poll_fn(move |cx| {
+      if failed {
+        return Poll::Ready(None);
+      }
+      let item = std::task::ready!(self.poll_next_result(cx));
+      failed = matches!(item, Some(Err(_)));
+      Poll::Ready(item)
+    })
+  }
+
+  fn poll_next_result(
+    &mut self,
+    cx: &mut std::task::Context<'_>,
+  ) -> Poll<Option<Result<FfmpegEvent, FfmpegError>>> {
+    if self.timed_out {
+      return Poll::Ready(None);
+    }
+
+    if let Some((timeout, sleep)) = self.deadline.as_mut() {
+      if sleep.as_mut().poll(cx).is_ready() {
+        let timeout = *timeout;
+        self.timed_out = true;
+        if let Some(kill_handle) = &self.kill_handle {
+          // Fails only if the process already exited.
+          let _ = kill_handle.kill();
+        }
+        return Poll::Ready(Some(Ok(FfmpegEvent::TimedOut(timeout))));
+      }
+    }
+
+    if let Some(event) = self.take_tee_error() {
+      return Poll::Ready(Some(Ok(event)));
+    }
+
+    let fut = self.log_parser.parse_next_event();
+    let item = {
+      pin!(fut);
+
+      match fut.poll(cx) {
+        Poll::Ready(Ok(event)) => event,
+        Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+        Poll::Pending => return Poll::Pending,
+      }
+    };
+
+    if item == FfmpegEvent::LogEOF {
+      // Writing the log file fails last when flushing it at the end
+      return Poll::Ready(self.take_tee_error().map(Ok));
+    }
+
+    if !self.metadata.is_completed() {
+      if let Err(e) = self.metadata.handle_event(&item) {
+        return Poll::Ready(Some(Err(FfmpegError::Metadata(e))));
+      }
+    }
+
+    Poll::Ready(Some(Ok(item)))
+  }
+
+  /// The warning about the first error writing the log file of
+  /// `tee_to_log_file`, once.
+  fn take_tee_error(&mut self) -> Option<FfmpegEvent> {
+    let message = self.tee_error.as_ref()?.lock().unwrap().take()?;
+    self.tee_error = None;
+    Some(FfmpegEvent::Log(LogLevel::Warning, message))
+  }
+
   //// Stream filters
 
-  /// Returns a stream over error messages (`FfmpegEvent::Error` and `FfmpegEvent::LogError`).
+  /// Returns a stream over error messages (`FfmpegEvent::Error`,
+  /// `FfmpegEvent::Log(LogLevel::Error, _)`, `FfmpegEvent::EmptyOutput` and
+  /// the raw message of `FfmpegEvent::BuildCapabilityError` and
+  /// `FfmpegEvent::UnsupportedContainerCodec`).
   pub fn filter_errors(self) -> impl Stream<Item = String> {
     self.filter_map(|event| {
       futures::future::ready(match event {
-        FfmpegEvent::Error(e) | FfmpegEvent::Log(LogLevel::Error, e) => Some(e),
+        FfmpegEvent::Error { message: e, .. }
+        | FfmpegEvent::Log(LogLevel::Error, e)
+        | FfmpegEvent::EmptyOutput(e) => Some(e),
+        FfmpegEvent::BuildCapabilityError(e) => Some(e.raw_log_message),
+        FfmpegEvent::UnsupportedContainerCodec(e) => Some(e.raw_log_message),
         _ => None,
       })
     })
   }
 
+  /// Returns a stream over warning messages
+  /// (`FfmpegEvent::Log(LogLevel::Warning, _)`).
+  pub fn filter_warnings(self) -> impl Stream<Item = String> {
+    self.filter_logs(LogLevel::Warning)
+  }
+
+  /// Returns a stream over the log messages of exactly `level`
+  /// (`FfmpegEvent::Log(level, _)`).
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::LogLevel;
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] Press [q] to stop, [?] for help\n\
+  ///   [warning] Guessed Channel Layout for Input Stream #0.1 : mono\n\
+  ///   [verbose] Stream #0:0 ignored\n";
+  /// let warnings = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .filter_logs(LogLevel::Warning)
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  /// assert_eq!(warnings.len(), 1);
+  /// assert!(warnings[0].contains("Guessed Channel Layout"));
+  /// # }
+  /// ```
+  pub fn filter_logs(self, level: LogLevel) -> impl Stream<Item = String> {
+    self.filter_map(move |event| {
+      futures::future::ready(match event {
+        FfmpegEvent::Log(l, message) if l == level => Some(message),
+        _ => None,
+      })
+    })
+  }
+
+  /// Filter out the events for which `predicate` returns `false`.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'a.mp4':\n\
+  ///   [info]   Duration: 00:00:40.00, start: 0.000000, bitrate: 16 kb/s\n\
+  ///   [info] Stream mapping:\n";
+  /// let events = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .filter_events(|event| matches!(event, FfmpegEvent::ParsedDuration(_)))
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  /// assert_eq!(events.len(), 1);
+  /// # }
+  /// ```
+  pub fn filter_events<F>(self, mut predicate: F) -> impl Stream<Item = FfmpegEvent>
+  where
+    F: FnMut(&FfmpegEvent) -> bool,
+  {
+    self.filter(move |event| futures::future::ready(predicate(event)))
+  }
+
   /// Filter out all events except for progress (`FfmpegEvent::Progress`).
   pub fn filter_progress(self) -> impl Stream<Item = FfmpegProgress> {
     self.filter_map(|event| {
@@ -82,6 +709,110 @@ impl FfmpegEventStream {
       })
     })
   }
+
+  /// Filter out all events except for progress, like
+  /// [`filter_progress`](Self::filter_progress), and emit at most one update
+  /// per `interval`, e.g. to keep a live encode from flooding a UI channel.
+  ///
+  /// The first update is emitted right away. The updates that arrive within
+  /// an interval are coalesced into the latest one, emitted when the interval
+  /// elapses, and the last update is always emitted when the stream ends.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  /// use std::time::{Duration, SystemTime};
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] frame=   48 fps=0.0 q=-0.0 size=       0KiB time=00:00:01.92 bitrate=   0.0kbits/s speed=3.8x\n\
+  ///   [info] frame=   96 fps= 95 q=-0.0 size=       0KiB time=00:00:03.84 bitrate=   0.0kbits/s speed=3.8x\n\
+  ///   [info] frame=  144 fps= 95 q=-0.0 size=       0KiB time=00:00:05.76 bitrate=   0.0kbits/s speed=3.8x\n";
+  /// let frames = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .throttle_progress(Duration::from_secs(1))
+  ///   .map(|progress| progress.frame)
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  /// assert_eq!(frames, [48, 144]);
+  /// # }
+  /// ```
+  pub fn throttle_progress(self, interval: Duration) -> impl Stream<Item = FfmpegProgress> {
+    let progress = Box::pin(self.filter_progress());
+    let state = (progress, None, Instant::now(), false);
+    futures::stream::unfold(
+      state,
+      move |(mut progress, mut pending, mut next_emit, ended)| async move {
+        loop {
+          if ended {
+            return None;
+          }
+
+          let Some(latest) = pending.take() else {
+            match progress.next().await {
+              Some(p) if Instant::now() >= next_emit => {
+                next_emit = Instant::now() + interval;
+                return Some((p, (progress, None, next_emit, false)));
+              }
+              Some(p) => pending = Some(p),
+              None => return None,
+            }
+            continue;
+          };
+
+          tokio::select! {
+            _ = tokio::time::sleep_until(next_emit) => {
+              next_emit = Instant::now() + interval;
+              return Some((latest, (progress, None, next_emit, false)));
+            }
+            next = progress.next() => match next {
+              Some(p) => pending = Some(p),
+              // Always emit the final update
+              None => return Some((latest, (progress, None, next_emit, true))),
+            }
+          }
+        }
+      },
+    )
+  }
+
+  /// Filter out all events except for progress, like
+  /// [`filter_progress`](Self::filter_progress), with the percentage and
+  /// estimated time left computed from the duration of the first input.
+  ///
+  /// ## Example
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::stream::FfmpegEventStream;
+  /// use futures_util::StreamExt;
+  ///
+  /// # #[tokio::main]
+  /// # async fn main() {
+  /// let log = "[info] Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'a.mp4':\n\
+  ///   [info]   Duration: 00:00:40.00, start: 0.000000, bitrate: 16 kb/s\n\
+  ///   [info] frame=  250 fps=50 q=28.0 size=     512KiB time=00:00:10.00 bitrate= 419.4kbits/s speed=2.00x\n";
+  /// let progress = FfmpegEventStream::from_reader(log.as_bytes())
+  ///   .filter_progress_with_eta()
+  ///   .collect::<Vec<_>>()
+  ///   .await;
+  /// assert_eq!(progress[0].percent, Some(25.0));
+  /// assert_eq!(progress[0].eta.map(|eta| eta.as_secs()), Some(15));
+  /// # }
+  /// ```
+  pub fn filter_progress_with_eta(self) -> impl Stream<Item = EnrichedProgress> {
+    futures::stream::unfold(self, |mut stream| async move {
+      loop {
+        if let FfmpegEvent::Progress(progress) = stream.next().await? {
+          let duration = stream
+            .metadata
+            .duration()
+            .and_then(|duration| Duration::try_from_secs_f64(duration).ok());
+          return Some((EnrichedProgress::new(progress, duration), stream));
+        }
+      }
+    })
+  }
 }
 
 impl Stream for FfmpegEventStream {
@@ -91,29 +822,242 @@ impl Stream for FfmpegEventStream {
     mut self: Pin<&mut Self>,
     cx: &mut std::task::Context<'_>,
   ) -> Poll<Option<FfmpegEvent>> {
-    let fut = self.log_parser.parse_next_event();
-    let item = {
-      pin!(fut);
+    self.poll_next_result(cx).map(|item| {
+      item.map(|result| {
+        result.unwrap_or_else(|e| FfmpegEvent::Error {
+          kind: ErrorKind::Other,
+          message: e.to_string(),
+        })
+      })
+    })
+  }
+}
 
-      match fut.poll(cx) {
-        Poll::Ready(Ok(event)) => {
-          if event == FfmpegEvent::LogEOF {
-            return Poll::Ready(None);
-          }
+/// A failure to read or parse the log, as opposed to an error reported by
+/// Ffmpeg itself, see [`FfmpegEventStream::try_stream`].
+#[derive(Debug)]
+pub enum FfmpegError {
+  /// Reading the log failed
+  Io(std::io::Error),
+  /// A log line couldn't be parsed
+  Parse(anyhow::Error),
+  /// An event contradicts the metadata gathered before it
+  Metadata(anyhow::Error),
+}
+
+impl From<anyhow::Error> for FfmpegError {
+  /// A parse error, or an I/O error if the log couldn't be read.
+  fn from(e: anyhow::Error) -> Self {
+    match e.downcast::<std::io::Error>() {
+      Ok(e) => FfmpegError::Io(e),
+      Err(e) => FfmpegError::Parse(e),
+    }
+  }
+}
+
+impl std::fmt::Display for FfmpegError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      FfmpegError::Io(e) => write!(f, "{e}"),
+      FfmpegError::Parse(e) | FfmpegError::Metadata(e) => write!(f, "{e}"),
+    }
+  }
+}
+
+impl std::error::Error for FfmpegError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      FfmpegError::Io(e) => e.source(),
+      FfmpegError::Parse(e) | FfmpegError::Metadata(e) => e.source(),
+    }
+  }
+}
 
-          event
+/// The error message of `event` to report when the stream runs out before
+/// the metadata is gathered.
+fn metadata_error(event: &FfmpegEvent) -> Option<String> {
+  match event {
+    FfmpegEvent::Error { message: e, .. } | FfmpegEvent::Log(LogLevel::Error, e) => {
+      Some(e.to_string())
+    }
+    FfmpegEvent::BuildCapabilityError(e) => Some(e.raw_log_message.clone()),
+    FfmpegEvent::UnsupportedContainerCodec(e) => Some(e.raw_log_message.clone()),
+    _ => None,
+  }
+}
+
+/// A reader that writes everything read through it to a file, see
+/// [`FfmpegEventStream::tee_to_log_file`].
+struct TeeReader {
+  inner: LogReader,
+  /// `None` once writing failed
+  file: Option<tokio::fs::File>,
+  path: PathBuf,
+  /// The log read but not handed to `file` yet
+  pending: Vec<u8>,
+  /// The first write error, reported by the event stream
+  error: Arc<Mutex<Option<String>>>,
+}
+
+impl TeeReader {
+  /// Hand the pending log to the file, which writes it in the background.
+  fn poll_write_pending(&mut self, cx: &mut std::task::Context<'_>) -> Poll<()> {
+    while let Some(file) = self.file.as_mut() {
+      if self.pending.is_empty() {
+        break;
+      }
+      match Pin::new(file).poll_write(cx, &self.pending) {
+        Poll::Ready(Ok(0)) => self.fail(std::io::ErrorKind::WriteZero.into()),
+        Poll::Ready(Ok(written)) => {
+          self.pending.drain(..written);
         }
-        Poll::Ready(Err(e)) => return Poll::Ready(Some(FfmpegEvent::Error(e.to_string()))),
+        Poll::Ready(Err(e)) => self.fail(e),
         Poll::Pending => return Poll::Pending,
       }
-    };
+    }
+    Poll::Ready(())
+  }
 
-    if !self.metadata.is_completed() {
-      if let Err(e) = self.metadata.handle_event(&item) {
-        return Poll::Ready(Some(FfmpegEvent::Error(e.to_string())));
+  fn fail(&mut self, error: std::io::Error) {
+    self.file = None;
+    self.pending = Vec::new();
+    *self.error.lock().unwrap() = Some(format!(
+      "failed to write the log to {}: {error}",
+      self.path.display()
+    ));
+  }
+}
+
+impl AsyncRead for TeeReader {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut std::task::Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<std::io::Result<()>> {
+    // Reading on only once the file caught up keeps it in step with the log
+    if self.poll_write_pending(cx).is_pending() {
+      return Poll::Pending;
+    }
+
+    let filled_before = buf.filled().len();
+    let result = self.inner.as_mut().poll_read(cx, buf);
+    if !matches!(result, Poll::Ready(Ok(()))) || self.file.is_none() {
+      return result;
+    }
+
+    let read = &buf.filled()[filled_before..];
+    if read.is_empty() {
+      // The log ended, so wait for the last write to complete the file
+      let flushed = Pin::new(self.file.as_mut().unwrap()).poll_flush(cx);
+      match flushed {
+        Poll::Ready(Ok(())) => self.file = None,
+        Poll::Ready(Err(e)) => self.fail(e),
+        Poll::Pending => return Poll::Pending,
+      }
+    } else {
+      self.pending.extend_from_slice(read);
+      // Start writing right away, the next read waits for it to complete
+      let _ = self.poll_write_pending(cx);
+    }
+
+    result
+  }
+}
+
+/// An event along with the log section it was parsed in, see
+/// [`FfmpegEventStream::with_sections`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectionedEvent {
+  pub section: LogSection,
+  pub event: FfmpegEvent,
+}
+
+/// An event along with the time it was parsed at, see
+/// [`FfmpegEventStream::timestamped`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampedEvent {
+  /// The system time, to correlate the event with other clocks
+  pub wall_time: SystemTime,
+  /// The monotonic time, to measure the intervals between events
+  pub instant: std::time::Instant,
+  pub event: FfmpegEvent,
+}
+
+/// Spawn `command` and stream the items `parse` extracts from its events as
+/// they arrive, followed by an error if the process fails. Dropping the
+/// stream kills the process.
+pub(crate) fn spawn_parsed<T, F>(
+  mut command: FfmpegCommand,
+  mut parse: F,
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<T>> + Send + Unpin>
+where
+  T: Send + 'static,
+  F: FnMut(FfmpegEvent) -> Option<T> + Send + 'static,
+{
+  let mut child = command.spawn()?;
+  let mut events = child.stream()?;
+
+  let (tx, rx) = mpsc::channel(64);
+  tokio::spawn(async move {
+    let mut errors = Vec::new();
+    while let Some(event) = events.next().await {
+      if let FfmpegEvent::Error { message: e, .. } | FfmpegEvent::Log(LogLevel::Error, e) = &event {
+        errors.push(e.clone());
       }
+      if let Some(item) = parse(event) {
+        if tx.send(Ok(item)).await.is_err() {
+          let _ = child.kill().await;
+          return;
+        }
+      }
+    }
+
+    let error = match child.wait().await {
+      Ok(status) if status.success() => return,
+      Ok(status) => anyhow::anyhow!("ffmpeg exited with {status}: {}", errors.join("")),
+      Err(e) => e.into(),
+    };
+    let _ = tx.send(Err(error)).await;
+  });
+
+  Ok(Box::pin(futures_util::stream::unfold(
+    rx,
+    |mut rx| async move {
+      let item = rx.recv().await?;
+      Some((item, rx))
+    },
+  )))
+}
+
+/// Run `command`, whose output must be piped to stdout, and collect that
+/// output. The log is drained concurrently so that a full stderr pipe can't
+/// block Ffmpeg. Fails with the logged errors if Ffmpeg does.
+pub(crate) async fn read_stdout(mut command: FfmpegCommand) -> anyhow::Result<Vec<u8>> {
+  let mut child = command.spawn()?;
+  let mut stdout = child
+    .take_stdout()
+    .context("stdout of ffmpeg is not piped")?;
+
+  let mut events = child.stream()?;
+  let collector = tokio::spawn(async move {
+    let mut tracker = FfmpegOutcomeTracker::new();
+    while let Some(event) = events.next().await {
+      tracker.observe(&event);
     }
+    tracker
+  });
+
+  let mut output = Vec::new();
+  stdout.read_to_end(&mut output).await?;
 
-    Poll::Ready(Some(item))
+  let exit_status = child.wait().await?;
+  let tracker = collector.await?;
+  if !exit_status.success() {
+    anyhow::bail!(
+      "ffmpeg exited with {exit_status}: {}",
+      tracker.errors().join("")
+    );
   }
+  Ok(output)
 }
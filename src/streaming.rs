@@ -0,0 +1,300 @@
+//! Pushing live streams to RTMP and SRT ingest servers.
+//!
+//! [`RtmpOutput`] and [`SrtOutput`] assemble the container format, protocol
+//! options and keyframe interval that ingest servers expect. Ffmpeg itself
+//! does not reconnect dropped output connections, so both can be turned into
+//! an [`FfmpegSupervisor`] that restarts the push whenever it fails.
+
+use crate::command::FfmpegCommand;
+use crate::supervisor::FfmpegSupervisor;
+use std::time::Duration;
+
+/// Keyframe interval recommended by most ingest services (Twitch, YouTube,
+/// Facebook).
+pub const DEFAULT_KEYFRAME_INTERVAL: Duration = Duration::from_secs(2);
+
+/// `-force_key_frames` arguments forcing a keyframe every `interval`,
+/// independent of the frame rate.
+fn keyframe_args(interval: Duration) -> [String; 2] {
+  [
+    "-force_key_frames".to_string(),
+    format!("expr:gte(t,n_forced*{})", interval.as_secs_f64()),
+  ]
+}
+
+/// A push to an RTMP server, muxed as FLV.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::streaming::RtmpOutput;
+/// use std::time::Duration;
+///
+/// let rtmp = RtmpOutput::new("rtmp://live.example.com/app/stream-key")
+///   .timeout(Duration::from_secs(5));
+///
+/// let mut command = FfmpegCommand::new();
+/// command
+///   .input("input.mp4")
+///   .codec_video("libx264")
+///   .codec_audio("aac")
+///   .rtmp_output(&rtmp);
+///
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert!(args.windows(2).any(|w| w == ["-f", "flv"]));
+/// assert!(args.windows(2).any(|w| w == ["-rw_timeout", "5000000"]));
+/// assert_eq!(*args.last().unwrap(), "rtmp://live.example.com/app/stream-key");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtmpOutput {
+  url: String,
+  keyframe_interval: Option<Duration>,
+  timeout: Option<Duration>,
+}
+
+impl RtmpOutput {
+  /// Push to `url`, including the stream key, with a keyframe every
+  /// [`DEFAULT_KEYFRAME_INTERVAL`].
+  pub fn new<S: AsRef<str>>(url: S) -> Self {
+    Self {
+      url: url.as_ref().to_string(),
+      keyframe_interval: Some(DEFAULT_KEYFRAME_INTERVAL),
+      timeout: None,
+    }
+  }
+
+  /// Force a keyframe every `interval`, or leave the keyframe placement to
+  /// the encoder with `None`. Has no effect on copied streams.
+  pub fn keyframe_interval(mut self, interval: Option<Duration>) -> Self {
+    self.keyframe_interval = interval;
+    self
+  }
+
+  /// Fail when the connection makes no progress for `timeout`
+  /// (`-rw_timeout`), so that a dead server is noticed and the push can be
+  /// restarted.
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// The output arguments, up to and including the URL.
+  pub fn to_args(&self) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(interval) = self.keyframe_interval {
+      args.extend(keyframe_args(interval));
+    }
+    if let Some(timeout) = self.timeout {
+      args.push("-rw_timeout".to_string());
+      args.push(timeout.as_micros().to_string());
+    }
+    args.extend([
+      "-f".to_string(),
+      "flv".to_string(),
+      "-flvflags".to_string(),
+      "no_duration_filesize".to_string(),
+      self.url.clone(),
+    ]);
+    args
+  }
+
+  /// Supervise the push, restarting it whenever it exits. `build` adds the
+  /// input and encoder options to each new command; the output is appended
+  /// afterwards. Configure the retry behavior on the returned supervisor.
+  pub fn supervise<F>(
+    self,
+    build: F,
+  ) -> FfmpegSupervisor<impl FnMut(u32) -> FfmpegCommand + Send + 'static>
+  where
+    F: FnMut(&mut FfmpegCommand) + Send + 'static,
+  {
+    supervise(build, self.to_args())
+  }
+}
+
+/// The connection mode of an SRT socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrtMode {
+  /// Connect to a listening server (the usual case for ingest).
+  Caller,
+  /// Wait for the receiver to connect.
+  Listener,
+  /// Both sides connect to each other at the same time.
+  Rendezvous,
+}
+
+/// A push over SRT, muxed as MPEG-TS.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::streaming::SrtOutput;
+/// use std::time::Duration;
+///
+/// let srt = SrtOutput::new("srt://ingest.example.com:9000")
+///   .latency(Duration::from_millis(200))
+///   .stream_id("live/camera1");
+/// assert_eq!(
+///   srt.url(),
+///   "srt://ingest.example.com:9000?mode=caller&latency=200000&streamid=live/camera1"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrtOutput {
+  address: String,
+  mode: SrtMode,
+  latency: Option<Duration>,
+  timeout: Option<Duration>,
+  stream_id: Option<String>,
+  passphrase: Option<String>,
+  keyframe_interval: Option<Duration>,
+}
+
+impl SrtOutput {
+  /// Push to `address`, e.g. `srt://host:port`, as caller with a keyframe
+  /// every [`DEFAULT_KEYFRAME_INTERVAL`].
+  pub fn new<S: AsRef<str>>(address: S) -> Self {
+    Self {
+      address: address.as_ref().to_string(),
+      mode: SrtMode::Caller,
+      latency: None,
+      timeout: None,
+      stream_id: None,
+      passphrase: None,
+      keyframe_interval: Some(DEFAULT_KEYFRAME_INTERVAL),
+    }
+  }
+
+  /// The connection mode (default: [`SrtMode::Caller`]).
+  pub fn mode(mut self, mode: SrtMode) -> Self {
+    self.mode = mode;
+    self
+  }
+
+  /// The receiver buffer used to recover lost packets. Should be a few
+  /// times the round trip time to the server.
+  pub fn latency(mut self, latency: Duration) -> Self {
+    self.latency = Some(latency);
+    self
+  }
+
+  /// Fail when the connection makes no progress for `timeout`.
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// The stream id, which many servers use to pick the application and key.
+  pub fn stream_id<S: AsRef<str>>(mut self, stream_id: S) -> Self {
+    self.stream_id = Some(stream_id.as_ref().to_string());
+    self
+  }
+
+  /// Encrypt the stream with `passphrase` (10 to 79 characters).
+  pub fn passphrase<S: AsRef<str>>(mut self, passphrase: S) -> Self {
+    self.passphrase = Some(passphrase.as_ref().to_string());
+    self
+  }
+
+  /// Force a keyframe every `interval`, or leave the keyframe placement to
+  /// the encoder with `None`. Has no effect on copied streams.
+  pub fn keyframe_interval(mut self, interval: Option<Duration>) -> Self {
+    self.keyframe_interval = interval;
+    self
+  }
+
+  /// The `srt://` URL including all socket options. Durations are given in
+  /// microseconds, as Ffmpeg expects. The stream id and passphrase are
+  /// percent-encoded.
+  ///
+  /// ```rust
+  /// use async_ffmpeg_sidecar::streaming::SrtOutput;
+  ///
+  /// let srt = SrtOutput::new("srt://ingest.example.com:9000")
+  ///   .stream_id("#!::r=live/camera1,m=publish")
+  ///   .passphrase("correct horse&battery");
+  /// assert_eq!(
+  ///   srt.url(),
+  ///   "srt://ingest.example.com:9000?mode=caller\
+  ///    &streamid=%23%21::r%3Dlive/camera1%2Cm%3Dpublish\
+  ///    &passphrase=correct%20horse%26battery"
+  /// );
+  /// ```
+  pub fn url(&self) -> String {
+    let mode = match self.mode {
+      SrtMode::Caller => "caller",
+      SrtMode::Listener => "listener",
+      SrtMode::Rendezvous => "rendezvous",
+    };
+
+    let mut params = vec![format!("mode={mode}")];
+    if let Some(latency) = self.latency {
+      params.push(format!("latency={}", latency.as_micros()));
+    }
+    if let Some(timeout) = self.timeout {
+      params.push(format!("timeout={}", timeout.as_micros()));
+    }
+    if let Some(stream_id) = &self.stream_id {
+      params.push(format!("streamid={}", percent_encode(stream_id)));
+    }
+    if let Some(passphrase) = &self.passphrase {
+      params.push(format!("passphrase={}", percent_encode(passphrase)));
+    }
+
+    let separator = if self.address.contains('?') { '&' } else { '?' };
+    format!("{}{separator}{}", self.address, params.join("&"))
+  }
+
+  /// The output arguments, up to and including the URL.
+  pub fn to_args(&self) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(interval) = self.keyframe_interval {
+      args.extend(keyframe_args(interval));
+    }
+    args.extend(["-f".to_string(), "mpegts".to_string(), self.url()]);
+    args
+  }
+
+  /// Supervise the push, restarting it whenever it exits, see
+  /// [`RtmpOutput::supervise`].
+  pub fn supervise<F>(
+    self,
+    build: F,
+  ) -> FfmpegSupervisor<impl FnMut(u32) -> FfmpegCommand + Send + 'static>
+  where
+    F: FnMut(&mut FfmpegCommand) + Send + 'static,
+  {
+    supervise(build, self.to_args())
+  }
+}
+
+/// Percent-encode `value` for a URL query, keeping only unreserved
+/// characters, `/` and `:` as is.
+fn percent_encode(value: &str) -> String {
+  let mut encoded = String::with_capacity(value.len());
+  for byte in value.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+        encoded.push(byte as char)
+      }
+      _ => encoded.push_str(&format!("%{byte:02X}")),
+    }
+  }
+  encoded
+}
+
+fn supervise<F>(
+  mut build: F,
+  output_args: Vec<String>,
+) -> FfmpegSupervisor<impl FnMut(u32) -> FfmpegCommand + Send + 'static>
+where
+  F: FnMut(&mut FfmpegCommand) + Send + 'static,
+{
+  FfmpegSupervisor::new(move |_| {
+    let mut command = FfmpegCommand::new();
+    build(&mut command);
+    command.args(&output_args);
+    command
+  })
+}
@@ -0,0 +1,243 @@
+//! Automatic restarts for long-running live jobs.
+//!
+//! Pulling from RTMP servers or RTSP cameras regularly fails: the source
+//! disconnects, the network drops, or Ffmpeg hangs. [`FfmpegSupervisor`]
+//! respawns the job whenever the process exits or stalls, and merges the
+//! events of every run into a single stream.
+
+use crate::command::FfmpegCommand;
+use crate::event::FfmpegEvent;
+use futures_util::{Stream, StreamExt};
+use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// An event of a supervised job, tagged with the generation it belongs to.
+/// The first process is generation `0`, and every restart increments it.
+// Most events are `Event`s, so boxing it wouldn't save any memory.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SupervisorEvent {
+  /// An event emitted by the process of the given generation.
+  Event(u32, FfmpegEvent),
+  /// The process of the given generation exited, or failed to spawn.
+  Exited(u32, Result<ExitStatus, String>),
+  /// The next generation will be spawned after the given delay.
+  Restarting(u32, Duration),
+}
+
+impl SupervisorEvent {
+  /// The generation this event belongs to.
+  pub fn generation(&self) -> u32 {
+    match self {
+      SupervisorEvent::Event(generation, _)
+      | SupervisorEvent::Exited(generation, _)
+      | SupervisorEvent::Restarting(generation, _) => *generation,
+    }
+  }
+}
+
+/// Runs a command over and over, restarting it with exponential backoff.
+///
+/// A fresh command is built for every generation, so inputs and outputs can
+/// change between runs, e.g. to write each run to a new segment file.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::supervisor::{FfmpegSupervisor, SupervisorEvent};
+/// use futures_util::StreamExt;
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let mut events = FfmpegSupervisor::new(|generation| {
+///   let mut command = FfmpegCommand::new();
+///   command
+///     .input("rtsp://camera.local/stream")
+///     .codec_video("copy")
+///     .output(format!("output/camera-{generation}.mkv"));
+///   command
+/// })
+/// .max_restarts(10)
+/// .backoff(Duration::from_secs(1))
+/// .stall_timeout(Duration::from_secs(20))
+/// .spawn();
+///
+/// while let Some(event) = events.next().await {
+///   if let SupervisorEvent::Exited(generation, status) = event {
+///     eprintln!("run {generation} ended: {status:?}");
+///   }
+/// }
+/// # }
+/// ```
+pub struct FfmpegSupervisor<F> {
+  build: F,
+  max_restarts: Option<u32>,
+  backoff: Duration,
+  max_backoff: Duration,
+  stall_timeout: Option<Duration>,
+}
+
+impl<F> FfmpegSupervisor<F>
+where
+  F: FnMut(u32) -> FfmpegCommand + Send + 'static,
+{
+  /// Supervise the commands returned by `build`, which receives the
+  /// generation number. Restarts indefinitely unless limited with
+  /// [`max_restarts`](Self::max_restarts).
+  pub fn new(build: F) -> Self {
+    Self {
+      build,
+      max_restarts: None,
+      backoff: Duration::from_secs(1),
+      max_backoff: Duration::from_secs(60),
+      stall_timeout: None,
+    }
+  }
+
+  /// Give up after `max_restarts` restarts, i.e. after `max_restarts + 1`
+  /// runs in total.
+  pub fn max_restarts(mut self, max_restarts: u32) -> Self {
+    self.max_restarts = Some(max_restarts);
+    self
+  }
+
+  /// Delay before the first restart (default: 1 second). The delay doubles
+  /// with every further restart, up to [`max_backoff`](Self::max_backoff),
+  /// and starts over after a run that reported progress.
+  pub fn backoff(mut self, backoff: Duration) -> Self {
+    self.backoff = backoff;
+    self
+  }
+
+  /// Upper bound of the restart delay (default: 60 seconds).
+  pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+    self.max_backoff = max_backoff;
+    self
+  }
+
+  /// Also restart when no progress is reported for `window`, see
+  /// [`FfmpegChild::with_watchdog`](crate::child::FfmpegChild::with_watchdog).
+  pub fn stall_timeout(mut self, window: Duration) -> Self {
+    self.stall_timeout = Some(window);
+    self
+  }
+
+  /// Start supervising in a background task. Must be called from within a
+  /// tokio runtime.
+  ///
+  /// Dropping the returned stream stops the supervisor and kills the
+  /// current process.
+  pub fn spawn(self) -> impl Stream<Item = SupervisorEvent> + Send + Unpin {
+    let (tx, rx) = mpsc::channel(64);
+    tokio::spawn(self.supervise(tx));
+
+    Box::pin(futures_util::stream::unfold(rx, |mut rx| async move {
+      let event = rx.recv().await?;
+      Some((event, rx))
+    }))
+  }
+
+  async fn supervise(mut self, tx: mpsc::Sender<SupervisorEvent>) {
+    let mut delay = self.backoff;
+
+    for generation in 0.. {
+      let command = (self.build)(generation);
+
+      let Some((result, progressed)) =
+        run_generation(command, self.stall_timeout, generation, &tx).await
+      else {
+        return;
+      };
+      if tx
+        .send(SupervisorEvent::Exited(generation, result))
+        .await
+        .is_err()
+      {
+        return;
+      }
+
+      if self.max_restarts.is_some_and(|max| generation >= max) {
+        return;
+      }
+
+      // The job worked until it failed, so it isn't failing repeatedly
+      if progressed {
+        delay = self.backoff;
+      }
+
+      if tx
+        .send(SupervisorEvent::Restarting(generation + 1, delay))
+        .await
+        .is_err()
+      {
+        return;
+      }
+      tokio::select! {
+        _ = tokio::time::sleep(delay) => {}
+        _ = tx.closed() => return,
+      }
+      delay = (delay * 2).min(self.max_backoff);
+    }
+  }
+}
+
+/// Runs one generation to completion, returning its result and whether it
+/// reported progress. Returns `None` if the supervisor stream was dropped in
+/// the meantime.
+async fn run_generation(
+  mut command: FfmpegCommand,
+  stall_timeout: Option<Duration>,
+  generation: u32,
+  tx: &mpsc::Sender<SupervisorEvent>,
+) -> Option<(Result<ExitStatus, String>, bool)> {
+  let mut child = match command.kill_on_drop(true).spawn() {
+    Ok(child) => child,
+    Err(e) => return Some((Err(e.to_string()), false)),
+  };
+
+  if let Some(window) = stall_timeout {
+    let mut events = match child.with_watchdog(window) {
+      Ok(events) => events,
+      Err(e) => return Some((Err(e.to_string()), false)),
+    };
+    let progressed = forward(&mut events, generation, tx).await?;
+    return Some((events.wait().await.map_err(|e| e.to_string()), progressed));
+  }
+
+  let mut events = match child.stream() {
+    Ok(events) => events,
+    Err(e) => return Some((Err(e.to_string()), false)),
+  };
+  let progressed = forward(&mut events, generation, tx).await?;
+  Some((child.wait().await.map_err(|e| e.to_string()), progressed))
+}
+
+/// Forwards all events of one generation, returning whether one of them was
+/// a progress update, or `None` if the supervisor stream was dropped in the
+/// meantime.
+async fn forward<S>(
+  events: &mut S,
+  generation: u32,
+  tx: &mpsc::Sender<SupervisorEvent>,
+) -> Option<bool>
+where
+  S: Stream<Item = FfmpegEvent> + Unpin,
+{
+  let mut progressed = false;
+  loop {
+    let event = tokio::select! {
+      event = events.next() => event,
+      _ = tx.closed() => return None,
+    };
+
+    let Some(event) = event else {
+      return Some(progressed);
+    };
+    progressed |= matches!(event, FfmpegEvent::Progress(_));
+    tx.send(SupervisorEvent::Event(generation, event))
+      .await
+      .ok()?;
+  }
+}
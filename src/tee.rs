@@ -0,0 +1,157 @@
+//! Writing one encode to several destinations with the `tee` muxer.
+//!
+//! The `tee` muxer takes a single output argument such as
+//! `[f=flv:onfail=ignore]rtmp://live.example.com/app/key|recording.mkv`,
+//! where every branch may carry its own options in brackets. Values are
+//! unescaped twice by Ffmpeg (once when splitting the branches on `|`, once
+//! when parsing the options), which makes the syntax easy to get wrong by
+//! hand. [`TeeOutput`] builds the branches and escapes them correctly.
+//!
+//! Note that the `tee` muxer only receives the streams selected for the
+//! output, so combine it with explicit `-map` arguments when the input has
+//! more streams than the default selection would pick.
+
+/// What happens to the whole output when one branch fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeeOnFail {
+  /// Abort the entire output (Ffmpeg's default).
+  Abort,
+  /// Stop writing to the failed branch and continue with the others.
+  Ignore,
+}
+
+/// One destination of a `tee` output.
+///
+/// ## Example
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::tee::{TeeOnFail, TeeOutput};
+///
+/// let mut command = FfmpegCommand::new();
+/// command
+///   .input("input.mp4")
+///   .codec_video("libx264")
+///   .args(["-map", "0:v", "-map", "0:a"])
+///   .tee_outputs(&[
+///     TeeOutput::new("recording.mkv"),
+///     TeeOutput::new("rtmp://live.example.com/app/key")
+///       .format("flv")
+///       .onfail(TeeOnFail::Ignore),
+///   ])
+///   .unwrap();
+///
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert_eq!(
+///   args[args.len() - 3..],
+///   ["-f", "tee", "recording.mkv|[f=flv:onfail=ignore]rtmp://live.example.com/app/key"]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeeOutput {
+  url: String,
+  options: Vec<(String, String)>,
+}
+
+impl TeeOutput {
+  /// A branch writing to the file or URL `url`.
+  pub fn new<S: AsRef<str>>(url: S) -> Self {
+    Self {
+      url: url.as_ref().to_string(),
+      options: Vec::new(),
+    }
+  }
+
+  /// The container format of this branch (`f=`), e.g. `flv` for RTMP.
+  /// Guessed from the file extension when not set.
+  pub fn format<S: AsRef<str>>(self, format: S) -> Self {
+    self.option("f", format)
+  }
+
+  /// Behavior when this branch fails (`onfail=`).
+  pub fn onfail(self, onfail: TeeOnFail) -> Self {
+    let value = match onfail {
+      TeeOnFail::Abort => "abort",
+      TeeOnFail::Ignore => "ignore",
+    };
+    self.option("onfail", value)
+  }
+
+  /// Only write the streams matching the stream specifier (`select=`),
+  /// e.g. `v` or `a:1`.
+  pub fn select<S: AsRef<str>>(self, specifier: S) -> Self {
+    self.option("select", specifier)
+  }
+
+  /// Bitstream filters to apply to this branch (`bsfs=`), e.g.
+  /// `h264_mp4toannexb`.
+  pub fn bsfs<S: AsRef<str>>(self, filters: S) -> Self {
+    self.option("bsfs", filters)
+  }
+
+  /// Any other muxer option of this branch, such as
+  /// `option("movflags", "+faststart")`.
+  pub fn option<K: AsRef<str>, V: AsRef<str>>(mut self, key: K, value: V) -> Self {
+    self
+      .options
+      .push((key.as_ref().to_string(), value.as_ref().to_string()));
+    self
+  }
+
+  /// The file or URL of this branch.
+  pub fn url(&self) -> &str {
+    &self.url
+  }
+
+  /// This branch in `tee` syntax, escaped for use in [`tee_spec`].
+  fn to_spec(&self) -> String {
+    let mut spec = String::new();
+
+    if !self.options.is_empty() {
+      let options = self
+        .options
+        .iter()
+        .map(|(key, value)| format!("{key}={}", escape(value, ":[]=")))
+        .collect::<Vec<_>>()
+        .join(":");
+      spec.push('[');
+      spec.push_str(&escape(&options, "|"));
+      spec.push(']');
+    }
+
+    spec.push_str(&escape(&self.url, "|"));
+    spec
+  }
+}
+
+/// The argument following `-f tee`, joining the escaped branches with `|`.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::tee::{tee_spec, TeeOutput};
+///
+/// let spec = tee_spec(&[
+///   TeeOutput::new("a|b.mkv"),
+///   TeeOutput::new("udp://239.0.0.1:1234").format("mpegts").select("a:1"),
+/// ]);
+/// assert_eq!(spec, r"a\|b.mkv|[f=mpegts:select=a\\:1]udp://239.0.0.1:1234");
+/// ```
+pub fn tee_spec(outputs: &[TeeOutput]) -> String {
+  outputs
+    .iter()
+    .map(TeeOutput::to_spec)
+    .collect::<Vec<_>>()
+    .join("|")
+}
+
+/// Backslash-escapes quotes, backslashes, whitespace and `specials`, undoing
+/// one level of Ffmpeg's `av_get_token` unescaping.
+pub(crate) fn escape(value: &str, specials: &str) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for c in value.chars() {
+    if c == '\\' || c == '\'' || c.is_whitespace() || specials.contains(c) {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
@@ -77,17 +77,16 @@ async fn test_duration() {
     .stream()
     .unwrap()
     .for_each(|e| {
-      futures::future::ready({
-        if let FfmpegEvent::ParsedDuration(duration) = e {
-          match duration_received {
-            false => {
-              assert_eq!(duration.duration, 5.0);
-              duration_received = true
-            }
-            true => panic!("Received multiple duration events."),
+      if let FfmpegEvent::ParsedDuration(duration) = e {
+        match duration_received {
+          false => {
+            assert_eq!(duration.duration, Some(5.0));
+            duration_received = true
           }
+          true => panic!("Received multiple duration events."),
         }
-      })
+      }
+      futures::future::ready(())
     })
     .await;
 
@@ -116,7 +115,76 @@ async fn test_metadata_duration() {
   let metadata = child.stream().unwrap().collect_metadata().await.unwrap();
   child.kill().await.unwrap();
 
-  assert_eq!(metadata.duration(), Some(5.0))
+  assert_eq!(metadata.duration(), Some(5.0));
+  let input = &metadata.inputs[0];
+  assert!(input.format.contains("mp4"));
+  assert_eq!(input.from, "output/test_metadata_duration.mp4");
+  assert_eq!(input.start_time, Some(0.0));
+  assert!(input.bitrate_kbps.is_some());
+}
+
+#[tokio::test]
+async fn test_probe_many() {
+  use crate::ffprobe::probe_many;
+
+  // Prepare input file
+  FfmpegCommand::new()
+    .args("-f lavfi -i testsrc=duration=5:rate=1 -y output/test_probe_many.mp4".split(' '))
+    .spawn()
+    .unwrap()
+    .stream()
+    .unwrap()
+    .count()
+    .await;
+
+  let inputs = [
+    "output/test_probe_many.mp4",
+    "output/test_probe_many_missing.mp4",
+    "output/test_probe_many.mp4",
+  ];
+  let results = probe_many(inputs, 2).collect::<Vec<_>>().await;
+
+  assert_eq!(results.len(), 3);
+  assert_eq!(results[0].as_ref().unwrap().duration(), Some(5.0));
+  assert_eq!(results[0].as_ref().unwrap().input_streams.len(), 1);
+  assert!(results[1].is_err());
+  assert!(results[2].is_ok());
+}
+
+#[tokio::test]
+async fn test_show_packets() {
+  use crate::ffprobe::FfprobeCommand;
+  use futures_util::TryStreamExt;
+
+  // Prepare input file
+  FfmpegCommand::new()
+    .args("-f lavfi -i testsrc=duration=5:rate=10 -g 10 -y output/test_show_packets.mp4".split(' '))
+    .spawn()
+    .unwrap()
+    .stream()
+    .unwrap()
+    .count()
+    .await;
+
+  let packets = FfprobeCommand::new()
+    .input("output/test_show_packets.mp4")
+    .show_packets()
+    .unwrap()
+    .try_collect::<Vec<_>>()
+    .await
+    .unwrap();
+
+  assert_eq!(packets.len(), 50);
+  assert!(packets.iter().all(|p| p.stream_index == 0 && p.size > 0));
+  assert_eq!(packets.iter().filter(|p| p.is_keyframe()).count(), 5);
+
+  let missing = FfprobeCommand::new()
+    .input("output/test_show_packets_missing.mp4")
+    .show_packets()
+    .unwrap()
+    .try_collect::<Vec<_>>()
+    .await;
+  assert!(missing.is_err());
 }
 
 #[tokio::test]
@@ -154,6 +222,1009 @@ async fn test_quit() {
   assert!(count <= 1);
 }
 
+#[tokio::test]
+async fn test_quit_with_timeout() {
+  let mut child = FfmpegCommand::new().testsrc().rawvideo().spawn().unwrap();
+  let drain = tokio::spawn(child.stream().unwrap().count());
+
+  let outcome = child
+    .quit_with_timeout(std::time::Duration::from_secs(5))
+    .await
+    .unwrap();
+
+  assert!(!outcome.was_killed());
+  drain.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_kill_on_drop() {
+  let mut child = FfmpegCommand::new()
+    .testsrc()
+    .rawvideo()
+    .kill_on_drop(true)
+    .spawn()
+    .unwrap();
+  assert!(child.kills_on_drop());
+  let stream = child.stream().unwrap();
+  drop(child);
+
+  // The killed process closes stderr, ending the stream well before the
+  // ten seconds of testsrc are encoded.
+  let events = tokio::time::timeout(std::time::Duration::from_secs(5), stream.count()).await;
+  assert!(events.is_ok());
+}
+
+#[tokio::test]
+async fn test_watchdog_stalled() {
+  // Reading from an idle stdin never produces any progress
+  let child = FfmpegCommand::new()
+    .format("s16le")
+    .input("pipe:0")
+    .format("null")
+    .output("-")
+    .spawn()
+    .unwrap();
+
+  let mut events = child
+    .with_watchdog(std::time::Duration::from_millis(500))
+    .unwrap();
+  let stalled = (&mut events)
+    .any(|e| futures::future::ready(matches!(e, FfmpegEvent::Stalled(_))))
+    .await;
+
+  assert!(stalled);
+  assert!(events.is_stalled());
+  assert!(!events.wait().await.unwrap().success());
+}
+
+#[tokio::test]
+async fn test_timeout() {
+  let mut child = FfmpegCommand::new()
+    .testsrc()
+    .rawvideo()
+    .timeout(std::time::Duration::from_millis(200))
+    .spawn()
+    .unwrap();
+
+  let events = child.stream().unwrap().collect::<Vec<_>>().await;
+  assert!(matches!(events.last(), Some(FfmpegEvent::TimedOut(_))));
+  assert!(!child.wait().await.unwrap().success());
+}
+
+#[tokio::test]
+async fn test_timeout_kills_without_wait() {
+  let mut child = FfmpegCommand::new()
+    .testsrc()
+    .rawvideo()
+    .timeout(std::time::Duration::from_millis(200))
+    .spawn()
+    .unwrap();
+
+  child.stream().unwrap().count().await;
+
+  // Poll without waiting on the child, which would kill it as well
+  let mut exited = None;
+  for _ in 0..50 {
+    exited = child.as_inner_mut().try_wait().unwrap();
+    if exited.is_some() {
+      break;
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+  }
+  assert!(!exited.expect("ffmpeg is still running").success());
+}
+
+#[tokio::test]
+async fn test_terminate_group() {
+  let mut child = FfmpegCommand::new()
+    .testsrc()
+    .rawvideo()
+    .process_group()
+    .spawn()
+    .unwrap();
+
+  child.terminate_group().unwrap();
+  let count = child.stream().unwrap().filter_progress().count().await;
+
+  assert!(count <= 1);
+  assert!(!child.wait().await.unwrap().success());
+}
+
+#[tokio::test]
+async fn test_wait_with_events() {
+  let summary = FfmpegCommand::new()
+    .testsrc()
+    .rawvideo()
+    .spawn()
+    .unwrap()
+    .wait_with_events()
+    .await
+    .unwrap();
+
+  assert!(summary.exit_status.success());
+  assert!(summary.outcome.is_success());
+  assert!(summary.metadata.is_completed());
+  assert!(summary.errors.is_empty());
+  assert!(summary.last_progress.unwrap().frame > 0);
+}
+
+#[tokio::test]
+async fn test_no_stdin() {
+  let mut child = FfmpegCommand::new()
+    .no_stdin()
+    .testsrc()
+    .rawvideo()
+    .spawn()
+    .unwrap();
+
+  let error = child.quit().await.unwrap_err();
+  assert_eq!(
+    error.downcast_ref::<crate::child::MissingChannel>(),
+    Some(&crate::child::MissingChannel::Stdin)
+  );
+
+  child.kill().await.unwrap();
+  child.wait().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_supervisor_restarts() {
+  use crate::supervisor::{FfmpegSupervisor, SupervisorEvent};
+  use std::time::Duration;
+
+  let events = FfmpegSupervisor::new(|_| {
+    let mut command = FfmpegCommand::new();
+    command
+      .args(["-f", "lavfi", "-i", "testsrc=duration=1"])
+      .rawvideo();
+    command
+  })
+  .max_restarts(1)
+  .backoff(Duration::from_millis(10))
+  .spawn()
+  .collect::<Vec<_>>()
+  .await;
+
+  let exits = events
+    .iter()
+    .filter_map(|event| match event {
+      SupervisorEvent::Exited(generation, status) => Some((*generation, status.is_ok())),
+      _ => None,
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(exits, vec![(0, true), (1, true)]);
+  assert!(events
+    .iter()
+    .any(|event| matches!(event, SupervisorEvent::Restarting(1, _))));
+}
+
+#[tokio::test]
+async fn test_supervisor_backoff() {
+  use crate::supervisor::{FfmpegSupervisor, SupervisorEvent};
+  use std::time::Duration;
+
+  let restart_delays = |events: Vec<SupervisorEvent>| {
+    events
+      .into_iter()
+      .filter_map(|event| match event {
+        SupervisorEvent::Restarting(_, delay) => Some(delay.as_millis()),
+        _ => None,
+      })
+      .collect::<Vec<_>>()
+  };
+
+  // Failing to spawn never makes progress, so the delay keeps doubling
+  let failing = FfmpegSupervisor::new(|_| FfmpegCommand::new_with_path("output/no-such-ffmpeg"))
+    .max_restarts(3)
+    .backoff(Duration::from_millis(1))
+    .spawn()
+    .collect::<Vec<_>>()
+    .await;
+  assert_eq!(restart_delays(failing), [1, 2, 4]);
+
+  // Every run makes progress, so the delay starts over each time
+  let progressing = FfmpegSupervisor::new(|_| {
+    let mut command = FfmpegCommand::new();
+    command
+      .args(["-f", "lavfi", "-i", "testsrc=duration=1"])
+      .rawvideo();
+    command
+  })
+  .max_restarts(2)
+  .backoff(Duration::from_millis(10))
+  .spawn()
+  .collect::<Vec<_>>()
+  .await;
+  assert_eq!(restart_delays(progressing), [10, 10]);
+}
+
+#[tokio::test]
+async fn test_pool_progress_and_cancel() {
+  use crate::pool::{FfmpegJobPool, FfmpegJobState};
+
+  let pool = FfmpegJobPool::new(1);
+
+  let mut first = FfmpegCommand::new();
+  first
+    .args(["-f", "lavfi", "-i", "testsrc=duration=1"])
+    .rawvideo();
+  let first = pool.submit(first).unwrap();
+
+  let mut second = FfmpegCommand::new();
+  second.testsrc().rawvideo();
+  let second = pool.submit(second).unwrap();
+  assert_eq!(second.status().borrow().state, FfmpegJobState::Queued);
+
+  second.cancel();
+  assert!(second.wait().await.is_err());
+  assert!(first.wait().await.unwrap().success());
+
+  let progress = pool.progress();
+  assert_eq!(progress.finished, 2);
+  assert_eq!(progress.fraction, 1.0);
+}
+
+#[tokio::test]
+async fn test_pool_progress_forgets_finished_jobs() {
+  use crate::pool::FfmpegJobPool;
+
+  let pool = FfmpegJobPool::new(2);
+  for _ in 0..3 {
+    // Fails to spawn, finishing right away
+    let job = pool
+      .submit(FfmpegCommand::new_with_path("output/no-such-ffmpeg"))
+      .unwrap();
+    assert!(job.wait().await.is_err());
+  }
+
+  // Finished jobs are counted, but no longer weigh on the average
+  let progress = pool.progress();
+  assert_eq!(progress.finished, 3);
+  assert_eq!(progress.queued + progress.running, 0);
+  assert_eq!(progress.fraction, 1.0);
+}
+
+#[test]
+fn test_video_filter_conflict() {
+  let mut filter = FfmpegCommand::new();
+  filter
+    .testsrc()
+    .filter("hflip")
+    .scale(640, -2)
+    .output("output/test_video_filter_conflict.mp4");
+  let error = filter.spawn().err().unwrap();
+  assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+
+  let mut raw = FfmpegCommand::new();
+  raw
+    .testsrc()
+    .args(["-vf", "hflip"])
+    .fps(30.0)
+    .output("output/test_video_filter_conflict.mp4");
+  assert!(raw.spawn().is_err());
+
+  // A raw filter of a previous output doesn't conflict
+  let mut separate = FfmpegCommand::new_with_path("output/no-such-ffmpeg");
+  separate
+    .testsrc()
+    .args(["-vf", "hflip"])
+    .output("output/test_video_filter_conflict_1.mp4")
+    .scale(640, -2)
+    .output("output/test_video_filter_conflict_2.mp4");
+  let error = separate.spawn().err().unwrap();
+  assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn test_recorder_split() {
+  use crate::recorder::{Recorder, RecorderEvent};
+  use std::path::PathBuf;
+  use std::time::Duration;
+
+  let mut recorder = Recorder::builder(
+    |command| {
+      command.testsrc().codec_video("mpeg4");
+    },
+    |index| PathBuf::from(format!("output/test_recorder_{index}.mkv")),
+  )
+  .rotate_every(Duration::from_secs(30))
+  .spawn();
+
+  tokio::time::sleep(Duration::from_millis(500)).await;
+  recorder.split_now();
+  tokio::time::sleep(Duration::from_millis(500)).await;
+  recorder.stop().await.unwrap();
+
+  let mut finished = Vec::new();
+  while let Some(event) = recorder.next_event().await {
+    if let RecorderEvent::FileFinished { path, result } = event {
+      assert!(result.unwrap().success());
+      finished.push(path);
+    }
+  }
+  assert_eq!(
+    finished,
+    [
+      PathBuf::from("output/test_recorder_0.mkv"),
+      PathBuf::from("output/test_recorder_1.mkv")
+    ]
+  );
+}
+
+#[tokio::test]
+async fn test_recorder_failure() {
+  use crate::recorder::{Recorder, RecorderEvent};
+  use std::path::PathBuf;
+
+  let mut recorder = Recorder::builder(
+    |command| {
+      command.input("output/no-such-input.mkv");
+    },
+    |index| PathBuf::from(format!("output/test_recorder_failure_{index}.mkv")),
+  )
+  .spawn();
+
+  // The process fails on its own, which ends the recording
+  assert!(matches!(
+    recorder.next_event().await,
+    Some(RecorderEvent::FileStarted(_))
+  ));
+  assert!(matches!(
+    recorder.next_event().await,
+    Some(RecorderEvent::FileFinished { result: Ok(status), .. }) if !status.success()
+  ));
+  assert!(recorder.stop().await.is_err());
+}
+
+#[tokio::test]
+async fn test_normalize_loudness() {
+  use crate::audio::normalize_loudness;
+
+  let input = "output/test_loudnorm_input.wav";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=5"])
+    .arg("-filter:a")
+    .arg("volume=-20dB")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let stats = normalize_loudness(input, "output/test_loudnorm_output.wav", -16.0)
+    .await
+    .unwrap();
+  assert!((stats.output_i + 16.0).abs() < 1.0);
+}
+
+#[tokio::test]
+async fn test_detect_silence() {
+  use crate::audio::detect_silence;
+  use std::time::Duration;
+
+  let input = "output/test_silence_input.wav";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=4"])
+    .arg("-filter:a")
+    .arg("volume=enable='between(t,1,2)':volume=0")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let silences = detect_silence(input, -50.0, Duration::from_millis(500))
+    .unwrap()
+    .collect::<Vec<_>>()
+    .await;
+  assert_eq!(silences.len(), 1);
+  let silence = silences[0].as_ref().unwrap();
+  assert!((silence.start - 1.0).abs() < 0.1);
+  assert!((silence.end - 2.0).abs() < 0.1);
+}
+
+#[tokio::test]
+async fn test_detect_scenes() {
+  use crate::video::detect_scenes;
+
+  let input = "output/test_scenes_input.mkv";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "color=c=black:s=320x240:d=1"])
+    .args(["-f", "lavfi", "-i", "color=c=white:s=320x240:d=1"])
+    .filter_complex("[0:v][1:v]concat=n=2:v=1")
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let scenes = detect_scenes(input, 0.4).unwrap().collect::<Vec<_>>().await;
+  assert_eq!(scenes.len(), 1);
+  let scene = scenes[0].as_ref().unwrap();
+  assert!((scene.time - 1.0).abs() < 0.1);
+  assert!(scene.score > 0.4);
+}
+
+#[tokio::test]
+async fn test_detect_black() {
+  use crate::video::detect_black;
+  use std::time::Duration;
+
+  let input = "output/test_black_input.mkv";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "color=c=black:s=320x240:d=1"])
+    .args(["-f", "lavfi", "-i", "testsrc=s=320x240:d=1"])
+    .filter_complex("[0:v][1:v]concat=n=2:v=1")
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let intervals = detect_black(input, Duration::from_millis(500), 0.1)
+    .unwrap()
+    .collect::<Vec<_>>()
+    .await;
+  assert_eq!(intervals.len(), 1);
+  let interval = intervals[0].as_ref().unwrap();
+  assert_eq!(interval.start, 0.0);
+  assert!((interval.end - 1.0).abs() < 0.1);
+}
+
+#[tokio::test]
+async fn test_detect_crop() {
+  use crate::video::{detect_crop, CropRect};
+  use std::time::Duration;
+
+  let input = "output/test_crop_input.mkv";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "testsrc=s=320x160:d=2"])
+    .pad(320, 240, 0, 40)
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let rect = detect_crop(input, Duration::from_secs(1)).await.unwrap();
+  assert_eq!(
+    rect,
+    CropRect {
+      width: 320,
+      height: 160,
+      x: 0,
+      y: 40
+    }
+  );
+}
+
+#[tokio::test]
+async fn test_detect_volume() {
+  use crate::audio::detect_volume;
+
+  let input = "output/test_volume_input.wav";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=1"])
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let stats = detect_volume(input).await.unwrap();
+  assert!(stats.n_samples > 0);
+  assert!(stats.max_volume > stats.mean_volume);
+  assert!(!stats.histogram.is_empty());
+}
+
+#[tokio::test]
+async fn test_quality_compare() {
+  use crate::quality::{compare, QualityMetric};
+
+  let reference = "output/test_quality_reference.mkv";
+  let distorted = "output/test_quality_distorted.mkv";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "testsrc=duration=1"])
+    .codec_video("ffv1")
+    .overwrite()
+    .output(reference);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+  let mut command = FfmpegCommand::new();
+  command
+    .input(reference)
+    .codec_video("mpeg4")
+    .arg("-q:v")
+    .arg("10")
+    .overwrite()
+    .output(distorted);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let report = compare(
+    reference,
+    distorted,
+    &[QualityMetric::Psnr, QualityMetric::Ssim],
+  )
+  .await
+  .unwrap();
+  assert!(report.psnr.unwrap().average > 20.0);
+  assert!(report.ssim.unwrap().all > 0.5);
+  assert!(report.vmaf.is_none());
+}
+
+#[tokio::test]
+async fn test_storyboard() {
+  use crate::storyboard::Storyboard;
+  use std::path::PathBuf;
+  use std::time::Duration;
+
+  let input = "output/test_storyboard_input.mkv";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "testsrc=s=320x240:d=3"])
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let storyboard = Storyboard::new(input, "output/test_storyboard_%02d.jpg")
+    .tile(2, 2)
+    .interval(Duration::from_secs(1))
+    .webvtt("output/test_storyboard.vtt")
+    .generate()
+    .await
+    .unwrap();
+  assert_eq!(
+    storyboard.sheets,
+    [PathBuf::from("output/test_storyboard_01.jpg")]
+  );
+  assert_eq!(storyboard.cues.len(), 3);
+  assert_eq!((storyboard.cues[1].x, storyboard.cues[1].y), (160, 0));
+  assert_eq!(storyboard.cues[2].height, 120);
+
+  let vtt = tokio::fs::read_to_string("output/test_storyboard.vtt")
+    .await
+    .unwrap();
+  assert!(vtt.contains("00:00:01.000 --> 00:00:02.000\ntest_storyboard_01.jpg#xywh=160,0,160,120"));
+}
+
+#[tokio::test]
+async fn test_gif_preview() {
+  use crate::video::gif_preview;
+  use std::time::Duration;
+
+  let input = "output/test_gif_input.mkv";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "testsrc=s=320x240:d=3"])
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let progress = gif_preview(
+    input,
+    "output/test_preview.gif",
+    Duration::from_secs(1),
+    Duration::from_secs(1),
+    10.0,
+    160,
+  )
+  .collect::<Vec<_>>()
+  .await;
+  let fractions = progress
+    .into_iter()
+    .collect::<anyhow::Result<Vec<_>>>()
+    .unwrap();
+  assert_eq!(fractions.last(), Some(&1.0));
+  assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]));
+  assert!(std::path::Path::new("output/test_preview.gif").exists());
+}
+
+#[tokio::test]
+async fn test_waveform_peaks() {
+  use crate::audio::waveform_peaks;
+
+  let input = "output/test_waveform_input.wav";
+  let mut command = FfmpegCommand::new();
+  command
+    .args([
+      "-f",
+      "lavfi",
+      "-i",
+      "sine=frequency=440:sample_rate=8000:duration=1",
+    ])
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let waveform = waveform_peaks(input, 100).await.unwrap();
+  assert_eq!(waveform.sample_rate, 8000);
+  assert_eq!(waveform.peaks.len(), 80);
+  assert!(waveform.peaks.iter().all(|(min, max)| min < &0 && max > &0));
+}
+
+#[tokio::test]
+async fn test_spectrogram() {
+  use crate::audio::Spectrogram;
+
+  let input = "output/test_spectrogram_input.wav";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=2"])
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let spectrogram = Spectrogram::new(input).size(320, 160);
+  let fractions = spectrogram
+    .write("output/test_spectrogram.png")
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .collect::<anyhow::Result<Vec<_>>>()
+    .unwrap();
+  assert_eq!(fractions.last(), Some(&1.0));
+
+  let png = spectrogram.legend(false).to_bytes().await.unwrap();
+  assert!(png.starts_with(b"\x89PNG"));
+}
+
+#[tokio::test]
+async fn test_extract_audio_track() {
+  use crate::audio::{extract_track, AudioFormat};
+
+  let input = "output/test_extract_input.mkv";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "testsrc=duration=1"])
+    .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=1"])
+    .args(["-f", "lavfi", "-i", "sine=frequency=880:duration=1"])
+    .args(["-map", "0", "-map", "1", "-map", "2"])
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let summary = extract_track(input, "output/test_extract.wav", AudioFormat::Wav, 1)
+    .await
+    .unwrap();
+  assert_eq!(summary.metadata.output_streams.len(), 1);
+  assert!(summary.metadata.output_streams[0].is_audio());
+}
+
+#[tokio::test]
+async fn test_burn_subtitles() {
+  let subtitles = std::env::temp_dir().join("async-ffmpeg-sidecar-burn-subtitles.srt");
+  std::fs::write(&subtitles, "1\n00:00:00,000 --> 00:00:01,000\nHello\n").unwrap();
+
+  let mut command = FfmpegCommand::new();
+  command
+    .testsrc()
+    .burn_subtitles(subtitles.to_str().unwrap())
+    .unwrap()
+    .overwrite()
+    .output("output/test_burn_subtitles.mp4");
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+}
+
+#[tokio::test]
+async fn test_overlay() {
+  use crate::command::OverlayPosition;
+
+  let image = "output/test_overlay_logo.png";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "color=red:size=64x64"])
+    .frames(1)
+    .overwrite()
+    .output(image);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let mut command = FfmpegCommand::new();
+  command
+    .testsrc()
+    .overlay(image, OverlayPosition::BottomRight, 0.5, 0.5)
+    .unwrap()
+    .overlay(image, OverlayPosition::Offset { x: 10, y: 10 }, 1.0, 1.0)
+    .unwrap()
+    .overwrite()
+    .output("output/test_overlay.mp4");
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+}
+
+#[tokio::test]
+async fn test_mix_audio() {
+  use crate::audio::AudioMix;
+
+  let mix = AudioMix::new().input(0, 1.0).input(1, 0.5).duck(0);
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "sine=frequency=440:duration=1"])
+    .args(["-f", "lavfi", "-i", "sine=frequency=220:duration=1"])
+    .mix_audio(&mix)
+    .unwrap()
+    .overwrite()
+    .output("output/test_mix_audio.wav");
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+}
+
+#[tokio::test]
+async fn test_compose() {
+  use crate::command::OverlayPosition;
+  use crate::compose::{Composition, Layout};
+
+  let input = "output/test_compose_input.mp4";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "testsrc=duration=1"])
+    .args(["-f", "lavfi", "-i", "sine=duration=1"])
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let pip = Composition::new(Layout::PictureInPicture {
+    position: OverlayPosition::TopRight,
+    width: 80,
+    margin: 10,
+  })
+  .input(input)
+  .input(input);
+  let grid = Composition::new(Layout::Grid {
+    columns: 2,
+    rows: 2,
+    cell_width: 160,
+    cell_height: 120,
+  })
+  .input(input)
+  .input(input)
+  .input(input)
+  .no_audio();
+
+  for (composition, output) in [
+    (pip, "output/test_compose_pip.mp4"),
+    (grid, "output/test_compose_grid.mp4"),
+  ] {
+    let mut command = FfmpegCommand::new();
+    command
+      .compose(&composition)
+      .unwrap()
+      .codec_video("mpeg4")
+      .overwrite()
+      .output(output);
+    assert!(command.spawn().unwrap().wait().await.unwrap().success());
+  }
+}
+
+#[tokio::test]
+async fn test_is_hdr() {
+  use crate::video::{is_hdr, tonemap_hdr_to_sdr, Tonemapper};
+
+  let sdr = "output/test_is_hdr_sdr.mkv";
+  let hdr = "output/test_is_hdr_pq.mkv";
+  for (output, transfer) in [(sdr, "bt709"), (hdr, "smpte2084")] {
+    let mut command = FfmpegCommand::new();
+    command
+      .testsrc()
+      .codec_video("mpeg4")
+      .args(["-color_trc", transfer])
+      .overwrite()
+      .output(output);
+    assert!(command.spawn().unwrap().wait().await.unwrap().success());
+  }
+
+  assert!(!is_hdr(sdr).await.unwrap());
+  assert!(is_hdr(hdr).await.unwrap());
+  assert!(
+    tonemap_hdr_to_sdr(sdr, "output/test_is_hdr_out.mp4", Tonemapper::Hable)
+      .await
+      .is_err()
+  );
+}
+
+#[tokio::test]
+async fn test_cut() {
+  use crate::video::{cut, cut_with, keyframes, CutMode};
+  use std::time::Duration;
+
+  let input = "output/test_cut_input.mp4";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "testsrc=duration=4:rate=25"])
+    .codec_video("mpeg4")
+    .args(["-g", "25"])
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let keyframes = keyframes(input).await.unwrap();
+  assert_eq!(keyframes.len(), 4);
+
+  let ranges = [
+    Duration::from_millis(500)..Duration::from_millis(1500),
+    Duration::from_millis(2500)..Duration::from_millis(3500),
+  ];
+  cut(input, &ranges, "output/test_cut.mp4").await.unwrap();
+  cut_with(
+    input,
+    &ranges,
+    "output/test_cut_precise.mp4",
+    CutMode::ReencodeBoundaries("mpeg4".to_string()),
+  )
+  .await
+  .unwrap();
+}
+
+#[tokio::test]
+async fn test_validate() {
+  use crate::integrity::{is_valid, validate_summary};
+
+  let valid = "output/test_validate.mp4";
+  let mut command = FfmpegCommand::new();
+  command
+    .testsrc()
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(valid);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let corrupt = "output/test_validate_corrupt.mp4";
+  let mut bytes = std::fs::read(valid).unwrap();
+  let len = bytes.len();
+  for byte in &mut bytes[len / 3..len / 2] {
+    *byte = 0xff;
+  }
+  std::fs::write(corrupt, bytes).unwrap();
+
+  let summary = validate_summary(valid).await.unwrap();
+  assert!(summary.is_valid());
+  assert!(is_valid(valid).await.unwrap());
+
+  let summary = validate_summary(corrupt).await.unwrap();
+  assert!(!summary.is_valid());
+  assert!(!summary.corrupt_regions.is_empty());
+  assert!(!is_valid(corrupt).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_hashes() {
+  use crate::integrity::{frame_hashes, stream_hashes};
+
+  let input = "output/test_hashes.mkv";
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "lavfi", "-i", "testsrc=duration=1"])
+    .codec_video("ffv1")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+
+  let frames = frame_hashes(input).await.unwrap();
+  assert_eq!(frames.len(), 25);
+  assert_eq!(frames, frame_hashes(input).await.unwrap());
+
+  let streams = stream_hashes(input).await.unwrap();
+  assert_eq!(streams.len(), 1);
+  assert_eq!(streams[0].media_type, "v");
+}
+
+#[tokio::test]
+async fn test_faststart() {
+  use crate::video::{faststart, is_faststart};
+
+  let input = "output/test_faststart.mp4";
+  let mut command = FfmpegCommand::new();
+  command
+    .testsrc()
+    .codec_video("mpeg4")
+    .overwrite()
+    .output(input);
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+  assert!(!is_faststart(input).await.unwrap());
+
+  let output = "output/test_faststart_copy.mp4";
+  let progress = faststart(input, Some(output)).collect::<Vec<_>>().await;
+  assert_eq!(*progress.last().unwrap().as_ref().unwrap(), 1.0);
+  assert!(is_faststart(output).await.unwrap());
+  assert!(!is_faststart(input).await.unwrap());
+
+  let progress = faststart(input, None).collect::<Vec<_>>().await;
+  assert!(progress.iter().all(|fraction| fraction.is_ok()));
+  assert!(is_faststart(input).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_image_sequence() {
+  let mut child = FfmpegCommand::new()
+    .testsrc()
+    .frames(3)
+    .start_number(10)
+    .output_image_sequence("output/test_image_sequence_%03d.png")
+    .unwrap()
+    .overwrite()
+    .spawn()
+    .unwrap();
+  let images = child
+    .stream()
+    .unwrap()
+    .filter_map(|event| async move {
+      match event {
+        FfmpegEvent::OutputImageWritten(image) => Some(image.path),
+        _ => None,
+      }
+    })
+    .collect::<Vec<_>>()
+    .await;
+  assert_eq!(
+    images,
+    [
+      "output/test_image_sequence_010.png",
+      "output/test_image_sequence_011.png",
+      "output/test_image_sequence_012.png",
+    ]
+  );
+  assert!(std::path::Path::new(&images[2]).exists());
+
+  let mut command = FfmpegCommand::new();
+  command
+    .start_number(10)
+    .input_image_sequence("output/test_image_sequence_%03d.png", 1.0)
+    .codec_video("mpeg4")
+    .overwrite()
+    .output("output/test_image_sequence.mp4");
+  assert!(command.spawn().unwrap().wait().await.unwrap().success());
+}
+
+#[tokio::test]
+async fn test_timelapse() {
+  use crate::image_sequence::timelapse;
+
+  let dir = "output/test_timelapse";
+  std::fs::create_dir_all(dir).unwrap();
+  for (name, size) in [
+    ("a.jpg", "320x240"),
+    ("b.png", "240x320"),
+    ("c.jpg", "640x360"),
+  ] {
+    let mut command = FfmpegCommand::new();
+    command
+      .args(["-f", "lavfi", "-i", &format!("testsrc=size={size}")])
+      .frames(1)
+      .overwrite()
+      .output(format!("{dir}/{name}"));
+    assert!(command.spawn().unwrap().wait().await.unwrap().success());
+  }
+
+  let progress = timelapse(dir, 2.0, "output/test_timelapse.mp4")
+    .collect::<Vec<_>>()
+    .await;
+  assert_eq!(*progress.last().unwrap().as_ref().unwrap(), 1.0);
+}
+
+#[tokio::test]
+async fn test_encode_summary() {
+  let summaries = FfmpegCommand::new()
+    .testsrc()
+    .frames(10)
+    .codec_video("mpeg4")
+    .overwrite()
+    .output("output/test_encode_summary.mp4")
+    .spawn()
+    .unwrap()
+    .stream()
+    .unwrap()
+    .filter_map(|event| async move {
+      match event {
+        FfmpegEvent::EncodeSummary(summary) => Some(summary),
+        _ => None,
+      }
+    })
+    .collect::<Vec<_>>()
+    .await;
+  assert_eq!(summaries.len(), 1);
+  assert!(summaries[0].video_kb > 0);
+  assert_eq!(summaries[0].audio_kb, 0);
+  assert!(summaries[0].muxing_overhead_pct.is_some());
+}
+
 // #[tokio::test]
 // async fn test_overwrite_fallback() -> anyhow::Result<()> {
 //   let output_path = "output/test_overwrite_fallback.jpg";
@@ -215,3 +1286,58 @@ async fn test_no_overwrite() -> anyhow::Result<()> {
 
   Ok(())
 }
+
+#[cfg(feature = "download_ffmpeg")]
+#[tokio::test]
+async fn test_remove_ffmpeg_from() -> anyhow::Result<()> {
+  use crate::download::{remove_ffmpeg_from, UNPACK_DIRNAME};
+
+  let dir = std::env::temp_dir().join("async-ffmpeg-sidecar-remove-ffmpeg-from");
+  let dir = dir.as_path();
+  let binary = dir.join(if cfg!(windows) {
+    "ffmpeg.exe"
+  } else {
+    "ffmpeg"
+  });
+  tokio::fs::create_dir_all(dir.join(UNPACK_DIRNAME)).await?;
+  tokio::fs::write(&binary, b"").await?;
+  tokio::fs::write(dir.join("unrelated.txt"), b"").await?;
+
+  let removed = remove_ffmpeg_from(dir).await?;
+
+  assert_eq!(removed.len(), 2);
+  assert!(!binary.exists());
+  assert!(!dir.join(UNPACK_DIRNAME).exists());
+  assert!(dir.join("unrelated.txt").exists());
+
+  tokio::fs::remove_dir_all(dir).await?;
+  Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_tee_to_log_file_write_error() -> anyhow::Result<()> {
+  use crate::event::LogLevel;
+  use crate::stream::FfmpegEventStream;
+
+  // Every write to `/dev/full` fails with "No space left on device"
+  let log = "[info] Input #0, lavfi, from 'testsrc':\n[error] Conversion failed!\n";
+  let events = FfmpegEventStream::from_reader(log.as_bytes())
+    .tee_to_log_file("/dev/full")
+    .await?
+    .collect::<Vec<_>>()
+    .await;
+
+  let warnings = events
+    .iter()
+    .filter(|event| {
+      matches!(event, FfmpegEvent::Log(LogLevel::Warning, message)
+        if message.contains("/dev/full"))
+    })
+    .count();
+  assert_eq!(warnings, 1);
+  // The log is still parsed in full
+  assert_eq!(events.len(), 3);
+
+  Ok(())
+}
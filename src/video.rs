@@ -0,0 +1,928 @@
+//! Video analysis and preview workflows.
+
+use crate::command::{BackgroundCommand, FfmpegCommand};
+use crate::event::{BlackInterval, FfmpegEvent};
+use crate::ffprobe::ffprobe_path;
+use crate::log_parser::{
+  try_parse_black_frame, try_parse_crop_suggestion, try_parse_frame_pts_time, try_parse_scene_score,
+};
+use crate::outcome::FfmpegJobSummary;
+use crate::progress::ByteProgress;
+use crate::stream::spawn_parsed;
+use anyhow::Context;
+use futures_util::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// A frame of the input that differs from the previous one by more than the
+/// threshold passed to [`detect_scenes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneChange {
+  /// The presentation time of the frame, in seconds
+  pub time: f64,
+  /// How much the frame differs from the previous one, from 0 to 1
+  pub score: f64,
+}
+
+/// Find the scene changes of the video of `input`, i.e. the frames whose
+/// scene score is above `threshold` (from 0 to 1, typically `0.3` to
+/// `0.5`).
+///
+/// Frames are picked by `select='gt(scene,threshold)'` and their time and
+/// score are printed by `metadata=print`. Scene changes are streamed as they
+/// are found; if Ffmpeg fails, the stream ends with the error. Dropping the
+/// stream stops the analysis.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::video::detect_scenes;
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut scenes = detect_scenes("movie.mp4", 0.4)?;
+/// while let Some(scene) = scenes.next().await {
+///   let scene = scene?;
+///   println!("cut at {}s (score {})", scene.time, scene.score);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn detect_scenes<S: AsRef<str>>(
+  input: S,
+  threshold: f64,
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<SceneChange>> + Send + Unpin> {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .filter_video(format!(
+      "select='gt(scene,{threshold})',metadata=print:key=lavfi.scene_score"
+    ))
+    .fps_mode("vfr")
+    .no_audio()
+    .format("null")
+    .output("-");
+
+  let mut time = None;
+  spawn_parsed(command, move |event| {
+    let FfmpegEvent::Log(_, line) = event else {
+      return None;
+    };
+    if let Some(pts_time) = try_parse_frame_pts_time(&line) {
+      time = Some(pts_time);
+      return None;
+    }
+    let score = try_parse_scene_score(&line)?;
+    Some(SceneChange {
+      time: time.take()?,
+      score,
+    })
+  })
+}
+
+/// Find the parts of the video of `input` that are black for at least
+/// `min_duration`, using the `blackdetect` filter. A pixel counts as black
+/// when its luminance is below `pixel_threshold` (from 0 to 1, Ffmpeg
+/// defaults to `0.1`).
+///
+/// The intervals are also reported as
+/// [`FfmpegEvent::BlackInterval`] by any command using `blackdetect`. They
+/// are streamed as they are found; if Ffmpeg fails, the stream ends with the
+/// error. Dropping the stream stops the analysis.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::video::detect_black;
+/// use futures_util::StreamExt;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut intervals = detect_black("broadcast.ts", Duration::from_millis(500), 0.1)?;
+/// while let Some(interval) = intervals.next().await {
+///   let interval = interval?;
+///   println!("black from {} to {}", interval.start, interval.end);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn detect_black<S: AsRef<str>>(
+  input: S,
+  min_duration: Duration,
+  pixel_threshold: f64,
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<BlackInterval>> + Send + Unpin> {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .filter_video(format!(
+      "blackdetect=d={}:pix_th={pixel_threshold}",
+      min_duration.as_secs_f64()
+    ))
+    .no_audio()
+    .format("null")
+    .output("-");
+
+  spawn_parsed(command, |event| match event {
+    FfmpegEvent::BlackInterval(interval) => Some(interval),
+    _ => None,
+  })
+}
+
+/// A frame reported by [`detect_black_frames`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlackFrame {
+  /// The index of the frame
+  pub frame: u32,
+  /// The percentage of black pixels in the frame
+  pub percent_black: u32,
+  /// The presentation time of the frame, in seconds
+  pub time: f64,
+}
+
+/// Find the single frames of the video of `input` of which at least
+/// `amount` percent of the pixels are darker than `threshold` (from 0 to
+/// 255), using the `blackframe` filter. Unlike [`detect_black`], this also
+/// catches isolated black frames, e.g. at the cuts of an ad break.
+///
+/// Frames are streamed as they are found; if Ffmpeg fails, the stream ends
+/// with the error. Dropping the stream stops the analysis.
+pub fn detect_black_frames<S: AsRef<str>>(
+  input: S,
+  amount: u32,
+  threshold: u8,
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<BlackFrame>> + Send + Unpin> {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .filter_video(format!("blackframe=amount={amount}:threshold={threshold}"))
+    .no_audio()
+    .format("null")
+    .output("-");
+
+  spawn_parsed(command, |event| {
+    let FfmpegEvent::Log(_, line) = event else {
+      return None;
+    };
+    let (frame, percent_black, time) = try_parse_black_frame(&line)?;
+    Some(BlackFrame {
+      frame,
+      percent_black,
+      time,
+    })
+  })
+}
+
+/// A crop rectangle suggested by [`detect_crop`], in the order expected by
+/// [`FfmpegCommand::crop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CropRect {
+  pub width: u32,
+  pub height: u32,
+  pub x: u32,
+  pub y: u32,
+}
+
+/// Find the black borders (e.g. letterboxing) of the video of `input` by
+/// running the `cropdetect` filter over its first `sample_duration`, and
+/// return the crop rectangle suggested most often.
+///
+/// Fails if Ffmpeg fails or no frame was analyzed.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::command::FfmpegCommand;
+/// use async_ffmpeg_sidecar::video::detect_crop;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let rect = detect_crop("movie.mkv", Duration::from_secs(60)).await?;
+///
+/// let mut command = FfmpegCommand::new();
+/// command
+///   .input("movie.mkv")
+///   .crop(rect.width, rect.height, rect.x, rect.y)
+///   .output("cropped.mkv");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn detect_crop<S: AsRef<str>>(
+  input: S,
+  sample_duration: Duration,
+) -> anyhow::Result<CropRect> {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .filter_video("cropdetect")
+    .duration(sample_duration)
+    .no_audio()
+    .format("null")
+    .output("-");
+
+  let mut suggestions = spawn_parsed(command, |event| {
+    let FfmpegEvent::Log(_, line) = event else {
+      return None;
+    };
+    let (width, height, x, y) = try_parse_crop_suggestion(&line)?;
+    Some(CropRect {
+      width,
+      height,
+      x,
+      y,
+    })
+  })?;
+
+  let mut counts = HashMap::new();
+  while let Some(rect) = suggestions.next().await {
+    *counts.entry(rect?).or_insert(0) += 1;
+  }
+
+  counts
+    .into_iter()
+    .max_by_key(|(_, count)| *count)
+    .map(|(rect, _)| rect)
+    .ok_or_else(|| anyhow::anyhow!("cropdetect did not analyze any frame"))
+}
+
+/// Render `duration` of `input` from `start` as an animated GIF or, if
+/// `output` ends with `.webp`, an animated WebP, at `fps` frames per second
+/// and `width` pixels wide. The animation loops forever.
+///
+/// GIFs are limited to 256 colors, so they are made in two passes: the
+/// first computes an optimal palette with `palettegen`, the second applies
+/// it with `paletteuse`. The returned stream reports the completion of the
+/// whole job from `0.0` to `1.0`, ending with `1.0` on success or with the
+/// error of the failed pass. Dropping the stream stops the job.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::video::gif_preview;
+/// use futures_util::StreamExt;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut progress = gif_preview(
+///   "movie.mp4",
+///   "preview.gif",
+///   Duration::from_secs(60),
+///   Duration::from_secs(3),
+///   12.0,
+///   480,
+/// );
+/// while let Some(fraction) = progress.next().await {
+///   println!("{:.0}%", fraction? * 100.0);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn gif_preview<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  output: T,
+  start: Duration,
+  duration: Duration,
+  fps: f32,
+  width: u32,
+) -> impl Stream<Item = anyhow::Result<f64>> + Send + Unpin {
+  let input = input.as_ref().to_string();
+  let output = output.as_ref().to_string();
+  // Trimming in the filtergraph also ends `palettegen` at the end of the clip
+  let scale = format!(
+    "trim=duration={},fps={fps},scale={width}:-1:flags=lanczos",
+    duration.as_secs_f64()
+  );
+
+  let clip = |command: &mut FfmpegCommand| {
+    command.seek(start).input(&input);
+  };
+  let mut passes = Vec::new();
+  let mut palette = None;
+  if output.to_ascii_lowercase().ends_with(".webp") {
+    let mut command = FfmpegCommand::new();
+    clip(&mut command);
+    command
+      .filter_video(&scale)
+      .codec_video("libwebp")
+      .args(["-loop", "0"])
+      .overwrite()
+      .output(&output);
+    passes.push(command);
+  } else {
+    let path = temp_path("palette", "png");
+
+    // A null output next to the palette makes Ffmpeg report progress
+    let mut command = FfmpegCommand::new();
+    clip(&mut command);
+    command
+      .filter_complex(format!("[0:v]{scale},split[a][b];[a]palettegen[p]"))
+      .map_str("[p]")
+      .frames(1)
+      .overwrite()
+      .output(path.to_string_lossy())
+      .map_str("[b]")
+      .format("null")
+      .output("-");
+    passes.push(command);
+
+    let mut command = FfmpegCommand::new();
+    clip(&mut command);
+    command
+      .input(path.to_string_lossy())
+      .filter_complex(format!("[0:v]{scale}[x];[x][1:v]paletteuse"))
+      .args(["-loop", "0"])
+      .overwrite()
+      .output(&output);
+    passes.push(command);
+    palette = Some(path);
+  }
+
+  let (tx, rx) = mpsc::channel(16);
+  tokio::spawn(async move {
+    let result = run_passes(passes, duration.as_secs_f64(), &tx).await;
+    if let Some(palette) = palette {
+      let _ = tokio::fs::remove_file(palette).await;
+    }
+    let _ = tx.send(result.map(|()| 1.0)).await;
+  });
+
+  Box::pin(futures_util::stream::unfold(rx, |mut rx| async move {
+    let item = rx.recv().await?;
+    Some((item, rx))
+  }))
+}
+
+/// Run `passes` one after the other, sending the progress over all of them.
+async fn run_passes(
+  passes: Vec<FfmpegCommand>,
+  duration: f64,
+  tx: &mpsc::Sender<anyhow::Result<f64>>,
+) -> anyhow::Result<()> {
+  let count = passes.len() as f64;
+  for (index, command) in passes.into_iter().enumerate() {
+    let mut times = spawn_parsed(command, |event| match event {
+      FfmpegEvent::Progress(progress) => progress.time.map(|time| time.as_secs_f64()),
+      _ => None,
+    })?;
+    while let Some(time) = times.next().await {
+      let fraction = (time? / duration).clamp(0.0, 1.0);
+      if tx
+        .send(Ok((index as f64 + fraction) / count))
+        .await
+        .is_err()
+      {
+        anyhow::bail!("progress stream was dropped");
+      }
+    }
+  }
+  Ok(())
+}
+
+/// A unique path for an intermediate file in the temporary directory.
+pub(crate) fn temp_path(name: &str, extension: &str) -> PathBuf {
+  static COUNTER: AtomicU64 = AtomicU64::new(0);
+  let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+  std::env::temp_dir().join(format!(
+    "async-ffmpeg-sidecar-{name}-{}-{n}.{extension}",
+    std::process::id()
+  ))
+}
+
+/// The curve mapping HDR brightness to the SDR range, see the `tonemap`
+/// [filter documentation](https://ffmpeg.org/ffmpeg-filters.html#tonemap-1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemapper {
+  /// Preserves dark and bright details, with a filmic look
+  Hable,
+  /// Keeps in-range colors accurate and compresses only the highlights
+  Mobius,
+  /// Simple curve, brightening dark scenes
+  Reinhard,
+}
+
+impl Tonemapper {
+  /// The name of the curve in the `tonemap` filter.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Tonemapper::Hable => "hable",
+      Tonemapper::Mobius => "mobius",
+      Tonemapper::Reinhard => "reinhard",
+    }
+  }
+}
+
+/// The filter chain converting HDR video (PQ or HLG, BT.2020) to 8-bit SDR
+/// BT.709: the video is linearized with `zscale`, tone-mapped in floating
+/// point, then converted to BT.709 limited range `yuv420p`. Requires an
+/// Ffmpeg build with `--enable-libzimg`.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::video::{tonemap_filter, Tonemapper};
+///
+/// assert_eq!(
+///   tonemap_filter(Tonemapper::Hable),
+///   "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+///    tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p"
+/// );
+/// ```
+pub fn tonemap_filter(tonemapper: Tonemapper) -> String {
+  format!(
+    "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+     tonemap=tonemap={}:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p",
+    tonemapper.as_str()
+  )
+}
+
+/// Whether a `color_transfer` reported by ffprobe is an HDR transfer
+/// function: PQ (`smpte2084`, used by HDR10 and Dolby Vision) or HLG
+/// (`arib-std-b67`).
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::video::is_hdr_transfer;
+///
+/// assert!(is_hdr_transfer("smpte2084"));
+/// assert!(is_hdr_transfer("arib-std-b67"));
+/// assert!(!is_hdr_transfer("bt709"));
+/// assert!(!is_hdr_transfer("unknown"));
+/// ```
+pub fn is_hdr_transfer(transfer: &str) -> bool {
+  matches!(transfer.trim(), "smpte2084" | "arib-std-b67")
+}
+
+/// Whether the first video stream of `input` is HDR, according to the
+/// transfer function reported by ffprobe. See [`is_hdr_transfer`].
+pub async fn is_hdr<S: AsRef<str>>(input: S) -> anyhow::Result<bool> {
+  let output = Command::new(ffprobe_path())
+    .create_no_window()
+    .args(["-v", "error", "-select_streams", "v:0"])
+    .args(["-show_entries", "stream=color_transfer"])
+    .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+    .arg(input.as_ref())
+    .stdin(Stdio::null())
+    .output()
+    .await
+    .context("failed to run ffprobe")?;
+
+  if !output.status.success() {
+    anyhow::bail!(
+      "ffprobe failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let transfer = stdout
+    .lines()
+    .next()
+    .with_context(|| format!("ffprobe found no video stream in {}", input.as_ref()))?;
+  Ok(is_hdr_transfer(transfer))
+}
+
+/// The command used by [`tonemap_hdr_to_sdr`]: the video of `input` goes
+/// through [`tonemap_filter`] and is tagged as BT.709, the other streams are
+/// encoded with the default encoders of `output`. Existing files are
+/// overwritten.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::video::{tonemap_command, Tonemapper};
+///
+/// let command = tonemap_command("hdr.mkv", "sdr.mp4", Tonemapper::Mobius);
+/// let args = command.get_args().collect::<Vec<_>>();
+/// assert_eq!(
+///   args[args.len() - 10..args.len() - 3],
+///   ["-color_primaries", "bt709", "-color_trc", "bt709", "-colorspace", "bt709", "-y"]
+/// );
+/// ```
+pub fn tonemap_command<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  output: T,
+  tonemapper: Tonemapper,
+) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .args(["-color_primaries", "bt709"])
+    .args(["-color_trc", "bt709"])
+    .args(["-colorspace", "bt709"])
+    .overwrite()
+    .filter_video(tonemap_filter(tonemapper))
+    .output(output);
+  command
+}
+
+/// Convert the HDR video of `input` to SDR in `output` with `tonemapper`.
+/// Fails without running Ffmpeg if ffprobe doesn't report `input` as HDR,
+/// since tone-mapping SDR video would distort its colors.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::video::{tonemap_hdr_to_sdr, Tonemapper};
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// tonemap_hdr_to_sdr("hdr10.mkv", "sdr.mp4", Tonemapper::Hable).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn tonemap_hdr_to_sdr<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  output: T,
+  tonemapper: Tonemapper,
+) -> anyhow::Result<FfmpegJobSummary> {
+  if !is_hdr(input.as_ref()).await? {
+    anyhow::bail!("{} is not HDR", input.as_ref());
+  }
+
+  let summary = tonemap_command(input, output, tonemapper)
+    .spawn()?
+    .wait_with_events()
+    .await?;
+  if !summary.outcome.is_success() {
+    anyhow::bail!("tone-mapping failed: {}", summary.errors.join(""));
+  }
+  Ok(summary)
+}
+
+/// How [`cut_with`] handles ranges that don't start on a keyframe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CutMode {
+  /// Start at the keyframe before the range, keeping the cut lossless at
+  /// the cost of up to one GOP of extra video.
+  Keyframes,
+  /// Re-encode the video from the start of the range to the next keyframe
+  /// with the given encoder, and copy the rest. The encoder must produce
+  /// the codec of the input, e.g. `libx264` for H.264, with compatible
+  /// parameters, for the parts to join cleanly.
+  ReencodeBoundaries(String),
+}
+
+/// Parse the output of `ffprobe -show_entries packet=pts_time,flags` in the
+/// `csv=p=0` format into the sorted times of the keyframes, in seconds.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::video::parse_keyframe_times;
+///
+/// let output = "0.000000,K__\n0.040000,___\n2.002000,K__\nN/A,K__\n";
+/// assert_eq!(parse_keyframe_times(output), [0.0, 2.002]);
+/// ```
+pub fn parse_keyframe_times(output: &str) -> Vec<f64> {
+  let mut times = output
+    .lines()
+    .filter_map(|line| {
+      let (time, flags) = line.trim().split_once(',')?;
+      if !flags.contains('K') {
+        return None;
+      }
+      time.parse::<f64>().ok()
+    })
+    .collect::<Vec<_>>();
+  times.sort_by(f64::total_cmp);
+  times
+}
+
+/// The times of the keyframes of the first video stream of `input`, in
+/// seconds, read from the packet index by ffprobe without decoding.
+pub async fn keyframes<S: AsRef<str>>(input: S) -> anyhow::Result<Vec<f64>> {
+  let output = Command::new(ffprobe_path())
+    .create_no_window()
+    .args(["-v", "error", "-select_streams", "v:0"])
+    .args(["-show_entries", "packet=pts_time,flags"])
+    .args(["-of", "csv=p=0"])
+    .arg(input.as_ref())
+    .stdin(Stdio::null())
+    .output()
+    .await
+    .context("failed to run ffprobe")?;
+
+  if !output.status.success() {
+    anyhow::bail!(
+      "ffprobe failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+
+  Ok(parse_keyframe_times(&String::from_utf8_lossy(
+    &output.stdout,
+  )))
+}
+
+/// The last keyframe at or before `time` among the sorted `keyframes`, or
+/// `0.0` if there is none.
+///
+/// ```rust
+/// use async_ffmpeg_sidecar::video::keyframe_before;
+///
+/// let keyframes = [0.0, 2.0, 4.0];
+/// assert_eq!(keyframe_before(&keyframes, 3.5), 2.0);
+/// assert_eq!(keyframe_before(&keyframes, 4.0), 4.0);
+/// assert_eq!(keyframe_before(&[], 3.5), 0.0);
+/// ```
+pub fn keyframe_before(keyframes: &[f64], time: f64) -> f64 {
+  let index = keyframes.partition_point(|&keyframe| keyframe <= time);
+  index.checked_sub(1).map_or(0.0, |index| keyframes[index])
+}
+
+/// Losslessly cut `ranges` out of `input` and join them into `output`,
+/// copying every stream. Each range starts at the keyframe before its
+/// start, see [`CutMode::Keyframes`] and [`cut_with`].
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::video::cut;
+/// use std::time::Duration;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let ranges = [
+///   Duration::from_secs(10)..Duration::from_secs(30),
+///   Duration::from_secs(95)..Duration::from_secs(120),
+/// ];
+/// cut("recording.mp4", &ranges, "highlights.mp4").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn cut<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  ranges: &[Range<Duration>],
+  output: T,
+) -> anyhow::Result<()> {
+  cut_with(input, ranges, output, CutMode::Keyframes).await
+}
+
+/// Cut `ranges` out of `input` and join them into `output` with the concat
+/// demuxer, copying every stream except for the boundaries re-encoded by
+/// [`CutMode::ReencodeBoundaries`]. Existing files are overwritten.
+///
+/// The keyframes are read with [`keyframes`], and the parts are written
+/// next to each other in the temporary directory with the extension of
+/// `output`, then removed.
+pub async fn cut_with<S: AsRef<str>, T: AsRef<str>>(
+  input: S,
+  ranges: &[Range<Duration>],
+  output: T,
+  mode: CutMode,
+) -> anyhow::Result<()> {
+  let input = input.as_ref();
+  let output = output.as_ref();
+  anyhow::ensure!(!ranges.is_empty(), "no ranges to cut");
+  if let Some(range) = ranges.iter().find(|range| range.start >= range.end) {
+    anyhow::bail!("empty cut range {range:?}");
+  }
+
+  let keyframes = keyframes(input).await?;
+  let extension = Path::new(output)
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .unwrap_or("mkv");
+
+  let mut parts = Vec::new();
+  let result = cut_parts(input, ranges, &keyframes, &mode, extension, &mut parts).await;
+  let result = match result {
+    Ok(()) => concat_parts(&parts, output, extension).await,
+    Err(e) => Err(e),
+  };
+  for part in parts {
+    let _ = tokio::fs::remove_file(part).await;
+  }
+  result
+}
+
+/// Write the parts of `ranges`, pushing their paths to `parts` as they are
+/// created.
+async fn cut_parts(
+  input: &str,
+  ranges: &[Range<Duration>],
+  keyframes: &[f64],
+  mode: &CutMode,
+  extension: &str,
+  parts: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+  for range in ranges {
+    let start = range.start.as_secs_f64();
+    let end = range.end.as_secs_f64();
+    let copy_start = match mode {
+      CutMode::Keyframes => keyframe_before(keyframes, start),
+      CutMode::ReencodeBoundaries(encoder) => {
+        let next_keyframe = keyframes
+          .iter()
+          .copied()
+          .find(|&keyframe| keyframe >= start)
+          .unwrap_or(end)
+          .min(end);
+        if next_keyframe > start {
+          let path = temp_path("cut", extension);
+          parts.push(path.clone());
+          let mut command = FfmpegCommand::new();
+          command.codec_video(encoder);
+          run_cut(command, input, start, next_keyframe, &path).await?;
+        }
+        next_keyframe
+      }
+    };
+    if copy_start < end {
+      let path = temp_path("cut", extension);
+      parts.push(path.clone());
+      let mut command = FfmpegCommand::new();
+      command.copy_video();
+      run_cut(command, input, copy_start, end, &path).await?;
+    }
+  }
+  Ok(())
+}
+
+/// Write `start..end` of `input` to `path`, copying the streams other than
+/// the video.
+async fn run_cut(
+  mut command: FfmpegCommand,
+  input: &str,
+  start: f64,
+  end: f64,
+  path: &Path,
+) -> anyhow::Result<()> {
+  command
+    .seek(start)
+    .input(input)
+    .duration(end - start)
+    .map(0, "")
+    .copy_audio()
+    .codec_subtitle("copy")
+    .args(["-avoid_negative_ts", "make_zero"])
+    .overwrite()
+    .output(path.to_string_lossy());
+  let summary = command.spawn()?.wait_with_events().await?;
+  if !summary.outcome.is_success() {
+    anyhow::bail!(
+      "cutting {start}s..{end}s failed: {}",
+      summary.errors.join("")
+    );
+  }
+  Ok(())
+}
+
+/// The `file` directive of an ffconcat script, quoting `path`.
+pub(crate) fn concat_file_line(path: &Path) -> String {
+  let path = path
+    .to_string_lossy()
+    .replace('\\', "/")
+    .replace('\'', r"'\''");
+  format!("file '{path}'\n")
+}
+
+/// Join `parts` into `output` with the concat demuxer.
+async fn concat_parts(parts: &[PathBuf], output: &str, extension: &str) -> anyhow::Result<()> {
+  let list = temp_path("cut", "ffconcat");
+  let mut script = "ffconcat version 1.0\n".to_string();
+  for part in parts {
+    script.push_str(&concat_file_line(part));
+  }
+  tokio::fs::write(&list, script).await?;
+
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-f", "concat", "-safe", "0"])
+    .input(list.to_string_lossy())
+    .map(0, "")
+    .copy_all()
+    .overwrite()
+    .output(output);
+  let result = command.spawn()?.wait_with_events().await;
+  let _ = tokio::fs::remove_file(&list).await;
+
+  let summary = result?;
+  if !summary.outcome.is_success() {
+    anyhow::bail!(
+      "joining the {extension} parts failed: {}",
+      summary.errors.join("")
+    );
+  }
+  Ok(())
+}
+
+/// Remux the MP4 or MOV file `input` with `-movflags +faststart`, which
+/// moves the index (the `moov` box) in front of the media data so that
+/// playback can start before the whole file is downloaded. Every stream is
+/// copied. With `output` set to `None`, `input` is replaced once the new
+/// file is complete.
+///
+/// The fraction of bytes written is reported from `0.0` to `1.0`, see
+/// [`ByteProgress`]. The result is checked with [`is_faststart`] before the
+/// stream ends with `1.0`, or with the error. Dropping the stream stops the
+/// job.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use async_ffmpeg_sidecar::video::faststart;
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut progress = faststart("upload.mp4", None);
+/// while let Some(fraction) = progress.next().await {
+///   println!("{:.0}%", fraction? * 100.0);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn faststart<S: AsRef<str>>(
+  input: S,
+  output: Option<&str>,
+) -> impl Stream<Item = anyhow::Result<f64>> + Send + Unpin {
+  let input = input.as_ref().to_string();
+  let output = output.map(str::to_string);
+
+  let (tx, rx) = mpsc::channel(16);
+  tokio::spawn(async move {
+    let result = match output {
+      Some(output) => run_faststart(&input, &output, &tx).await,
+      None => {
+        // The extension is kept so that Ffmpeg picks the same muxer
+        let path = Path::new(&input);
+        let temp = path.with_file_name(format!(
+          ".{}.faststart.{}",
+          path.file_stem().unwrap_or_default().to_string_lossy(),
+          path.extension().unwrap_or_default().to_string_lossy()
+        ));
+        let temp = temp.to_string_lossy().into_owned();
+        let result = match run_faststart(&input, &temp, &tx).await {
+          Ok(()) => tokio::fs::rename(&temp, &input).await.map_err(Into::into),
+          Err(e) => Err(e),
+        };
+        if result.is_err() {
+          let _ = tokio::fs::remove_file(&temp).await;
+        }
+        result
+      }
+    };
+    let _ = tx.send(result.map(|()| 1.0)).await;
+  });
+
+  Box::pin(futures_util::stream::unfold(rx, |mut rx| async move {
+    let item = rx.recv().await?;
+    Some((item, rx))
+  }))
+}
+
+async fn run_faststart(
+  input: &str,
+  output: &str,
+  tx: &mpsc::Sender<anyhow::Result<f64>>,
+) -> anyhow::Result<()> {
+  let tracker = ByteProgress::probe(input).await?;
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input)
+    .map(0, "")
+    .copy_all()
+    .args(["-movflags", "+faststart"])
+    .progress_feed()
+    .overwrite()
+    .output(output);
+
+  let mut percents = spawn_parsed(command, move |event| tracker.percent(&event))?;
+  while let Some(percent) = percents.next().await {
+    if tx.send(Ok(percent? / 100.0)).await.is_err() {
+      anyhow::bail!("progress stream was dropped");
+    }
+  }
+
+  if !is_faststart(output).await? {
+    anyhow::bail!("the index of {output} is still after the media data");
+  }
+  Ok(())
+}
+
+/// Whether the `moov` box of the MP4 or MOV file at `path` comes before its
+/// `mdat` box, i.e. whether the file is ready for progressive playback.
+/// Only the headers of the top-level boxes are read.
+pub async fn is_faststart<P: AsRef<Path>>(path: P) -> anyhow::Result<bool> {
+  let mut file = tokio::fs::File::open(path.as_ref()).await?;
+  let mut header = [0u8; 8];
+  loop {
+    if file.read_exact(&mut header).await.is_err() {
+      anyhow::bail!("{} has no moov box", path.as_ref().display());
+    }
+    match &header[4..] {
+      b"moov" => return Ok(true),
+      b"mdat" => return Ok(false),
+      _ => {}
+    }
+
+    let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let remaining = match size {
+      // The box extends to the end of the file
+      0 => anyhow::bail!("{} has no moov box", path.as_ref().display()),
+      // A 64-bit size follows the type
+      1 => file.read_u64().await?.checked_sub(16),
+      size => u64::from(size).checked_sub(8),
+    }
+    .context("invalid MP4 box size")?;
+    file.seek(SeekFrom::Current(remaining as i64)).await?;
+  }
+}
@@ -0,0 +1,132 @@
+//! Automatic termination of Ffmpeg processes that stop making progress.
+//!
+//! Live inputs (RTMP, RTSP, HLS) can hang indefinitely when the source stops
+//! sending data without closing the connection. A [`WatchdogStream`] kills
+//! the process if no progress update arrives within a configured window.
+
+use crate::child::FfmpegChild;
+use crate::event::FfmpegEvent;
+use crate::stream::FfmpegEventStream;
+use futures_util::{Stream, StreamExt};
+use std::future::Future;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::task::Poll;
+use std::time::Duration;
+use tokio::io;
+use tokio::time::{Instant, Sleep};
+
+impl FfmpegChild {
+  /// Consume the child and return its event stream, guarded by a watchdog
+  /// that kills the process when no `Progress` (or `ProgressFeed`) event
+  /// arrives within `window`.
+  ///
+  /// The window starts when the watchdog is created, so it must also cover
+  /// the time Ffmpeg needs to open its inputs. When the watchdog fires, the
+  /// stream yields [`FfmpegEvent::Stalled`] and then ends once the killed
+  /// process has closed its stderr.
+  ///
+  /// ## Example
+  ///
+  /// ```rust,no_run
+  /// use async_ffmpeg_sidecar::command::FfmpegCommand;
+  /// use async_ffmpeg_sidecar::event::FfmpegEvent;
+  /// use futures_util::StreamExt;
+  /// use std::time::Duration;
+  ///
+  /// # async fn run() -> anyhow::Result<()> {
+  /// let child = FfmpegCommand::new()
+  ///   .input("rtmp://localhost/live/stream")
+  ///   .output("output/recording.mp4")
+  ///   .spawn()?;
+  ///
+  /// let mut events = child.with_watchdog(Duration::from_secs(30))?;
+  /// while let Some(event) = events.next().await {
+  ///   if let FfmpegEvent::Stalled(window) = event {
+  ///     eprintln!("no progress for {window:?}, ffmpeg was killed");
+  ///   }
+  /// }
+  /// let exit_status = events.wait().await?;
+  /// # Ok(())
+  /// # }
+  /// ```
+  pub fn with_watchdog(mut self, window: Duration) -> anyhow::Result<WatchdogStream> {
+    let events = self.stream()?;
+
+    Ok(WatchdogStream {
+      child: self,
+      events,
+      window,
+      timer: Box::pin(tokio::time::sleep(window)),
+      stalled: false,
+    })
+  }
+}
+
+/// An event stream which owns its [`FfmpegChild`] so that it can kill the
+/// process once progress stalls, see [`FfmpegChild::with_watchdog`].
+pub struct WatchdogStream {
+  child: FfmpegChild,
+  events: FfmpegEventStream,
+  window: Duration,
+  timer: Pin<Box<Sleep>>,
+  stalled: bool,
+}
+
+impl WatchdogStream {
+  /// Whether the watchdog has fired and killed the process.
+  pub fn is_stalled(&self) -> bool {
+    self.stalled
+  }
+
+  /// Access the watched child, e.g. to send a `quit` command.
+  pub fn child_mut(&mut self) -> &mut FfmpegChild {
+    &mut self.child
+  }
+
+  /// Stop watching, returning the child. Remaining events are discarded.
+  pub fn into_child(self) -> FfmpegChild {
+    self.child
+  }
+
+  /// Waits for the child process to finish execution.
+  pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+    self.child.wait().await
+  }
+}
+
+impl Stream for WatchdogStream {
+  type Item = FfmpegEvent;
+
+  fn poll_next(
+    mut self: Pin<&mut Self>,
+    cx: &mut std::task::Context<'_>,
+  ) -> Poll<Option<FfmpegEvent>> {
+    match self.events.poll_next_unpin(cx) {
+      Poll::Ready(Some(event)) => {
+        if matches!(
+          event,
+          FfmpegEvent::Progress(_) | FfmpegEvent::ProgressFeed(_)
+        ) {
+          let deadline = Instant::now() + self.window;
+          self.timer.as_mut().reset(deadline);
+        } else if matches!(event, FfmpegEvent::TimedOut(_)) {
+          let _ = self.child.as_inner_mut().start_kill();
+        }
+        return Poll::Ready(Some(event));
+      }
+      Poll::Ready(None) => return Poll::Ready(None),
+      Poll::Pending => {}
+    }
+
+    if !self.stalled && self.timer.as_mut().poll(cx).is_ready() {
+      self.stalled = true;
+      // Fails only if the process already exited, in which case the stream
+      // is about to end anyway.
+      let _ = self.child.as_inner_mut().start_kill();
+      return Poll::Ready(Some(FfmpegEvent::Stalled(self.window)));
+    }
+
+    Poll::Pending
+  }
+}